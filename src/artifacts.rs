@@ -0,0 +1,120 @@
+//! Remotes which hold the lockspec files of tags instead of a git history: OCI registries and
+//! araki registries. araki repos backed by one are cloned, pulled, and pushed through it rather
+//! than with git.
+
+use git2::{Oid, Repository};
+use std::fmt::{self, Display};
+use std::path::Path;
+
+use crate::oci;
+use crate::registry;
+
+/// The registry holding the lockspec artifacts of an araki repo.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ArtifactOrigin {
+    Oci(oci::Reference),
+    Registry(registry::Location),
+}
+
+impl ArtifactOrigin {
+    /// Parse a lockspec location in an OCI registry or an araki registry. Returns `None` for
+    /// anything else, e.g. a git URL.
+    ///
+    /// * `env`: Lockspec location to parse
+    pub fn parse(env: &str) -> Option<ArtifactOrigin> {
+        oci::Reference::parse(env)
+            .map(ArtifactOrigin::Oci)
+            .or_else(|| registry::Location::parse(env).map(ArtifactOrigin::Registry))
+    }
+
+    /// Get the registry an araki repo is backed by, if its `origin` isn't a git remote.
+    ///
+    /// * `repo`: araki repo
+    pub fn of(repo: &Repository) -> Option<ArtifactOrigin> {
+        oci::origin(repo)
+            .map(ArtifactOrigin::Oci)
+            .or_else(|| registry::origin(repo).map(ArtifactOrigin::Registry))
+    }
+
+    /// Render the lockspec without its tag.
+    pub fn short_name(&self) -> String {
+        match self {
+            ArtifactOrigin::Oci(reference) => reference.short_name(),
+            ArtifactOrigin::Registry(location) => location.short_name(),
+        }
+    }
+
+    /// Get the tag the location was given with, if any.
+    pub fn tag(&self) -> Option<&str> {
+        match self {
+            ArtifactOrigin::Oci(reference) => reference.tag.as_deref(),
+            ArtifactOrigin::Registry(location) => location.tag.as_deref(),
+        }
+    }
+
+    /// Clone the lockspec to a directory, importing each tag as a commit of `main`.
+    ///
+    /// * `path`: Directory to clone the lockspec to
+    pub fn clone_to(&self, path: &Path) -> Result<(), String> {
+        match self {
+            ArtifactOrigin::Oci(reference) => oci::clone(reference, path),
+            ArtifactOrigin::Registry(location) => registry::clone(location, path),
+        }
+    }
+
+    /// Create an empty araki repo with the lockspec as its `origin`.
+    ///
+    /// * `path`: Directory containing the lockspec
+    pub fn init(&self, path: &Path) -> Result<(), String> {
+        match self {
+            ArtifactOrigin::Oci(reference) => oci::init(reference, path),
+            ArtifactOrigin::Registry(location) => registry::init(location, path),
+        }
+    }
+}
+
+impl Display for ArtifactOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactOrigin::Oci(reference) => reference.fmt(f),
+            ArtifactOrigin::Registry(location) => location.fmt(f),
+        }
+    }
+}
+
+/// Import the tags pushed to the registry of an araki repo since it was cloned or last pulled,
+/// as commits on top of `origin/main`. Returns the new `origin/main`.
+///
+/// * `repo`: araki repo backed by a registry
+pub fn fetch(repo: &Repository) -> Result<Oid, String> {
+    match ArtifactOrigin::of(repo) {
+        Some(ArtifactOrigin::Oci(_)) => oci::fetch(repo),
+        Some(ArtifactOrigin::Registry(_)) => registry::fetch(repo),
+        None => Err("The origin of the araki repo is not a registry".to_string()),
+    }
+}
+
+/// Check whether a tag was pushed to the registry of an araki repo with other lockspec files
+/// than it has locally. Returns `false` if the tag wasn't pushed.
+///
+/// * `repo`: araki repo backed by a registry
+/// * `tag`: Tag to check
+pub fn pushed_tag_differs(repo: &Repository, tag: &str) -> Result<bool, String> {
+    match ArtifactOrigin::of(repo) {
+        Some(ArtifactOrigin::Oci(_)) => oci::pushed_tag_differs(repo, tag),
+        Some(ArtifactOrigin::Registry(_)) => registry::pushed_tag_differs(repo, tag),
+        None => Err("The origin of the araki repo is not a registry".to_string()),
+    }
+}
+
+/// Push the lockspec files of a tag to the registry of an araki repo.
+///
+/// * `repo`: araki repo backed by a registry
+/// * `tag`: Tag to push
+pub fn push_tag(repo: &Repository, tag: &str) -> Result<(), String> {
+    match ArtifactOrigin::of(repo) {
+        Some(ArtifactOrigin::Oci(_)) => oci::push_tag(repo, tag),
+        Some(ArtifactOrigin::Registry(_)) => registry::push_tag(repo, tag),
+        None => Err("The origin of the araki repo is not a registry".to_string()),
+    }
+}
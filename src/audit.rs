@@ -0,0 +1,280 @@
+//! Vulnerability audits of locked packages against the [OSV](https://osv.dev) database.
+//!
+//! OSV has no conda ecosystem, so conda packages are only audited if they are Python packages,
+//! which conda-forge publishes under their PyPI names.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use tokio::task::JoinSet;
+
+use crate::backends::BackendError;
+use crate::lockfile::{LockFile, Package, PackageKind};
+use crate::network;
+
+const OSV_API_URL: &str = "https://api.osv.dev/v1";
+
+/// OSV accepts at most this many queries per batch request.
+const OSV_BATCH_SIZE: usize = 1000;
+
+/// Severity of a vulnerability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Unknown => "unknown",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A known vulnerability affecting a locked package.
+#[derive(Debug, Clone)]
+pub struct Vulnerability {
+    /// OSV id of the vulnerability, e.g. `GHSA-9wx4-h78v-vm56`
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub summary: Option<String>,
+    pub severity: Severity,
+
+    /// Versions of the package which fix the vulnerability
+    pub fixed: Vec<String>,
+}
+
+/// Result of auditing a lockfile.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub vulnerabilities: Vec<Vulnerability>,
+
+    /// Number of packages that were checked
+    pub audited: usize,
+
+    /// Conda packages which could not be checked because OSV does not cover them
+    pub skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct QueryBatch {
+    queries: Vec<Query>,
+}
+
+#[derive(Serialize)]
+struct Query {
+    package: QueryPackage,
+    version: String,
+}
+
+#[derive(Serialize)]
+struct QueryPackage {
+    name: String,
+    ecosystem: &'static str,
+}
+
+#[derive(Deserialize)]
+struct QueryBatchResponse {
+    results: Vec<QueryResult>,
+}
+
+#[derive(Deserialize)]
+struct QueryResult {
+    #[serde(default)]
+    vulns: Vec<VulnId>,
+}
+
+#[derive(Deserialize)]
+struct VulnId {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct OsvVulnerability {
+    id: String,
+    summary: Option<String>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    database_specific: Option<OsvDatabaseSpecific>,
+}
+
+#[derive(Deserialize)]
+struct OsvAffected {
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+}
+
+#[derive(Deserialize)]
+struct OsvPackage {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct OsvRange {
+    #[serde(default)]
+    events: Vec<HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct OsvDatabaseSpecific {
+    severity: Option<String>,
+}
+
+/// Normalize a Python package name, as described in PEP 503.
+///
+/// * `name`: Package name to normalize
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Whether a conda package is a Python package, i.e. whether it depends on python.
+///
+/// * `package`: Conda package to check
+fn is_python_package(package: &Package) -> bool {
+    package
+        .depends
+        .iter()
+        .any(|dep| dep.split_whitespace().next() == Some("python"))
+        && package.name != "python"
+}
+
+/// Check every package of a lockfile for known vulnerabilities.
+///
+/// * `lockfile`: Lockfile listing the packages to check
+pub async fn audit(lockfile: &LockFile) -> Result<Report, BackendError> {
+    let mut report = Report::default();
+    let mut audited: Vec<&Package> = vec![];
+    for package in &lockfile.packages {
+        if package.kind == PackageKind::Pypi || is_python_package(package) {
+            audited.push(package);
+        } else {
+            report.skipped.push(package.name.clone());
+        }
+    }
+    report.audited = audited.len();
+
+    let client = network::client_builder()?.build()?;
+
+    // Find the ids of the vulnerabilities affecting each package
+    let mut affecting: Vec<(&Package, Vec<String>)> = vec![];
+    for chunk in audited.chunks(OSV_BATCH_SIZE) {
+        let batch = QueryBatch {
+            queries: chunk
+                .iter()
+                .map(|package| Query {
+                    package: QueryPackage {
+                        name: normalize_name(&package.name),
+                        ecosystem: "PyPI",
+                    },
+                    version: package.version.clone(),
+                })
+                .collect(),
+        };
+        let response: QueryBatchResponse = client
+            .post(format!("{OSV_API_URL}/querybatch"))
+            .json(&batch)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        for (package, result) in chunk.iter().zip(response.results) {
+            if !result.vulns.is_empty() {
+                affecting.push((package, result.vulns.into_iter().map(|v| v.id).collect()));
+            }
+        }
+    }
+
+    // The batch endpoint only returns ids; fetch the details of each vulnerability
+    let ids: BTreeSet<String> = affecting
+        .iter()
+        .flat_map(|(_, ids)| ids.iter().cloned())
+        .collect();
+    let mut requests = JoinSet::new();
+    for id in ids {
+        let client = client.clone();
+        requests.spawn(async move {
+            client
+                .get(format!("{OSV_API_URL}/vulns/{id}"))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<OsvVulnerability>()
+                .await
+        });
+    }
+    let mut details: HashMap<String, OsvVulnerability> = HashMap::new();
+    while let Some(result) = requests.join_next().await {
+        let vuln = result??;
+        details.insert(vuln.id.clone(), vuln);
+    }
+
+    for (package, ids) in affecting {
+        for id in ids {
+            let Some(vuln) = details.get(&id) else {
+                continue;
+            };
+            report.vulnerabilities.push(Vulnerability {
+                id,
+                package: package.name.clone(),
+                version: package.version.clone(),
+                summary: vuln.summary.clone(),
+                severity: severity(vuln),
+                fixed: fixed_versions(vuln, &normalize_name(&package.name)),
+            });
+        }
+    }
+    report
+        .vulnerabilities
+        .sort_by(|a, b| b.severity.cmp(&a.severity).then(a.package.cmp(&b.package)));
+    Ok(report)
+}
+
+/// Get the severity of a vulnerability, as rated by the database it comes from.
+///
+/// * `vuln`: Vulnerability to get the severity of
+fn severity(vuln: &OsvVulnerability) -> Severity {
+    let rating = vuln
+        .database_specific
+        .as_ref()
+        .and_then(|db| db.severity.as_deref())
+        .unwrap_or_default();
+    match rating.to_lowercase().as_str() {
+        "low" => Severity::Low,
+        "moderate" | "medium" => Severity::Medium,
+        "high" => Severity::High,
+        "critical" => Severity::Critical,
+        _ => Severity::Unknown,
+    }
+}
+
+/// Get the versions of a package in which a vulnerability is fixed.
+///
+/// * `vuln`: Vulnerability affecting the package
+/// * `name`: Normalized name of the package
+fn fixed_versions(vuln: &OsvVulnerability, name: &str) -> Vec<String> {
+    vuln.affected
+        .iter()
+        .filter(|affected| {
+            affected
+                .package
+                .as_ref()
+                .is_some_and(|package| normalize_name(&package.name) == name)
+        })
+        .flat_map(|affected| &affected.ranges)
+        .flat_map(|range| &range.events)
+        .filter_map(|event| event.get("fixed").cloned())
+        .collect()
+}
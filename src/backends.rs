@@ -1,19 +1,32 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use console::style;
-use reqwest::{ClientBuilder, RequestBuilder, Url};
+use reqwest::{RequestBuilder, Response, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
-use std::fs;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
 use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::process::Stdio;
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
 use tokio::time;
+use uuid::Uuid;
 
 use reqwest::{Client, header};
 
-use crate::cli::clone::RemoteRepo;
-use crate::common::get_araki_cache;
+use crate::common::{self, get_araki_cache, get_araki_dir};
+use crate::network::{self, SendWithRetries};
+use crate::oci::OciBackend;
+use crate::preflight;
+use crate::registry::RegistryBackend;
+use crate::remote::RemoteRepo;
+use crate::secrets;
+use crate::settings::get_settings;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct GitHubCreateRepositoryRequestBody {
@@ -21,8 +34,265 @@ struct GitHubCreateRepositoryRequestBody {
     private: bool,
 }
 
+#[derive(Serialize, Debug)]
+struct GitHubBranchProtectionRequestBody {
+    required_status_checks: Option<()>,
+    enforce_admins: bool,
+    required_pull_request_reviews: GitHubRequiredReviews,
+    restrictions: Option<()>,
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubRequiredReviews {
+    required_approving_review_count: u32,
+}
+
+/// Settings of the lockspec repositories created by `araki init`, configured in the `[init]`
+/// section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct InitSettings {
+    /// Require reviewed pull requests for changes to `main` of new repositories
+    pub protect_default_branch: bool,
+
+    /// Visibility of new repositories, unless `araki init` is passed `--public` or `--private`
+    pub default_visibility: Visibility,
+}
+
+/// Where the tokens of `araki auth login` are stored.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialStore {
+    /// The OS keyring if it can be reached, else a file only readable by the user
+    #[default]
+    Auto,
+
+    /// The OS keyring only (`secret-tool` on Linux, `security` on macOS)
+    Keyring,
+
+    /// A file in the araki cache directory, only readable by the user
+    File,
+}
+
+/// Authentication settings, configured in the `[auth]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AuthSettings {
+    /// Where the tokens of `araki auth login` are stored
+    pub credential_store: CredentialStore,
+}
+
+/// The account the stored credentials of a backend map to, see [`Backend::auth_status`].
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthStatus {
+    /// Host the credentials are for
+    pub host: String,
+
+    /// Account the credentials authenticate as
+    pub login: String,
+
+    /// Scopes granted to the token, if the backend reports them
+    pub scopes: Option<Vec<String>>,
+
+    /// When the token expires; None if it doesn't, or the backend doesn't say
+    pub expires_at: Option<String>,
+}
+
+/// Who can see a new lockspec repository.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Only members of the org
+    #[default]
+    Private,
+
+    /// Anyone
+    Public,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Private => write!(f, "private"),
+            Visibility::Public => write!(f, "public"),
+        }
+    }
+}
+
+/// A lockspec repository hosted on a backend.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepositoryInfo {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+
+    /// When the repository was last updated, as an RFC 3339 timestamp
+    pub updated: Option<String>,
+}
+
+/// Order of listed repositories.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RepositorySort {
+    #[default]
+    Name,
+
+    /// Most recently updated first
+    Updated,
+}
+
+/// Which repositories to list, and in what order.
+#[derive(Debug, Clone, Default)]
+pub struct RepositoryQuery {
+    /// Only list repositories whose name or description contains this substring
+    pub filter: Option<String>,
+
+    /// Only list repositories with this topic
+    pub topic: Option<String>,
+    pub sort: RepositorySort,
+}
+
+impl RepositoryQuery {
+    /// Filter and sort repositories according to the query.
+    ///
+    /// * `repositories`: Repositories to filter and sort
+    pub fn apply(&self, mut repositories: Vec<RepositoryInfo>) -> Vec<RepositoryInfo> {
+        if let Some(filter) = &self.filter {
+            let filter = filter.to_lowercase();
+            repositories.retain(|repo| {
+                repo.name.to_lowercase().contains(&filter)
+                    || repo
+                        .description
+                        .as_ref()
+                        .is_some_and(|description| description.to_lowercase().contains(&filter))
+            });
+        }
+        if let Some(topic) = &self.topic {
+            repositories.retain(|repo| repo.topics.contains(topic));
+        }
+        match self.sort {
+            RepositorySort::Name => repositories.sort_by(|a, b| a.name.cmp(&b.name)),
+            RepositorySort::Updated => repositories.sort_by(|a, b| b.updated.cmp(&a.updated)),
+        }
+        repositories
+    }
+}
+
+/// Outcome of a step of a backend health check.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Failed,
+
+    /// The step doesn't apply to the backend, or depends on a step which failed
+    Skipped,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => f.pad("ok"),
+            Self::Failed => f.pad("failed"),
+            Self::Skipped => f.pad("skipped"),
+        }
+    }
+}
+
+/// A step of a backend health check, e.g. reaching its API or authenticating.
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthCheck {
+    /// Name of the step: `api`, `auth`, `org`, or `ssh`
+    pub name: &'static str,
+    pub status: HealthStatus,
+
+    /// What was found, or why the step failed
+    pub detail: String,
+
+    /// Round trip time of the request made by the step
+    pub latency_ms: Option<u128>,
+}
+
+impl HealthCheck {
+    /// Create a step which succeeded.
+    ///
+    /// * `name`: Name of the step
+    /// * `detail`: What was found
+    /// * `started`: When the request made by the step was sent
+    pub fn ok(name: &'static str, detail: impl Into<String>, started: Instant) -> Self {
+        HealthCheck {
+            name,
+            status: HealthStatus::Ok,
+            detail: detail.into(),
+            latency_ms: Some(started.elapsed().as_millis()),
+        }
+    }
+
+    /// Create a step which failed.
+    ///
+    /// * `name`: Name of the step
+    /// * `detail`: Why the step failed
+    pub fn failed(name: &'static str, detail: impl Into<String>) -> Self {
+        HealthCheck {
+            name,
+            status: HealthStatus::Failed,
+            detail: detail.into(),
+            latency_ms: None,
+        }
+    }
+
+    /// Create a step which was skipped.
+    ///
+    /// * `name`: Name of the step
+    /// * `detail`: Why the step was skipped
+    pub fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        HealthCheck {
+            name,
+            status: HealthStatus::Skipped,
+            detail: detail.into(),
+            latency_ms: None,
+        }
+    }
+}
+
+/// Check that the git host can be reached over SSH with the user's keys, with
+/// `ssh -T git@<host>`. Hosts like GitHub refuse shell access, so any exit code other than
+/// ssh's own failure code (255) means the connection and authentication worked.
+///
+/// * `host`: Git host, e.g. `github.com`
+pub async fn check_ssh(host: &str) -> HealthCheck {
+    let started = Instant::now();
+    let output = Command::new("ssh")
+        .args([
+            "-T",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=10",
+            &format!("git@{host}"),
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .await;
+    match output {
+        Err(err) => HealthCheck::failed("ssh", format!("Unable to run ssh: {err}")),
+        Ok(output) if output.status.code() == Some(255) => HealthCheck::failed(
+            "ssh",
+            format!(
+                "git@{host}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ),
+        Ok(_) => HealthCheck::ok("ssh", format!("Authenticated as git@{host}"), started),
+    }
+}
+
+/// A service hosting lockspec repositories.
+///
+/// Third-party crates can implement this trait and make their backend selectable through the
+/// `backend` setting with [`register_backend`].
 #[async_trait]
-pub trait Backend {
+pub trait Backend: Send + Sync {
     /// Check if a lockspec exists under an org.
     ///
     /// * `org`: Organization containing lockspec repositories
@@ -33,10 +303,43 @@ pub trait Backend {
     ///
     /// * `org`: Organization where the repository should be created
     /// * `name`: Name of the repository (i.e. the name of the lockspec)
-    async fn create_repository(&self, org: &str, name: &str) -> Result<(), BackendError>;
+    /// * `visibility`: Who can see the repository; backends without access control ignore it
+    async fn create_repository(
+        &self,
+        org: &str,
+        name: &str,
+        visibility: Visibility,
+    ) -> Result<(), BackendError>;
 
     /// Log in to the backend.
-    async fn login(&self) -> Result<(), BackendError>;
+    ///
+    /// * `scopes`: Permissions to request on top of the ones araki always needs, e.g.
+    ///   `delete_repo` for `araki delete --remote`
+    async fn login(&self, scopes: &[String]) -> Result<(), BackendError>;
+
+    /// Log in to the backend with an existing token, e.g. a personal access token, checking it
+    /// against the backend before storing it.
+    ///
+    /// * `token`: Token to authenticate with
+    async fn login_with_token(&self, _token: &str) -> Result<(), BackendError> {
+        Err("This backend doesn't support logging in with a token.".into())
+    }
+
+    /// Forget the stored credentials of the backend.
+    async fn logout(&self) -> Result<(), BackendError> {
+        Err("This backend doesn't store credentials to log out from.".into())
+    }
+
+    /// Get the token araki authenticates to the backend with, refreshed if it expired; None if
+    /// no token is cached.
+    async fn token(&self) -> Result<Option<String>, BackendError> {
+        Err("This backend doesn't store a token.".into())
+    }
+
+    /// Describe the credentials araki uses for the backend, checking them against it.
+    async fn auth_status(&self) -> Result<AuthStatus, BackendError> {
+        Err("This backend doesn't report the status of its credentials.".into())
+    }
 
     /// Build a RemoteRepo containing repository information.
     ///
@@ -49,6 +352,67 @@ pub trait Backend {
     /// * `path`: Suffix to join with the API url to send the request to
     fn get(&self, path: &str) -> Result<RequestBuilder, BackendError>;
 
+    /// List the lockspec repositories of an org.
+    ///
+    /// * `org`: Organization containing lockspec repositories
+    /// * `query`: Which repositories to list, and in what order
+    async fn list_repositories(
+        &self,
+        _org: &str,
+        _query: &RepositoryQuery,
+    ) -> Result<Vec<RepositoryInfo>, BackendError> {
+        Err("This backend doesn't support listing repositories.".into())
+    }
+
+    /// Protect a branch of a repository, so that it can only be changed through reviewed pull
+    /// requests.
+    ///
+    /// * `org`: Organization containing the repository
+    /// * `name`: Name of the repository
+    /// * `branch`: Branch to protect
+    async fn protect_branch(
+        &self,
+        _org: &str,
+        _name: &str,
+        _branch: &str,
+    ) -> Result<(), BackendError> {
+        Err("This backend doesn't support branch protection.".into())
+    }
+
+    /// Delete a repository, with all of its tags.
+    ///
+    /// * `org`: Organization containing the repository
+    /// * `name`: Name of the repository
+    async fn delete_repository(&self, _org: &str, _name: &str) -> Result<(), BackendError> {
+        Err("This backend doesn't support deleting repositories.".into())
+    }
+
+    /// Read a file from the default branch of a repository.
+    ///
+    /// * `org`: Organization containing the repository
+    /// * `name`: Name of the repository
+    /// * `path`: Path of the file in the repository, e.g. `pixi.lock`
+    async fn read_file(
+        &self,
+        _org: &str,
+        _name: &str,
+        _path: &str,
+    ) -> Result<String, BackendError> {
+        Err("This backend doesn't support reading files of repositories.".into())
+    }
+
+    /// Check that the backend can be used end to end: that its API is reachable, that the
+    /// credentials are valid, and that the org is accessible, step by step, so that connectivity
+    /// problems can be told apart from failures of commands.
+    ///
+    /// * `org`: Organization containing lockspec repositories
+    async fn ping(&self, _org: &str) -> Vec<HealthCheck> {
+        vec![HealthCheck::skipped(
+            "api",
+            "This backend doesn't support health checks.",
+        )]
+    }
+
     /// Create an authenticated GET request builder.
     ///
     /// * `path`: Suffix to join with the API url to send the request to
@@ -57,7 +421,143 @@ pub trait Backend {
 
 pub struct GitHubBackend {
     api_url: Url,
-    client: Option<Client>,
+    git_domain: String,
+    client_id: String,
+
+    /// Cached token and the client authenticating with it, replaced when the token is refreshed
+    session: RwLock<Option<GitHubSession>>,
+}
+
+struct GitHubSession {
+    token: GitHubToken,
+    client: Client,
+}
+
+/// A GitHub token as cached by araki. Tokens of OAuth apps and personal access tokens are
+/// stored as is; tokens of GitHub Apps which expire are stored as JSON, with the refresh token
+/// the device flow issued alongside them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct GitHubToken {
+    access_token: String,
+
+    /// Unix time at which the access token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+
+    /// Unix time at which the refresh token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token_expires_at: Option<i64>,
+}
+
+impl GitHubToken {
+    /// Parse a cached token.
+    ///
+    /// * `stored`: Content of the token store
+    fn parse(stored: &str) -> Self {
+        serde_json::from_str(stored).unwrap_or_else(|_| GitHubToken {
+            access_token: stored.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Read the token from a response of the OAuth token endpoint.
+    ///
+    /// * `response`: Body of the response
+    fn from_response(response: &serde_json::Value) -> Result<Self, BackendError> {
+        let now = Utc::now().timestamp();
+        let expiry = |key: &str| Some(now + response.get(key)?.as_i64()?);
+        Ok(GitHubToken {
+            access_token: response
+                .get("access_token")
+                .and_then(|token| token.as_str())
+                .ok_or("Unexpected response while getting a GitHub user access token")?
+                .to_string(),
+            expires_at: expiry("expires_in"),
+            refresh_token: response
+                .get("refresh_token")
+                .and_then(|token| token.as_str())
+                .map(|token| token.to_string()),
+            refresh_token_expires_at: expiry("refresh_token_expires_in"),
+        })
+    }
+
+    /// Serialize the token for the token store.
+    fn to_stored(&self) -> Result<String, BackendError> {
+        match self.expires_at.is_none() && self.refresh_token.is_none() {
+            true => Ok(self.access_token.clone()),
+            false => Ok(serde_json::to_string(self)?),
+        }
+    }
+
+    /// Check if the access token has expired, or is about to, so that it is refreshed before a
+    /// request fails with it.
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now().timestamp() + 60)
+    }
+}
+
+/// Settings of the GitHub backend, read from `[backends.github]`. Only needed for GitHub
+/// Enterprise Server; by default, github.com is used.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GitHubSettings {
+    /// URL of the REST API, e.g. `https://github.mycorp.com/api/v3/`
+    pub api_url: Option<String>,
+
+    /// Host of the repositories and of the login pages, e.g. `github.mycorp.com`. Defaults to
+    /// the host of `api_url`, without a leading `api.`
+    pub git_domain: Option<String>,
+
+    /// Client ID of the OAuth app `araki auth login` authorizes; GitHub Enterprise Server needs
+    /// an OAuth app registered on the server, with the device flow enabled
+    pub client_id: Option<String>,
+}
+
+impl GitHubSettings {
+    /// Load the `[backends.github]` settings.
+    pub fn load() -> Result<Self, String> {
+        get_settings()?.backend_settings("github")
+    }
+
+    /// Get the URL of the REST API, ending with a slash so that paths are joined below it.
+    ///
+    /// The token of `git_domain` is sent to this URL, so its host must be `git_domain` or
+    /// `api.<git_domain>`; otherwise a settings file could send the token to another host.
+    pub fn api_url(&self) -> Result<Url, BackendError> {
+        let url = match self.api_url.as_deref() {
+            Some(url) if url.ends_with('/') => url.to_string(),
+            Some(url) => format!("{url}/"),
+            None => "https://api.github.com/".to_string(),
+        };
+        let parsed = Url::parse(&url).map_err(|err| format!("Invalid api_url {url}: {err}"))?;
+        if let Some(domain) = &self.git_domain {
+            let host = parsed.host_str().unwrap_or_default();
+            if host != domain && host != format!("api.{domain}") {
+                return Err(format!(
+                    "The host of api_url {url} doesn't match git_domain {domain}; it must be \
+                    {domain} or api.{domain}."
+                )
+                .into());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Get the host of the repositories and of the login pages.
+    pub fn git_domain(&self) -> String {
+        if let Some(domain) = &self.git_domain {
+            return domain.clone();
+        }
+        let host = self.api_url().ok().and_then(|url| {
+            let host = url.host_str()?;
+            Some(host.strip_prefix("api.").unwrap_or(host).to_string())
+        });
+        host.unwrap_or("github.com".to_string())
+    }
 }
 
 // An error type which is safe to send and share with other threads. Needed for async/await traits.
@@ -67,37 +567,40 @@ pub type BackendError = Box<dyn Error + Send + Sync>;
 impl Backend for GitHubBackend {
     fn get(&self, path: &str) -> Result<RequestBuilder, BackendError> {
         Ok(self
-            .client
-            .as_ref()
-            .ok_or("Please authenticate with `araki auth login` before continuing.")?
-            .get(self.api_url.join(path)?))
+            .client()?
+            .get(self.api_url.join(path.trim_start_matches('/'))?))
     }
     fn post(&self, path: &str) -> Result<RequestBuilder, BackendError> {
         Ok(self
-            .client
-            .as_ref()
-            .ok_or("Please authenticate with `araki auth login` before continuing.")?
-            .post(self.api_url.join(path)?))
+            .client()?
+            .post(self.api_url.join(path.trim_start_matches('/'))?))
     }
     async fn is_existing_lockspec(&self, org: &str, name: &str) -> Result<bool, BackendError> {
         let resp = self
-            .get(format!("/repos/{org}/{name}").as_str())?
-            .send()
+            .send(|| self.get(format!("/repos/{org}/{name}").as_str()))
             .await?
-            .json::<HashMap<String, String>>()
+            .json::<HashMap<String, serde_json::Value>>()
             .await?;
 
         Ok(resp.contains_key("name"))
     }
-    async fn create_repository(&self, org: &str, name: &str) -> Result<(), BackendError> {
+    async fn create_repository(
+        &self,
+        org: &str,
+        name: &str,
+        visibility: Visibility,
+    ) -> Result<(), BackendError> {
         let body = GitHubCreateRepositoryRequestBody {
             name: name.to_string(),
-            private: true,
+            private: visibility == Visibility::Private,
         };
+        let body = serde_json::to_string(&body)?;
         let result = self
-            .post(format!("/orgs/{org}/repos").as_str())?
-            .body(serde_json::to_string(&body)?)
-            .send()
+            .send(|| {
+                Ok(self
+                    .post(format!("/orgs/{org}/repos").as_str())?
+                    .body(body.clone()))
+            })
             .await?;
 
         if result.status().is_success() {
@@ -106,11 +609,205 @@ impl Backend for GitHubBackend {
             Err(result.text().await?.into())
         }
     }
+    async fn list_repositories(
+        &self,
+        org: &str,
+        query: &RepositoryQuery,
+    ) -> Result<Vec<RepositoryInfo>, BackendError> {
+        // The search API can filter by topic, but only matches whole words of names; substring
+        // filtering is done locally by the query
+        let mut search = format!("org:{org}");
+        if let Some(topic) = &query.topic {
+            search.push_str(&format!(" topic:{topic}"));
+        }
+
+        let mut repositories = vec![];
+        for page in 1..=GITHUB_SEARCH_MAX_PAGES {
+            let response = self
+                .send(|| {
+                    Ok(self.get("/search/repositories")?.query(&[
+                        ("q", search.as_str()),
+                        ("sort", "updated"),
+                        ("per_page", &GITHUB_PAGE_SIZE.to_string()),
+                        ("page", &page.to_string()),
+                    ]))
+                })
+                .await?
+                .error_for_status()?
+                .json::<GitHubSearchResponse>()
+                .await?;
+            let done = response.items.len() < GITHUB_PAGE_SIZE;
+            repositories.extend(response.items.into_iter().map(|item| RepositoryInfo {
+                name: item.name,
+                description: item.description,
+                topics: item.topics,
+                updated: item.updated_at,
+            }));
+            if done {
+                break;
+            }
+        }
+        Ok(query.apply(repositories))
+    }
+    async fn protect_branch(
+        &self,
+        org: &str,
+        name: &str,
+        branch: &str,
+    ) -> Result<(), BackendError> {
+        let body = GitHubBranchProtectionRequestBody {
+            required_status_checks: None,
+            enforce_admins: true,
+            required_pull_request_reviews: GitHubRequiredReviews {
+                required_approving_review_count: 1,
+            },
+            restrictions: None,
+        };
+        let body = serde_json::to_string(&body)?;
+        let result = self
+            .send(|| {
+                Ok(self
+                    .client()?
+                    .put(
+                        self.api_url
+                            .join(&format!("repos/{org}/{name}/branches/{branch}/protection"))?,
+                    )
+                    .body(body.clone()))
+            })
+            .await?;
+
+        if result.status().is_success() {
+            Ok(())
+        } else {
+            Err(result.text().await?.into())
+        }
+    }
+    async fn delete_repository(&self, org: &str, name: &str) -> Result<(), BackendError> {
+        let result = self
+            .send(|| {
+                Ok(self
+                    .client()?
+                    .delete(self.api_url.join(&format!("repos/{org}/{name}"))?))
+            })
+            .await?;
+
+        match result.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::FORBIDDEN => Err(format!(
+                "Not allowed to delete {org}/{name}. Deleting requires admin access to the \
+                repository and a token with the delete_repo scope; run \
+                `araki auth login --scope delete_repo` to get one."
+            )
+            .into()),
+            _ => Err(result.text().await?.into()),
+        }
+    }
+    async fn read_file(&self, org: &str, name: &str, path: &str) -> Result<String, BackendError> {
+        Ok(self
+            .send(|| {
+                Ok(self
+                    .get(&format!("/repos/{org}/{name}/contents/{path}"))?
+                    .header(header::ACCEPT, "application/vnd.github.raw+json"))
+            })
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+    async fn ping(&self, org: &str) -> Vec<HealthCheck> {
+        let mut checks = vec![];
+        let started = Instant::now();
+        let api = match network::client_builder().and_then(|builder| Ok(builder.build()?)) {
+            Ok(client) => client.get(self.api_url.clone()).send().await,
+            Err(err) => {
+                checks.push(HealthCheck::failed("api", err.to_string()));
+                checks.push(check_ssh(&self.git_domain).await);
+                return checks;
+            }
+        };
+        checks.push(match api {
+            Ok(response) if response.status().is_success() => {
+                HealthCheck::ok("api", format!("Reached {}", self.api_url), started)
+            }
+            Ok(response) => HealthCheck::failed(
+                "api",
+                format!("{} answered {}", self.api_url, response.status()),
+            ),
+            Err(err) => {
+                HealthCheck::failed("api", format!("Unable to reach {}: {err}", self.api_url))
+            }
+        });
+
+        let started = Instant::now();
+        let user = match self.get("/user") {
+            Ok(request) => request.send().await.map_err(BackendError::from),
+            Err(err) => Err(err),
+        };
+        let authenticated = match user {
+            Ok(response) if response.status().is_success() => {
+                let login = response
+                    .json::<HashMap<String, serde_json::Value>>()
+                    .await
+                    .ok()
+                    .and_then(|user| user.get("login")?.as_str().map(|login| login.to_string()))
+                    .unwrap_or("unknown user".to_string());
+                checks.push(HealthCheck::ok(
+                    "auth",
+                    format!("Authenticated as {login}"),
+                    started,
+                ));
+                true
+            }
+            Ok(response) => {
+                checks.push(HealthCheck::failed(
+                    "auth",
+                    format!(
+                        "The cached token was rejected ({}); run `araki auth login` again.",
+                        response.status()
+                    ),
+                ));
+                false
+            }
+            Err(err) => {
+                checks.push(HealthCheck::failed("auth", err.to_string()));
+                false
+            }
+        };
+
+        if authenticated {
+            let started = Instant::now();
+            let response = match self.get(&format!("/orgs/{org}")) {
+                Ok(request) => request.send().await.map_err(BackendError::from),
+                Err(err) => Err(err),
+            };
+            checks.push(match response {
+                Ok(response) if response.status().is_success() => {
+                    HealthCheck::ok("org", format!("{org} is accessible"), started)
+                }
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+                    HealthCheck::failed(
+                        "org",
+                        format!("{org} doesn't exist, or isn't visible to you"),
+                    )
+                }
+                Ok(response) => HealthCheck::failed(
+                    "org",
+                    format!("Unable to access {org}: {}", response.status()),
+                ),
+                Err(err) => HealthCheck::failed("org", format!("Unable to access {org}: {err}")),
+            });
+        } else {
+            checks.push(HealthCheck::skipped("org", "Authentication failed"));
+        }
+
+        checks.push(check_ssh(&self.git_domain).await);
+        checks
+    }
     fn get_repo_info(&self, org: &str, repo: &str) -> RemoteRepo {
         RemoteRepo::new(
             Some(org.to_string()),
             repo.to_string(),
-            Some("github.com".to_string()),
+            Some(self.git_domain.clone()),
             Some("https://".to_string()),
         )
     }
@@ -118,8 +815,8 @@ impl Backend for GitHubBackend {
     /// Log the user in.
     /// See https://docs.github.com/en/enterprise-cloud@latest/apps/creating-github-apps/writing-code-for-a-github-app/building-a-cli-with-a-github-app
     /// for the reference followed here.
-    async fn login(&self) -> Result<(), BackendError> {
-        let resp = Self::request_device_code().await?;
+    async fn login(&self, scopes: &[String]) -> Result<(), BackendError> {
+        let resp = self.request_device_code(scopes).await?;
 
         println!(
             "{}{}",
@@ -132,8 +829,129 @@ impl Backend for GitHubBackend {
             resp.user_code
         );
 
-        Self::poll_for_token(&resp.device_code, Duration::from_secs(resp.interval)).await
+        self.poll_for_token(&resp.device_code, Duration::from_secs(resp.interval))
+            .await
+    }
+
+    /// Log the user in with a personal access token, for orgs which don't allow OAuth apps.
+    async fn login_with_token(&self, token: &str) -> Result<(), BackendError> {
+        let response = network::client_builder()?
+            .default_headers(Self::make_authenticated_request_headers(token)?)
+            .build()?
+            .get(self.api_url.join("user")?)
+            .send_with_retries()
+            .await?;
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::UNAUTHORIZED => {
+                return Err(format!("{} rejected the token.", self.git_domain).into());
+            }
+            status => {
+                return Err(
+                    format!("Unable to check the token with {}: {status}", self.api_url).into(),
+                );
+            }
+        }
+        Self::token_store(&self.git_domain)?.write(token)?;
+        Ok(())
     }
+
+    /// Remove the cached token. GitHub only lets the owner of an OAuth app revoke its tokens, so
+    /// tokens remain valid until they expire or are revoked in the GitHub settings.
+    async fn logout(&self) -> Result<(), BackendError> {
+        let store = Self::token_store(&self.git_domain)?;
+        if store.read()?.is_none() {
+            return Err(format!("Not logged in to {}.", self.git_domain).into());
+        }
+        store.remove()
+    }
+
+    async fn token(&self) -> Result<Option<String>, BackendError> {
+        let expired = match self.session.read().unwrap().as_ref() {
+            Some(session) => session.token.is_expired(),
+            None => return Ok(None),
+        };
+        if expired {
+            self.refresh_token().await?;
+        }
+        Ok(self
+            .session
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.token.access_token.clone()))
+    }
+
+    async fn auth_status(&self) -> Result<AuthStatus, BackendError> {
+        if self.session.read().unwrap().is_none() {
+            return Err(format!(
+                "Not logged in to {}; run `araki auth login`.",
+                self.git_domain
+            )
+            .into());
+        }
+        let response = self.send(|| self.get("/user")).await?;
+        match response.status() {
+            status if status.is_success() => {}
+            status => {
+                return Err(
+                    format!("Unable to check the token with {}: {status}", self.api_url).into(),
+                );
+            }
+        }
+        // Classic and OAuth tokens report their scopes; fine-grained tokens have permissions
+        // instead, and only expiring tokens report an expiry
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let scopes = header("x-oauth-scopes").map(|scopes| {
+            scopes
+                .split(',')
+                .map(|scope| scope.trim().to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect()
+        });
+        let expires_at = header("github-authentication-token-expiration").or_else(|| {
+            let session = self.session.read().unwrap();
+            let expires_at = session.as_ref()?.token.expires_at?;
+            Some(DateTime::from_timestamp(expires_at, 0)?.to_rfc3339())
+        });
+        let user: HashMap<String, serde_json::Value> = response.json().await?;
+        let login = user
+            .get("login")
+            .and_then(|login| login.as_str())
+            .ok_or("The GitHub API didn't return the login of the user.")?;
+        Ok(AuthStatus {
+            host: self.git_domain.clone(),
+            login: login.to_string(),
+            scopes,
+            expires_at,
+        })
+    }
+}
+
+/// Number of results requested per page from the GitHub API.
+const GITHUB_PAGE_SIZE: usize = 100;
+
+/// The GitHub search API returns at most 1000 results.
+const GITHUB_SEARCH_MAX_PAGES: usize = 10;
+
+#[derive(Deserialize, Debug)]
+struct GitHubSearchResponse {
+    items: Vec<GitHubRepository>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubRepository {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    updated_at: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -147,6 +965,9 @@ struct GitHubDeviceCodeResponse {
 impl GitHubBackend {
     const CLIENT_ID: &str = "Ov23liFxqmYL2jVV2QZ0"; // araki OAuth app
 
+    /// Scopes requested by `araki auth login`; more are only requested with `--scope`.
+    const SCOPES: &str = "repo admin:org";
+
     /// Create a new set of authenticated headers.
     ///
     /// * `token`: Token to use for authentication with the API
@@ -168,49 +989,204 @@ impl GitHubBackend {
         Ok(headers)
     }
 
-    /// Create a new GitHubBackend.
+    /// Create a new GitHubBackend from the `[backends.github]` settings.
     pub fn new() -> Result<Self, BackendError> {
-        let client = Self::get_cached_token().and_then(|token| {
-            ClientBuilder::new()
-                .default_headers(Self::make_authenticated_request_headers(&token).ok()?)
-                .build()
-                .ok()
-        });
+        let settings = GitHubSettings::load()?;
+        let git_domain = settings.git_domain();
+        let session =
+            Self::get_cached_token(&git_domain).and_then(|token| Self::make_session(token).ok());
 
         Ok(Self {
-            api_url: Url::parse("https://api.github.com/")?,
-            client,
+            api_url: settings.api_url()?,
+            client_id: settings.client_id.unwrap_or(Self::CLIENT_ID.to_string()),
+            git_domain,
+            session: RwLock::new(session),
         })
     }
 
+    /// Build a client authenticating with a token.
+    ///
+    /// * `token`: Token to authenticate with
+    fn make_session(token: GitHubToken) -> Result<GitHubSession, BackendError> {
+        let client = network::client_builder()?
+            .default_headers(Self::make_authenticated_request_headers(
+                &token.access_token,
+            )?)
+            .build()?;
+        Ok(GitHubSession { token, client })
+    }
+
+    /// Get the client authenticating with the cached token.
+    fn client(&self) -> Result<Client, BackendError> {
+        Ok(self
+            .session
+            .read()
+            .unwrap()
+            .as_ref()
+            .ok_or("Please authenticate with `araki auth login` before continuing.")?
+            .client
+            .clone())
+    }
+
     /// Return the cached token, if possible.
-    /// The token is stored at <araki-cache-dir>/github-araki-token
-    fn get_cached_token() -> Option<String> {
-        fs::read_to_string(Self::get_cached_token_file().ok()?).ok()
+    ///
+    /// * `git_domain`: Host the token was issued by
+    fn get_cached_token(git_domain: &str) -> Option<GitHubToken> {
+        let stored = Self::token_store(git_domain).ok()?.read().ok()??;
+        Some(GitHubToken::parse(&stored))
     }
 
-    /// Get the path to the file where araki caches its backend token
-    fn get_cached_token_file() -> Result<PathBuf, BackendError> {
-        Ok(get_araki_cache()?.join("github-araki-token"))
+    /// Send an authenticated request. An expired token is refreshed first if the device flow
+    /// issued a refresh token, and so is a token the API rejects, before sending the request
+    /// again; when the token can't be refreshed, the user is asked to log in again.
+    ///
+    /// * `build`: Build the request; called again to resend it with a refreshed token
+    async fn send(
+        &self,
+        build: impl Fn() -> Result<RequestBuilder, BackendError>,
+    ) -> Result<Response, BackendError> {
+        let expired = self
+            .session
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|session| session.token.is_expired());
+        if expired {
+            self.refresh_token().await?;
+        }
+        let response = build()?.send_with_retries().await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        if !expired && self.refresh_token().await.is_ok() {
+            let response = build()?.send_with_retries().await?;
+            if response.status() != StatusCode::UNAUTHORIZED {
+                return Ok(response);
+            }
+        }
+        Err(self.expired_token_error())
+    }
+
+    /// Error asking the user to log in again, for a token which expired or was revoked.
+    fn expired_token_error(&self) -> BackendError {
+        format!(
+            "{} rejected the cached token, which may have expired or been revoked; run \
+            `araki auth login` again.",
+            self.git_domain
+        )
+        .into()
+    }
+
+    /// Replace the token by a new one, obtained with the refresh token of the current one, and
+    /// store it. Refresh tokens can only be used once: if another araki process refreshed the
+    /// token already, the token it stored is used instead.
+    async fn refresh_token(&self) -> Result<(), BackendError> {
+        let current = self
+            .session
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.token.clone())
+            .ok_or("Please authenticate with `araki auth login` before continuing.")?;
+        let store = Self::token_store(&self.git_domain)?;
+        if let Some(stored) = store.read()?.map(|stored| GitHubToken::parse(&stored))
+            && stored != current
+            && !stored.is_expired()
+        {
+            *self.session.write().unwrap() = Some(Self::make_session(stored)?);
+            return Ok(());
+        }
+
+        let refresh_token = current
+            .refresh_token
+            .as_deref()
+            .filter(|_| {
+                current
+                    .refresh_token_expires_at
+                    .is_none_or(|expires_at| expires_at > Utc::now().timestamp())
+            })
+            .ok_or_else(|| self.expired_token_error())?;
+        let url = Url::parse_with_params(
+            &format!("https://{}/login/oauth/access_token", self.git_domain),
+            &[
+                ("client_id", self.client_id.as_str()),
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ],
+        )?;
+        let response = network::client_builder()?
+            .build()?
+            .post(url)
+            .header(header::ACCEPT, "application/json")
+            .send_with_retries()
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+        if response.get("error").is_some() {
+            return Err(self.expired_token_error());
+        }
+        let token = GitHubToken::from_response(&response)?;
+        store.write(&token.to_stored()?)?;
+        *self.session.write().unwrap() = Some(Self::make_session(token)?);
+        Ok(())
+    }
+
+    /// Get the store of the token araki caches for GitHub: the `github-token/<host>` entry of
+    /// the OS keyring, or the file `<araki-cache-dir>/github-araki-token`
+    /// (`github-<host>-araki-token` for GitHub Enterprise Server hosts), following
+    /// `auth.credential_store`. Each profile has its own: `github-token/<profile>/<host>` in the
+    /// keyring, and files in `~/.araki/profiles/<profile>/`.
+    ///
+    /// * `git_domain`: Host the token is issued by
+    fn token_store(git_domain: &str) -> Result<TokenStore, BackendError> {
+        let name = match git_domain {
+            "github.com" => "github-araki-token".to_string(),
+            host => format!("github-{host}-araki-token"),
+        };
+        let settings = get_settings()?;
+        let (dir, account) = match &settings.profile {
+            Some(profile) => {
+                let dir = get_araki_dir()?.join("profiles").join(profile);
+                preflight::create_dir(&dir)?;
+                (dir, format!("github-token/{profile}/{git_domain}"))
+            }
+            None => (get_araki_cache()?, format!("github-token/{git_domain}")),
+        };
+        Ok(TokenStore::new(dir.join(name)).with_keyring(&account, settings.auth.credential_store))
     }
 
     /// Request a device code to use to initiate authentication with the GH API.
-    async fn request_device_code() -> Result<GitHubDeviceCodeResponse, BackendError> {
+    ///
+    /// * `scopes`: Scopes to request on top of [`Self::SCOPES`]
+    async fn request_device_code(
+        &self,
+        scopes: &[String],
+    ) -> Result<GitHubDeviceCodeResponse, BackendError> {
         let mut headers = header::HeaderMap::new();
         headers.insert(
             "Accept",
             header::HeaderValue::from_static("application/json"),
         );
-        let client = Client::builder().default_headers(headers).build()?;
+        let client = network::client_builder()?
+            .default_headers(headers)
+            .build()?;
 
+        let scope = [Self::SCOPES]
+            .into_iter()
+            .chain(scopes.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
         let url = Url::parse_with_params(
-            "https://github.com/login/device/code",
-            &[("client_id", Self::CLIENT_ID), ("scope", "repo admin:org")],
+            &format!("https://{}/login/device/code", self.git_domain),
+            &[
+                ("client_id", self.client_id.as_str()),
+                ("scope", scope.as_str()),
+            ],
         )?;
 
         let response = client
             .post(url)
-            .send()
+            .send_with_retries()
             .await?
             .error_for_status()?
             .json::<GitHubDeviceCodeResponse>()
@@ -221,18 +1197,20 @@ impl GitHubBackend {
     /// Request a token for the given device code.
     ///
     /// * `device_code`: Device code to use to authenticate
-    async fn request_token(device_code: &str) -> Result<serde_json::Value, BackendError> {
+    async fn request_token(&self, device_code: &str) -> Result<serde_json::Value, BackendError> {
         let mut headers = header::HeaderMap::new();
         headers.insert(
             "Accept",
             header::HeaderValue::from_static("application/json"),
         );
-        let client = Client::builder().default_headers(headers).build()?;
+        let client = network::client_builder()?
+            .default_headers(headers)
+            .build()?;
 
         let url = Url::parse_with_params(
-            "https://github.com/login/oauth/access_token",
+            &format!("https://{}/login/oauth/access_token", self.git_domain),
             &[
-                ("client_id", Self::CLIENT_ID),
+                ("client_id", self.client_id.as_str()),
                 ("device_code", device_code),
                 ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
             ],
@@ -240,7 +1218,7 @@ impl GitHubBackend {
 
         Ok(client
             .post(url)
-            .send()
+            .send_with_retries()
             .await?
             .json::<serde_json::Value>()
             .await?)
@@ -251,9 +1229,13 @@ impl GitHubBackend {
     /// * `device_code`: Device code to use to authenticate
     /// * `interval`: Interval to poll the API at. If the API says to slow down, we automatically
     ///   add a 5s delay to this before the next poll
-    async fn poll_for_token(device_code: &str, interval: Duration) -> Result<(), BackendError> {
+    async fn poll_for_token(
+        &self,
+        device_code: &str,
+        interval: Duration,
+    ) -> Result<(), BackendError> {
         loop {
-            let response = match Self::request_token(device_code).await {
+            let response = match self.request_token(device_code).await {
                 Ok(resp) => resp,
                 Err(err) => {
                     eprintln!("ERROR: {err}");
@@ -281,19 +1263,9 @@ impl GitHubBackend {
                     return Err(format!("Error getting araki github app token: {err}").into());
                 }
                 None => {
-                    // Write the new token to the araki-token file
-                    let token = serde_json::from_value::<String>(
-                        response
-                            .get("access_token")
-                            .ok_or("Unexpected response whil getting a GitHub user access token")?
-                            .clone(),
-                    )?;
-                    let mut file = fs::OpenOptions::new()
-                        .write(true)
-                        .create(true)
-                        .truncate(true)
-                        .open(Self::get_cached_token_file()?)?;
-                    writeln!(file, "{}", token)?;
+                    // Store the new token, with its refresh token if it expires
+                    let token = GitHubToken::from_response(&response)?;
+                    Self::token_store(&self.git_domain)?.write(&token.to_stored()?)?;
                     return Ok(());
                 }
             }
@@ -301,7 +1273,398 @@ impl GitHubBackend {
     }
 }
 
+/// Settings of the git backend, read from `[backends.git]`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GitSettings {
+    /// URL of lockspec repositories, where `{org}` and `{repo}` are replaced by the organization
+    /// and name of a repository, e.g. `git@git.mycorp.com:/srv/git/{org}/{repo}.git`
+    pub url: Option<String>,
+}
+
+impl GitSettings {
+    /// Load the `[backends.git]` settings, if the git backend is the configured backend.
+    pub fn load() -> Result<Option<Self>, String> {
+        let settings = get_settings()?;
+        if settings.backend != "git" {
+            return Ok(None);
+        }
+        settings.backend_settings("git").map(Some)
+    }
+
+    /// Get the URL of a repository from the URL template, if one is configured.
+    ///
+    /// * `org`: Organization of the repository
+    /// * `repo`: Name of the repository
+    pub fn repo_url(&self, org: &str, repo: &str) -> Option<String> {
+        self.url
+            .as_ref()
+            .map(|url| url.replace("{org}", org).replace("{repo}", repo))
+    }
+}
+
+/// Backend for lockspec repositories reached over plain git, e.g. bare repositories on an SSH
+/// server, for hosts without an API. Authentication is left to git (ssh-agent or credential
+/// helpers), and repositories can't be created through the backend: they must exist, empty,
+/// before `araki init` or `araki push` use them.
+pub struct GitOnlyBackend {
+    settings: GitSettings,
+}
+
+impl GitOnlyBackend {
+    /// Create a new GitOnlyBackend from the `[backends.git]` settings.
+    pub fn new() -> Result<Self, BackendError> {
+        let settings: GitSettings = get_settings()?.backend_settings("git")?;
+        if settings.url.is_none() {
+            return Err(
+                "No repository URL configured. Set `url` in [backends.git] in \
+                araki.toml, e.g. to \"git@git.mycorp.com:/srv/git/{org}/{repo}.git\"."
+                    .into(),
+            );
+        }
+        Ok(Self { settings })
+    }
+
+    /// Get the URL of a repository.
+    ///
+    /// * `org`: Organization of the repository
+    /// * `name`: Name of the repository
+    fn url(&self, org: &str, name: &str) -> String {
+        self.settings.repo_url(org, name).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Backend for GitOnlyBackend {
+    fn get(&self, _path: &str) -> Result<RequestBuilder, BackendError> {
+        Err("The git backend has no API to send requests to.".into())
+    }
+    fn post(&self, _path: &str) -> Result<RequestBuilder, BackendError> {
+        Err("The git backend has no API to send requests to.".into())
+    }
+    async fn is_existing_lockspec(&self, org: &str, name: &str) -> Result<bool, BackendError> {
+        Ok(common::git_remote_exists(&self.url(org, name))?)
+    }
+    async fn create_repository(
+        &self,
+        org: &str,
+        name: &str,
+        _visibility: Visibility,
+    ) -> Result<(), BackendError> {
+        // An empty repository created beforehand is used as is
+        if self.is_existing_lockspec(org, name).await? {
+            return Ok(());
+        }
+        let url = self.url(org, name);
+        Err(format!(
+            "The git backend can't create repositories. Create an empty bare repository for \
+            {url} on the server (e.g. with `git init --bare --initial-branch=main`), then try again."
+        )
+        .into())
+    }
+    fn get_repo_info(&self, org: &str, repo: &str) -> RemoteRepo {
+        RemoteRepo::new(Some(org.to_string()), repo.to_string(), None, None)
+            .with_url(Some(self.url(org, repo)))
+    }
+    async fn login(&self, _scopes: &[String]) -> Result<(), BackendError> {
+        Err(
+            "The git backend authenticates with your ssh keys or git credential helper; \
+            there is nothing to log in to."
+                .into(),
+        )
+    }
+}
+
+/// A token cached by `araki auth login`, in the OS keyring or in a file.
+///
+/// Concurrent logins, or a login racing a command reading the token, must not see or leave a
+/// partially written token file: readers and writers hold a lock on a sidecar `.lock` file, and
+/// tokens are written to a temporary file which is then renamed over the token file.
+pub struct TokenStore {
+    path: PathBuf,
+
+    /// Entry of the OS keyring holding the token, if the keyring is used
+    account: Option<String>,
+    storage: CredentialStore,
+}
+
+impl TokenStore {
+    /// Create a store for the token in a file.
+    ///
+    /// * `path`: File holding the token
+    pub fn new(path: PathBuf) -> Self {
+        TokenStore {
+            path,
+            account: None,
+            storage: CredentialStore::File,
+        }
+    }
+
+    /// Keep the token in the OS keyring instead. With [`CredentialStore::Auto`], the file is
+    /// used when the keyring can't be reached, and tokens stored in the file before are still
+    /// read.
+    ///
+    /// * `account`: Entry of the keyring holding the token
+    /// * `storage`: Where the token is stored
+    pub fn with_keyring(mut self, account: &str, storage: CredentialStore) -> Self {
+        self.account = Some(account.to_string());
+        self.storage = storage;
+        self
+    }
+
+    /// Get the keyring entry of the token, if the keyring is used.
+    fn keyring_account(&self) -> Option<&str> {
+        match self.storage {
+            CredentialStore::File => None,
+            CredentialStore::Auto | CredentialStore::Keyring => self.account.as_deref(),
+        }
+    }
+
+    /// Open the lock file of the token, creating it if needed.
+    fn lock_file(&self) -> Result<File, BackendError> {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(".lock");
+        Ok(OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(PathBuf::from(name))?)
+    }
+
+    /// Read the token; None if no token is stored.
+    pub fn read(&self) -> Result<Option<String>, BackendError> {
+        if let Some(account) = self.keyring_account() {
+            match (
+                secrets::keyring_read(secrets::CREDENTIALS_SERVICE, account),
+                self.storage,
+            ) {
+                (Ok(Some(token)), _) => return Ok(Some(token)),
+                (Ok(None), CredentialStore::Keyring) => return Ok(None),
+                (Err(err), CredentialStore::Keyring) => return Err(err.into()),
+                _ => {}
+            }
+        }
+        let lock = self.lock_file()?;
+        lock.lock_shared()?;
+        let token = match fs::read_to_string(&self.path) {
+            Ok(token) => Ok(Some(token.trim().to_string()).filter(|token| !token.is_empty())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        };
+        lock.unlock()?;
+        token
+    }
+
+    /// Replace the stored token. A token file is only readable by the user; once the token is in
+    /// the keyring, a token file left from before is removed.
+    ///
+    /// * `token`: Token to store
+    pub fn write(&self, token: &str) -> Result<(), BackendError> {
+        if let Some(account) = self.keyring_account() {
+            match (
+                secrets::keyring_write(secrets::CREDENTIALS_SERVICE, account, token),
+                self.storage,
+            ) {
+                (Ok(()), _) => return self.remove_file(),
+                (Err(err), CredentialStore::Keyring) => return Err(err.into()),
+                _ => {}
+            }
+        }
+        let lock = self.lock_file()?;
+        lock.lock()?;
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}.tmp", Uuid::new_v4()));
+        let temp = PathBuf::from(name);
+        let result = (|| -> Result<(), BackendError> {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&temp)?;
+            writeln!(file, "{token}")?;
+            file.sync_all()?;
+            fs::rename(&temp, &self.path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        lock.unlock()?;
+        result
+    }
+
+    /// Remove the stored token, from the keyring and from the file.
+    pub fn remove(&self) -> Result<(), BackendError> {
+        if let Some(account) = self.keyring_account()
+            && let (Err(err), CredentialStore::Keyring) = (
+                secrets::keyring_delete(secrets::CREDENTIALS_SERVICE, account),
+                self.storage,
+            )
+        {
+            return Err(err.into());
+        }
+        self.remove_file()
+    }
+
+    /// Remove the token file, if there is one.
+    fn remove_file(&self) -> Result<(), BackendError> {
+        let lock = self.lock_file()?;
+        lock.lock()?;
+        let removed = match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+        lock.unlock()?;
+        Ok(removed?)
+    }
+}
+
+/// Function which constructs a backend.
+pub type BackendFactory = fn() -> Result<Box<dyn Backend>, BackendError>;
+
+/// Backends which can be selected with the `backend` setting, keyed by name.
+static BACKENDS: LazyLock<Mutex<HashMap<String, BackendFactory>>> = LazyLock::new(|| {
+    let mut backends: HashMap<String, BackendFactory> = HashMap::new();
+    backends.insert("git".to_string(), || Ok(Box::new(GitOnlyBackend::new()?)));
+    backends.insert("github".to_string(), || Ok(Box::new(GitHubBackend::new()?)));
+    backends.insert("oci".to_string(), || Ok(Box::new(OciBackend::new()?)));
+    backends.insert("registry".to_string(), || {
+        Ok(Box::new(RegistryBackend::new()?))
+    });
+    Mutex::new(backends)
+});
+
+/// Register a backend so that it can be selected with the `backend` setting.
+///
+/// Registering a backend under an existing name replaces the existing backend.
+///
+/// * `name`: Name used to select the backend in the araki settings
+/// * `factory`: Function which constructs the backend
+pub fn register_backend(name: &str, factory: BackendFactory) {
+    BACKENDS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(name.to_string(), factory);
+}
+
+/// Get the names of all registered backends, sorted alphabetically.
+pub fn registered_backends() -> Vec<String> {
+    let mut names: Vec<String> = BACKENDS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .keys()
+        .cloned()
+        .collect();
+    names.sort();
+    names
+}
+
+/// Construct the backend registered under the given name.
+///
+/// * `name`: Name of the backend
+pub fn get_backend(name: &str) -> Result<Box<dyn Backend>, BackendError> {
+    let factory = BACKENDS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(name)
+        .copied();
+    match factory {
+        Some(factory) => factory(),
+        None => Err(format!(
+            "Unknown backend '{name}'. Available backends: {}",
+            registered_backends().join(", ")
+        )
+        .into()),
+    }
+}
+
 /// Get the currently configured araki backend.
-pub fn get_current_backend() -> Result<impl Backend, BackendError> {
-    GitHubBackend::new()
+pub fn get_current_backend() -> Result<Box<dyn Backend>, BackendError> {
+    get_backend(&get_settings()?.backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::thread;
+
+    #[test]
+    fn test_github_token() {
+        // Tokens without expiry are stored as is, as before refresh tokens were supported
+        let token = GitHubToken::parse("gho_abc");
+        assert_eq!(token.access_token, "gho_abc");
+        assert_eq!(token.to_stored().unwrap(), "gho_abc");
+        assert!(!token.is_expired());
+
+        let response = serde_json::json!({
+            "access_token": "ghu_abc",
+            "expires_in": 28800,
+            "refresh_token": "ghr_abc",
+            "refresh_token_expires_in": 15897600,
+        });
+        let token = GitHubToken::from_response(&response).unwrap();
+        assert!(!token.is_expired());
+        assert_eq!(GitHubToken::parse(&token.to_stored().unwrap()), token);
+
+        let expired = GitHubToken {
+            expires_at: Some(Utc::now().timestamp() - 1),
+            ..token
+        };
+        assert!(expired.is_expired());
+    }
+
+    #[test]
+    fn test_api_url_host() {
+        let settings = |api_url: Option<&str>, git_domain: Option<&str>| GitHubSettings {
+            api_url: api_url.map(str::to_string),
+            git_domain: git_domain.map(str::to_string),
+            client_id: None,
+        };
+        assert!(settings(None, None).api_url().is_ok());
+        assert!(settings(None, Some("github.com")).api_url().is_ok());
+        let enterprise = settings(
+            Some("https://github.mycorp.com/api/v3"),
+            Some("github.mycorp.com"),
+        );
+        assert!(enterprise.api_url().is_ok());
+        assert_eq!(
+            settings(Some("https://github.mycorp.com/api/v3"), None).git_domain(),
+            "github.mycorp.com"
+        );
+
+        // The token of git_domain is never sent to another host
+        assert!(
+            settings(Some("https://attacker/"), Some("github.com"))
+                .api_url()
+                .is_err()
+        );
+        assert!(settings(None, Some("github.mycorp.com")).api_url().is_err());
+    }
+
+    #[test]
+    fn test_token_store() {
+        let dir = temp_dir().join(format!("araki-test-token-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = TokenStore::new(dir.join("token"));
+        assert!(store.read().unwrap().is_none());
+
+        // Concurrent writers never leave a mix of their tokens behind
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let store = TokenStore::new(dir.join("token"));
+                thread::spawn(move || store.write(&format!("token-{i}").repeat(1000)).unwrap())
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let token = store.read().unwrap().unwrap();
+        assert!((0..8).any(|i| token == format!("token-{i}").repeat(1000)));
+
+        store.remove().unwrap();
+        assert!(store.read().unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,536 @@
+//! Self-contained archives of an environment, for moving it to machines without network access.
+//!
+//! A bundle is a tar archive holding the araki repo of a lockspec under `lockspec/`, every
+//! conda and PyPI artifact its pixi.lock references for the selected platforms under
+//! `conda/<channel>/<subdir>/` and `pypi/`, and a manifest (`araki-bundle.json`) listing them.
+//!
+//! Unbundling serves the artifacts from a local HTTP server, which pixi is pointed at through
+//! mirrors of the locked channels, so that the environment installs without network access.
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env::temp_dir;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tar::{Archive, Builder, Header};
+use toml::Table;
+use uuid::Uuid;
+
+use crate::backends::BackendError;
+use crate::common::{self, ARAKI_GIT_DIR_NAME, LockSpec};
+use crate::lockfile::{LockFile, Package, PackageKind};
+use crate::network;
+use crate::ops::{self, LOCKSPEC_FILES, Progress, full_tag_name};
+use crate::preflight;
+use crate::projects;
+use crate::rebuild::current_platform;
+use crate::settings::merge_tables;
+
+/// Name of the manifest at the root of a bundle.
+pub const MANIFEST_FILE: &str = "araki-bundle.json";
+
+/// Directory of a bundle holding the araki repo of the lockspec.
+const LOCKSPEC_DIR: &str = "lockspec";
+
+/// Contents of a bundle, as recorded in its manifest.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BundleManifest {
+    /// Name of the bundled lockspec
+    pub name: String,
+
+    /// Tag, branch, or commit bundled, if not the checked out lockspec
+    #[serde(default)]
+    pub reference: Option<String>,
+
+    /// Platforms whose artifacts are bundled, e.g. `linux-64`
+    pub platforms: Vec<String>,
+
+    pub artifacts: Vec<Artifact>,
+
+    /// URLs of locked packages which can't be downloaded (e.g. local paths or git
+    /// repositories), and so aren't bundled
+    pub skipped: Vec<String>,
+}
+
+/// A package artifact stored in a bundle.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Artifact {
+    pub name: String,
+    pub version: String,
+    pub kind: PackageKind,
+
+    /// Location the artifact was downloaded from
+    pub url: String,
+
+    /// Path of the artifact in the bundle
+    pub path: String,
+    pub sha256: Option<String>,
+}
+
+/// Get the path of the artifact of a package in a bundle, or `None` if the package can't be
+/// downloaded.
+///
+/// * `package`: Locked package
+fn artifact_path(package: &Package) -> Option<String> {
+    if !package.url.starts_with("https://") && !package.url.starts_with("http://") {
+        return None;
+    }
+    let url = package.url.split(['#', '?']).next()?;
+    let mut segments = url.rsplit('/');
+    let file = segments.next().filter(|file| !file.is_empty())?;
+    match package.kind {
+        PackageKind::Conda => {
+            let subdir = segments.next()?;
+            let channel = segments.next()?;
+            Some(format!("conda/{channel}/{subdir}/{file}"))
+        }
+        PackageKind::Pypi => Some(format!("pypi/{file}")),
+    }
+}
+
+/// Get the packages of a lockfile used on any of the given platforms, by any environment.
+///
+/// * `lockfile`: Lockfile of the lockspec
+/// * `platforms`: Platforms to get the packages of, e.g. `linux-64`
+pub fn platform_packages<'a>(
+    lockfile: &'a LockFile,
+    platforms: &[String],
+) -> Result<Vec<&'a Package>, String> {
+    let mut urls = BTreeSet::new();
+    for platform in platforms {
+        let locked: Vec<&Vec<String>> = lockfile
+            .environments
+            .values()
+            .filter_map(|environment| environment.packages.get(platform))
+            .collect();
+        if locked.is_empty() {
+            return Err(format!(
+                "pixi.lock doesn't lock any environment for {platform}."
+            ));
+        }
+        urls.extend(locked.into_iter().flatten());
+    }
+    Ok(lockfile
+        .packages
+        .iter()
+        .filter(|package| urls.contains(&package.url))
+        .collect())
+}
+
+/// Download an artifact, checking it against its locked hash.
+///
+/// * `client`: HTTP client to download with
+/// * `artifact`: Artifact to download
+async fn download(client: &reqwest::Client, artifact: &Artifact) -> Result<Vec<u8>, BackendError> {
+    let contents = client
+        .get(&artifact.url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec();
+    if let Some(expected) = &artifact.sha256 {
+        let digest: String = Sha256::digest(&contents)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        if &digest != expected {
+            return Err(format!(
+                "{} doesn't match its locked sha256 (expected {expected}, got {digest})",
+                artifact.url
+            )
+            .into());
+        }
+    }
+    Ok(contents)
+}
+
+/// Read the lockspec files to bundle, keyed by their path in the araki repo.
+///
+/// * `lockspec`: Lockspec to bundle
+/// * `rev`: Tag, branch, or commit to read the files at; the files of the working directory
+///   are read if `None`
+fn lockspec_files(
+    lockspec: &LockSpec,
+    rev: Option<&str>,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let Some(rev) = rev else {
+        return LOCKSPEC_FILES
+            .iter()
+            .map(|file| {
+                let path = lockspec.dir().join(file);
+                fs::read(&path)
+                    .map(|contents| (lockspec.tracked_path(file), contents))
+                    .map_err(|err| format!("Unable to read {path:?}: {err}"))
+            })
+            .collect();
+    };
+    let repo = common::open_araki_git_repo(&lockspec.path)
+        .map_err(|err| format!("Couldn't recognize the araki repo: {err}"))?;
+    let tag = format!("refs/tags/{}", full_tag_name(&repo, rev));
+    let rev = match repo.revparse_single(&tag) {
+        Ok(_) => tag.as_str(),
+        Err(_) => rev,
+    };
+    LOCKSPEC_FILES
+        .iter()
+        .map(|file| {
+            let path = lockspec.tracked_path(file);
+            common::read_rev_file(&repo, rev, &path)
+                .map(|contents| (path, contents))
+                .map_err(|err| format!("Unable to read {file} at {rev}: {err}"))
+        })
+        .collect()
+}
+
+/// Write a bundle of a lockspec: its araki repo, and the artifacts of its packages for the
+/// given platforms. The bundle is only written once every artifact has been downloaded.
+///
+/// * `lockspec`: Lockspec to bundle
+/// * `rev`: Tag, branch, or commit to bundle; the checked out lockspec is bundled if `None`
+/// * `platforms`: Platforms to bundle the artifacts of, e.g. `linux-64`
+/// * `output`: Path of the bundle to write
+/// * `on_progress`: Called as the bundle progresses
+pub async fn bundle(
+    lockspec: &LockSpec,
+    rev: Option<&str>,
+    platforms: &[String],
+    output: &Path,
+    on_progress: &dyn Fn(Progress),
+) -> Result<BundleManifest, BackendError> {
+    let files = lockspec_files(lockspec, rev)?;
+    let lockfile = files
+        .iter()
+        .find(|(path, _)| path.ends_with("pixi.lock"))
+        .map(|(_, contents)| LockFile::parse(&String::from_utf8_lossy(contents)))
+        .ok_or("The lockspec has no pixi.lock")??;
+    let mut manifest = BundleManifest {
+        name: lockspec.display_name(),
+        reference: rev.map(|rev| rev.to_string()),
+        platforms: platforms.to_vec(),
+        artifacts: vec![],
+        skipped: vec![],
+    };
+    for package in platform_packages(&lockfile, platforms)? {
+        match artifact_path(package) {
+            Some(path) => manifest.artifacts.push(Artifact {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                kind: package.kind,
+                url: package.url.clone(),
+                path,
+                sha256: package.sha256.clone(),
+            }),
+            None => manifest.skipped.push(package.url.clone()),
+        }
+    }
+
+    let partial = output.with_extension("partial");
+    let result = async {
+        let mut builder = Builder::new(File::create(&partial)?);
+        builder.follow_symlinks(false);
+
+        on_progress(Progress::Phase {
+            step: 1,
+            total: 2,
+            message: "Adding the lockspec...",
+        });
+        builder.append_dir_all(
+            format!("{LOCKSPEC_DIR}/{ARAKI_GIT_DIR_NAME}"),
+            lockspec.path.join(ARAKI_GIT_DIR_NAME),
+        )?;
+        for (path, contents) in &files {
+            append_file(&mut builder, &format!("{LOCKSPEC_DIR}/{path}"), contents)?;
+        }
+
+        on_progress(Progress::Phase {
+            step: 2,
+            total: 2,
+            message: &format!("Downloading {} artifacts...", manifest.artifacts.len()),
+        });
+        let client = network::client_builder()?.build()?;
+        for (index, artifact) in manifest.artifacts.iter().enumerate() {
+            let contents = download(&client, artifact).await?;
+            append_file(&mut builder, &artifact.path, &contents)?;
+            on_progress(Progress::Transfer {
+                received: index + 1,
+                total: manifest.artifacts.len(),
+            });
+        }
+
+        append_file(
+            &mut builder,
+            MANIFEST_FILE,
+            serde_json::to_string_pretty(&manifest)?.as_bytes(),
+        )?;
+        builder.into_inner()?;
+        fs::rename(&partial, output)?;
+        Ok::<(), BackendError>(())
+    }
+    .await;
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("Unable to create the bundle {output:?}: {err}").into());
+    }
+    Ok(manifest)
+}
+
+/// Unpack a bundle into a directory and install its environment from the bundled artifacts,
+/// without network access. The environment is recorded in the project registry as if it had
+/// been cloned.
+///
+/// * `archive`: Path of the bundle
+/// * `path`: Directory to unpack the lockspec to
+/// * `on_progress`: Called as the installation progresses
+pub async fn unbundle(
+    archive: &Path,
+    path: &Path,
+    on_progress: &dyn Fn(Progress),
+) -> Result<BundleManifest, String> {
+    on_progress(Progress::Phase {
+        step: 1,
+        total: 3,
+        message: "Checking the target directory...",
+    });
+    preflight::check_clone_target(path)?;
+
+    on_progress(Progress::Phase {
+        step: 2,
+        total: 3,
+        message: &format!("Unpacking {archive:?}..."),
+    });
+    let scratch = temp_dir().join(format!("araki-unbundle-{}", Uuid::new_v4()));
+    let result = async {
+        let file =
+            File::open(archive).map_err(|err| format!("Unable to open {archive:?}: {err}"))?;
+        Archive::new(file)
+            .unpack(&scratch)
+            .map_err(|err| format!("Unable to unpack {archive:?}: {err}"))?;
+        let manifest: BundleManifest = serde_json::from_str(
+            &fs::read_to_string(scratch.join(MANIFEST_FILE))
+                .map_err(|_| format!("{archive:?} is not an araki bundle"))?,
+        )
+        .map_err(|err| format!("Unable to parse the manifest of {archive:?}: {err}"))?;
+        if let Some(platform) = current_platform()
+            && !manifest.platforms.iter().any(|bundled| bundled == platform)
+        {
+            return Err(format!(
+                "{archive:?} holds the artifacts for {}, not for {platform}. Bundle the \
+                environment again with --platform {platform}.",
+                manifest.platforms.join(", ")
+            ));
+        }
+
+        preflight::create_dir(path)?;
+        common::copy_directory_contents(&scratch.join(LOCKSPEC_DIR), &path.to_path_buf())
+            .map_err(|err| format!("Unable to copy the lockspec to {path:?}: {err}"))?;
+        common::ignore_lockspec_files(path)?;
+        // The bundled repo may record the working directory it was bundled from; without it,
+        // the project containing the repo is its working directory
+        let open = || {
+            common::open_araki_git_repo(path)
+                .map_err(|err| format!("Couldn't recognize the unbundled araki repo: {err}"))
+        };
+        if let Ok(mut config) = open()?.config() {
+            let _ = config.remove("core.worktree");
+        }
+        if let Some(reference) = &manifest.reference {
+            ops::checkout_ref(&open()?, reference)?;
+        }
+        let lockspec = LockSpec::from_path(path)?;
+
+        on_progress(Progress::Phase {
+            step: 3,
+            total: 3,
+            message: "Installing the environment from the bundle...",
+        });
+        if let Err(err) = install_offline(&lockspec, &scratch, &manifest).await {
+            let _ = lockspec.remove_files();
+            return Err(err);
+        }
+        ops::verify_install(&lockspec, &open()?)?;
+        // The project registry is bookkeeping only; failing to update it doesn't fail the install
+        let _ = projects::record(path, &manifest.name, manifest.reference.as_deref());
+        Ok(manifest)
+    }
+    .await;
+
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+/// Get the pixi mirrors which redirect the locked URLs of the artifacts of a bundle to a local
+/// server, keyed by the URL they replace.
+///
+/// * `manifest`: Manifest of the bundle
+/// * `server`: Base URL of the server, e.g. `http://127.0.0.1:8000`
+fn local_mirrors(manifest: &BundleManifest, server: &str) -> BTreeMap<String, String> {
+    let mut mirrors = BTreeMap::new();
+    for artifact in &manifest.artifacts {
+        match artifact.kind {
+            PackageKind::Conda => {
+                // Both the URL and the path in the bundle end with `<subdir>/<file>`
+                if let (Some(channel), Some(dir)) = (
+                    artifact.url.rsplitn(3, '/').nth(2),
+                    artifact.path.rsplitn(3, '/').nth(2),
+                ) {
+                    mirrors.insert(channel.to_string(), format!("{server}/{dir}"));
+                }
+            }
+            PackageKind::Pypi => {
+                // PyPI artifacts are served by file name, whatever their path on the index
+                let origin: Vec<&str> = artifact.url.splitn(4, '/').take(3).collect();
+                mirrors.insert(origin.join("/"), format!("{server}/pypi"));
+            }
+        }
+    }
+    mirrors
+}
+
+/// Serve an artifact of an unpacked bundle.
+///
+/// * `root`: Directory the bundle was unpacked to
+/// * `uri`: Requested URI
+async fn serve_artifact(State(root): State<Arc<PathBuf>>, uri: Uri) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    let path = match path.strip_prefix("pypi/") {
+        Some(rest) => format!("pypi/{}", rest.rsplit('/').next().unwrap_or_default()),
+        None => path.to_string(),
+    };
+    if path.split('/').any(|segment| segment == "..") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match tokio::fs::read(root.join(path)).await {
+        Ok(contents) => contents.into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Install the environment of an unbundled lockspec with pixi, serving the bundled artifacts
+/// from a local server for the duration of the install.
+///
+/// * `lockspec`: Unbundled lockspec
+/// * `root`: Directory the bundle was unpacked to
+/// * `manifest`: Manifest of the bundle
+async fn install_offline(
+    lockspec: &LockSpec,
+    root: &Path,
+    manifest: &BundleManifest,
+) -> Result<(), String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|err| format!("Unable to serve the bundled artifacts: {err}"))?;
+    let address = listener
+        .local_addr()
+        .map_err(|err| format!("Unable to serve the bundled artifacts: {err}"))?;
+    let app = Router::new()
+        .fallback(serve_artifact)
+        .with_state(Arc::new(root.to_path_buf()));
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // The mirrors are only needed for this install; the pixi config is restored afterwards
+    let dir = lockspec.dir().join(".pixi");
+    let config_path = dir.join("config.toml");
+    let original = fs::read_to_string(&config_path).ok();
+    let mut config: Table = original
+        .as_deref()
+        .unwrap_or_default()
+        .parse()
+        .map_err(|err| format!("Unable to parse {config_path:?}: {err}"))?;
+    let mirrors: Table = local_mirrors(manifest, &format!("http://{address}"))
+        .into_iter()
+        .map(|(url, mirror)| (url, toml::Value::Array(vec![mirror.into()])))
+        .collect();
+    merge_tables(
+        &mut config,
+        Table::from_iter([("mirrors".to_string(), toml::Value::Table(mirrors))]),
+    );
+    preflight::create_dir(&dir)?;
+    fs::write(&config_path, config.to_string())
+        .map_err(|err| format!("Unable to write {config_path:?}: {err}"))?;
+
+    let status = tokio::process::Command::from(common::pixi_command())
+        .args(["install", "--frozen", "--locked", "--color", "always"])
+        .current_dir(lockspec.dir())
+        // Requests to the local server must not go through a proxy
+        .env("NO_PROXY", "127.0.0.1")
+        .env("no_proxy", "127.0.0.1")
+        .status()
+        .await;
+    server.abort();
+    let _ = match original {
+        Some(original) => fs::write(&config_path, original),
+        None => fs::remove_file(&config_path),
+    };
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err("Failed to install the environment from the bundle.".to_string()),
+        Err(err) => Err(format!("Failed to execute pixi install: {err}")),
+    }
+}
+
+/// Append a file to a tar archive.
+///
+/// * `builder`: Archive to append to
+/// * `path`: Path of the file in the archive
+/// * `contents`: Contents of the file
+fn append_file(builder: &mut Builder<File>, path: &str, contents: &[u8]) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_path() {
+        let package = |kind, url: &str| Package {
+            kind,
+            name: "pkg".to_string(),
+            version: "1.0".to_string(),
+            url: url.to_string(),
+            sha256: None,
+            md5: None,
+            license: None,
+            depends: vec![],
+        };
+        assert_eq!(
+            artifact_path(&package(
+                PackageKind::Conda,
+                "https://conda.anaconda.org/conda-forge/linux-64/pkg-1.0-h0_0.conda"
+            )),
+            Some("conda/conda-forge/linux-64/pkg-1.0-h0_0.conda".to_string())
+        );
+        assert_eq!(
+            artifact_path(&package(
+                PackageKind::Pypi,
+                "https://files.pythonhosted.org/packages/ab/cd/pkg-1.0-py3-none-any.whl#sha256=0"
+            )),
+            Some("pypi/pkg-1.0-py3-none-any.whl".to_string())
+        );
+
+        // Local and git packages can't be downloaded
+        assert_eq!(artifact_path(&package(PackageKind::Pypi, "./pkg")), None);
+        assert_eq!(
+            artifact_path(&package(
+                PackageKind::Pypi,
+                "git+https://github.com/org/pkg"
+            )),
+            None
+        );
+    }
+}
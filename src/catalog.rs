@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::backends::{Backend, BackendError, RepositoryInfo};
+use crate::common::{get_araki_cache, get_project_dirs};
+use crate::lockfile::{LockFile, PackageConstraint};
+use crate::network;
+use crate::preflight;
+
+/// Directory of the araki cache holding the names of the lockspecs of each listed org.
+const LISTINGS_DIR: &str = "listings";
+
+/// Locked packages of a remote lockspec, cached until its repository is updated again.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CachedPackages {
+    /// When the repository was last updated, as reported by the backend
+    updated: Option<String>,
+
+    /// Locked versions of each package, on any platform
+    packages: BTreeMap<String, Vec<String>>,
+}
+
+/// Get the path of the file caching the locked packages of a remote lockspec.
+///
+/// * `org`: Organization containing the lockspec
+/// * `name`: Name of the lockspec
+fn cache_file(org: &str, name: &str) -> Result<PathBuf, String> {
+    let cache = get_araki_cache().map_err(|err| format!("Unable to get the araki cache: {err}"))?;
+    Ok(cache
+        .join("lockspecs")
+        .join(org)
+        .join(format!("{name}.json")))
+}
+
+/// Get the locked versions of each package of a remote lockspec, keyed by package name.
+///
+/// The packages are read from the pixi.lock of the repository's default branch, and cached
+/// until the backend reports that the repository was updated. Offline, the cache is used
+/// however old it is.
+///
+/// * `backend`: Backend hosting the lockspec
+/// * `org`: Organization containing the lockspec
+/// * `repository`: Repository of the lockspec, as listed by the backend
+pub async fn locked_packages(
+    backend: &dyn Backend,
+    org: &str,
+    repository: &RepositoryInfo,
+) -> Result<BTreeMap<String, Vec<String>>, BackendError> {
+    let path = cache_file(org, &repository.name)?;
+    let cached: Option<CachedPackages> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+    if let Some(cached) = cached
+        && (network::is_offline()
+            || (cached.updated.is_some() && cached.updated == repository.updated))
+    {
+        return Ok(cached.packages);
+    }
+    network::require_online(&format!("read the packages of {org}/{}", repository.name))?;
+
+    let lockfile = LockFile::parse(
+        &backend
+            .read_file(org, &repository.name, "pixi.lock")
+            .await?,
+    )?;
+    let mut packages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for package in lockfile.packages {
+        let versions = packages.entry(package.name).or_default();
+        if !versions.contains(&package.version) {
+            versions.push(package.version);
+        }
+    }
+    let cached = CachedPackages {
+        updated: repository.updated.clone(),
+        packages,
+    };
+    if let Some(parent) = path.parent() {
+        let _ = preflight::create_dir(parent);
+    }
+    let _ = fs::write(&path, serde_json::to_string(&cached)?);
+    Ok(cached.packages)
+}
+
+/// Get the path of the file caching the names of the lockspecs of an org.
+///
+/// * `org`: Organization containing the lockspecs
+fn listing_file(org: &str) -> Result<PathBuf, String> {
+    let cache = get_araki_cache().map_err(|err| format!("Unable to get the araki cache: {err}"))?;
+    Ok(cache.join(LISTINGS_DIR).join(format!("{org}.json")))
+}
+
+/// Cache the names of the lockspecs of an org, as listed by the backend, so that they can be
+/// completed without reaching the backend.
+///
+/// * `org`: Organization containing the lockspecs
+/// * `repositories`: Every lockspec repository of the org
+pub fn record_listing(org: &str, repositories: &[RepositoryInfo]) -> Result<(), String> {
+    let path = listing_file(org)?;
+    if let Some(parent) = path.parent() {
+        preflight::create_dir(parent)?;
+    }
+    let names: Vec<&str> = repositories.iter().map(|repo| repo.name.as_str()).collect();
+    let contents = serde_json::to_string(&names)
+        .map_err(|err| format!("Unable to serialize the listing of {org}: {err}"))?;
+    fs::write(&path, contents).map_err(|err| format!("Unable to write {path:?}: {err}"))
+}
+
+/// Get the remote lockspecs cached by earlier listings, as `<org>/<name>`.
+pub fn cached_listings() -> Vec<String> {
+    // get_araki_cache would create the cache, printing to stdout in the middle of completions
+    let Ok(dirs) = get_project_dirs() else {
+        return vec![];
+    };
+    let Ok(entries) = fs::read_dir(dirs.cache_dir().join(LISTINGS_DIR)) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let org = path.file_stem()?.to_str()?.to_string();
+            let names: Vec<String> = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some(names.into_iter().map(move |name| format!("{org}/{name}")))
+        })
+        .flatten()
+        .collect()
+}
+
+/// Check whether locked packages satisfy every constraint: each constrained package is locked,
+/// at a matching version on at least one platform.
+///
+/// * `packages`: Locked versions of each package, keyed by package name
+/// * `constraints`: Constraints to check
+pub fn satisfies(
+    packages: &BTreeMap<String, Vec<String>>,
+    constraints: &[PackageConstraint],
+) -> bool {
+    constraints.iter().all(|constraint| {
+        packages
+            .get(&constraint.name)
+            .is_some_and(|versions| versions.iter().any(|version| constraint.matches(version)))
+    })
+}
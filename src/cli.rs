@@ -1,10 +1,50 @@
+pub mod alias;
+pub mod audit;
 pub mod auth;
+pub mod backend;
+pub mod bundle;
+pub mod changelog;
 pub mod checkout;
+pub mod ci;
 pub mod clone;
+pub mod completion;
+pub mod config;
+pub mod delete;
+pub mod envrc;
+pub mod exec;
+pub mod export;
+pub mod gc;
+pub mod global;
+pub mod ide;
+pub mod info;
 pub mod init;
+pub mod kernel;
+pub mod licenses;
+pub mod lint;
 pub mod list;
+pub mod merge_lock;
+pub mod migrate;
+pub mod outdated;
+pub mod projects;
 pub mod pull;
 pub mod push;
+pub mod registry;
+pub mod relink;
+pub mod run;
+pub mod sbom;
+pub mod serve;
 pub mod shell;
 pub mod shim;
+pub mod stale;
+pub mod stats;
+pub mod status;
+pub mod sync;
 pub mod tag;
+pub mod task;
+pub mod tools;
+pub mod unbundle;
+pub mod untag;
+pub mod update;
+pub mod verify;
+pub mod which;
+pub mod why;
@@ -0,0 +1,97 @@
+use clap::Parser;
+use std::process::exit;
+
+use araki::settings::{get_settings, update_user_settings};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: AliasSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum AliasSubcommand {
+    /// Define a short name for a lockspec repository in the user settings
+    Set(SetArgs),
+
+    /// Remove an alias from the user settings
+    Remove(RemoveArgs),
+
+    /// List the defined aliases
+    List,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetArgs {
+    /// Short name to use instead of the repository
+    name: String,
+
+    /// URL or <github org>/<repo name> of the lockspec
+    env: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct RemoveArgs {
+    /// Alias to remove
+    name: String,
+}
+
+pub fn execute(args: Args) {
+    match args.subcommand {
+        AliasSubcommand::Set(args) => {
+            if args.name.contains(['/', '#', ':']) {
+                eprintln!("Aliases cannot contain '/', '#', or ':'.");
+                exit(1);
+            }
+            update_user_settings(|settings| {
+                let aliases = settings
+                    .entry("aliases")
+                    .or_insert(toml::Value::Table(toml::Table::new()));
+                if let Some(aliases) = aliases.as_table_mut() {
+                    aliases.insert(args.name.clone(), args.env.clone().into());
+                }
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("{err}");
+                exit(1);
+            });
+            println!("{} -> {}", args.name, args.env);
+        }
+        AliasSubcommand::Remove(args) => {
+            let mut removed = false;
+            update_user_settings(|settings| {
+                if let Some(aliases) = settings
+                    .get_mut("aliases")
+                    .and_then(|aliases| aliases.as_table_mut())
+                {
+                    removed = aliases.remove(&args.name).is_some();
+                }
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("{err}");
+                exit(1);
+            });
+            if !removed {
+                eprintln!(
+                    "No alias named {} is defined in the user settings.",
+                    args.name
+                );
+                exit(1);
+            }
+            println!("Removed alias {}", args.name);
+        }
+        AliasSubcommand::List => {
+            let settings = get_settings().unwrap_or_else(|err| {
+                eprintln!("{err}");
+                exit(1);
+            });
+            if settings.aliases.is_empty() {
+                println!("No aliases are defined.");
+            }
+            for (name, env) in settings.aliases {
+                println!("{name} -> {env}");
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use clap::{Parser, ValueEnum};
+use console::style;
+use std::env::current_dir;
+use std::process::exit;
+
+use araki::audit::{self, Severity};
+use araki::common::LockSpec;
+use araki::lockfile::LockFile;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum FailOn {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl From<FailOn> for Severity {
+    fn from(value: FailOn) -> Self {
+        match value {
+            FailOn::Low => Severity::Low,
+            FailOn::Medium => Severity::Medium,
+            FailOn::High => Severity::High,
+            FailOn::Critical => Severity::Critical,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Exit with an error if a vulnerability of at least this severity is found
+    #[arg(long, value_enum)]
+    fail_on: Option<FailOn>,
+}
+
+pub async fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let lockfile = LockFile::from_path(&lockspec.lockfile()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let report = audit::audit(&lockfile).await.unwrap_or_else(|err| {
+        eprintln!("Unable to query the OSV database: {err}");
+        exit(1);
+    });
+
+    for vuln in &report.vulnerabilities {
+        let severity = match vuln.severity {
+            Severity::Critical | Severity::High => style(vuln.severity).bold().red(),
+            Severity::Medium => style(vuln.severity).bold().yellow(),
+            _ => style(vuln.severity).bold(),
+        };
+        let fixed = if vuln.fixed.is_empty() {
+            "no fix available".to_string()
+        } else {
+            format!("fixed in {}", vuln.fixed.join(", "))
+        };
+        println!(
+            "{} {} {} [{severity}] ({fixed})",
+            vuln.package, vuln.version, vuln.id
+        );
+        if let Some(summary) = &vuln.summary {
+            println!("  {summary}");
+        }
+    }
+    println!(
+        "Found {} vulnerabilities in {} audited packages.",
+        report.vulnerabilities.len(),
+        report.audited
+    );
+    if !report.skipped.is_empty() {
+        println!(
+            "{} conda packages were not audited, since OSV does not cover non-Python conda \
+            packages.",
+            report.skipped.len()
+        );
+    }
+
+    if let Some(fail_on) = args.fail_on {
+        let threshold = Severity::from(fail_on);
+        if report
+            .vulnerabilities
+            .iter()
+            .any(|vuln| vuln.severity >= threshold)
+        {
+            eprintln!("Found vulnerabilities with severity {threshold} or higher.");
+            exit(1);
+        }
+    }
+}
@@ -1,8 +1,15 @@
-use crate::backends::Backend;
 use clap::Parser;
+use console::style;
+use serde_json::json;
+use std::io::{self, Read};
 use std::process::exit;
 
-use crate::backends;
+use araki::backends;
+use araki::settings::get_settings;
+
+/// Exit code of `araki auth token` when no token is cached, so that scripts can tell it apart
+/// from other failures.
+const NO_TOKEN_EXIT_CODE: i32 = 3;
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -14,22 +21,173 @@ pub struct Args {
 #[command(arg_required_else_help = true)]
 pub enum AuthSubcommand {
     // Log in to the configured backend
-    Login,
+    Login(LoginArgs),
+
+    /// Forget the token stored for the configured backend
+    Logout,
+
+    /// Show which account the stored token of the configured backend authenticates as
+    Status(StatusArgs),
+
+    /// Print the token araki uses for the configured backend, e.g. to call its API from CI.
+    /// Exits with code 3 if no token is cached
+    Token(TokenArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct LoginArgs {
+    /// Log in with a personal access token instead of the browser, e.g. in orgs which don't
+    /// allow OAuth apps. Prefer --with-token, which keeps the token out of the shell history
+    #[arg(long, value_name = "TOKEN", conflicts_with = "with_token")]
+    token: Option<String>,
+
+    /// Log in with a personal access token read from stdin, e.g.
+    /// `araki auth login --with-token < token.txt`
+    #[arg(long)]
+    with_token: bool,
+
+    /// Permission to request on top of the ones araki always needs, e.g. `delete_repo` to
+    /// delete repositories with `araki delete --remote`; may be repeated
+    #[arg(long = "scope", value_name = "SCOPE", conflicts_with_all = ["token", "with_token"])]
+    scopes: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct TokenArgs {
+    /// Only print the token, without any other output
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct StatusArgs {
+    /// Print the status as JSON
+    #[arg(long)]
+    json: bool,
 }
 
 pub async fn execute(args: Args) {
     match args.subcommand {
-        AuthSubcommand::Login => {
+        AuthSubcommand::Login(args) => {
+            let token = match (args.token, args.with_token) {
+                (Some(token), _) => Some(token),
+                (None, true) => {
+                    let mut token = String::new();
+                    io::stdin()
+                        .read_to_string(&mut token)
+                        .unwrap_or_else(|err| {
+                            eprintln!("Unable to read the token from stdin: {err}");
+                            exit(1);
+                        });
+                    Some(token)
+                }
+                (None, false) => None,
+            };
             let backend = backends::get_current_backend().unwrap_or_else(|err| {
                 eprintln!("Unable to get the current backend: {err}");
                 exit(1);
             });
-            backend.login().await.unwrap_or_else(|err| {
+            let result = match token.as_deref().map(str::trim) {
+                Some("") => {
+                    eprintln!("The token is empty.");
+                    exit(1);
+                }
+                Some(token) => backend.login_with_token(token).await,
+                None => backend.login(&args.scopes).await,
+            };
+            result.unwrap_or_else(|err| {
                 eprintln!("Unable to login: {err}");
                 exit(1);
             });
 
             println!("Successfully authenticated.");
         }
+        AuthSubcommand::Logout => {
+            let backend = backends::get_current_backend().unwrap_or_else(|err| {
+                eprintln!("Unable to get the current backend: {err}");
+                exit(1);
+            });
+            backend.logout().await.unwrap_or_else(|err| {
+                eprintln!("Unable to log out: {err}");
+                exit(1);
+            });
+            println!(
+                "Logged out. The token stays valid until it expires or is revoked with the backend."
+            );
+        }
+        AuthSubcommand::Status(args) => status(args).await,
+        AuthSubcommand::Token(args) => token(args).await,
+    }
+}
+
+/// Print the cached token of the configured backend.
+async fn token(args: TokenArgs) {
+    let backend = backends::get_current_backend().unwrap_or_else(|err| {
+        eprintln!("Unable to get the current backend: {err}");
+        exit(1);
+    });
+    match backend.token().await {
+        Ok(Some(token)) => {
+            if !args.quiet {
+                eprintln!("Token araki uses for the configured backend; keep it secret:");
+            }
+            println!("{token}");
+        }
+        Ok(None) => {
+            if !args.quiet {
+                eprintln!("No token is cached; run `araki auth login` first.");
+            }
+            exit(NO_TOKEN_EXIT_CODE);
+        }
+        Err(err) => {
+            eprintln!("Unable to get the token: {err}");
+            exit(1);
+        }
+    }
+}
+
+/// Print the backend in use, and the account, scopes and expiry of its stored token.
+async fn status(args: StatusArgs) {
+    let settings = get_settings().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let name = settings.backend;
+    let backend = backends::get_current_backend().unwrap_or_else(|err| {
+        eprintln!("Unable to get the current backend: {err}");
+        exit(1);
+    });
+    let status = backend.auth_status().await.unwrap_or_else(|err| {
+        eprintln!("{} backend: {err}", style(&name).bold());
+        exit(1);
+    });
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(
+                &json!({ "backend": name, "profile": settings.profile, "status": status })
+            )
+            .unwrap_or_default()
+        );
+        return;
+    }
+    match &settings.profile {
+        Some(profile) => println!(
+            "{} backend ({}), profile {}",
+            style(&name).bold(),
+            status.host,
+            style(profile).bold()
+        ),
+        None => println!("{} backend ({})", style(&name).bold(), status.host),
+    }
+    println!("  Logged in as {}", style(&status.login).bold().green());
+    match &status.scopes {
+        Some(scopes) if scopes.is_empty() => println!("  Token scopes: none"),
+        Some(scopes) => println!("  Token scopes: {}", scopes.join(", ")),
+        None => println!("  Token scopes: not reported"),
     }
+    println!(
+        "  Token expires: {}",
+        status.expires_at.as_deref().unwrap_or("no expiry reported")
+    );
 }
@@ -0,0 +1,127 @@
+use clap::Parser;
+use console::style;
+use serde_json::json;
+use std::process::exit;
+
+use araki::backends::{self, HealthStatus};
+use araki::common;
+use araki::remote::{RemoteRepo, parse_repo_arg};
+use araki::settings::get_settings;
+
+use crate::cli::init::ORG;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: BackendSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum BackendSubcommand {
+    /// List the backends which can be selected with the `backend` setting
+    List(ListArgs),
+
+    /// Check that the configured backend is reachable and usable, step by step
+    Ping(PingArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct ListArgs {
+    /// Print the backends as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct PingArgs {
+    /// Organization to check access to. Defaults to the org of this lockspec's origin
+    #[arg(long)]
+    org: Option<String>,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn execute(args: Args) {
+    match args.subcommand {
+        BackendSubcommand::List(args) => list(args),
+        BackendSubcommand::Ping(args) => ping(args).await,
+    }
+}
+
+/// Print the registered backends, marking the one selected by the `backend` setting.
+fn list(args: ListArgs) {
+    let selected = get_settings()
+        .map(|settings| settings.backend)
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+    let names = backends::registered_backends();
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({ "selected": selected, "backends": names }))
+                .unwrap_or_default()
+        );
+        return;
+    }
+    for name in &names {
+        match *name == selected {
+            true => println!("{} {}", style("*").bold().green(), style(name).bold()),
+            false => println!("  {name}"),
+        }
+    }
+    if !names.contains(&selected) {
+        eprintln!("The `backend` setting selects '{selected}', which is not registered.");
+        exit(1);
+    }
+}
+
+async fn ping(args: PingArgs) {
+    let org = args.org.unwrap_or_else(|| {
+        common::get_araki_git_repo()
+            .ok()
+            .and_then(|repo| {
+                let origin = repo.find_remote("origin").ok()?;
+                parse_repo_arg(origin.url()?).ok()
+            })
+            .map(|remote: RemoteRepo| remote.get_org())
+            .unwrap_or(ORG.to_string())
+    });
+    let backend = backends::get_current_backend().unwrap_or_else(|err| {
+        eprintln!("Unable to get the current backend: {err}");
+        exit(1);
+    });
+
+    let checks = backend.ping(&org).await;
+    let failed = checks
+        .iter()
+        .any(|check| check.status == HealthStatus::Failed);
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({ "org": org, "checks": checks }))
+                .unwrap_or_default()
+        );
+    } else {
+        for check in &checks {
+            let status = style(format!("{:<7}", check.status));
+            let status = match check.status {
+                HealthStatus::Ok => status.bold().green(),
+                HealthStatus::Failed => status.bold().red(),
+                HealthStatus::Skipped => status.dim(),
+            };
+            let latency = check
+                .latency_ms
+                .map(|ms| format!(" ({ms} ms)"))
+                .unwrap_or_default();
+            println!("{:<4} {status} {}{latency}", check.name, check.detail);
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
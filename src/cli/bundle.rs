@@ -0,0 +1,118 @@
+use clap::Parser;
+use clap_complete::ArgValueCompleter;
+use std::env::{current_dir, temp_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::cli::completion;
+use crate::output;
+use araki::bundle;
+use araki::common::LockSpec;
+use araki::ops::{self, Progress};
+use araki::rebuild::current_platform;
+use araki::remote::parse_repo_arg;
+use araki::settings::get_settings;
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct Args {
+    /// URL, <org>/<repo>, or alias of the lockspec to bundle, or a directory containing one
+    #[arg(value_name = "NAME", add = ArgValueCompleter::new(completion::environments))]
+    env: String,
+
+    /// Path of the bundle to write. Defaults to <name>.araki in the current directory
+    // `--output` is the global output format
+    #[arg(short = 'o', long = "out", value_name = "PATH")]
+    out: Option<PathBuf>,
+
+    /// Tag, branch, or commit to bundle instead of the default branch, or instead of the
+    /// checked out lockspec of a directory
+    #[arg(short, long)]
+    tag: Option<String>,
+
+    /// Platform to bundle the artifacts of, e.g. linux-64; can be repeated. Defaults to the
+    /// platform araki runs on
+    #[arg(long = "platform", value_name = "PLATFORM")]
+    platforms: Vec<String>,
+}
+
+pub async fn execute(args: Args) {
+    let platforms = match args.platforms.is_empty() {
+        true => vec![
+            current_platform()
+                .unwrap_or_else(|| output::fail("Unsupported platform; pass --platform instead."))
+                .to_string(),
+        ],
+        false => args.platforms.clone(),
+    };
+
+    // Remote lockspecs are cloned to a scratch directory, at the tag if one is given
+    let scratch = temp_dir().join(format!("araki-bundle-{}", Uuid::new_v4()));
+    let local = LockSpec::from_path(&args.env).ok();
+    let source = match local {
+        Some(lockspec) => Ok((lockspec, args.tag.as_deref())),
+        None => fetch(&args, &scratch).map(|lockspec| (lockspec, None)),
+    };
+    let result = match source {
+        Ok((lockspec, rev)) => {
+            let output = args.out.clone().unwrap_or_else(|| {
+                current_dir()
+                    .unwrap_or_else(|err| {
+                        output::fail(&format!("Could not get the current directory: {err}"))
+                    })
+                    .join(format!("{}.araki", lockspec.display_name()))
+            });
+            bundle::bundle(
+                &lockspec,
+                rev,
+                &platforms,
+                &output,
+                &|progress| match progress {
+                    Progress::Phase {
+                        step,
+                        total,
+                        message,
+                    } => output::phase(step, total, message),
+                    Progress::Transfer { received, total } => output::progress(received, total),
+                },
+            )
+            .await
+            .map(|manifest| (manifest, output))
+            .map_err(|err| err.to_string())
+        }
+        Err(err) => Err(err),
+    };
+    let _ = fs::remove_dir_all(&scratch);
+
+    let (manifest, output) = result.unwrap_or_else(|err| output::fail(&err));
+    for url in &manifest.skipped {
+        output::warning(&format!(
+            "{url} can't be downloaded, and isn't in the bundle"
+        ));
+    }
+    output::success(&format!(
+        "Bundled {} with {} artifacts for {} to {output:?}",
+        manifest.name,
+        manifest.artifacts.len(),
+        manifest.platforms.join(", ")
+    ));
+}
+
+/// Clone the remote lockspec to bundle to the scratch directory, without installing it.
+///
+/// * `args`: Arguments of the command
+/// * `scratch`: Scratch directory, which doesn't exist yet
+fn fetch(args: &Args, scratch: &Path) -> Result<LockSpec, String> {
+    let env = get_settings()?.resolve_alias(&args.env);
+    let remote = parse_repo_arg(&env)
+        .map_err(|err| format!("{env} is not a lockspec directory or repository: {err}"))?;
+    output::message(&format!("Cloning {remote}..."));
+    ops::fetch_lockspec(
+        &remote,
+        scratch,
+        args.tag.as_deref(),
+        None,
+        &output::progress,
+    )
+}
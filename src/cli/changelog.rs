@@ -0,0 +1,153 @@
+use clap::Parser;
+use clap_complete::ArgValueCompleter;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::cli::completion;
+use crate::output;
+use araki::lockfile::{PackageChange, PackageKind, count_changes};
+use araki::{common, ops};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Tags or commits to compare, as FROM..TO; TO defaults to HEAD. Compares the two newest
+    /// tags if not given
+    #[arg(value_name = "FROM..TO", add = ArgValueCompleter::new(completion::tags))]
+    range: Option<String>,
+
+    /// Print the changes as JSON
+    #[arg(long, conflicts_with = "markdown")]
+    json: bool,
+
+    /// Print the changes as Markdown, e.g. for release notes or pull request descriptions
+    #[arg(long)]
+    markdown: bool,
+}
+
+pub fn execute(args: Args) {
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    let (from, to) = match &args.range {
+        Some(range) => match range.split_once("..") {
+            Some((from, "")) => (from.to_string(), "HEAD".to_string()),
+            Some((from, to)) => (from.to_string(), to.to_string()),
+            None => (range.to_string(), "HEAD".to_string()),
+        },
+        None => match ops::newest_tags(&repo)
+            .unwrap_or_else(|err| output::fail(&err))
+            .as_slice()
+        {
+            [to, from, ..] => (from.clone(), to.clone()),
+            _ => output::fail("The environment needs two tags to compare; pass FROM..TO instead."),
+        },
+    };
+    let changes = ops::changelog(&repo, &from, &to).unwrap_or_else(|err| output::fail(&err));
+
+    if args.json {
+        println!("{}", render_json(&from, &to, &changes));
+    } else if args.markdown {
+        print!("{}", render_markdown(&from, &to, &changes));
+    } else {
+        print!("{}", render_text(&from, &to, &changes));
+    }
+}
+
+/// Render the package changes as plain text, one change per line.
+///
+/// * `from`: Older revision
+/// * `to`: Newer revision
+/// * `changes`: Package changes, grouped by ecosystem
+fn render_text(
+    from: &str,
+    to: &str,
+    changes: &BTreeMap<PackageKind, Vec<PackageChange>>,
+) -> String {
+    if changes.is_empty() {
+        return format!("No package changes from {from} to {to}\n");
+    }
+    let all: Vec<PackageChange> = changes.values().flatten().cloned().collect();
+    let mut text = format!(
+        "Package changes from {from} to {to}: {}\n",
+        count_changes(&all)
+    );
+    for (kind, changes) in changes {
+        text.push_str(&format!("\n{kind} ({})\n", count_changes(changes)));
+        for change in changes {
+            text.push_str(&format!("  {change}\n"));
+        }
+    }
+    text
+}
+
+/// Render the package changes as Markdown, with a section per ecosystem.
+///
+/// * `from`: Older revision
+/// * `to`: Newer revision
+/// * `changes`: Package changes, grouped by ecosystem
+fn render_markdown(
+    from: &str,
+    to: &str,
+    changes: &BTreeMap<PackageKind, Vec<PackageChange>>,
+) -> String {
+    let mut markdown = format!("## Package changes from `{from}` to `{to}`\n");
+    if changes.is_empty() {
+        markdown.push_str("\nNo package changes.\n");
+        return markdown;
+    }
+    for (kind, changes) in changes {
+        markdown.push_str(&format!("\n### {kind} ({})\n\n", count_changes(changes)));
+        for change in changes {
+            let line = match change {
+                PackageChange::Added { name, version } => format!("Added `{name}` {version}"),
+                PackageChange::Removed { name, version } => {
+                    format!("Removed `{name}` {version}")
+                }
+                PackageChange::Updated { name, from, to } => {
+                    format!("Updated `{name}` {from} → {to}")
+                }
+            };
+            markdown.push_str(&format!("- {line}\n"));
+        }
+    }
+    markdown
+}
+
+/// Render the package changes as JSON, with an object per change.
+///
+/// * `from`: Older revision
+/// * `to`: Newer revision
+/// * `changes`: Package changes, grouped by ecosystem
+fn render_json(
+    from: &str,
+    to: &str,
+    changes: &BTreeMap<PackageKind, Vec<PackageChange>>,
+) -> String {
+    let changes: Vec<_> = changes
+        .iter()
+        .flat_map(|(kind, changes)| {
+            changes.iter().map(move |change| match change {
+                PackageChange::Added { name, version } => json!({
+                    "ecosystem": kind.to_string(),
+                    "change": "added",
+                    "name": name,
+                    "to": version,
+                }),
+                PackageChange::Removed { name, version } => json!({
+                    "ecosystem": kind.to_string(),
+                    "change": "removed",
+                    "name": name,
+                    "from": version,
+                }),
+                PackageChange::Updated { name, from, to } => json!({
+                    "ecosystem": kind.to_string(),
+                    "change": "updated",
+                    "name": name,
+                    "from": from,
+                    "to": to,
+                }),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({"from": from, "to": to, "changes": changes}))
+        .unwrap_or_default()
+}
@@ -1,41 +1,98 @@
 use clap::Parser;
-use std::process::{Command, exit};
+use clap_complete::ArgValueCompleter;
+use std::path::PathBuf;
 
-use crate::common;
+use crate::cli::completion;
+use crate::output;
+use araki::common::LockSpec;
+use araki::{common, ops};
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
-    // name of the tag
-    #[arg(help = "Name of the tag")]
-    tag: String,
+    /// Tag, branch, or commit SHA to checkout; `latest` is the tip of main, `-` is the
+    /// previous checkout, and `@{-N}` the checkout N steps back
+    #[arg(required_unless_present = "history", add = ArgValueCompleter::new(completion::tags))]
+    tag: Option<String>,
+
+    /// List previous checkouts
+    #[arg(long, conflicts_with = "tag")]
+    history: bool,
+
+    /// Checkout into this directory and install the environment there, instead of replacing the
+    /// current checkout
+    #[arg(long, value_name = "DIR", conflicts_with = "history")]
+    path: Option<PathBuf>,
+
+    /// Stash uncommitted changes to the lockspec instead of refusing to checkout
+    #[arg(long)]
+    force: bool,
 }
 
 pub fn execute(args: Args) {
-    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
-        eprintln!("Couldn't recognize the araki repo: {err}");
-        exit(1);
-    });
-
-    let git_ref = if args.tag == "latest" {
-        repo.find_reference("refs/heads/main")
-            .expect("No tag found")
-    } else {
-        repo.find_reference(&format!("refs/tags/{}", args.tag))
-            .expect("No tag found")
-    };
-
-    let git_ref_object = git_ref.peel(git2::ObjectType::Commit).unwrap();
-    let commit = git_ref_object
-        .as_commit()
-        .ok_or_else(|| git2::Error::from_str("Tag did not peel to a commit"))
-        .unwrap();
-    repo.checkout_tree(commit.as_object(), None)
-        .expect("Unable to checkout tag");
-    repo.set_head_detached(commit.id())
-        .expect("Unable to set head");
-
-    let _ = Command::new("pixi")
-        .arg("install")
-        .output()
-        .expect("Failed to execute command");
+    let mut repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    if args.history {
+        return print_history(&repo);
+    }
+    let tag = args.tag.unwrap_or_default();
+    let tag = ops::expand_previous_checkout(&repo, &tag).unwrap_or_else(|err| output::fail(&err));
+
+    if let Some(path) = args.path {
+        output::phase(
+            1,
+            1,
+            &format!("Checking out {tag} into {}...", path.display()),
+        );
+        ops::checkout_to_path(&repo, &tag, &path).unwrap_or_else(|err| output::fail(&err));
+        output::success(&format!("Checked out {tag} into {}", path.display()));
+        return;
+    }
+
+    let modified = repo
+        .workdir()
+        .map(LockSpec::from_path)
+        .and_then(Result::ok)
+        .and_then(|lockspec| ops::status(&lockspec, &repo).ok())
+        .map(|status| status.modified)
+        .unwrap_or_default();
+    if !modified.is_empty() {
+        if !args.force {
+            output::fail(&format!(
+                "{} {} uncommitted changes. Tag them with `araki tag`, or pass --force to stash \
+                them before checking out {}.",
+                modified.join(" and "),
+                if modified.len() == 1 { "has" } else { "have" },
+                tag
+            ));
+        }
+        let stash = ops::stash(&mut repo, &format!("araki: before checking out {tag}"))
+            .unwrap_or_else(|err| output::fail(&err));
+        output::warning(&format!(
+            "Stashed changes to {} as {stash:.7}. Recover them with \
+            `git --git-dir=.araki-git --work-tree=. stash pop`.",
+            modified.join(" and ")
+        ));
+    }
+
+    output::phase(1, 1, &format!("Checking out {tag}..."));
+    ops::checkout(&repo, &tag).unwrap_or_else(|err| output::fail(&err));
+    output::success(&format!("Checked out {tag}"));
+}
+
+/// Print the checkout history, labelling each entry with the reference that checks it out.
+///
+/// * `repo`: araki repo
+fn print_history(repo: &git2::Repository) {
+    let history = ops::checkout_history(repo);
+    if history.is_empty() {
+        println!("Nothing has been checked out yet.");
+        return;
+    }
+    for (steps, record) in history.iter().enumerate() {
+        let label = match steps {
+            0 => "*".to_string(),
+            steps => format!("@{{-{steps}}}"),
+        };
+        println!("{label:>6}  {:.7}  {}", record.commit, record.reference);
+    }
 }
@@ -0,0 +1,109 @@
+use clap::{Parser, ValueEnum};
+use std::env::current_dir;
+use std::process::exit;
+
+use crate::cli::export::write_files;
+use araki::{common, export, ops};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: CiSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum CiSubcommand {
+    /// Generate a CI workflow which reproduces the environment
+    Generate(GenerateArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Provider {
+    /// .github/workflows/araki.yml
+    GithubActions,
+
+    /// .gitlab-ci.yml
+    GitlabCi,
+}
+
+#[derive(Parser, Debug)]
+pub struct GenerateArgs {
+    /// CI provider to generate a workflow for
+    #[arg(long, value_enum)]
+    provider: Provider,
+
+    /// Tag of the environment to install in CI. Defaults to the most recent tag
+    #[arg(long)]
+    tag: Option<String>,
+
+    /// Overwrite an existing workflow file
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn execute(args: Args) {
+    match args.subcommand {
+        CiSubcommand::Generate(args) => generate(args),
+    }
+}
+
+fn generate(args: GenerateArgs) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
+        eprintln!("Couldn't recognize the araki repo: {err}");
+        exit(1);
+    });
+    let remote = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|remote| remote.url().map(|url| url.to_string()))
+        .unwrap_or_else(|| {
+            eprintln!("The araki repo has no 'origin' remote to clone the environment from.");
+            exit(1);
+        });
+
+    let tag = args.tag.unwrap_or_else(|| {
+        let tags = ops::list_tags(&repo).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+        latest_tag(&repo, tags).unwrap_or_else(|| {
+            eprintln!("The environment has no tags. Create one with `araki tag` first.");
+            exit(1);
+        })
+    });
+
+    let file = match args.provider {
+        Provider::GithubActions => (
+            cwd.join(".github").join("workflows").join("araki.yml"),
+            export::render_github_actions(&remote, &tag),
+        ),
+        Provider::GitlabCi => (
+            cwd.join(".gitlab-ci.yml"),
+            export::render_gitlab_ci(&remote, &tag),
+        ),
+    };
+    write_files(&[file], args.force);
+}
+
+/// Get the name of the tag pointing at the most recent commit.
+///
+/// * `repo`: araki repo the tags belong to
+/// * `tags`: Tags to choose from
+fn latest_tag(repo: &git2::Repository, tags: Vec<ops::TagInfo>) -> Option<String> {
+    tags.into_iter()
+        .filter_map(|tag| {
+            let commit = repo
+                .revparse_single(&tag.name)
+                .ok()?
+                .peel_to_commit()
+                .ok()?;
+            Some((commit.time().seconds(), tag.name))
+        })
+        .max()
+        .map(|(_, name)| name)
+}
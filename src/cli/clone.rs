@@ -1,181 +1,111 @@
-use std::{
-    env::current_dir,
-    fmt::Display,
-    path::PathBuf,
-    process::{Command, exit},
-    str::FromStr,
-};
+use std::{env::current_dir, path::PathBuf, str::FromStr};
 
-use crate::common::{self, LockSpec};
-use clap::Parser;
-use regex::Regex;
+use crate::cli::completion;
+use crate::output;
+use araki::common;
+use araki::ops::{self, Progress};
+use araki::remote::Protocol;
+use araki::variant::{self, Variant};
+use clap::{Parser, ValueEnum};
+use clap_complete::ArgValueCompleter;
 
 #[derive(Parser, Debug, Default)]
 #[command(arg_required_else_help = true)]
 pub struct Args {
     /// URL or <github org>/<repo name> of the lockspec to grab
-    #[arg(value_name = "NAME")]
+    #[arg(value_name = "NAME", add = ArgValueCompleter::new(completion::environments))]
     env: String,
 
     /// Path where the lockspec should be cloned
     #[arg(short, long, value_name = "PATH")]
     path: Option<String>,
-}
 
-#[derive(Debug, Default)]
-pub struct RemoteRepo {
-    org: Option<String>,
-    repo: String,
-    domain: Option<String>,
-    protocol: Option<String>,
-}
+    /// Tag, branch, or commit to checkout and install instead of the default branch
+    #[arg(short, long)]
+    tag: Option<String>,
 
-impl RemoteRepo {
-    pub fn new(
-        org: Option<String>,
-        repo: String,
-        domain: Option<String>,
-        protocol: Option<String>,
-    ) -> RemoteRepo {
-        RemoteRepo {
-            org,
-            repo,
-            domain,
-            protocol,
-        }
-    }
-    /// Render the repository as a git url
-    pub fn as_url(&self) -> String {
-        format!(
-            "{}{}/{}/{}",
-            self.get_protocol(),
-            self.get_domain(),
-            self.get_org(),
-            self.get_repo(),
-        )
-    }
+    /// Protocol to clone with. By default ssh is tried first, then anonymous https for public
+    /// repositories
+    #[arg(long, value_enum)]
+    protocol: Option<CloneProtocol>,
 
-    /// Render the repository as an ssh URL
-    pub fn as_ssh_url(&self) -> String {
-        format!(
-            "git@{}:{}/{}.git",
-            self.get_domain(),
-            self.get_org(),
-            self.get_repo(),
-        )
-    }
+    /// pixi environment of the lockspec to install instead of the default one; can be repeated.
+    /// The selection is kept for later checkouts and syncs
+    #[arg(long = "environment", value_name = "ENVIRONMENT")]
+    environments: Vec<String>,
 
-    fn get_org(&self) -> String {
-        self.org.clone().unwrap_or("nos-environments".into())
-    }
-    fn get_repo(&self) -> String {
-        self.repo.clone()
-    }
-    fn get_protocol(&self) -> String {
-        self.protocol.clone().unwrap_or("https://".into())
-    }
-    fn get_domain(&self) -> String {
-        self.domain.clone().unwrap_or("github.com".into())
-    }
+    /// Hardware variant to install, from the pixi environment of the same name; `auto` picks
+    /// gpu if an NVIDIA GPU is present. The choice is kept for later checkouts, syncs, and runs
+    #[arg(long, value_enum, conflicts_with = "environments")]
+    variant: Option<CloneVariant>,
 }
 
-impl Display for RemoteRepo {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.as_url())
-    }
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CloneProtocol {
+    Ssh,
+    Https,
 }
 
-/// Clone the given lockspec URL
-///
-/// * `env`: Remote URL for an lockspec. If only <org>/<repo> is passed, the repository is
-///   assumed to live on github.
-fn parse_repo_arg(env: &str) -> Result<RemoteRepo, String> {
-    let re = Regex::new(
-        r"((?<protocol>(git\+)?https?://)?(?<domain>github\.com)/)?((?<org>[-a-zA-Z0-9_.]{1,100})/)?(?<repo>[-a-zA-Z0-9_.]{1,100}$)",
-    )
-    .map_err(|_| "Invalid regex for processing git url.")?;
-
-    let captures = re
-        .captures(env)
-        .ok_or(format!("Unrecognized format for repo name or URL: {env}."))?;
-
-    Ok(RemoteRepo::new(
-        captures.name("org").map(|name| name.as_str().to_string()),
-        captures
-            .name("repo")
-            .ok_or(format!("No repo name found in {env}"))?
-            .as_str()
-            .to_string(),
-        captures
-            .name("protocol")
-            .map(|name| name.as_str().to_string()),
-        captures
-            .name("domain")
-            .map(|name| name.as_str().to_string()),
-    ))
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CloneVariant {
+    Cpu,
+    Gpu,
+    Auto,
 }
 
 pub fn execute(args: Args) {
-    let cwd = current_dir().unwrap_or_else(|err| {
-        eprintln!("Could not get the current directory: {err}");
-        exit(1);
-    });
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
     let path = args
         .path
         .map(|p| {
-            PathBuf::from_str(&p).unwrap_or_else(|_| {
-                eprintln!("{p} is not a valid path.");
-                exit(1);
-            })
+            PathBuf::from_str(&p)
+                .unwrap_or_else(|_| output::fail(&format!("{p} is not a valid path.")))
         })
         .unwrap_or(cwd.clone());
-
-    // Check that the target directory has no existing lockspec pixi.lock and pixi.toml
-    if LockSpec::from_path(&path).is_ok() {
-        eprintln!("A lockspec already exists at {path:?}. Aborting.");
-        exit(1);
-    }
-
-    let remote = parse_repo_arg(&args.env).unwrap_or_else(|err| {
-        eprintln!("{} is not a valid lockspec repository: {err}", &args.env);
-        exit(1);
-    });
-
-    common::git_clone(remote.as_ssh_url(), &path).unwrap_or_else(|err| {
-        eprintln!("Unable to clone the lockspec: {err}");
-        exit(1);
-    });
-
-    if LockSpec::from_path(&path).is_err() {
-        eprintln!(
-            "The cloned lockspec repo is not valid. Is pixi.toml or pixi.lock missing from \
-                {}/{} ?",
-            remote.get_org(),
-            remote.get_repo()
-        );
-        exit(1);
+    let environments = match args.variant {
+        Some(CloneVariant::Cpu) => vec![Variant::Cpu.environment().to_string()],
+        Some(CloneVariant::Gpu) => vec![Variant::Gpu.environment().to_string()],
+        Some(CloneVariant::Auto) => {
+            let variant = variant::detect();
+            output::message(&format!(
+                "{}; installing the {variant} variant.",
+                match variant {
+                    Variant::Gpu => "Found an NVIDIA GPU",
+                    Variant::Cpu => "No NVIDIA GPU found",
+                }
+            ));
+            vec![variant.environment().to_string()]
+        }
+        None => args.environments,
+    };
+
+    ops::clone(
+        &args.env,
+        &path,
+        args.tag.as_deref(),
+        args.protocol.map(|protocol| match protocol {
+            CloneProtocol::Ssh => Protocol::Ssh,
+            CloneProtocol::Https => Protocol::Https,
+        }),
+        &environments,
+        &|progress| match progress {
+            Progress::Phase {
+                step,
+                total,
+                message,
+            } => output::phase(step, total, message),
+            Progress::Transfer { received, total } => output::progress(received, total),
+        },
+    )
+    .unwrap_or_else(|err| output::fail(&err));
+    if let Ok(repo) = common::open_araki_git_repo(&path) {
+        for warning in ops::staleness(&repo).unwrap_or_default() {
+            output::warning(&warning);
+        }
     }
-
-    // Install the pixi project.
-    // If this fails, remove the lockspec repository if it was cloned before,
-    // in addition to the hardlinked files.
-    let status = Command::new("pixi")
-        .args(["install", "--frozen", "--locked", "--color", "always"])
-        .current_dir(&path)
-        .status();
-
-    if status.is_err() || status.is_ok_and(|code| !code.success()) {
-        eprintln!("Failed to install the environment with pixi.");
-        match LockSpec::from_path(&path) {
-            Ok(env_lockspec) => {
-                env_lockspec.remove_files().unwrap_or_else(|rmerr| {
-                    eprintln!("Unable to clean up the lockspec in {path:?}: {rmerr}")
-                });
-            }
-            Err(othererr) => {
-                eprintln!("Unable to clean up the lockspec in {path:?}: {othererr}")
-            }
-        };
-        exit(1);
+    match &args.tag {
+        Some(tag) => output::success(&format!("Cloned {} at {tag} to {path:?}", args.env)),
+        None => output::success(&format!("Cloned {} to {path:?}", args.env)),
     }
 }
@@ -0,0 +1,54 @@
+use clap_complete::engine::CompletionCandidate;
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+
+use araki::settings::get_settings;
+use araki::{catalog, common, ops, projects};
+
+/// Complete the tags of the lockspec in the current directory, along with `latest`.
+///
+/// * `current`: Word being completed
+pub fn tags(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let Ok(repo) = common::get_araki_git_repo() else {
+        return vec![];
+    };
+    let mut candidates: Vec<CompletionCandidate> = ops::list_tags(&repo)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|tag| tag.name.starts_with(current.as_ref()))
+        .map(|tag| {
+            CompletionCandidate::new(tag.name).help(tag.message.and_then(|message| {
+                message
+                    .lines()
+                    .next()
+                    .map(|line| line.trim().to_string().into())
+            }))
+        })
+        .collect();
+    if "latest".starts_with(current.as_ref()) {
+        candidates.push(CompletionCandidate::new("latest").help(Some("Tip of main".into())));
+    }
+    candidates
+}
+
+/// Complete the names of environments araki knows of: aliases, the environments of the
+/// recorded projects, and the remote lockspecs seen by `araki list --remote`.
+///
+/// * `current`: Word being completed
+pub fn environments(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let mut names = BTreeSet::new();
+    if let Ok(settings) = get_settings() {
+        names.extend(settings.aliases.into_keys());
+    }
+    if let Ok(projects) = projects::load() {
+        names.extend(projects.into_values().map(|record| record.environment));
+    }
+    names.extend(catalog::cached_listings());
+    names
+        .into_iter()
+        .filter(|name| name.starts_with(current.as_ref()))
+        .map(CompletionCandidate::new)
+        .collect()
+}
@@ -0,0 +1,48 @@
+use clap::Parser;
+use std::process::exit;
+
+use crate::output;
+use araki::schema::Schema;
+use araki::settings::{Settings, check_settings_files};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: ConfigSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum ConfigSubcommand {
+    /// Print the JSON Schema of araki.toml, e.g. for editor completion and validation
+    Schema,
+
+    /// Check the user and project araki.toml for unknown keys and invalid values
+    Check,
+}
+
+pub fn execute(args: Args) {
+    match args.subcommand {
+        ConfigSubcommand::Schema => {
+            let schema = serde_json::to_string_pretty(&Settings::schema()).unwrap_or_else(|err| {
+                output::fail(&format!("Unable to serialize the schema: {err}"))
+            });
+            println!("{schema}");
+        }
+        ConfigSubcommand::Check => {
+            let problems = check_settings_files().unwrap_or_else(|err| output::fail(&err));
+            for (path, diagnostic) in &problems {
+                output::warning(&format!(
+                    "{}:{}: {}",
+                    path.display(),
+                    diagnostic.line,
+                    diagnostic.message
+                ));
+            }
+            if !problems.is_empty() {
+                exit(1);
+            }
+            output::success("The araki settings are valid");
+        }
+    }
+}
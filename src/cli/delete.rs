@@ -0,0 +1,109 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::fs;
+use std::io::ErrorKind;
+
+use crate::output;
+use araki::backends;
+use araki::common::{self, LockSpec};
+use araki::projects;
+use araki::remote::parse_repo_arg;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Also delete the repository of the lockspec from the backend, with all of its tags
+    #[arg(long)]
+    remote: bool,
+
+    /// Delete without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Print what would be deleted without deleting anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Delete the lockspec of the current directory: its araki repo, pixi.toml, pixi.lock, and the
+/// installed environment, and with `--remote` its repository on the backend.
+pub async fn execute(args: Args) {
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| output::fail(&err));
+
+    let remote = args.remote.then(|| {
+        // The repository holds the other lockspecs of the monorepo as well
+        if let Some(subdir) = common::lockspec_subdir(&repo) {
+            output::fail(&format!(
+                "The lockspec in {} is one of several in its remote repository; deleting the \
+                repository would delete the others. Delete it without --remote.",
+                subdir.display()
+            ));
+        }
+        let url = repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|origin| origin.url().map(|url| url.to_string()))
+            .unwrap_or_else(|| output::fail("The araki repo has no remote 'origin' to delete."));
+        let remote = parse_repo_arg(&url).unwrap_or_else(|err| {
+            output::fail(&format!("Unable to parse the URL of origin: {err}"))
+        });
+        (remote.get_org(), remote.get_repo())
+    });
+
+    let mut targets = vec![format!(
+        "the lockspec in {} (pixi.toml, pixi.lock, .araki-git, and the installed environment)",
+        lockspec.dir().display()
+    )];
+    if let Some((org, name)) = &remote {
+        targets.push(format!(
+            "the remote repository {org}/{name}, with all of its tags"
+        ));
+    }
+    if args.dry_run {
+        for target in &targets {
+            output::dry_run(&format!("Would delete {target}"));
+        }
+        return;
+    }
+    if !args.yes && !output::confirm(&format!("Delete {}?", targets.join(" and "))) {
+        output::fail("Nothing was deleted. Pass --yes to delete without confirmation.");
+    }
+
+    // The remote repository goes first, so that a failure leaves the local lockspec to retry from
+    if let Some((org, name)) = &remote {
+        let backend = backends::get_current_backend().unwrap_or_else(|err| {
+            output::fail(&format!("Unable to get the current backend: {err}"))
+        });
+        backend
+            .delete_repository(org, name)
+            .await
+            .unwrap_or_else(|err| output::fail(&format!("Unable to delete {org}/{name}: {err}")));
+        output::message(&format!("Deleted the remote repository {org}/{name}"));
+    }
+
+    let project = repo.workdir().map(|workdir| workdir.to_path_buf());
+    drop(repo);
+    lockspec
+        .remove_files()
+        .unwrap_or_else(|err| output::fail(&format!("Unable to delete the lockspec: {err}")));
+    let environment = lockspec.dir().join(".pixi");
+    match fs::remove_dir_all(&environment) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => output::fail(&format!("Unable to delete {environment:?}: {err}")),
+    }
+    if let Some(project) = project
+        && let Err(err) = projects::forget(&project)
+    {
+        output::warning(&format!(
+            "Unable to forget the project in {project:?}: {err}"
+        ));
+    }
+    output::success(&format!(
+        "Deleted the lockspec in {}",
+        lockspec.dir().display()
+    ));
+}
@@ -0,0 +1,106 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::fs;
+use std::process::exit;
+
+use araki::common::LockSpec;
+
+const BLOCK_START: &str = "# >>> araki >>>";
+const BLOCK_END: &str = "# <<< araki <<<";
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Print the .envrc instead of writing it
+    #[arg(long)]
+    stdout: bool,
+}
+
+/// Get the araki section of the .envrc, which activates the pixi environment of the lockspec,
+/// exports the environment variables declared for it, reloads it whenever the lockspec or the
+/// project settings change, and warns if the environment is out of date.
+fn envrc_block() -> String {
+    format!(
+        "{BLOCK_START}\n\
+        watch_file pixi.toml pixi.lock araki.toml\n\
+        eval \"$(pixi shell-hook --shell bash)\"\n\
+        eval \"$(araki shell env bash)\"\n\
+        araki stale\n\
+        {BLOCK_END}\n"
+    )
+}
+
+/// Insert the araki section into the contents of an .envrc, replacing an existing araki section
+/// if there is one. Everything outside the section is left untouched.
+///
+/// * `contents`: Current contents of the .envrc
+fn update_envrc(contents: &str) -> String {
+    let block = envrc_block();
+    if let Some(start) = contents.find(BLOCK_START)
+        && let Some(end) = contents[start..].find(BLOCK_END)
+    {
+        let mut end = start + end + BLOCK_END.len();
+        if contents[end..].starts_with('\n') {
+            end += 1;
+        }
+        return format!("{}{block}{}", &contents[..start], &contents[end..]);
+    }
+
+    if contents.is_empty() || contents.ends_with('\n') {
+        format!("{contents}{block}")
+    } else {
+        format!("{contents}\n{block}")
+    }
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let path = cwd.join(".envrc");
+    let existing = if path.exists() {
+        fs::read_to_string(&path).unwrap_or_else(|err| {
+            eprintln!("Unable to read {path:?}: {err}");
+            exit(1);
+        })
+    } else {
+        String::new()
+    };
+    let contents = update_envrc(&existing);
+
+    if args.stdout {
+        print!("{contents}");
+        return;
+    }
+    fs::write(&path, contents).unwrap_or_else(|err| {
+        eprintln!("Unable to write {path:?}: {err}");
+        exit(1);
+    });
+    println!("Wrote {path:?}. Run `direnv allow` to activate the environment.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_envrc() {
+        let block = envrc_block();
+        assert_eq!(update_envrc(""), block);
+        assert_eq!(
+            update_envrc("export FOO=1"),
+            format!("export FOO=1\n{block}")
+        );
+
+        let stale = format!("export FOO=1\n{BLOCK_START}\nold\n{BLOCK_END}\nexport BAR=2\n");
+        assert_eq!(
+            update_envrc(&stale),
+            format!("export FOO=1\n{block}export BAR=2\n")
+        );
+    }
+}
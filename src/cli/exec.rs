@@ -0,0 +1,47 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::process::{Command, exit};
+
+use crate::output;
+use araki::common::LockSpec;
+use araki::{ops, projects};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Command to run, followed by its arguments
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
+    let Some((project, record)) = projects::find(&cwd).unwrap_or_else(|err| output::fail(&err))
+    else {
+        output::fail(&format!(
+            "No environment is registered for {} or its parents. Run `araki clone` there first.",
+            cwd.display()
+        ));
+    };
+    let lockspec = LockSpec::from_path(&project).unwrap_or_else(|err| {
+        output::fail(&format!(
+            "The environment {} of {} is gone: {err}",
+            record.environment,
+            project.display()
+        ))
+    });
+
+    let activation = ops::pixi_activation(&lockspec).unwrap_or_else(|err| output::fail(&err));
+    let env = ops::activation_env(&lockspec).unwrap_or_else(|err| output::fail(&err));
+    let status = Command::new(&args.command[0])
+        .args(&args.command[1..])
+        .envs(activation)
+        .envs(env)
+        .status()
+        .unwrap_or_else(|err| {
+            output::fail(&format!("Failed to execute {}: {err}", args.command[0]))
+        });
+    // Usage statistics are bookkeeping only; failing to record them doesn't fail the command
+    let _ = projects::record_run(&project);
+    exit(status.code().unwrap_or(1));
+}
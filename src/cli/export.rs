@@ -0,0 +1,89 @@
+use clap::{Parser, ValueEnum};
+use std::env::current_dir;
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+
+use araki::common::LockSpec;
+use araki::export;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    /// A Dockerfile in the current directory
+    Dockerfile,
+
+    /// A .devcontainer/ directory with a devcontainer.json and Dockerfile
+    Devcontainer,
+}
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Format to export the environment as
+    #[arg(long, value_enum)]
+    format: ExportFormat,
+
+    /// Image to build the container on
+    #[arg(long, default_value = "ubuntu:24.04")]
+    base_image: String,
+
+    /// Version of pixi to install in the container
+    #[arg(long, default_value = "latest")]
+    pixi_version: String,
+
+    /// Overwrite existing files
+    #[arg(long)]
+    force: bool,
+}
+
+/// Write files, refusing to overwrite existing ones unless `force` is set.
+///
+/// All paths are checked before anything is written, so that no partial export is left behind.
+///
+/// * `files`: Paths and contents of the files to write
+/// * `force`: Whether existing files may be overwritten
+pub fn write_files(files: &[(PathBuf, String)], force: bool) {
+    if !force && let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        eprintln!("{path:?} already exists. Use --force to overwrite it.");
+        exit(1);
+    }
+    for (path, contents) in files {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).unwrap_or_else(|err| {
+                eprintln!("Unable to create {dir:?}: {err}");
+                exit(1);
+            });
+        }
+        fs::write(path, contents).unwrap_or_else(|err| {
+            eprintln!("Unable to write {path:?}: {err}");
+            exit(1);
+        });
+        println!("Wrote {path:?}");
+    }
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let dockerfile = export::render_dockerfile(&args.base_image, &args.pixi_version);
+    let files = match args.format {
+        ExportFormat::Dockerfile => vec![(cwd.join("Dockerfile"), dockerfile)],
+        ExportFormat::Devcontainer => {
+            let dir = cwd.join(".devcontainer");
+            vec![
+                (dir.join("Dockerfile"), dockerfile),
+                (
+                    dir.join("devcontainer.json"),
+                    export::render_devcontainer(&lockspec.display_name()),
+                ),
+            ]
+        }
+    };
+    write_files(&files, args.force);
+}
@@ -0,0 +1,53 @@
+use chrono::TimeDelta;
+use clap::Parser;
+use indicatif::HumanBytes;
+
+use crate::output;
+use araki::gc;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Remove the installed environments of projects not used for this long, e.g. 90d or 12w
+    #[arg(long, value_name = "AGE", value_parser = gc::parse_age)]
+    unused_for: TimeDelta,
+
+    /// Only list the environments which would be removed
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn execute(args: Args) {
+    let unused = gc::unused(args.unused_for).unwrap_or_else(|err| output::fail(&err));
+    if unused.is_empty() {
+        output::success("No installed environments are unused.");
+        return;
+    }
+
+    let mut freed = 0;
+    for env in &unused {
+        let description = format!(
+            "{} ({}, last used {}, {})",
+            env.project.display(),
+            env.environment,
+            env.last_used.format("%Y-%m-%d"),
+            HumanBytes(env.size)
+        );
+        if args.dry_run {
+            output::dry_run(&format!("Would remove the environments of {description}"));
+            continue;
+        }
+        match gc::remove(env) {
+            Ok(()) => {
+                output::message(&format!("Removed the environments of {description}"));
+                freed += env.size;
+            }
+            Err(err) => output::warning(&err),
+        }
+    }
+    if !args.dry_run {
+        output::success(&format!(
+            "Freed {}; run `pixi install` in a project to install its environments again.",
+            HumanBytes(freed)
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+use clap::Parser;
+use clap_complete::ArgValueCompleter;
+use std::process::{Command, exit};
+
+use crate::cli::completion;
+use crate::output;
+use araki::common::LockSpec;
+use araki::global::{self, Store};
+use araki::ops::Progress;
+use araki::{projects, secrets};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: GlobalSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum GlobalSubcommand {
+    /// Install an environment outside of any project and put its entry points on PATH
+    Add(AddArgs),
+
+    /// List the global environments
+    List,
+
+    /// Remove a global environment and its entry points
+    Remove(RemoveArgs),
+
+    /// Run a command in a global environment
+    Run(RunArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct AddArgs {
+    /// URL or <github org>/<repo name> of the lockspec to install
+    #[arg(value_name = "NAME", add = ArgValueCompleter::new(completion::environments))]
+    env: String,
+
+    /// Name of the global environment. Defaults to the name of the repository
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Install in your own environment store, even if a shared one is configured
+    #[arg(long)]
+    user: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct RemoveArgs {
+    /// Name of the global environment
+    name: String,
+
+    /// Remove the environment from your own environment store, even if a shared one is
+    /// configured
+    #[arg(long)]
+    user: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct RunArgs {
+    /// Name of the global environment
+    name: String,
+
+    /// Command to run, followed by its arguments
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+pub fn execute(args: Args) {
+    match args.subcommand {
+        GlobalSubcommand::Add(args) => {
+            let (name, exposed) = global::add(
+                &args.env,
+                args.name.as_deref(),
+                if args.user {
+                    Store::User
+                } else {
+                    global::default_store()
+                },
+                &|progress| match progress {
+                    Progress::Phase {
+                        step,
+                        total,
+                        message,
+                    } => output::phase(step, total, message),
+                    Progress::Transfer { received, total } => output::progress(received, total),
+                },
+            )
+            .unwrap_or_else(|err| output::fail(&err));
+            if exposed.is_empty() {
+                output::warning(&format!("{name} has no entry points to expose."));
+            } else {
+                output::message(&format!("Exposed {}", exposed.join(", ")));
+            }
+            output::success(&format!("Installed the global environment {name}"));
+        }
+        GlobalSubcommand::List => {
+            let names = global::list().unwrap_or_else(|err| output::fail(&err));
+            if names.is_empty() {
+                println!("No global environments are installed.");
+            }
+            for name in names {
+                match global::store_of(&name) {
+                    Some(Store::Shared) => println!("{name} (shared)"),
+                    _ => println!("{name}"),
+                }
+            }
+        }
+        GlobalSubcommand::Remove(args) => {
+            global::remove(&args.name, args.user.then_some(Store::User))
+                .unwrap_or_else(|err| output::fail(&err));
+            output::success(&format!("Removed the global environment {}", args.name));
+        }
+        GlobalSubcommand::Run(args) => {
+            let lockspec = global::get_global_env_dir(&args.name)
+                .and_then(LockSpec::from_path)
+                .unwrap_or_else(|_| {
+                    output::fail(&format!(
+                        "No global environment named {} is installed.",
+                        args.name
+                    ))
+                });
+            let mut command = Command::new("pixi");
+            command
+                .arg("run")
+                .arg("--manifest-path")
+                .arg(lockspec.specfile());
+            // Environments of a shared store may be read-only; use them as installed
+            if !global::is_writable(&args.name) {
+                command.arg("--frozen");
+            }
+            let env = lockspec
+                .env_vars()
+                .and_then(secrets::resolve_env)
+                .unwrap_or_else(|err| output::fail(&err));
+            let status = command
+                .args(&args.command)
+                .envs(env)
+                .status()
+                .unwrap_or_else(|err| output::fail(&format!("Failed to execute pixi run: {err}")));
+            let _ = projects::record_run(&lockspec.dir());
+            exit(status.code().unwrap_or(1));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use clap::Parser;
+use serde_json::{Map, Value, json};
+use std::env::current_dir;
+use std::fs;
+use std::process::exit;
+
+use araki::common::LockSpec;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: IdeSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum IdeSubcommand {
+    /// Point VS Code at the environment in .vscode/settings.json
+    Vscode,
+}
+
+/// Get the VS Code settings which make the editor and its terminals use the environment.
+fn vscode_settings() -> Map<String, Value> {
+    let prefix = "${workspaceFolder}/.pixi/envs/default";
+    let terminal_env = json!({
+        "PATH": format!("{prefix}/bin:${{env:PATH}}"),
+        "CONDA_PREFIX": prefix,
+    });
+    let settings = json!({
+        "python.defaultInterpreterPath": format!("{prefix}/bin/python"),
+        "python.terminal.activateEnvironment": true,
+        "terminal.integrated.env.linux": terminal_env,
+        "terminal.integrated.env.osx": terminal_env,
+    });
+    match settings {
+        Value::Object(settings) => settings,
+        _ => unreachable!("VS Code settings are always an object"),
+    }
+}
+
+pub fn execute(args: Args) {
+    match args.subcommand {
+        IdeSubcommand::Vscode => vscode(),
+    }
+}
+
+/// Merge the environment settings into .vscode/settings.json, keeping any other settings.
+fn vscode() {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let dir = cwd.join(".vscode");
+    let path = dir.join("settings.json");
+    let mut settings = if path.exists() {
+        let contents = fs::read_to_string(&path).unwrap_or_else(|err| {
+            eprintln!("Unable to read {path:?}: {err}");
+            exit(1);
+        });
+        match serde_json::from_str::<Value>(&contents) {
+            Ok(Value::Object(settings)) => settings,
+            _ => {
+                eprintln!(
+                    "Unable to parse {path:?}. araki can only update settings files which are \
+                    plain JSON objects without comments."
+                );
+                exit(1);
+            }
+        }
+    } else {
+        Map::new()
+    };
+    settings.extend(vscode_settings());
+
+    fs::create_dir_all(&dir).unwrap_or_else(|err| {
+        eprintln!("Unable to create {dir:?}: {err}");
+        exit(1);
+    });
+    let contents = serde_json::to_string_pretty(&settings).unwrap_or_default() + "\n";
+    fs::write(&path, contents).unwrap_or_else(|err| {
+        eprintln!("Unable to write {path:?}: {err}");
+        exit(1);
+    });
+    println!("Wrote {path:?}");
+}
@@ -0,0 +1,89 @@
+use clap::Parser;
+use clap_complete::ArgValueCompleter;
+use std::process::exit;
+
+use crate::cli::completion;
+use araki::security;
+use araki::settings::get_settings;
+use araki::{common, ops};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Tag to show. Defaults to the tag pointing at the checked out commit
+    #[arg(add = ArgValueCompleter::new(completion::tags))]
+    tag: Option<String>,
+}
+
+pub fn execute(args: Args) {
+    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
+        eprintln!("Couldn't recognize the araki repo: {err}");
+        exit(1);
+    });
+    let name = match args.tag {
+        Some(tag) => ops::full_tag_name(&repo, &tag),
+        None => current_tag(&repo),
+    };
+
+    let tag = repo
+        .find_reference(&format!("refs/tags/{name}"))
+        .and_then(|reference| reference.peel_to_tag())
+        .unwrap_or_else(|_| {
+            eprintln!("{name} is not an annotated tag.");
+            exit(1);
+        });
+    println!("Tag:       {name}");
+    println!("Commit:    {}", tag.target_id());
+    if let Some(tagger) = tag.tagger() {
+        println!(
+            "Tagger:    {} <{}>",
+            tagger.name().unwrap_or_default(),
+            tagger.email().unwrap_or_default()
+        );
+    }
+
+    let signature = match security::signature_kind(&repo, &name) {
+        Ok(Some(kind)) => {
+            let settings = get_settings().unwrap_or_default().security;
+            match security::verify_tag(&repo, &name, &settings) {
+                Ok(signer) => format!("signed by {signer}"),
+                Err(err) => format!("{kind} signature, not verified: {err}"),
+            }
+        }
+        Ok(None) => "unsigned".to_string(),
+        Err(err) => err,
+    };
+    println!("Signature: {signature}");
+
+    let message = tag.message().unwrap_or_default();
+    let message = message
+        .split_once("-----BEGIN")
+        .map_or(message, |(message, _)| message);
+    println!("\n{}", message.trim_end());
+}
+
+/// Get the name of the tag pointing at HEAD, exiting if there is none.
+///
+/// * `repo`: araki repo
+fn current_tag(repo: &git2::Repository) -> String {
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to find HEAD: {err}");
+            exit(1);
+        });
+    let tags = ops::list_tags(repo).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    tags.into_iter()
+        .map(|tag| tag.name)
+        .find(|name| {
+            repo.revparse_single(&format!("refs/tags/{name}^{{commit}}"))
+                .is_ok_and(|commit| commit.id() == head.id())
+        })
+        .unwrap_or_else(|| {
+            eprintln!("No tag points at the checked out commit; pass the tag to show.");
+            exit(1);
+        })
+}
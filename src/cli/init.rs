@@ -1,16 +1,21 @@
 use clap::Parser;
-use console::style;
 use indicatif::HumanDuration;
 use std::env::current_dir;
 use std::path::{Path, PathBuf};
-use std::process::exit;
 use std::str::FromStr;
 use std::time::Instant;
 
-use crate::backends::{self, Backend};
-use crate::common;
+use crate::output;
+use araki::artifacts::ArtifactOrigin;
+use araki::backends::{self, Visibility};
+use araki::common;
+use araki::ops;
+use araki::preflight;
+use araki::readme;
+use araki::settings::get_settings;
 
-const ORG: &str = "nos-environments";
+/// Organization which new lockspec repositories are created in
+pub const ORG: &str = "nos-environments";
 
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
@@ -25,6 +30,41 @@ pub struct Args {
     /// Path to the target directory
     #[arg()]
     path: Option<String>,
+
+    /// URL of an existing git project whose pixi.toml and pixi.lock should be used as the
+    /// lockspec, instead of those in the target directory
+    #[arg(long, value_name = "URL")]
+    repository: Option<String>,
+
+    /// Print what would be done without creating, cloning, committing, or pushing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Create a public repository, overriding the init.default_visibility setting
+    #[arg(long, conflicts_with = "private")]
+    public: bool,
+
+    /// Create a private repository, overriding the init.default_visibility setting
+    #[arg(long)]
+    private: bool,
+}
+
+impl Args {
+    /// Arguments publishing the lockspec in the current directory under a name.
+    ///
+    /// * `name`: Name of the lockspec
+    /// * `message`: Commit message; defaults to "Initial commit"
+    pub fn new(name: &str, message: Option<String>) -> Self {
+        Args {
+            name: name.to_string(),
+            message,
+            path: None,
+            repository: None,
+            dry_run: false,
+            public: false,
+            private: false,
+        }
+    }
 }
 
 // Committing is complicated with libgit2. See
@@ -32,100 +72,157 @@ pub struct Args {
 // for the approach used here.
 pub async fn execute(args: Args) {
     let started = Instant::now();
-    let cwd = current_dir().unwrap_or_else(|err| {
-        eprintln!("Could not get the current directory: {err}");
-        exit(1);
-    });
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
     let path = args
         .path
         .map(|p| {
-            PathBuf::from_str(&p).unwrap_or_else(|_| {
-                eprintln!("{p} is not a valid path.");
-                exit(1);
-            })
+            PathBuf::from_str(&p)
+                .unwrap_or_else(|_| output::fail(&format!("{p} is not a valid path.")))
         })
         .unwrap_or(cwd.clone());
-    let path_str = path.to_str().unwrap_or_else(|| {
-        eprintln!("Could not convert {path:?} to a string.");
-        exit(1);
-    });
+    let path_str = path
+        .to_str()
+        .unwrap_or_else(|| output::fail(&format!("Could not convert {path:?} to a string.")));
 
     if common::get_araki_git_repo().is_ok() {
-        eprintln!("{path_str} is already managed by araki.");
-        exit(1);
+        output::fail(&format!("{path_str} is already managed by araki."));
     }
 
-    // Ensure the project has a pixi.toml and pixi.lock
-    if common::LockSpec::from_path(&path).is_err() {
-        eprintln!("No lockspec found at {path_str}");
-        exit(1);
+    // Ensure the project has a pixi.toml and pixi.lock, unless they are taken from another
+    // repository
+    match &args.repository {
+        Some(url) => {
+            if let Err(err) = preflight::check_no_lockspec(&path) {
+                output::fail(&err);
+            }
+            if args.dry_run {
+                output::dry_run(&format!(
+                    "Would clone {url} to a temporary directory, and copy its pixi.toml and \
+                    pixi.lock to {path_str}"
+                ));
+                output::dry_run(&format!("Would install the lockspec in {path_str}"));
+            }
+        }
+        None => {
+            if common::LockSpec::from_path(&path).is_err() {
+                output::fail(&format!("No lockspec found at {path_str}"));
+            }
+        }
     }
+    let visibility = match (args.public, args.private) {
+        (true, _) => Visibility::Public,
+        (_, true) => Visibility::Private,
+        _ => get_settings()
+            .map(|settings| settings.init.default_visibility)
+            .unwrap_or_default(),
+    };
+
     // Create a new respository
-    let backend = backends::get_current_backend().unwrap_or_else(|err| {
-        eprintln!("Unable to get the current backend: {err}");
-        exit(1);
-    });
-    println!(
-        "{} Creating lockspec repository at {}...",
-        style("[1/4]").bold().dim(),
-        backend.get_repo_info(ORG, &args.name).as_url(),
+    let backend = backends::get_current_backend()
+        .unwrap_or_else(|err| output::fail(&format!("Unable to get the current backend: {err}")));
+
+    if args.dry_run {
+        let repo_info = backend.get_repo_info(ORG, &args.name);
+        output::dry_run(&format!(
+            "Would create {visibility} repository '{}' for organization '{ORG}' at {}",
+            args.name,
+            repo_info.as_url(),
+        ));
+        output::dry_run(&format!(
+            "Would clone {} to {path_str}",
+            repo_info.as_ssh_url()
+        ));
+        output::dry_run(&format!(
+            "Would add .araki-git/, pixi.lock, and pixi.toml to {:?}",
+            path.join(".gitignore")
+        ));
+        output::dry_run(&format!(
+            "Would commit pixi.toml, pixi.lock, and a generated README.md with message '{}'",
+            args.message.as_deref().unwrap_or("Initial commit")
+        ));
+        output::dry_run("Would push refs/heads/main to origin");
+        if get_settings().is_ok_and(|settings| settings.init.protect_default_branch) {
+            output::dry_run("Would require reviewed pull requests for changes to main");
+        }
+        return;
+    }
+
+    if let Some(url) = &args.repository {
+        output::message(&format!("Importing the lockspec of {url}..."));
+        ops::import(url, &path).unwrap_or_else(|err| output::fail(&err));
+    }
+
+    output::phase(
+        1,
+        4,
+        &format!(
+            "Creating lockspec repository at {}...",
+            backend.get_repo_info(ORG, &args.name).as_url()
+        ),
     );
     backend
-        .create_repository(ORG, &args.name)
+        .create_repository(ORG, &args.name, visibility)
         .await
         .unwrap_or_else(|err| {
-            eprintln!(
+            // Don't leave an imported lockspec behind, so that the command can be run again
+            if args.repository.is_some() {
+                let _ = common::LockSpec::from_path(&path).and_then(|ls| ls.remove_files());
+            }
+            output::fail(&format!(
                 "Error creating a new repository '{}' for organization '{}': {err}",
-                args.name, ORG,
-            );
-            exit(1);
+                args.name, ORG
+            ))
         });
 
     // Clone the repository to the target directory. This also creates a .araki-git for tracking
     // lockspec git versions
-    println!(
-        "{} Cloning lockspec repository to {path_str}...",
-        style("[2/4]").bold().dim(),
-    );
-    common::git_clone(backend.get_repo_info(ORG, &args.name).as_ssh_url(), &path).unwrap_or_else(
-        |err| {
-            eprintln!("Failed to clone the repository: {err}");
-            exit(1);
-        },
+    output::phase(
+        2,
+        4,
+        &format!("Cloning lockspec repository to {path_str}..."),
     );
+    let url = backend.get_repo_info(ORG, &args.name).as_ssh_url();
+    let registry = ArtifactOrigin::parse(&url);
+    match &registry {
+        Some(origin) => origin.init(&path),
+        None => common::git_clone(url, &path),
+    }
+    .unwrap_or_else(|err| output::fail(&format!("Failed to clone the repository: {err}")));
 
     // Commit the lockspec as a new change
-    println!("{} Committing lockspec...", style("[3/4]").bold().dim(),);
-    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
-        eprintln!("Couldn't recognize the araki repo: {err}");
-        exit(1);
-    });
+    output::phase(3, 4, "Committing lockspec...");
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
 
     let mut index = repo.index().unwrap_or_else(|err| {
-        eprintln!("Couln't get the index for the araki repo: {err}");
-        exit(1);
+        output::fail(&format!("Couln't get the index for the araki repo: {err}"))
     });
     for item in ["pixi.toml", "pixi.lock"] {
         index.add_path(Path::new(item)).unwrap_or_else(|err| {
-            eprintln!("Couldn't add {item} to the git index: {err}");
-            exit(1);
+            output::fail(&format!("Couldn't add {item} to the git index: {err}"))
         });
     }
-    index.write().unwrap_or_else(|err| {
-        eprintln!("Couldn't write to the git index: {err}");
-        exit(1);
+    readme::stage(&repo, &mut index).unwrap_or_else(|err| {
+        output::fail(&format!("Couldn't add a README to the git index: {err}"))
     });
+    index
+        .write()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't write to the git index: {err}")));
     let new_tree_oid = index.write_tree().unwrap_or_else(|err| {
-        eprintln!("Failed to write the git tree from the index: {err}");
-        exit(1);
+        output::fail(&format!(
+            "Failed to write the git tree from the index: {err}"
+        ))
     });
     let new_tree = repo.find_tree(new_tree_oid).unwrap_or_else(|err| {
-        eprintln!("Unable to find the git tree associated with the new commit: {err}");
-        exit(1);
+        output::fail(&format!(
+            "Unable to find the git tree associated with the new commit: {err}"
+        ))
     });
     let author = repo.signature().unwrap_or_else(|err| {
-        eprintln!("Unable to get the author to use for the commit: {err}");
-        exit(1);
+        output::fail(&format!(
+            "Unable to get the author to use for the commit: {err}"
+        ))
     });
     let commit_oid = repo
         .commit(
@@ -136,46 +233,55 @@ pub async fn execute(args: Args) {
             &new_tree,
             &[],
         )
-        .unwrap_or_else(|err| {
-            eprintln!("Error committing changes: {err}");
-            exit(1);
-        });
+        .unwrap_or_else(|err| output::fail(&format!("Error committing changes: {err}")));
 
     // Create a new (default) branch called 'main'
     let branch = repo
         .branch(
             "main",
             &repo.find_commit(commit_oid).unwrap_or_else(|err| {
-                eprintln!("Unable to find the new commit: {err}");
-                exit(1);
+                output::fail(&format!("Unable to find the new commit: {err}"))
             }),
             true,
         )
         .unwrap_or_else(|err| {
-            eprintln!("Unable to generate a main branch with the new commit: {err}");
-            exit(1);
+            output::fail(&format!(
+                "Unable to generate a main branch with the new commit: {err}"
+            ))
         });
 
     // Set the head to the new branch reference
     let branch_ref = branch.into_reference();
-    let branch_ref_name = branch_ref.name().unwrap_or_else(|| {
-        eprintln!("Could not convert branch reference into name.");
-        exit(1);
-    });
-    repo.set_head(branch_ref_name).unwrap_or_else(|err| {
-        eprintln!("Unable to set the repository head: {err}");
-        exit(1);
-    });
+    let branch_ref_name = branch_ref
+        .name()
+        .unwrap_or_else(|| output::fail("Could not convert branch reference into name."));
+    repo.set_head(branch_ref_name)
+        .unwrap_or_else(|err| output::fail(&format!("Unable to set the repository head: {err}")));
 
-    // Push to remote
-    println!(
-        "{} Pushing changes to remote...",
-        style("[4/4]").bold().dim(),
-    );
-    common::git_push("origin", &["refs/heads/main"]).unwrap_or_else(|err| {
-        eprintln!("Unable to push to remote: {err}");
-        exit(1);
-    });
-    println!("Lockspec changes pushed to remote.");
-    println!("Done in {}", HumanDuration(started.elapsed()));
+    // Push to remote. Registries only hold tags, so nothing is published there until the first
+    // tag is pushed.
+    output::phase(4, 4, "Pushing changes to remote...");
+    match registry {
+        Some(origin) => output::message(&format!(
+            "Tag the lockspec and push the tag to publish it to {origin}."
+        )),
+        None => {
+            common::git_push("origin", &["refs/heads/main"])
+                .unwrap_or_else(|err| output::fail(&format!("Unable to push to remote: {err}")));
+            output::message("Lockspec changes pushed to remote.");
+        }
+    }
+    if get_settings().is_ok_and(|settings| settings.init.protect_default_branch) {
+        // The repository exists and holds the lockspec at this point, so failing to protect it
+        // doesn't fail the command
+        match backend.protect_branch(ORG, &args.name, "main").await {
+            Ok(()) => output::message("Required reviewed pull requests for changes to main."),
+            Err(err) => output::warning(&format!("Unable to protect the main branch: {err}")),
+        }
+    }
+    output::success(&format!(
+        "Created {} in {}",
+        backend.get_repo_info(ORG, &args.name).as_url(),
+        HumanDuration(started.elapsed())
+    ));
 }
@@ -0,0 +1,145 @@
+use clap::Parser;
+use directories::BaseDirs;
+use serde_json::json;
+use std::env::{self, current_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use araki::common::LockSpec;
+use araki::lockfile::LockFile;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: KernelSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum KernelSubcommand {
+    /// Register the environment as a Jupyter kernel
+    Install(KernelArgs),
+
+    /// Remove the Jupyter kernel of the environment
+    Remove(KernelArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct KernelArgs {
+    /// Name of the kernel. Defaults to the name of the lockspec
+    #[arg(long)]
+    name: Option<String>,
+}
+
+/// Get the directory where Jupyter looks for user kernelspecs.
+fn get_kernels_dir() -> Result<PathBuf, String> {
+    if let Ok(dir) = env::var("JUPYTER_DATA_DIR") {
+        return Ok(PathBuf::from(dir).join("kernels"));
+    }
+    let dirs = BaseDirs::new().ok_or("Could not get the home directory for the system.")?;
+    if cfg!(target_os = "macos") {
+        Ok(dirs
+            .home_dir()
+            .join("Library")
+            .join("Jupyter")
+            .join("kernels"))
+    } else {
+        Ok(dirs.data_dir().join("jupyter").join("kernels"))
+    }
+}
+
+/// Turn a name into a valid kernelspec directory name.
+///
+/// * `name`: Name to convert
+fn kernel_dir_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let kernels_dir = get_kernels_dir().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    match args.subcommand {
+        KernelSubcommand::Install(args) => {
+            let display_name = args.name.unwrap_or_else(|| lockspec.display_name());
+            let dir = kernels_dir.join(kernel_dir_name(&display_name));
+            install(&lockspec, &display_name, &dir)
+        }
+        KernelSubcommand::Remove(args) => {
+            let name = args.name.unwrap_or_else(|| lockspec.display_name());
+            let dir = kernels_dir.join(kernel_dir_name(&name));
+            if !dir.exists() {
+                eprintln!("No kernel named {name} is installed.");
+                exit(1);
+            }
+            fs::remove_dir_all(&dir).unwrap_or_else(|err| {
+                eprintln!("Unable to remove {dir:?}: {err}");
+                exit(1);
+            });
+            println!("Removed the {name} kernel.");
+        }
+    }
+}
+
+/// Write a kernelspec which runs ipykernel with the python of the environment.
+///
+/// * `lockspec`: Lockspec of the environment
+/// * `display_name`: Name of the kernel shown in Jupyter
+/// * `dir`: Kernelspec directory to write
+fn install(lockspec: &LockSpec, display_name: &str, dir: &Path) {
+    let python = lockspec.env_prefix().join("bin").join("python");
+    if !python.exists() {
+        eprintln!("{python:?} does not exist. Is the environment installed?");
+        exit(1);
+    }
+
+    let has_ipykernel = LockFile::from_path(&lockspec.lockfile())
+        .is_ok_and(|lockfile| lockfile.packages.iter().any(|p| p.name == "ipykernel"));
+    if !has_ipykernel {
+        eprintln!(
+            "warning: ipykernel is not part of the environment; the kernel will not start until \
+            it is added with `pixi add ipykernel`."
+        );
+    }
+
+    let kernelspec = json!({
+        "argv": [python, "-m", "ipykernel_launcher", "-f", "{connection_file}"],
+        "display_name": display_name,
+        "language": "python",
+        "metadata": {"araki": {"lockspec": lockspec.path}},
+    });
+    fs::create_dir_all(dir).unwrap_or_else(|err| {
+        eprintln!("Unable to create {dir:?}: {err}");
+        exit(1);
+    });
+    let path = dir.join("kernel.json");
+    fs::write(
+        &path,
+        serde_json::to_string_pretty(&kernelspec).unwrap_or_default(),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Unable to write {path:?}: {err}");
+        exit(1);
+    });
+    println!("Installed the {display_name} kernel to {dir:?}");
+}
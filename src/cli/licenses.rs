@@ -0,0 +1,99 @@
+use clap::Parser;
+use console::style;
+use std::collections::BTreeMap;
+use std::env::current_dir;
+use std::process::exit;
+
+use araki::common::LockSpec;
+use araki::licenses;
+use araki::lockfile::LockFile;
+use araki::settings::get_settings;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// License to deny, in addition to those in the `[licenses] deny` setting. Can be repeated
+    #[arg(long)]
+    deny: Vec<String>,
+
+    /// List the license of every package instead of a summary
+    #[arg(long)]
+    all: bool,
+}
+
+pub async fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let lockfile = LockFile::from_path(&lockspec.lockfile()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let settings = get_settings().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let mut deny = settings.licenses.deny;
+    deny.extend(args.deny);
+
+    let packages = licenses::collect(&lockfile).await.unwrap_or_else(|err| {
+        eprintln!("Unable to get package licenses: {err}");
+        exit(1);
+    });
+
+    if args.all {
+        for package in &packages {
+            println!(
+                "{} {} ({}): {}",
+                package.name,
+                package.version,
+                package.kind,
+                package.license.as_deref().unwrap_or("unknown")
+            );
+        }
+    } else {
+        let mut summary: BTreeMap<&str, usize> = BTreeMap::new();
+        for package in &packages {
+            *summary
+                .entry(package.license.as_deref().unwrap_or("unknown"))
+                .or_default() += 1;
+        }
+        let width = summary
+            .keys()
+            .map(|license| license.len())
+            .max()
+            .unwrap_or(0);
+        for (license, count) in summary {
+            println!("{license:<width$}  {count}");
+        }
+    }
+
+    let denied: Vec<_> = packages
+        .iter()
+        .filter(|package| {
+            package
+                .license
+                .as_deref()
+                .is_some_and(|license| licenses::is_denied(license, &deny))
+        })
+        .collect();
+    if !denied.is_empty() {
+        eprintln!(
+            "\n{} The following packages have a denied license:",
+            style("error:").bold().red()
+        );
+        for package in denied {
+            eprintln!(
+                "  {} {}: {}",
+                package.name,
+                package.version,
+                package.license.as_deref().unwrap_or_default()
+            );
+        }
+        exit(1);
+    }
+}
@@ -0,0 +1,74 @@
+use clap::Parser;
+use console::style;
+use serde_json::json;
+use std::env::current_dir;
+use std::process::exit;
+
+use araki::common::{self, LockSpec};
+use araki::lint::{self, Level};
+use araki::settings::get_settings;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Print the findings as JSON, e.g. for CI annotations
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let repo = common::open_araki_git_repo(&cwd).unwrap_or_else(|err| {
+        eprintln!("Couldn't recognize the araki repo: {err}");
+        exit(1);
+    });
+    let settings = get_settings().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let findings = lint::lint(&lockspec, &repo, &settings.lint).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.level == Level::Error)
+        .count();
+
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "findings": findings,
+                "errors": errors,
+                "warnings": findings.len() - errors,
+            }))
+            .unwrap_or_default()
+        );
+    } else {
+        for finding in &findings {
+            let level = match finding.level {
+                Level::Error => style(format!("error[{}]:", finding.rule)).bold().red(),
+                _ => style(format!("warning[{}]:", finding.rule)).bold().yellow(),
+            };
+            println!("{level} {}", finding.message);
+        }
+        match findings.is_empty() {
+            true => println!("No issues found."),
+            false => println!(
+                "\nFound {errors} errors and {} warnings.",
+                findings.len() - errors
+            ),
+        }
+    }
+    if errors > 0 {
+        exit(1);
+    }
+}
@@ -1,16 +1,179 @@
-use clap::Parser;
-use git2::Tag;
+use clap::{Parser, ValueEnum};
+use console::style;
+use std::fmt::Write;
 use std::process::{Command, exit};
+use std::time::Duration;
 
-use crate::common;
+use crate::output;
+use araki::backends::{RepositoryQuery, RepositorySort, get_current_backend};
+use araki::catalog;
+use araki::common;
+use araki::lockfile::PackageConstraint;
+use araki::ops::{self, TagSync};
+use araki::projects;
+use araki::remote::parse_repo_arg;
+
+/// How long the tags of the remote are cached before `list` contacts the remote again.
+const REMOTE_TAGS_MAX_AGE: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
     #[arg(long, help = "Switch to print out the list of checkpoints as a tree")]
     tree: bool,
+
+    /// Only list local tags, without comparing them to the remote
+    #[arg(long)]
+    local: bool,
+
+    /// Contact the remote even if its tags were cached recently
+    #[arg(long)]
+    refresh: bool,
+
+    /// List the lockspecs available on the backend instead of the tags of this lockspec
+    #[arg(long)]
+    remote: bool,
+
+    /// Organization to list lockspecs of. Defaults to the org of this lockspec's origin
+    #[arg(long, requires = "remote")]
+    org: Option<String>,
+
+    /// Only list lockspecs whose name or description contains this substring
+    #[arg(long, requires = "remote")]
+    filter: Option<String>,
+
+    /// Only list lockspecs with this topic
+    #[arg(long, requires = "remote")]
+    topic: Option<String>,
+
+    /// Order of the listed lockspecs
+    #[arg(long, value_enum, default_value_t, requires = "remote")]
+    sort: Sort,
+
+    /// Only list lockspecs whose pixi.lock satisfies this package constraint, e.g. pytorch or
+    /// 'python>=3.11'; can be given several times
+    #[arg(long, value_name = "CONSTRAINT", requires = "remote")]
+    has: Vec<PackageConstraint>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Sort {
+    /// Alphabetically by name
+    #[default]
+    Name,
+
+    /// Most recently updated first
+    Updated,
+}
+
+/// List the lockspecs of an org on the backend.
+///
+/// * `args`: Arguments of the list command
+async fn list_remote(args: Args) {
+    let org = args.org.unwrap_or_else(|| {
+        common::get_araki_git_repo()
+            .ok()
+            .and_then(|repo| {
+                let origin = repo.find_remote("origin").ok()?;
+                Some(parse_repo_arg(origin.url()?).ok()?.get_org())
+            })
+            .unwrap_or_else(|| {
+                eprintln!("Unable to determine the org to list. Pass it with --org.");
+                exit(1);
+            })
+    });
+    let query = RepositoryQuery {
+        filter: args.filter,
+        topic: args.topic,
+        sort: match args.sort {
+            Sort::Name => RepositorySort::Name,
+            Sort::Updated => RepositorySort::Updated,
+        },
+    };
+
+    let backend = get_current_backend().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let repositories = backend
+        .list_repositories(&org, &query)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to list the lockspecs of {org}: {err}");
+            exit(1);
+        });
+
+    // Only complete listings are cached for completion; filtered ones would drop lockspecs
+    if query.filter.is_none() && query.topic.is_none() {
+        let _ = catalog::record_listing(&org, &repositories);
+    }
+
+    let repositories = if args.has.is_empty() {
+        repositories
+    } else {
+        let mut matching = vec![];
+        for repository in repositories {
+            match catalog::locked_packages(backend.as_ref(), &org, &repository).await {
+                Ok(packages) if catalog::satisfies(&packages, &args.has) => {
+                    matching.push(repository)
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!(
+                    "warning: Unable to read the packages of {org}/{}: {err}",
+                    repository.name
+                ),
+            }
+        }
+        matching
+    };
+
+    // Merge in the local projects, so that the lockspecs already in use here stand out
+    let environments: Vec<String> = projects::load()
+        .unwrap_or_default()
+        .into_values()
+        .map(|record| record.environment.to_lowercase())
+        .collect();
+    let width = repositories
+        .iter()
+        .map(|repo| repo.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut text = String::new();
+    for repo in &repositories {
+        let updated = repo
+            .updated
+            .as_deref()
+            .and_then(|updated| updated.get(..10))
+            .unwrap_or("");
+        let name = format!("{org}/{}", repo.name).to_lowercase();
+        let local = environments
+            .iter()
+            .filter(|environment| {
+                **environment == name
+                    || environment
+                        .strip_prefix(&name)
+                        .is_some_and(|rest| rest.starts_with('#'))
+            })
+            .count();
+        let local = match local {
+            0 => String::new(),
+            1 => "1 project".to_string(),
+            count => format!("{count} projects"),
+        };
+        let _ = writeln!(
+            text,
+            "{:<width$}  {updated:<10}  {local:<11}  {}",
+            repo.name,
+            repo.description.as_deref().unwrap_or("")
+        );
+    }
+    output::page(&text);
 }
 
-pub fn execute(args: Args) {
+pub async fn execute(args: Args) {
+    if args.remote {
+        return list_remote(args).await;
+    }
+
     let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
         eprintln!("Couldn't recognize the araki repo: {err}");
         exit(1);
@@ -24,25 +187,68 @@ pub fn execute(args: Args) {
             .expect("Failed to execute command");
         let tree_stdout = String::from_utf8_lossy(&tree_output.stdout);
         println!("{}", tree_stdout);
-    } else {
-        let tags = repo.tag_names(Some("*")).unwrap();
+        return;
+    }
 
-        for name_w in tags.iter() {
-            let name = name_w.unwrap();
-            let obj = repo.revparse_single(name).expect("Unable to get tag");
+    let remote = if args.local || repo.find_remote("origin").is_err() {
+        None
+    } else {
+        let max_age = if args.refresh {
+            Duration::ZERO
+        } else {
+            REMOTE_TAGS_MAX_AGE
+        };
+        ops::remote_tags(&repo, max_age)
+            .inspect_err(|err| eprintln!("{err}; only listing local tags."))
+            .ok()
+    };
 
-            if let Some(tag) = obj.as_tag() {
-                print_tag(tag);
-            } else {
-                print_name(name);
+    let Some(remote) = remote else {
+        let tags = ops::list_tags(&repo).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+        for tag in tags {
+            match tag.message {
+                Some(message) => print_tag(&tag.name, &message),
+                None => print_name(&tag.name),
             }
         }
+        return;
+    };
+
+    let tags = ops::compare_tags(&repo, &remote).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    for tag in &tags {
+        let sync = match tag.sync {
+            TagSync::Synced => style("synced").green(),
+            TagSync::LocalOnly => style("local-only").cyan(),
+            TagSync::RemoteOnly => style("remote-only").yellow().bold(),
+            TagSync::Diverged => style("diverged").red().bold(),
+        };
+        print!("{:<16}{:<13}", tag.name, sync.to_string());
+        print_list_lines(tag.message.as_deref().or(Some("")));
+    }
+
+    let behind = tags
+        .iter()
+        .filter(|tag| tag.sync == TagSync::RemoteOnly)
+        .count();
+    if behind > 0 {
+        println!(
+            "\n{} origin has {behind} tag{} not available locally. Run `araki pull` to get {}.",
+            style("note:").bold().yellow(),
+            if behind == 1 { "" } else { "s" },
+            if behind == 1 { "it" } else { "them" },
+        );
     }
 }
 
-fn print_tag(tag: &Tag) {
-    print!("{:<16}", tag.name().unwrap());
-    print_list_lines(tag.message());
+fn print_tag(name: &str, message: &str) {
+    print!("{:<16}", name);
+    print_list_lines(Some(message));
 }
 
 fn print_name(name: &str) {
@@ -0,0 +1,102 @@
+use clap::Parser;
+use git2::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use toml::Table;
+
+use crate::output;
+use araki::{common, merge};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// File of the common ancestor (%O)
+    #[arg(required_unless_present = "register")]
+    base: Option<PathBuf>,
+
+    /// File of our side (%A), overwritten with the merged file
+    #[arg(required_unless_present = "register")]
+    ours: Option<PathBuf>,
+
+    /// File of their side (%B)
+    #[arg(required_unless_present = "register")]
+    theirs: Option<PathBuf>,
+
+    /// Path of the merged file in the repo (%P); detected from its contents if not given
+    path: Option<String>,
+
+    /// Register araki as the merge driver of pixi.toml and pixi.lock in the araki repo
+    #[arg(long, exclusive = true)]
+    register: bool,
+}
+
+pub fn execute(args: Args) {
+    if args.register {
+        let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
+            output::fail(&format!("Couldn't recognize the araki repo: {err}"))
+        });
+        merge::register(&repo).unwrap_or_else(|err| output::fail(&err));
+        output::success("Registered the araki merge driver for pixi.toml and pixi.lock");
+        return;
+    }
+    let (Some(base), Some(ours), Some(theirs)) = (args.base, args.ours, args.theirs) else {
+        unreachable!("clap requires the files to merge without --register");
+    };
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .unwrap_or_else(|err| output::fail(&format!("Unable to read {path:?}: {err}")))
+    };
+    let (base_contents, ours_contents, theirs_contents) = (read(&base), read(&ours), read(&theirs));
+
+    let path = args.path.unwrap_or_else(|| {
+        match ours_contents.parse::<Table>() {
+            Ok(_) => "pixi.toml",
+            Err(_) => "pixi.lock",
+        }
+        .to_string()
+    });
+    let merged = match Path::new(&path).file_name().and_then(|name| name.to_str()) {
+        Some("pixi.toml") => {
+            merge::merge_specfile(&base_contents, &ours_contents, &theirs_contents)
+        }
+        Some("pixi.lock") => {
+            // git runs merge drivers with GIT_DIR set to the repo being merged
+            let specfile = Path::new(&path).with_file_name("pixi.toml");
+            let specfile = Repository::open_from_env()
+                .map_err(|err| err.to_string())
+                .and_then(|repo| merge::merging_specfile(&repo, &specfile.to_string_lossy()))
+                .unwrap_or_else(|err| {
+                    output::warning(&format!("Unable to merge pixi.toml to lock against: {err}"));
+                    None
+                });
+            merge::merge_lockfile(
+                &base_contents,
+                &ours_contents,
+                &theirs_contents,
+                specfile.as_deref(),
+            )
+        }
+        _ => Ok(None),
+    };
+
+    match merged {
+        Ok(Some(contents)) => {
+            fs::write(&ours, contents)
+                .unwrap_or_else(|err| output::fail(&format!("Unable to write {ours:?}: {err}")));
+            output::message(&format!("Merged {path}"));
+        }
+        Ok(None) | Err(_) => {
+            if let Err(err) = merged {
+                output::warning(&err);
+            }
+            output::warning(&format!(
+                "Unable to merge {path} semantically; falling back to a text merge."
+            ));
+            let clean =
+                merge::merge_text(&base, &ours, &theirs).unwrap_or_else(|err| output::fail(&err));
+            if !clean {
+                exit(1);
+            }
+        }
+    }
+}
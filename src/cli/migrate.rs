@@ -0,0 +1,114 @@
+use clap::Parser;
+use console::Term;
+use std::env::{current_dir, set_current_dir};
+use std::path::PathBuf;
+
+use crate::cli::init;
+use crate::output;
+use araki::migrate::{self, CondaEnvironment};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Names of the conda environments to migrate. If none are given, the environments found
+    /// are listed, and asked for on a terminal
+    #[arg(value_name = "NAME")]
+    names: Vec<String>,
+
+    /// Directory to create the pixi projects in, one subdirectory per environment. Defaults to
+    /// the current directory
+    #[arg(long, value_name = "PATH")]
+    dir: Option<PathBuf>,
+
+    /// Only convert the environments to pixi projects, without publishing them with `araki init`
+    #[arg(long)]
+    no_publish: bool,
+}
+
+pub async fn execute(args: Args) {
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
+    let environments = migrate::discover().unwrap_or_else(|err| output::fail(&err));
+    if environments.is_empty() {
+        output::fail("No conda environments found.");
+    }
+
+    let selected: Vec<&CondaEnvironment> = match args.names.is_empty() {
+        true => pick(&environments),
+        false => args
+            .names
+            .iter()
+            .map(|name| {
+                environments
+                    .iter()
+                    .find(|env| env.name == *name)
+                    .unwrap_or_else(|| output::fail(&format!("No conda environment named {name}.")))
+            })
+            .collect(),
+    };
+    if selected.is_empty() {
+        return;
+    }
+
+    let dir = args.dir.unwrap_or(cwd.clone());
+    for (step, env) in selected.iter().enumerate() {
+        let target = dir.join(&env.name);
+        output::phase(
+            step + 1,
+            selected.len(),
+            &format!("Converting {} to a pixi project in {target:?}...", env.name),
+        );
+        migrate::convert(env, &target).unwrap_or_else(|err| output::fail(&err));
+        if args.no_publish {
+            continue;
+        }
+
+        // `araki init` publishes the lockspec of the current directory
+        set_current_dir(&target).unwrap_or_else(|err| {
+            output::fail(&format!("Unable to change directory to {target:?}: {err}"))
+        });
+        init::execute(init::Args::new(
+            &env.name,
+            Some(format!("Migrate the conda environment {}", env.name)),
+        ))
+        .await;
+        let _ = set_current_dir(&cwd);
+    }
+    output::success(&format!(
+        "Migrated {} environments to {dir:?}",
+        selected.len()
+    ));
+}
+
+/// List the conda environments found, and ask which to migrate if running on a terminal.
+///
+/// * `environments`: Conda environments found on the machine
+fn pick(environments: &[CondaEnvironment]) -> Vec<&CondaEnvironment> {
+    for (number, env) in environments.iter().enumerate() {
+        output::message(&format!(
+            "{:>3}. {} ({})",
+            number + 1,
+            env.name,
+            env.prefix.display()
+        ));
+    }
+    let term = Term::stdout();
+    if !term.is_term() {
+        output::message("Pass the names of the environments to migrate.");
+        return vec![];
+    }
+    let _ = term.write_str("Environments to migrate, by number or name (empty to cancel): ");
+    let answer = term
+        .read_line()
+        .unwrap_or_else(|err| output::fail(&format!("Unable to read the answer: {err}")));
+    answer
+        .split([' ', ','])
+        .filter(|choice| !choice.is_empty())
+        .map(|choice| {
+            let found = match choice.parse::<usize>() {
+                Ok(number) => environments.get(number.wrapping_sub(1)),
+                Err(_) => environments.iter().find(|env| env.name == choice),
+            };
+            found.unwrap_or_else(|| output::fail(&format!("No environment {choice}.")))
+        })
+        .collect()
+}
@@ -0,0 +1,65 @@
+use clap::Parser;
+use serde_json::json;
+use std::env::current_dir;
+
+use crate::output;
+use araki::common::LockSpec;
+use araki::lockfile::PackageChange;
+use araki::ops;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Print the outdated packages as JSON, e.g. for bots opening update pull requests
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| output::fail(&err));
+    let outdated = ops::outdated(&lockspec).unwrap_or_else(|err| output::fail(&err));
+    let packages: Vec<_> = outdated
+        .iter()
+        .flat_map(|(kind, changes)| {
+            changes.iter().filter_map(move |change| match change {
+                PackageChange::Updated { name, from, to } => {
+                    Some((kind.to_string(), name, from, to))
+                }
+                _ => None,
+            })
+        })
+        .collect();
+
+    if args.json {
+        let packages: Vec<_> = packages
+            .iter()
+            .map(|(ecosystem, name, locked, available)| {
+                json!({"name": name, "ecosystem": ecosystem, "locked": locked, "available": available})
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({ "outdated": packages })).unwrap_or_default()
+        );
+        return;
+    }
+    if packages.is_empty() {
+        println!("All packages are up to date.");
+        return;
+    }
+    let width = packages
+        .iter()
+        .map(|(_, name, _, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Package".len());
+    println!(
+        "{:<width$}  {:<9}  {:<16}  Available",
+        "Package", "Ecosystem", "Locked"
+    );
+    for (ecosystem, name, locked, available) in &packages {
+        println!("{name:<width$}  {ecosystem:<9}  {locked:<16}  {available}");
+    }
+    println!("\nUpdate them with `araki update`, or `araki update --only <package>`.");
+}
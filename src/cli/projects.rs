@@ -0,0 +1,58 @@
+use clap::Parser;
+use std::process::exit;
+
+use araki::projects;
+use araki::remote::parse_repo_arg;
+use araki::settings::get_settings;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Only list the projects using this environment (`<org>/<repo>` or alias)
+    #[arg(value_name = "NAME")]
+    env: Option<String>,
+
+    /// Forget projects which no longer contain a lockspec
+    #[arg(long)]
+    prune: bool,
+}
+
+pub fn execute(args: Args) {
+    if args.prune {
+        let pruned = projects::prune().unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+        for project in pruned {
+            eprintln!("Forgot {}", project.display());
+        }
+    }
+
+    let recorded = match &args.env {
+        Some(env) => {
+            let env = get_settings()
+                .map(|settings| settings.resolve_alias(env))
+                .and_then(|env| parse_repo_arg(&env))
+                .unwrap_or_else(|err| {
+                    eprintln!("{err}");
+                    exit(1);
+                });
+            projects::users_of(&env.short_name())
+        }
+        None => projects::load().map(|projects| projects.into_iter().collect()),
+    }
+    .unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    if recorded.is_empty() {
+        println!("No projects are recorded.");
+    }
+    for (project, record) in recorded {
+        println!(
+            "{}  {}@{}",
+            project.display(),
+            record.environment,
+            record.reference.as_deref().unwrap_or("main")
+        );
+    }
+}
@@ -1,14 +1,34 @@
 use clap::Parser;
-use git2::{AutotagOption, Cred, FetchOptions, RemoteCallbacks, Repository};
-use std::process::exit;
+use git2::{AutotagOption, Cred, FetchOptions, Oid, RemoteCallbacks, Repository};
 
-use crate::common;
+use std::path::PathBuf;
+use std::thread;
+
+use crate::output;
+use araki::artifacts::{self, ArtifactOrigin};
+use araki::network::{self, Throttle};
+use araki::ops::{self, Updates};
+use araki::settings::get_settings;
+use araki::{common, global, projects};
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
     // name of the tag
     // #[arg()]
     // tag: String,
+    /// Fetch every environment of the recorded projects and global environments, and report
+    /// which have new tags or commits
+    #[arg(long)]
+    all: bool,
+
+    /// With --all, also fast-forward the environments whose main has new commits
+    #[arg(long, requires = "all")]
+    fast_forward: bool,
+
+    /// Install the new lockfile with `pixi install --frozen` when the pull fast-forwards to
+    /// one, even if pull.autoinstall is not set
+    #[arg(long)]
+    install: bool,
 }
 
 fn fast_forward(
@@ -21,16 +41,17 @@ fn fast_forward(
         None => String::from_utf8_lossy(lb.name_bytes()).to_string(),
     };
     let msg = format!("Fast-Forward: Setting {} to id: {}", name, rc.id());
-    println!("{}", msg);
+    output::message(&msg);
     lb.set_target(rc.id(), &msg)?;
     repo.set_head(&name)?;
-    repo.checkout_head(Some(
-        git2::build::CheckoutBuilder::default()
-            // For some reason the force is required to make the working directory actually get updated
-            // I suspect we should be adding some logic to handle dirty working directory states
-            // but this is just an example so maybe not.
-            .force(),
-    ))?;
+    common::checkout_lockspec(repo, &repo.head()?.peel(git2::ObjectType::Commit)?, {
+        let mut builder = git2::build::CheckoutBuilder::default();
+        // For some reason the force is required to make the working directory actually get updated
+        // I suspect we should be adding some logic to handle dirty working directory states
+        // but this is just an example so maybe not.
+        builder.force();
+        builder
+    })?;
     Ok(())
 }
 
@@ -47,7 +68,7 @@ fn normal_merge(
     let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
 
     if idx.has_conflicts() {
-        println!("Merge conflicts detected...");
+        output::warning("Merge conflicts detected...");
         repo.checkout_index(Some(&mut idx), None)?;
         return Ok(());
     }
@@ -67,48 +88,101 @@ fn normal_merge(
         &[&local_commit, &remote_commit],
     )?;
     // Set working tree to match head.
-    repo.checkout_head(None)?;
+    common::checkout_lockspec(
+        repo,
+        &repo.head()?.peel(git2::ObjectType::Commit)?,
+        git2::build::CheckoutBuilder::default(),
+    )?;
     Ok(())
 }
 
-pub fn execute(_args: Args) {
-    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
-        eprintln!("Couldn't recognize the araki repo: {err}");
-        exit(1);
-    });
-    let mut remote = repo.find_remote("origin").unwrap();
-
-    let mut callbacks = RemoteCallbacks::new();
-    // TODO: allow user to configure their ssh key
-    callbacks.credentials(|_url, username_from_url, _allowed_types| {
-        Cred::ssh_key_from_agent(username_from_url.unwrap())
-    });
+pub fn execute(args: Args) {
+    network::require_online("pull").unwrap_or_else(|err| output::fail(&err));
+    let install = args.install || get_settings().is_ok_and(|settings| settings.pull.autoinstall);
+    if args.all {
+        pull_all(args.fast_forward, install);
+        return;
+    }
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    let lockfile = ops::head_lockfile(&repo);
+    output::phase(1, 2, "Fetching changes from origin...");
+    let fetch_commit = match ArtifactOrigin::of(&repo) {
+        // Registries keep no history; their new tags are imported as commits instead
+        Some(_) => artifacts::fetch(&repo)
+            .and_then(|oid| {
+                repo.find_annotated_commit(oid)
+                    .map_err(|err| format!("Unable to find the fetched commit: {err}"))
+            })
+            .unwrap_or_else(|err| output::fail(&format!("Unable to pull from remote: {err}"))),
+        None => fetch_origin(&repo),
+    };
+    merge_fetched(&repo, &fetch_commit, lockfile, install);
+    output::success("Pulled changes from origin.");
+}
 
-    let mut fetch_opts = FetchOptions::new();
-    fetch_opts.remote_callbacks(callbacks);
-    fetch_opts.download_tags(AutotagOption::All);
+/// Fetch `main` and the tags of origin with git, returning the fetched commit.
+///
+/// * `repo`: araki repo
+fn fetch_origin(repo: &Repository) -> git2::AnnotatedCommit<'_> {
+    let mut remote = repo
+        .find_remote("origin")
+        .unwrap_or_else(|err| output::fail(&format!("Unable to find the remote 'origin': {err}")));
+    common::with_transfer_retries(|| {
+        let throttle = Throttle::new();
+        let mut callbacks = RemoteCallbacks::new();
+        // TODO: allow user to configure their ssh key
+        callbacks.credentials(|_url, username_from_url, _allowed_types| {
+            Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
+        });
+        let mut last_received = 0;
+        callbacks.transfer_progress(|stats| {
+            if stats.received_objects() != last_received {
+                last_received = stats.received_objects();
+                output::progress(stats.received_objects(), stats.total_objects());
+            }
+            throttle.wait(stats.received_bytes());
+            true
+        });
 
-    // Pull changes
-    remote
-        .fetch(&["main"], Some(&mut fetch_opts), None)
-        .expect("Unable to pull from remote");
+        let mut fetch_opts = FetchOptions::new();
+        fetch_opts.remote_callbacks(callbacks);
+        fetch_opts.download_tags(AutotagOption::All);
+        remote.fetch(&["main"], Some(&mut fetch_opts), None)
+    })
+    .unwrap_or_else(|err| output::fail(&format!("Unable to pull from remote: {err}")));
 
-    let fetch_head = repo.find_reference("FETCH_HEAD");
-    let fetch_commit = repo
-        .reference_to_annotated_commit(&fetch_head.unwrap())
-        .unwrap();
+    repo.find_reference("FETCH_HEAD")
+        .and_then(|fetch_head| repo.reference_to_annotated_commit(&fetch_head))
+        .unwrap_or_else(|err| output::fail(&format!("Unable to find the fetched commit: {err}")))
+}
 
+/// Fast-forward or merge the current branch to a fetched commit.
+///
+/// * `repo`: araki repo
+/// * `fetch_commit`: Commit fetched from origin
+/// * `lockfile`: Lockfile of HEAD before the pull, to tell whether it changed
+/// * `install`: Install the new lockfile if the pull fast-forwards to one
+fn merge_fetched(
+    repo: &Repository,
+    fetch_commit: &git2::AnnotatedCommit,
+    lockfile: Option<Oid>,
+    install: bool,
+) {
     // ref: https://github.com/rust-lang/git2-rs/blob/master/examples/pull.rs
     // Determine type of merge
-    let analysis = repo.merge_analysis(&[&fetch_commit]).unwrap();
+    let analysis = repo
+        .merge_analysis(&[fetch_commit])
+        .unwrap_or_else(|err| output::fail(&format!("Unable to analyze the merge: {err}")));
     // 2. Do the appropriate merge
     if analysis.0.is_fast_forward() {
-        println!("Doing a fast forward");
+        output::phase(2, 2, "Doing a fast forward");
         // do a fast forward
         let refname = format!("refs/heads/{}", "main");
         match repo.find_reference(&refname) {
             Ok(mut r) => {
-                fast_forward(&repo, &mut r, &fetch_commit).expect("Unable to fast forward");
+                fast_forward(repo, &mut r, fetch_commit)
+                    .unwrap_or_else(|err| output::fail(&format!("Unable to fast forward: {err}")));
             }
             Err(_) => {
                 // The branch doesn't exist so just set the reference to the
@@ -120,22 +194,140 @@ pub fn execute(_args: Args) {
                     true,
                     &format!("Setting {} to {}", "main", fetch_commit.id()),
                 )
-                .unwrap();
-                repo.set_head(&refname).unwrap();
-                repo.checkout_head(Some(
-                    git2::build::CheckoutBuilder::default()
+                .and_then(|_| repo.set_head(&refname))
+                .and_then(|_| {
+                    let mut builder = git2::build::CheckoutBuilder::default();
+                    builder
                         .allow_conflicts(true)
                         .conflict_style_merge(true)
-                        .force(),
-                ))
-                .expect("Unable to checkout head");
+                        .force();
+                    common::checkout_lockspec(
+                        repo,
+                        &repo.head()?.peel(git2::ObjectType::Commit)?,
+                        builder,
+                    )
+                })
+                .unwrap_or_else(|err| output::fail(&format!("Unable to checkout head: {err}")));
             }
         };
+        if install && ops::head_lockfile(repo) != lockfile {
+            output::message("Installing the new lockfile...");
+            ops::install_frozen(repo).unwrap_or_else(|err| output::fail(&err));
+        }
     } else if analysis.0.is_normal() {
         // do a normal merge
+        output::phase(2, 2, "Merging changes");
         let head_commit = repo
-            .reference_to_annotated_commit(&repo.head().unwrap())
-            .unwrap();
-        normal_merge(&repo, &head_commit, &fetch_commit).expect("Unable to merge");
+            .head()
+            .and_then(|head| repo.reference_to_annotated_commit(&head))
+            .unwrap_or_else(|err| output::fail(&format!("Unable to find the HEAD commit: {err}")));
+        normal_merge(repo, &head_commit, fetch_commit)
+            .unwrap_or_else(|err| output::fail(&format!("Unable to merge: {err}")));
+    } else {
+        output::phase(2, 2, "Already up to date");
+    }
+}
+
+/// Fetch the environments of all recorded projects and global environments concurrently, and
+/// report what is new in each.
+///
+/// * `fast_forward`: Also fast-forward `main` of the environments which have new commits
+/// * `install`: Install the new lockfiles of the environments which were fast-forwarded
+fn pull_all(fast_forward: bool, install: bool) {
+    let mut dirs: Vec<PathBuf> = projects::load()
+        .unwrap_or_else(|err| output::fail(&err))
+        .into_keys()
+        .collect();
+    // Shared environments which the user can't modify are left to their owner
+    for name in global::list().unwrap_or_default() {
+        if !global::is_writable(&name) {
+            continue;
+        }
+        if let Ok(dir) = global::get_global_env_dir(&name) {
+            dirs.push(dir);
+        }
+    }
+    dirs.retain(|dir| common::open_araki_git_repo(dir).is_ok());
+    if dirs.is_empty() {
+        output::success("No environments to pull.");
+        return;
+    }
+
+    output::phase(1, 1, &format!("Fetching {} environments...", dirs.len()));
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = dirs
+            .iter()
+            .map(|dir| {
+                scope.spawn(move || -> Result<(Updates, bool, bool), String> {
+                    let repo = common::open_araki_git_repo(dir).map_err(|err| err.to_string())?;
+                    let lockfile = ops::head_lockfile(&repo);
+                    let updates = ops::fetch_updates(&repo)?;
+                    let moved = fast_forward && ops::fast_forward_main(&repo)?;
+                    Ok((updates, moved, ops::head_lockfile(&repo) != lockfile))
+                })
+            })
+            .collect();
+        dirs.iter()
+            .cloned()
+            .zip(handles.into_iter().map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("The fetch panicked".to_string()))
+            }))
+            .collect()
+    });
+
+    let mut failed = 0;
+    let mut not_installed = 0;
+    for (dir, result) in results {
+        // Installs run one at a time, after the concurrent fetches
+        let installed = match &result {
+            Ok((_, true, true)) if install => Some(
+                common::open_araki_git_repo(&dir)
+                    .map_err(|err| err.to_string())
+                    .and_then(|repo| ops::install_frozen(&repo)),
+            ),
+            _ => None,
+        };
+        let dir = dir.display();
+        match result {
+            Ok((updates, _, _)) if updates.is_empty() => {
+                output::message(&format!("{dir}: up to date"))
+            }
+            Ok((updates, moved, _)) => {
+                let mut news = vec![];
+                if updates.new_commits > 0 {
+                    news.push(format!("{} new commits on main", updates.new_commits));
+                }
+                if !updates.new_tags.is_empty() {
+                    news.push(format!("new tags {}", updates.new_tags.join(", ")));
+                }
+                if moved {
+                    news.push("fast-forwarded".to_string());
+                }
+                match installed {
+                    Some(Ok(())) => news.push("installed the new lockfile".to_string()),
+                    Some(Err(err)) => {
+                        not_installed += 1;
+                        news.push(format!("unable to install the new lockfile: {err}"));
+                    }
+                    None => {}
+                }
+                output::message(&format!("{dir}: {}", news.join("; ")));
+            }
+            Err(err) => {
+                failed += 1;
+                output::warning(&format!("{dir}: {err}"));
+            }
+        }
+    }
+    if failed > 0 {
+        output::fail(&format!("Unable to fetch {failed} environments."));
+    }
+    if not_installed > 0 {
+        output::fail(&format!(
+            "Unable to install the new lockfiles of {not_installed} environments."
+        ));
     }
+    output::success("Fetched all environments.");
 }
@@ -1,25 +1,296 @@
 use clap::Parser;
-use std::process::exit;
+use clap_complete::ArgValueCompleter;
 
-use crate::common;
+use crate::cli::completion;
+use crate::cli::init::ORG;
+use crate::cli::tag::send_notification;
+use crate::output;
+use araki::artifacts::{self, ArtifactOrigin};
+use araki::backends;
+use araki::common::LockSpec;
+use araki::remote::parse_repo_arg;
+use araki::settings::get_settings;
+use araki::{common, ops, readme};
 
 #[derive(Parser, Debug, Default)]
 pub struct Args {
     /// name of the tag
-    #[arg()]
+    #[arg(add = ArgValueCompleter::new(completion::tags))]
     tag: String,
+
+    /// Print the refs that would be pushed without pushing them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Create the remote repository with the backend if it doesn't exist, and add it as origin
+    /// if the araki repo has none
+    #[arg(long)]
+    create: bool,
+}
+
+impl Args {
+    /// Arguments pushing `main` and a tag to origin.
+    ///
+    /// * `tag`: Name of the tag
+    pub fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+pub async fn execute(mut args: Args) {
+    if let Ok(repo) = common::get_araki_git_repo() {
+        args.tag = ops::full_tag_name(&repo, &args.tag);
+    }
+    let refs = [
+        "refs/heads/main".to_string(),
+        format!("refs/tags/{}", args.tag),
+    ];
+
+    if args.create {
+        create_remote(args.dry_run).await;
+    }
+
+    if args.dry_run {
+        let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
+            output::fail(&format!("Couldn't recognize the araki repo: {err}"))
+        });
+        if args.create && repo.find_remote("origin").is_err() {
+            return;
+        }
+        let remote = repo.find_remote("origin").unwrap_or_else(|err| {
+            output::fail(&format!(
+                "Unable to find the remote 'origin': {err}. Pass --create to create it."
+            ))
+        });
+        let origin = remote.url().unwrap_or("<unknown url>");
+        if repo
+            .head()
+            .and_then(|head| head.peel_to_tree())
+            .is_ok_and(|tree| tree.get_name(readme::README_FILE).is_none())
+        {
+            output::dry_run("Would commit a generated README.md if HEAD is on a branch");
+        }
+        for git_ref in &refs {
+            output::dry_run(&format!("Would push {git_ref} to origin ({origin})"));
+            for mirror in mirror_urls() {
+                output::dry_run(&format!("Would push {git_ref} to mirror {mirror}"));
+            }
+        }
+        return;
+    }
+
+    if common::get_araki_git_repo().is_ok_and(|repo| repo.find_remote("origin").is_err()) {
+        output::fail("The araki repo has no remote 'origin'. Pass --create to create it.");
+    }
+    check_protection(&args.tag);
+    check_policy(&refs);
+
+    let registry = common::get_araki_git_repo()
+        .ok()
+        .and_then(|repo| ArtifactOrigin::of(&repo));
+    if let Ok(repo) = common::get_araki_git_repo()
+        && registry.is_none()
+    {
+        match readme::ensure(&repo) {
+            Ok(true) => output::message("Committed a generated README.md"),
+            Ok(false) => {}
+            Err(err) => output::warning(&format!("Unable to generate a README.md: {err}")),
+        }
+    }
+
+    let refs: Vec<&str> = refs.iter().map(|r| r.as_str()).collect();
+    let mirrors = mirror_urls();
+    let total = 1 + mirrors.len();
+    match registry {
+        // Registries only hold the artifacts of tags, not the history of main
+        Some(origin) => {
+            output::phase(1, total, &format!("Pushing {} to {origin}...", args.tag));
+            common::get_araki_git_repo()
+                .map_err(|err| err.to_string())
+                .and_then(|repo| artifacts::push_tag(&repo, &args.tag))
+                .unwrap_or_else(|err| output::fail(&format!("Unable to push to remote: {err}")));
+        }
+        None => {
+            output::phase(
+                1,
+                total,
+                &format!("Pushing {} to origin...", refs.join(", ")),
+            );
+            common::git_push("origin", &refs)
+                .unwrap_or_else(|err| output::fail(&format!("Unable to push to remote: {err}")));
+        }
+    }
+
+    // The push to origin already succeeded, so mirrors which fail are reported together at the end
+    let mut failed = vec![];
+    for (step, mirror) in mirrors.iter().enumerate() {
+        output::phase(step + 2, total, &format!("Pushing to mirror {mirror}..."));
+        match common::git_push_url(mirror, &refs) {
+            Ok(()) => output::message(&format!("Pushed to {mirror}")),
+            Err(err) => {
+                output::warning(&format!("Unable to push to {mirror}: {err}"));
+                failed.push(mirror.as_str());
+            }
+        }
+    }
+
+    if let Ok(repo) = common::get_araki_git_repo() {
+        send_notification(&repo, "pushed", &args.tag).await;
+    }
+    if !failed.is_empty() {
+        output::fail(&format!(
+            "Pushed to origin, but not to {} of {} mirrors: {}",
+            failed.len(),
+            mirrors.len(),
+            failed.join(", ")
+        ));
+    }
+    output::success("Lockspec changes pushed to remote.");
+}
+
+/// Create the remote repository of the araki repo with the backend if it doesn't exist yet.
+///
+/// Without an `origin` remote, a repository named after the lockspec is created in the default
+/// organization and added as `origin`.
+///
+/// * `dry_run`: Only print what would be created
+async fn create_remote(dry_run: bool) {
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    let backend = backends::get_current_backend()
+        .unwrap_or_else(|err| output::fail(&format!("Unable to get the current backend: {err}")));
+    let origin = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|origin| origin.url().map(|url| url.to_string()));
+    let (org, name) = match (ArtifactOrigin::of(&repo), &origin) {
+        // OCI registries create repositories when the first artifact is pushed to them
+        (Some(ArtifactOrigin::Oci(_)), _) => return,
+        (Some(ArtifactOrigin::Registry(location)), _) => (location.org, location.name),
+        (None, Some(url)) => {
+            let remote = parse_repo_arg(url).unwrap_or_else(|err| {
+                output::fail(&format!("Unable to parse the URL of origin: {err}"))
+            });
+            (remote.get_org(), remote.get_repo())
+        }
+        (None, None) => {
+            let workdir = repo
+                .workdir()
+                .unwrap_or_else(|| output::fail("The araki repo has no working directory"));
+            let lockspec = LockSpec::from_path(workdir).unwrap_or_else(|err| output::fail(&err));
+            (ORG.to_string(), lockspec.display_name())
+        }
+    };
+    let repo_info = backend.get_repo_info(&org, &name);
+    let visibility = get_settings()
+        .unwrap_or_else(|err| output::fail(&err))
+        .init
+        .default_visibility;
+
+    let exists = origin.is_some()
+        && backend
+            .is_existing_lockspec(&org, &name)
+            .await
+            .unwrap_or_else(|err| {
+                output::fail(&format!(
+                    "Unable to check whether {org}/{name} exists: {err}"
+                ))
+            });
+    if !exists {
+        if dry_run {
+            output::dry_run(&format!(
+                "Would create {visibility} repository '{name}' for organization '{org}' at {}",
+                repo_info.as_url()
+            ));
+        } else {
+            output::message(&format!(
+                "Creating lockspec repository at {}...",
+                repo_info.as_url()
+            ));
+            backend
+                .create_repository(&org, &name, visibility)
+                .await
+                .unwrap_or_else(|err| {
+                    output::fail(&format!(
+                        "Error creating a new repository '{name}' for organization '{org}': {err}"
+                    ))
+                });
+        }
+    }
+    if origin.is_none() {
+        let url = repo_info.as_ssh_url();
+        if dry_run {
+            output::dry_run(&format!("Would add {url} as origin"));
+        } else {
+            repo.remote("origin", &url).unwrap_or_else(|err| {
+                output::fail(&format!("Unable to add {url} as origin: {err}"))
+            });
+        }
+    }
+}
+
+/// Get the URLs of the configured mirrors of the araki repo.
+fn mirror_urls() -> Vec<String> {
+    let settings = get_settings().unwrap_or_else(|err| output::fail(&err));
+    let origin = common::get_araki_git_repo()
+        .ok()
+        .and_then(|repo| {
+            repo.find_remote("origin")
+                .ok()
+                .and_then(|origin| origin.url().map(|url| url.to_string()))
+        })
+        .unwrap_or_default();
+    settings.remotes.mirror_urls(&origin)
+}
+
+/// Exit if a ref to push violates the content policy.
+///
+/// * `refs`: Refs to push
+fn check_policy(refs: &[String]) {
+    let policy = get_settings()
+        .unwrap_or_else(|err| output::fail(&err))
+        .policy;
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    for git_ref in refs {
+        policy
+            .check_rev(&repo, git_ref)
+            .unwrap_or_else(|err| output::fail(&format!("Refusing to push {git_ref}. {err}")));
+    }
 }
 
-pub fn execute(args: Args) {
-    common::git_push(
-        "origin",
-        &[
-            "refs/heads/main",
-            format!("refs/tags/{}", args.tag).as_str(),
-        ],
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("Unable to push to remote: {err}");
-        exit(1);
-    })
+/// Refuse to push a protected tag which already exists on origin at a different commit.
+///
+/// * `tag`: Name of the tag to push
+fn check_protection(tag: &str) {
+    let settings = get_settings().unwrap_or_else(|err| output::fail(&err));
+    if !settings.tag.is_protected(tag) {
+        return;
+    }
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+    if ArtifactOrigin::of(&repo).is_some() {
+        if artifacts::pushed_tag_differs(&repo, tag).unwrap_or_else(|err| output::fail(&err)) {
+            output::fail(&format!(
+                "Tag {tag} is protected and was pushed to the registry with other lockspec \
+                files; refusing to overwrite it."
+            ));
+        }
+        return;
+    }
+    let local = repo
+        .revparse_single(&format!("refs/tags/{tag}^{{commit}}"))
+        .unwrap_or_else(|err| output::fail(&format!("Unable to find tag {tag}: {err}")))
+        .id();
+    let remote = common::git_ls_remote_tags(&repo, "origin")
+        .unwrap_or_else(|err| output::fail(&format!("Unable to list the tags of origin: {err}")));
+    if remote.get(tag).is_some_and(|oid| *oid != local) {
+        output::fail(&format!(
+            "Tag {tag} is protected and points at a different commit on origin; refusing to \
+            overwrite it."
+        ));
+    }
 }
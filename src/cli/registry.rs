@@ -0,0 +1,51 @@
+use clap::Parser;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::exit;
+
+use araki::registry;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: RegistrySubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum RegistrySubcommand {
+    /// Serve lockspecs stored in a directory over HTTP
+    Serve(ServeArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct ServeArgs {
+    /// Directory where lockspecs are stored
+    #[arg(long, value_name = "PATH")]
+    root: PathBuf,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    address: SocketAddr,
+
+    /// Token clients must send to create or upload lockspecs
+    #[arg(long)]
+    token: Option<String>,
+}
+
+pub async fn execute(args: Args) {
+    match args.subcommand {
+        RegistrySubcommand::Serve(serve_args) => {
+            println!(
+                "Serving lockspecs from {:?} on http://{}",
+                serve_args.root, serve_args.address
+            );
+            registry::serve(serve_args.root, serve_args.address, serve_args.token)
+                .await
+                .unwrap_or_else(|err| {
+                    eprintln!("{err}");
+                    exit(1);
+                });
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use clap::Parser;
+use git2::Patch;
+use std::env::current_dir;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+use araki::common::{self, LinkState, LockSpec};
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Relink even if the project's lockspec files differ from the araki repo
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
+        eprintln!("Couldn't recognize the araki repo: {err}");
+        exit(1);
+    });
+
+    match lockspec.link_state(&repo) {
+        LinkState::Linked => {
+            println!("The lockspec is already linked to the araki repo.");
+            return;
+        }
+        LinkState::Broken(reason) => println!("{reason}"),
+    }
+
+    // Only relink files whose contents match the repo, unless the user forces it
+    let mut differs = false;
+    for file in ["pixi.toml", "pixi.lock"] {
+        let committed =
+            common::read_head_file(&repo, &lockspec.tracked_path(file)).unwrap_or_else(|err| {
+                eprintln!("Unable to read {file} from the araki repo: {err}");
+                exit(1);
+            });
+        let current = fs::read(lockspec.dir().join(file)).unwrap_or_else(|err| {
+            eprintln!("Unable to read {file}: {err}");
+            exit(1);
+        });
+        if committed != current {
+            differs = true;
+            print_diff(file, &committed, &current);
+        }
+    }
+    if differs && !args.force {
+        eprintln!(
+            "The lockspec differs from the araki repo. Run `araki tag` to save the changes or \
+            `araki checkout latest` to discard them, or rerun with --force to relink anyway."
+        );
+        exit(1);
+    }
+
+    repo.set_workdir(&cwd, true).unwrap_or_else(|err| {
+        eprintln!("Unable to set the araki repo's working directory to {cwd:?}: {err}");
+        exit(1);
+    });
+    let head = repo
+        .head()
+        .and_then(|head| head.peel(git2::ObjectType::Commit))
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to find the araki repo's HEAD commit: {err}");
+            exit(1);
+        });
+    let tracked = [
+        lockspec.tracked_path("pixi.toml"),
+        lockspec.tracked_path("pixi.lock"),
+    ];
+    repo.reset_default(Some(&head), tracked.iter().map(Path::new))
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to track the lockspec files in the araki repo: {err}");
+            exit(1);
+        });
+
+    println!("Lockspec relinked to the araki repo.");
+}
+
+/// Print a unified diff between the committed and current contents of a file.
+///
+/// * `file`: Name of the file being compared
+/// * `committed`: Contents of the file in the araki repo
+/// * `current`: Contents of the file in the project
+fn print_diff(file: &str, committed: &[u8], current: &[u8]) {
+    let patch = Patch::from_buffers(
+        committed,
+        Some(Path::new(file)),
+        current,
+        Some(Path::new(file)),
+        None,
+    )
+    .and_then(|mut patch| patch.to_buf());
+    match patch {
+        Ok(buf) => print!("{}", String::from_utf8_lossy(&buf)),
+        Err(err) => eprintln!("Unable to diff {file}: {err}"),
+    }
+}
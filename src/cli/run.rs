@@ -0,0 +1,95 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::path::PathBuf;
+use std::process::{Command, exit};
+
+use crate::cli::tag;
+use araki::common::{self, LockSpec};
+use araki::settings::get_settings;
+use araki::{ops, projects};
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Environment of the workspace to run the command in. Defaults to the lockspec in the
+    /// current directory, or the only environment of the workspace
+    #[arg(short, long)]
+    environment: Option<String>,
+
+    /// Command to run, followed by its arguments
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+pub fn execute(args: Args) {
+    let dir = environment_dir(args.environment.as_deref());
+    let lockspec = LockSpec::from_path(&dir).unwrap_or_else(|err| {
+        eprintln!("{err}. Run `araki sync` to create it.");
+        exit(1);
+    });
+
+    let env = ops::activation_env(&lockspec).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let status = Command::new("pixi")
+        .arg("run")
+        .arg("--manifest-path")
+        .arg(lockspec.specfile())
+        .args(
+            common::run_environment(&dir)
+                .map(|environment| ["--environment".to_string(), environment])
+                .into_iter()
+                .flatten(),
+        )
+        .args(&args.command)
+        .envs(env)
+        .status()
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to execute pixi run: {err}");
+            exit(1);
+        });
+    tag::auto_snapshot(&lockspec.dir());
+    // Usage statistics are bookkeeping only; failing to record them doesn't fail the command
+    let _ = projects::record_run(&dir);
+    exit(status.code().unwrap_or(1));
+}
+
+/// Get the directory of the environment to run in, exiting if it can't be determined.
+///
+/// * `environment`: Environment of the workspace; defaults to the lockspec in the current
+///   directory, or the only environment of the workspace
+pub fn environment_dir(environment: Option<&str>) -> PathBuf {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let environments = get_settings()
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        })
+        .environments;
+
+    match environment {
+        Some(name) => match environments.get(name) {
+            Some(env) => env.dir(&cwd, name),
+            None => {
+                eprintln!("No environment named {name} is declared.");
+                exit(1);
+            }
+        },
+        None if LockSpec::from_path(&cwd).is_ok() => cwd.clone(),
+        None if environments.len() == 1 => {
+            let (name, env) = environments.iter().next().expect("one environment");
+            env.dir(&cwd, name)
+        }
+        None => {
+            eprintln!(
+                "Select an environment with -e; available: {}",
+                environments.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use clap::{Parser, ValueEnum};
+use std::env::current_dir;
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+
+use araki::common::LockSpec;
+use araki::lockfile::LockFile;
+use araki::sbom;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    #[default]
+    Cyclonedx,
+
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Format of the SBOM
+    #[arg(long, value_enum, default_value_t)]
+    format: SbomFormat,
+
+    /// Write the SBOM to a file instead of stdout
+    #[arg(long)]
+    file: Option<PathBuf>,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let lockfile = LockFile::from_path(&lockspec.lockfile()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    let name = lockspec.display_name();
+
+    let document = match args.format {
+        SbomFormat::Cyclonedx => sbom::render_cyclonedx(&name, &lockfile),
+        SbomFormat::Spdx => sbom::render_spdx(&name, &lockfile),
+    };
+    let contents = serde_json::to_string_pretty(&document).unwrap_or_else(|err| {
+        eprintln!("Unable to serialize the SBOM: {err}");
+        exit(1);
+    });
+
+    match args.file {
+        Some(path) => {
+            fs::write(&path, contents + "\n").unwrap_or_else(|err| {
+                eprintln!("Unable to write {path:?}: {err}");
+                exit(1);
+            });
+            eprintln!("Wrote {path:?}");
+        }
+        None => println!("{contents}"),
+    }
+}
@@ -0,0 +1,293 @@
+use clap::Parser;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+use araki::common::{self, LinkState, LockSpec};
+use araki::ops;
+use araki::projects;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+const DEFAULT_WATCH_INTERVAL_MS: u64 = 2000;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Serve JSON-RPC 2.0 over stdin/stdout, one JSON message per line
+    #[arg(long, required = true)]
+    stdio: bool,
+}
+
+/// An error to be sent back to the client as a JSON-RPC error object.
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Get a required string parameter from the request params.
+///
+/// * `params`: Params of the request
+/// * `name`: Name of the parameter
+fn string_param(params: &Value, name: &str) -> Result<String, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or(RpcError::new(
+            INVALID_PARAMS,
+            format!("Missing string parameter '{name}'"),
+        ))
+}
+
+/// Get the status of the lockspec at the given path as JSON.
+///
+/// * `path`: Directory containing the lockspec
+fn status_json(path: &PathBuf) -> Result<Value, String> {
+    let lockspec = LockSpec::from_path(path)?;
+    let repo = common::open_araki_git_repo(path).map_err(|err| err.to_string())?;
+    let status = ops::status(&lockspec, &repo)?;
+    let (linked, link_error) = match status.link {
+        LinkState::Linked => (true, None),
+        LinkState::Broken(reason) => (false, Some(reason)),
+    };
+    Ok(json!({
+        "path": path,
+        "head": status.head,
+        "linked": linked,
+        "link_error": link_error,
+        "tag": status.tag,
+        "modified": status.modified,
+        "untracked": status.untracked,
+        "deleted": status.deleted,
+    }))
+}
+
+/// Handle a request which doesn't need access to server state.
+///
+/// * `method`: Name of the method to call
+/// * `params`: Params of the request
+fn handle_request(method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "status" => {
+            let path = PathBuf::from(string_param(params, "path")?);
+            status_json(&path).map_err(|err| RpcError::new(SERVER_ERROR, err))
+        }
+        "tags/list" => {
+            let path = PathBuf::from(string_param(params, "path")?);
+            let repo = common::open_araki_git_repo(&path)
+                .map_err(|err| RpcError::new(SERVER_ERROR, err))?;
+            let tags = ops::list_tags(&repo).map_err(|err| RpcError::new(SERVER_ERROR, err))?;
+            Ok(Value::Array(
+                tags.into_iter()
+                    .map(|tag| json!({"name": tag.name, "message": tag.message}))
+                    .collect(),
+            ))
+        }
+        "environments/list" => {
+            let projects = projects::load().map_err(|err| RpcError::new(SERVER_ERROR, err))?;
+            Ok(Value::Array(
+                projects
+                    .into_iter()
+                    .map(|(path, record)| {
+                        json!({
+                            "path": path,
+                            "environment": record.environment,
+                            "reference": record.reference,
+                            "last_used": record.last_used().map(|time| time.to_rfc3339()),
+                        })
+                    })
+                    .collect(),
+            ))
+        }
+        "checkout" => {
+            let path = PathBuf::from(string_param(params, "path")?);
+            let tag = string_param(params, "tag")?;
+            let repo = common::open_araki_git_repo(&path)
+                .map_err(|err| RpcError::new(SERVER_ERROR, err))?;
+            ops::checkout(&repo, &tag).map_err(|err| RpcError::new(SERVER_ERROR, err))?;
+            Ok(Value::Null)
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{method}'"),
+        )),
+    }
+}
+
+/// Get the `interval_ms` parameter of a watch request, which must be a positive integer.
+///
+/// * `params`: Params of the request
+fn interval_param(params: &Value) -> Result<Duration, RpcError> {
+    match params.get("interval_ms") {
+        None => Ok(Duration::from_millis(DEFAULT_WATCH_INTERVAL_MS)),
+        Some(value) => match value.as_u64() {
+            Some(interval) if interval > 0 => Ok(Duration::from_millis(interval)),
+            _ => Err(RpcError::new(
+                INVALID_PARAMS,
+                "Parameter 'interval_ms' must be a positive integer",
+            )),
+        },
+    }
+}
+
+/// Poll the status of a lockspec, sending a `drift` notification whenever it changes.
+///
+/// * `path`: Directory containing the lockspec
+/// * `interval`: How often to check the status
+/// * `sender`: Channel to send notifications to
+fn spawn_watch(path: PathBuf, interval: Duration, sender: mpsc::Sender<Value>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last: Option<Value> = None;
+        let mut ticker = time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let polled = path.clone();
+            let current = tokio::task::spawn_blocking(move || status_json(&polled))
+                .await
+                .unwrap_or_else(|err| Err(err.to_string()))
+                .unwrap_or_else(|err| json!({"path": path, "error": err}));
+
+            if last.as_ref().is_some_and(|last| *last != current) {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "drift",
+                    "params": current,
+                });
+                if sender.send(notification).await.is_err() {
+                    return;
+                }
+            }
+            last = Some(current);
+        }
+    })
+}
+
+pub async fn execute(_args: Args) {
+    let (sender, mut receiver) = mpsc::channel::<Value>(64);
+
+    // All output goes through a single writer so that responses and notifications don't interleave
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = receiver.recv().await {
+            let line = format!("{message}\n");
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut watches: HashMap<PathBuf, JoinHandle<()>> = HashMap::new();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(err) => {
+                eprintln!("Unable to read from stdin: {err}");
+                exit(1);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                let _ = sender
+                    .send(error_response(Value::Null, RpcError::new(PARSE_ERROR, err)))
+                    .await;
+                continue;
+            }
+        };
+        let id = request.get("id").cloned();
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            let error = RpcError::new(INVALID_REQUEST, "Missing method");
+            let _ = sender
+                .send(error_response(id.unwrap_or(Value::Null), error))
+                .await;
+            continue;
+        };
+
+        if method == "shutdown" {
+            if let Some(id) = id {
+                let _ = sender
+                    .send(json!({"jsonrpc": "2.0", "id": id, "result": null}))
+                    .await;
+            }
+            break;
+        }
+
+        let result = match method {
+            "watch" => string_param(&params, "path").and_then(|path| {
+                let interval = interval_param(&params)?;
+                let path = PathBuf::from(path);
+                let handle = spawn_watch(path.clone(), interval, sender.clone());
+                if let Some(previous) = watches.insert(path, handle) {
+                    previous.abort();
+                }
+                Ok(Value::Null)
+            }),
+            "unwatch" => string_param(&params, "path").map(|path| {
+                if let Some(handle) = watches.remove(&PathBuf::from(path)) {
+                    handle.abort();
+                }
+                Value::Null
+            }),
+            method => {
+                let method = method.to_string();
+                tokio::task::spawn_blocking(move || handle_request(&method, &params))
+                    .await
+                    .unwrap_or_else(|err| Err(RpcError::new(SERVER_ERROR, err)))
+            }
+        };
+
+        // Requests without an id are notifications, which get no response
+        if let Some(id) = id {
+            let response = match result {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err(error) => error_response(id, error),
+            };
+            let _ = sender.send(response).await;
+        }
+    }
+
+    for handle in watches.values() {
+        handle.abort();
+    }
+    drop(sender);
+    let _ = writer.await;
+}
+
+/// Build a JSON-RPC error response.
+///
+/// * `id`: Id of the request the error is a response to
+/// * `error`: Error to send
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": error.code, "message": error.message},
+    })
+}
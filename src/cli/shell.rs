@@ -1,5 +1,6 @@
 use clap::Parser;
 use directories::UserDirs;
+use std::env::current_dir;
 use std::fs::{Permissions, exists, remove_file, set_permissions};
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
@@ -11,7 +12,8 @@ use std::{
     str::FromStr,
 };
 
-use crate::common::get_araki_bin_dir;
+use araki::common::{LockSpec, get_araki_bin_dir};
+use araki::ops::activation_env;
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -33,6 +35,11 @@ pub enum ShellSubcommand {
     /// take precedence over other system binaries
     #[command(hide = true)]
     Generate(ShellArg),
+
+    /// Generate the exports of the environment variables declared for the lockspec in the
+    /// current directory, to be evaluated by the shell
+    #[command(hide = true)]
+    Env(ShellArg),
 }
 
 enum Shell {
@@ -123,7 +130,15 @@ impl Shell {
     fn print_env(&self) -> Result<(), String> {
         match self {
             Shell::Bash | Shell::Zsh => {
-                print!("PATH={}:$PATH", get_araki_bin_dir()?.to_string_lossy());
+                // Completions are generated by araki on every <TAB>, see `cli::completion`
+                print!(
+                    "PATH={}:$PATH; source <(COMPLETE={self} araki)",
+                    get_araki_bin_dir()?.to_string_lossy()
+                );
+                // Shells started in a lockspec get its environment variables as well
+                if let Ok(exports) = self.exports() {
+                    print!("; {exports}");
+                }
                 Ok(())
             }
             Shell::Unknown(shell) => {
@@ -132,6 +147,28 @@ impl Shell {
         }
     }
 
+    /// Get the commands exporting the environment variables declared for the lockspec in the
+    /// current directory. Nothing is exported outside of a lockspec.
+    fn exports(&self) -> Result<String, String> {
+        match self {
+            Shell::Bash | Shell::Zsh => {
+                let cwd = current_dir()
+                    .map_err(|err| format!("Could not get the current directory: {err}"))?;
+                let Ok(lockspec) = LockSpec::from_path(&cwd) else {
+                    return Ok(String::new());
+                };
+                Ok(activation_env(&lockspec)?
+                    .iter()
+                    .map(|(name, value)| {
+                        format!("export {name}='{}';", value.replace('\'', "'\\''"))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "))
+            }
+            Shell::Unknown(shell) => Err(format!("Cannot generate exports for {shell}")),
+        }
+    }
+
     /// Get the shell configuration file
     fn get_shell_config(&self) -> Result<PathBuf, String> {
         let home_dir = UserDirs::new()
@@ -215,5 +252,20 @@ pub fn execute(args: Args) {
 
             let _ = shell.print_env().map_err(|error| eprintln!("{error}"));
         }
+        ShellSubcommand::Env(shell_arg) => {
+            let shell: Shell = shell_arg
+                .shell
+                .map(|name| {
+                    name.parse::<Shell>().unwrap_or_else(|_| {
+                        unreachable!("All string shell names are valid Shell types")
+                    })
+                })
+                .unwrap_or_else(Shell::detect);
+
+            match shell.exports() {
+                Ok(exports) => println!("{exports}"),
+                Err(error) => eprintln!("{error}"),
+            }
+        }
     }
 }
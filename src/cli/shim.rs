@@ -1,8 +1,10 @@
 use clap::Parser;
 use std::env;
+use std::path::Path;
 use std::process::{Command, exit};
 
-use crate::common::get_araki_bin_dir;
+use crate::cli::tag;
+use araki::common::get_araki_bin_dir;
 
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
@@ -49,10 +51,18 @@ pub fn execute(args: Args) {
                     }
                 };
             }
-            let _ = command
+            let status = command
                 .args(arguments)
                 .status()
                 .map_err(|err| eprintln!("Error running command {tool}: {err}"));
+            if status.is_ok()
+                && Path::new(tool)
+                    .file_name()
+                    .is_some_and(|name| name == "pixi")
+                && let Ok(cwd) = env::current_dir()
+            {
+                tag::auto_snapshot(&cwd);
+            }
         } else {
             eprintln!("Could not destructure the command you passed.");
         }
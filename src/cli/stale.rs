@@ -0,0 +1,28 @@
+use clap::Parser;
+
+use crate::output;
+use araki::{common, ops, projects};
+
+#[derive(Parser, Debug)]
+pub struct Args {}
+
+pub fn execute(_args: Args) {
+    // Run on every activation, so problems are reported without failing the activation
+    let Ok(repo) = common::get_araki_git_repo() else {
+        return;
+    };
+    // Activations count as uses of the environment, see `araki gc`
+    if let Some(workdir) = repo.workdir() {
+        let _ = projects::record_use(workdir);
+    }
+    match ops::staleness(&repo) {
+        Ok(warnings) => {
+            for warning in warnings {
+                output::warning(&warning);
+            }
+        }
+        Err(err) => output::warning(&format!(
+            "Unable to check whether the environment is current: {err}"
+        )),
+    }
+}
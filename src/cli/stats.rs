@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use indicatif::HumanBytes;
+use std::fmt::Write;
+
+use crate::output;
+use araki::stats;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {}
+
+pub fn execute(_args: Args) {
+    let stats = stats::stats().unwrap_or_else(|err| output::fail(&err));
+    if stats.is_empty() {
+        println!(
+            "No projects are recorded. Projects are recorded when they are cloned or checked out."
+        );
+        return;
+    }
+
+    let width = stats
+        .iter()
+        .map(|env| env.environment.len())
+        .max()
+        .unwrap_or(0)
+        .max("ENVIRONMENT".len());
+    let mut text = format!(
+        "{:<width$}  {:>8}  {:>5}  {:>10}  {:>10}  {:>8}\n",
+        "ENVIRONMENT", "PROJECTS", "TAGS", "DISK", "LAST RUN", "UPDATED"
+    );
+    for env in &stats {
+        let _ = writeln!(
+            text,
+            "{:<width$}  {:>8}  {:>5}  {:>10}  {:>10}  {:>8}",
+            env.environment,
+            env.projects,
+            env.tags,
+            HumanBytes(env.disk_usage).to_string(),
+            env.last_run
+                .map(|time| time.format("%Y-%m-%d").to_string())
+                .unwrap_or("never".to_string()),
+            days_ago(env.updated),
+        );
+    }
+    output::page(&text);
+}
+
+/// Describe how many days ago something happened, e.g. `12d ago`.
+///
+/// * `time`: When it happened, if known
+fn days_ago(time: Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => format!("{}d ago", (Utc::now() - time).num_days()),
+        None => "-".to_string(),
+    }
+}
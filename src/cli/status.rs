@@ -0,0 +1,82 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::path::Path;
+use std::process::exit;
+
+use araki::common::{self, LinkState, LockSpec};
+use araki::ops;
+use araki::settings::get_settings;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {}
+
+pub fn execute(_args: Args) {
+    let cwd = current_dir().unwrap_or_else(|err| {
+        eprintln!("Could not get the current directory: {err}");
+        exit(1);
+    });
+    let environments = get_settings()
+        .map(|settings| settings.environments)
+        .unwrap_or_default();
+
+    if environments.is_empty() {
+        if let Err(err) = print_status(&cwd, "") {
+            eprintln!("{err}");
+            exit(1);
+        }
+        return;
+    }
+    for (name, env) in &environments {
+        println!("{name}:");
+        if let Err(err) = print_status(&env.dir(&cwd, name), "  ") {
+            println!("  {err}. Run `araki sync {name}` to create it.");
+        }
+    }
+}
+
+/// Print the state of the lockspec in a directory.
+///
+/// * `dir`: Directory containing the lockspec
+/// * `indent`: Prefix of every printed line
+fn print_status(dir: &Path, indent: &str) -> Result<(), String> {
+    let lockspec = LockSpec::from_path(dir)?;
+    let repo = common::open_araki_git_repo(dir)
+        .map_err(|err| format!("Couldn't recognize the araki repo: {err}"))?;
+    let status = ops::status(&lockspec, &repo)?;
+
+    match (status.head.starts_with("detached"), &status.tag) {
+        (true, _) => println!("{indent}HEAD {}", status.head),
+        (false, Some(tag)) => println!("{indent}On branch {}, at tag {tag}", status.head),
+        (false, None) => println!("{indent}On branch {}", status.head),
+    }
+    // A broken link and drifted contents have different fixes, so they are reported separately
+    match &status.link {
+        LinkState::Linked => println!("{indent}Linked to the araki repo"),
+        LinkState::Broken(reason) => {
+            println!("{indent}Link broken: {reason} Run `araki relink` to fix it.")
+        }
+    }
+    if status.modified.is_empty() {
+        println!("{indent}No changes to the lockspec");
+    } else {
+        println!("{indent}Content drifted from the araki repo:");
+        for file in &status.modified {
+            let change = if status.untracked.contains(file) {
+                "untracked"
+            } else if status.deleted.contains(file) {
+                "deleted"
+            } else {
+                "modified"
+            };
+            println!("{indent}  {change}: {file}");
+        }
+        let head = status
+            .head
+            .strip_prefix("detached at ")
+            .unwrap_or(&status.head);
+        println!(
+            "{indent}Run `araki tag` to save the changes, or `araki checkout {head} --force` to stash them."
+        );
+    }
+    Ok(())
+}
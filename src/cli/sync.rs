@@ -0,0 +1,74 @@
+use clap::Parser;
+use std::env::current_dir;
+
+use crate::output;
+use araki::ops::Progress;
+use araki::projects;
+use araki::settings::get_settings;
+use araki::workspace::{self, SyncAction};
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Environments to sync. Defaults to all environments of the project
+    #[arg(value_name = "NAME")]
+    names: Vec<String>,
+
+    /// pixi environment to install instead of the ones declared in araki.toml; can be repeated
+    #[arg(long = "environment", value_name = "ENVIRONMENT")]
+    environments: Vec<String>,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
+    let environments = get_settings()
+        .unwrap_or_else(|err| output::fail(&err))
+        .environments;
+    if environments.is_empty() {
+        output::fail("No environments are declared in the [environments] section of araki.toml.");
+    }
+    for name in &args.names {
+        if !environments.contains_key(name) {
+            output::fail(&format!("No environment named {name} is declared."));
+        }
+    }
+
+    let selected: Vec<_> = environments
+        .iter()
+        .filter(|(name, _)| args.names.is_empty() || args.names.contains(name))
+        .collect();
+    let mut failed = false;
+    for (step, (name, env)) in selected.iter().enumerate() {
+        output::phase(step + 1, selected.len(), &format!("Syncing {name}..."));
+        let mut env = (*env).clone();
+        if !args.environments.is_empty() {
+            env.environments = args.environments.clone();
+        }
+        let result = workspace::sync(&cwd, name, &env, &|progress| {
+            if let Progress::Transfer { received, total } = progress {
+                output::progress(received, total)
+            }
+        });
+        if result.is_ok() {
+            let _ = projects::record_use(&env.dir(&cwd, name));
+        }
+        match result {
+            Ok(SyncAction::Cloned) => output::message(&format!("{name}: cloned {}", env.lockspec)),
+            Ok(SyncAction::CheckedOut(tag)) => {
+                output::message(&format!("{name}: checked out {tag}"))
+            }
+            Ok(SyncAction::Reinstalled(environments)) => {
+                output::message(&format!("{name}: installed {}", environments.join(", ")))
+            }
+            Ok(SyncAction::UpToDate) => output::message(&format!("{name}: up to date")),
+            Err(err) => {
+                output::warning(&format!("{name}: {err}"));
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        output::fail("Some environments could not be synced.");
+    }
+    output::success("Environments synced.");
+}
@@ -1,77 +1,365 @@
-use clap::Parser;
-use git2::Signature;
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCompleter;
 use std::path::Path;
 use std::process::exit;
 
-use crate::common;
+use crate::cli::{completion, push};
+use crate::output;
+use araki::common::LockSpec;
+use araki::lockfile::LockFile;
+use araki::notifications::{self, Notification};
+use araki::settings::get_settings;
+use araki::tags::Bump;
+use araki::{common, ops};
 
 #[derive(Parser, Debug, Default)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<TagCommand>,
+
     // TODO: name of the environment, defaults to the current active environment
     // #[arg(short, long, help="Name of target environment. Defaults to the current active environment if available")]
     // name: Option<String>,
 
     // name of the tag
-    #[arg(help = "Name of the tag")]
-    tag: String,
+    #[arg(
+        help = "Name of the tag",
+        required_unless_present = "bump",
+        conflicts_with = "bump"
+    )]
+    tag: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Name the tag by incrementing the latest version tag, following the [tag] settings"
+    )]
+    bump: Option<BumpLevel>,
 
-    #[arg(short, long, help = "Description of the tag")]
+    #[arg(
+        short,
+        long,
+        help = "Description of the tag; defaults to a summary of the package changes since the previous tag"
+    )]
     description: Option<String>,
+
+    #[arg(long, help = "Print what would be done without committing or tagging")]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        help = "Move the tag if it already exists, unless it is protected"
+    )]
+    force: bool,
+
+    #[arg(
+        long = "ref",
+        value_name = "REF",
+        add = ArgValueCompleter::new(completion::tags),
+        help = "Tag an existing tag, branch, or commit instead of committing the working tree"
+    )]
+    reference: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip checking that the lockfile installs, even if tag.verify is set"
+    )]
+    no_verify: bool,
+
+    #[arg(long, help = "Push main and the new tag to origin")]
+    push: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "push",
+        help = "Don't push the new tag, even if tag.auto_push is set"
+    )]
+    no_push: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommand {
+    /// Delete the tags which the tag retention policy doesn't keep
+    Prune(PruneArgs),
 }
 
-pub fn execute(args: Args) {
+#[derive(Parser, Debug)]
+pub struct PruneArgs {
+    /// Keep this many of the most recent tags; overrides tag.retention.keep_last
+    #[arg(long, value_name = "N")]
+    keep_last: Option<usize>,
+
+    /// Keep tags matching this glob pattern, e.g. 'v*'; replaces tag.retention.keep_matching
+    #[arg(long, value_name = "PATTERN")]
+    keep_matching: Vec<String>,
+
+    /// Also delete the tags from origin
+    #[arg(long)]
+    remote: bool,
+
+    /// Print the tags that would be deleted without deleting them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BumpLevel {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl From<BumpLevel> for Bump {
+    fn from(level: BumpLevel) -> Self {
+        match level {
+            BumpLevel::Major => Bump::Major,
+            BumpLevel::Minor => Bump::Minor,
+            BumpLevel::Patch => Bump::Patch,
+        }
+    }
+}
+
+/// Post a notification about a tag of the environment to the configured webhook, if any.
+///
+/// Failing to notify only produces a warning, since the tag itself succeeded.
+///
+/// * `repo`: araki repo containing the tag
+/// * `action`: What happened to the tag, e.g. `tagged`
+/// * `tag`: Name of the tag
+pub async fn send_notification(repo: &git2::Repository, action: &str, tag: &str) {
+    let settings = match get_settings() {
+        Ok(settings) => settings.notifications,
+        Err(err) => return output::warning(&format!("Unable to send a notification: {err}")),
+    };
+    if settings.webhook.is_none() {
+        return;
+    }
+    let environment = repo
+        .workdir()
+        .map(|workdir| LockSpec::from_path(workdir).map(|lockspec| lockspec.display_name()))
+        .and_then(Result::ok)
+        .unwrap_or("araki".to_string());
+    let changes = ops::lock_changes(repo, tag).unwrap_or_else(|err| {
+        output::warning(&format!(
+            "Unable to list the package changes of {tag}: {err}"
+        ));
+        vec![]
+    });
+
+    let notification = Notification {
+        action,
+        environment: &environment,
+        tag,
+        changes: &changes,
+    };
+    if let Err(err) = notifications::notify(&settings, &notification).await {
+        output::warning(&format!("Unable to send a notification: {err}"));
+    }
+}
+
+pub async fn execute(args: Args) {
     let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
         eprintln!("Couldn't recognize the araki repo: {err}");
         exit(1);
     });
+    if let Some(TagCommand::Prune(prune_args)) = args.command {
+        return prune(&repo, prune_args);
+    }
+    let tag = match (args.tag, args.bump) {
+        (Some(tag), _) => tag,
+        (None, Some(level)) => next_tag(&repo, level),
+        (None, None) => unreachable!("clap requires a tag or --bump"),
+    };
+    let tag = ops::full_tag_name(&repo, &tag);
+    let push =
+        args.push || (!args.no_push && get_settings().is_ok_and(|settings| settings.tag.auto_push));
 
-    let mut index = repo.index().expect("Failed to get index");
-
-    // Add files
-    index
-        .add_path(Path::new("pixi.toml"))
-        .expect("unable to add pixi.toml");
-    index
-        .add_path(Path::new("pixi.lock"))
-        .expect("unable to add pixi.lock");
-    index.write().expect("Failed to write index");
-
-    let tree_oid = index.write_tree().expect("failed to write tree");
-    let tree = repo.find_tree(tree_oid).expect("failed to find tree");
-    let signature = Signature::now("araki", "place@holder.com").expect("failed to get signature");
-    let head = repo.head().expect("Failed to get HEAD");
-    let parent_commit = repo
-        .find_commit(head.target().expect("Failed to get HEAD target OID"))
-        .expect("Failed to find parent commit");
-
-    // Commit change
-    repo.commit(
-        Some("HEAD"),      // Update the HEAD reference
-        &signature,        // Author
-        &signature,        // Committer
-        &args.tag,         // Commit message
-        &tree,             // Tree containing the staged changes
-        &[&parent_commit], // Parent commit(s)
-    )
-    .expect("Failed to create commit");
-
-    // Create tag
-    // Get the OID of the commit to tag (e.g., HEAD)
-    let head = repo.revparse_single("HEAD").expect("unable to find HEAD");
-
-    let tag_message: String;
-    if let Some(ref message) = args.description {
-        tag_message = message.to_string();
-    } else {
-        tag_message = format!("araki environment tag: {}", args.tag)
+    if args.dry_run {
+        match &args.reference {
+            Some(reference) => {
+                let commit = repo
+                    .revparse_single(&format!("{reference}^{{commit}}"))
+                    .unwrap_or_else(|_| {
+                        eprintln!("No tag, branch, or commit found: {reference}");
+                        exit(1);
+                    });
+                output::dry_run(&format!("Would tag commit {:.7}", commit.id()));
+            }
+            None => {
+                let head = repo.head().expect("Failed to get HEAD");
+                output::dry_run(&format!(
+                    "Would commit pixi.toml and pixi.lock to {} with message '{}'",
+                    head.shorthand().unwrap_or("HEAD"),
+                    tag
+                ));
+            }
+        }
+        let exists = repo.find_reference(&format!("refs/tags/{tag}")).is_ok();
+        if exists && !args.force {
+            eprintln!("Tag {tag} already exists; tagging would fail.");
+            exit(1);
+        }
+        if exists && get_settings().is_ok_and(|settings| settings.tag.is_protected(&tag)) {
+            eprintln!("Tag {tag} is protected; moving it would fail.");
+            exit(1);
+        }
+        let message = args
+            .description
+            .clone()
+            .unwrap_or_else(|| generated_message(&repo, &tag, args.reference.as_deref()));
+        output::dry_run(&format!(
+            "Would {} tag {} with message '{}'",
+            if exists { "move" } else { "create" },
+            tag,
+            message.trim_end()
+        ));
+        if push {
+            output::dry_run(&format!(
+                "Would push refs/heads/main and refs/tags/{tag} to origin"
+            ));
+        }
+        return;
     }
 
-    repo.tag(
-        &args.tag,
-        &head,
-        &signature,
-        &tag_message,
-        false, // Set to false for an annotated tag, true for a lightweight tag
-    )
-    .expect("Unable to tag");
+    let result = match &args.reference {
+        // The lockfile of a historical commit isn't in the working tree, so it isn't verified
+        Some(reference) => ops::tag_ref(
+            &repo,
+            &tag,
+            reference,
+            args.description.as_deref(),
+            args.force,
+        ),
+        None => {
+            if !args.no_verify && get_settings().is_ok_and(|settings| settings.tag.verify) {
+                verify(&repo);
+            }
+            ops::tag(&repo, &tag, args.description.as_deref(), args.force)
+        }
+    };
+    result.unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    send_notification(&repo, "tagged", &tag).await;
+    if push {
+        push::execute(push::Args::new(&tag)).await;
+    }
+}
+
+/// Snapshot the lockspec in a directory after pixi ran, if the `tag.auto_snapshot` setting is
+/// enabled. Failing to snapshot only produces a warning.
+///
+/// * `dir`: Directory containing the lockspec
+pub fn auto_snapshot(dir: &Path) {
+    if !get_settings().is_ok_and(|settings| settings.tag.auto_snapshot) {
+        return;
+    }
+    let Ok(repo) = common::open_araki_git_repo(dir) else {
+        return;
+    };
+    match ops::snapshot(&repo) {
+        Ok(Some(tag)) => eprintln!("Saved the changed pixi.lock as {tag}"),
+        Ok(None) => {}
+        Err(err) => eprintln!("warning: Unable to snapshot pixi.lock: {err}"),
+    }
+}
+
+/// Delete the tags which the retention policy doesn't keep, and report what was deleted.
+///
+/// * `repo`: araki repo to prune
+/// * `args`: Arguments of the prune command, overriding the `[tag.retention]` settings
+fn prune(repo: &git2::Repository, args: PruneArgs) {
+    let mut settings = get_settings()
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        })
+        .tag;
+    if args.keep_last.is_some() {
+        settings.retention.keep_last = args.keep_last;
+    }
+    if !args.keep_matching.is_empty() {
+        settings.retention.keep_matching = args.keep_matching;
+    }
+
+    let tags = ops::prunable_tags(repo, &settings).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    if tags.is_empty() {
+        println!("No tags to prune.");
+        return;
+    }
+    for tag in &tags {
+        if args.dry_run {
+            output::dry_run(&format!("Would delete tag {tag}"));
+            continue;
+        }
+        ops::untag(repo, tag, args.remote).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+        println!("Deleted tag {tag}");
+    }
+}
+
+/// Check that the lockfile of the repo installs before it is tagged, exiting if it doesn't.
+///
+/// * `repo`: araki repo to tag
+fn verify(repo: &git2::Repository) {
+    let lockspec = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory".to_string())
+        .and_then(LockSpec::from_path)
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1);
+        });
+    eprintln!("Verifying that the lockfile installs...");
+    ops::verify_installable(&lockspec).unwrap_or_else(|err| {
+        eprintln!("{err} Not tagging; use --no-verify to tag anyway.");
+        exit(1);
+    });
+}
+
+/// Generate the message a tag would get without a description, from the lockfile of the
+/// reference or of the working tree.
+///
+/// * `repo`: araki repo to tag
+/// * `tag`: Full name of the tag
+/// * `reference`: Tag, branch, or commit to tag; the working tree if `None`
+fn generated_message(repo: &git2::Repository, tag: &str, reference: Option<&str>) -> String {
+    let rev = reference.unwrap_or("HEAD");
+    let commit = repo
+        .revparse_single(&format!("{rev}^{{commit}}"))
+        .map(|commit| commit.id());
+    let lockfile = match reference {
+        Some(reference) => {
+            common::read_rev_file(repo, reference, &common::tracked_path(repo, "pixi.lock"))
+                .map_err(|err| err.to_string())
+                .and_then(|contents| LockFile::parse(&String::from_utf8_lossy(&contents)))
+        }
+        None => repo
+            .workdir()
+            .ok_or("The araki repo has no working directory".to_string())
+            .and_then(LockSpec::from_path)
+            .and_then(|lockspec| LockFile::from_path(&lockspec.lockfile())),
+    };
+    match (lockfile, commit) {
+        (Ok(lockfile), Ok(commit)) => ops::tag_message(repo, tag, &lockfile, commit),
+        _ => format!("araki environment tag: {tag}"),
+    }
+}
+
+/// Get the name of the tag following the latest version tag of the repo.
+///
+/// * `repo`: araki repo to tag
+/// * `level`: Part of the version to increment
+fn next_tag(repo: &git2::Repository, level: BumpLevel) -> String {
+    ops::next_tag(repo, level.into()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    })
 }
@@ -0,0 +1,72 @@
+use clap::Parser;
+use std::process::{Command, exit};
+
+use crate::cli::{run, tag};
+use araki::common::{self, LockSpec};
+use araki::projects;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Environment of the workspace whose task to run. Defaults to the lockspec in the current
+    /// directory, or the only environment of the workspace
+    #[arg(short, long)]
+    environment: Option<String>,
+
+    /// Task defined in the pixi.toml of the environment. Lists the tasks if omitted
+    name: Option<String>,
+
+    /// Arguments passed to the task
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+pub fn execute(args: Args) {
+    let dir = run::environment_dir(args.environment.as_deref());
+    let lockspec = LockSpec::from_path(&dir).unwrap_or_else(|err| {
+        eprintln!("{err}. Run `araki sync` to create it.");
+        exit(1);
+    });
+    let tasks = lockspec.tasks().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+
+    let Some(name) = args.name else {
+        if tasks.is_empty() {
+            println!("The environment defines no tasks.");
+        }
+        for task in tasks {
+            println!("{task}");
+        }
+        return;
+    };
+    if !tasks.contains(&name) {
+        eprintln!(
+            "The environment defines no task named {name}. Available tasks: {}",
+            tasks.join(", ")
+        );
+        exit(1);
+    }
+
+    let status = Command::new("pixi")
+        .arg("run")
+        .arg("--manifest-path")
+        .arg(lockspec.specfile())
+        .args(
+            common::run_environment(&dir)
+                .map(|environment| ["--environment".to_string(), environment])
+                .into_iter()
+                .flatten(),
+        )
+        .arg(&name)
+        .args(&args.args)
+        .status()
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to execute pixi run: {err}");
+            exit(1);
+        });
+    tag::auto_snapshot(&lockspec.dir());
+    // Usage statistics are bookkeeping only; failing to record them doesn't fail the command
+    let _ = projects::record_run(&dir);
+    exit(status.code().unwrap_or(1));
+}
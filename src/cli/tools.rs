@@ -0,0 +1,180 @@
+use clap::Parser;
+
+use crate::cli::init::ORG;
+use crate::output;
+use araki::backends;
+use araki::ops::Progress;
+use araki::remote::RemoteRepo;
+use araki::settings::get_settings;
+use araki::tools;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    subcommand: ToolsSubcommand,
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub enum ToolsSubcommand {
+    /// Restore the tools of a tag and install them with pixi global
+    Checkout(CheckoutArgs),
+
+    /// Clone a tools repo and install its tools with pixi global
+    Clone(CloneArgs),
+
+    /// Import the tools installed with pixi global into a new, tagged lockspec repo
+    Import(ImportArgs),
+
+    /// Create a lockspec repo for the tools installed with pixi global
+    Init(InitArgs),
+
+    /// List the tags of the tools repo
+    List,
+
+    /// Push the tools repo and its tags to the remote
+    Push,
+
+    /// Save the current set of tools installed with pixi global
+    Tag(TagArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct CheckoutArgs {
+    /// Tag, branch, or commit to restore
+    tag: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct CloneArgs {
+    /// URL or <github org>/<repo name> of the tools repo
+    #[arg(value_name = "NAME")]
+    env: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct ImportArgs {
+    /// Name of the repo to create
+    name: String,
+
+    /// Name of the tag of the imported tools
+    #[arg(long, default_value = "v1")]
+    tag: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct InitArgs {
+    /// Name of the repo to create
+    name: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct TagArgs {
+    /// Name of the tag
+    tag: String,
+
+    /// Description of the tag
+    #[arg(short, long, value_name = "MESSAGE")]
+    message: Option<String>,
+}
+
+pub async fn execute(args: Args) {
+    match args.subcommand {
+        ToolsSubcommand::Checkout(args) => {
+            let repo = tools::open().unwrap_or_else(|err| output::fail(&err));
+            tools::checkout(&repo, &args.tag).unwrap_or_else(|err| output::fail(&err));
+            output::success(&format!("Restored the tools of {}", args.tag));
+        }
+        ToolsSubcommand::Clone(args) => {
+            tools::clone(&args.env, &|progress| match progress {
+                Progress::Phase {
+                    step,
+                    total,
+                    message,
+                } => output::phase(step, total, message),
+                Progress::Transfer { received, total } => output::progress(received, total),
+            })
+            .unwrap_or_else(|err| output::fail(&err));
+            output::success(&format!("Installed the tools of {}", args.env));
+        }
+        ToolsSubcommand::Import(args) => {
+            // Check for tools before creating a remote repo which would stay empty
+            let envs = tools::manifest_envs().unwrap_or_else(|err| output::fail(&err));
+            if envs.is_empty() {
+                output::fail("The pixi global manifest has no environments to import.");
+            }
+            if tools::open().is_ok() {
+                output::fail("A tools repo already exists; use `araki tools tag` instead.");
+            }
+            output::message(&format!("Importing {}", envs.join(", ")));
+            let repo_info = create_repository(&args.name).await;
+            output::phase(
+                2,
+                2,
+                "Committing, tagging, and pushing the pixi global manifest...",
+            );
+            tools::import(&repo_info.as_ssh_url(), &args.tag)
+                .unwrap_or_else(|err| output::fail(&err));
+            output::success(&format!(
+                "Imported {} environments to {} as {}",
+                envs.len(),
+                repo_info.as_url(),
+                args.tag
+            ));
+        }
+        ToolsSubcommand::Init(args) => {
+            let repo_info = create_repository(&args.name).await;
+            output::phase(2, 2, "Committing and pushing the pixi global manifest...");
+            tools::init(&repo_info.as_ssh_url()).unwrap_or_else(|err| output::fail(&err));
+            output::success(&format!("Tools are versioned in {}", repo_info.as_url()));
+        }
+        ToolsSubcommand::List => {
+            let repo = tools::open().unwrap_or_else(|err| output::fail(&err));
+            let names = tools::list(&repo).unwrap_or_else(|err| output::fail(&err));
+            if names.is_empty() {
+                println!("No tags found.");
+            }
+            for name in names {
+                println!("{name}");
+            }
+        }
+        ToolsSubcommand::Push => {
+            let repo = tools::open().unwrap_or_else(|err| output::fail(&err));
+            tools::push(&repo).unwrap_or_else(|err| output::fail(&err));
+            output::success("Pushed the tools repo to the remote");
+        }
+        ToolsSubcommand::Tag(args) => {
+            let repo = tools::open().unwrap_or_else(|err| output::fail(&err));
+            tools::tag(&repo, &args.tag, args.message.as_deref())
+                .unwrap_or_else(|err| output::fail(&err));
+            output::success(&format!("Tagged the current tools as {}", args.tag));
+        }
+    }
+}
+
+/// Create the remote repo of the tools with the current backend, as the first of two phases.
+///
+/// * `name`: Name of the repo to create
+async fn create_repository(name: &str) -> RemoteRepo {
+    let backend = backends::get_current_backend()
+        .unwrap_or_else(|err| output::fail(&format!("Unable to get the current backend: {err}")));
+    let repo_info = backend.get_repo_info(ORG, name);
+    let visibility = get_settings()
+        .unwrap_or_else(|err| output::fail(&err))
+        .init
+        .default_visibility;
+    output::phase(
+        1,
+        2,
+        &format!("Creating the tools repository at {}...", repo_info.as_url()),
+    );
+    backend
+        .create_repository(ORG, name, visibility)
+        .await
+        .unwrap_or_else(|err| {
+            output::fail(&format!(
+                "Error creating a new repository '{name}' for organization '{ORG}': {err}"
+            ))
+        });
+    repo_info
+}
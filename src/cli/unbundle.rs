@@ -0,0 +1,47 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::path::PathBuf;
+
+use crate::output;
+use araki::bundle;
+use araki::ops::Progress;
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct Args {
+    /// Bundle written by `araki bundle`
+    #[arg(value_name = "BUNDLE")]
+    archive: PathBuf,
+
+    /// Path where the lockspec should be unpacked. Defaults to the current directory
+    #[arg(short, long, value_name = "PATH")]
+    path: Option<PathBuf>,
+}
+
+pub async fn execute(args: Args) {
+    let path = args.path.unwrap_or_else(|| {
+        current_dir().unwrap_or_else(|err| {
+            output::fail(&format!("Could not get the current directory: {err}"))
+        })
+    });
+    let manifest = bundle::unbundle(&args.archive, &path, &|progress| match progress {
+        Progress::Phase {
+            step,
+            total,
+            message,
+        } => output::phase(step, total, message),
+        Progress::Transfer { received, total } => output::progress(received, total),
+    })
+    .await
+    .unwrap_or_else(|err| output::fail(&err));
+    match &manifest.reference {
+        Some(reference) => output::success(&format!(
+            "Installed {} at {reference} from {:?} to {path:?}",
+            manifest.name, args.archive
+        )),
+        None => output::success(&format!(
+            "Installed {} from {:?} to {path:?}",
+            manifest.name, args.archive
+        )),
+    }
+}
@@ -0,0 +1,29 @@
+use clap::Parser;
+use clap_complete::ArgValueCompleter;
+use std::process::exit;
+
+use crate::cli::completion;
+use araki::{common, ops};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Name of the tag to delete
+    #[arg(add = ArgValueCompleter::new(completion::tags))]
+    tag: String,
+
+    /// Also delete the tag from origin
+    #[arg(long)]
+    remote: bool,
+}
+
+pub fn execute(args: Args) {
+    let repo = common::get_araki_git_repo().unwrap_or_else(|err| {
+        eprintln!("Couldn't recognize the araki repo: {err}");
+        exit(1);
+    });
+    ops::untag(&repo, &args.tag, args.remote).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    println!("Deleted tag {}", args.tag);
+}
@@ -0,0 +1,116 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::path::PathBuf;
+
+use crate::cli::tag::{BumpLevel, send_notification};
+use crate::output;
+use araki::{common, global, ops, projects};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Update every environment of the recorded projects and the global environments, instead
+    /// of the one in the current directory
+    #[arg(long)]
+    all: bool,
+
+    /// With --all, only update the environments whose name or path contains this text
+    #[arg(long, value_name = "TEXT", requires = "all")]
+    matching: Option<String>,
+
+    /// Only update this package; can be given several times
+    #[arg(long, value_name = "PACKAGE")]
+    only: Vec<String>,
+
+    /// Part of the version to increment for the tags of updated environments
+    #[arg(long, value_enum, default_value = "patch")]
+    bump: BumpLevel,
+}
+
+pub async fn execute(args: Args) {
+    let dirs = if args.all {
+        environment_dirs(args.matching.as_deref())
+    } else {
+        vec![current_dir().unwrap_or_else(|err| {
+            output::fail(&format!("Could not get the current directory: {err}"))
+        })]
+    };
+    if dirs.is_empty() {
+        output::success("No environments to update.");
+        return;
+    }
+
+    let total = dirs.len();
+    let mut updated = vec![];
+    let mut failed = vec![];
+    for (step, dir) in dirs.iter().enumerate() {
+        output::phase(step + 1, total, &format!("Updating {}...", dir.display()));
+        let result = common::open_araki_git_repo(dir)
+            .map_err(|err| format!("Couldn't recognize the araki repo: {err}"))
+            .and_then(|repo| {
+                let result = ops::update(&repo, &args.only, args.bump.into())?;
+                Ok((repo, result))
+            });
+        match result {
+            Ok((repo, Some((tag, changes)))) => {
+                output::message(&format!(
+                    "Tagged {tag} with {} package changes",
+                    changes.len()
+                ));
+                for change in &changes {
+                    output::message(&format!("  {change}"));
+                }
+                send_notification(&repo, "tagged", &tag).await;
+                updated.push(format!("{} ({tag})", dir.display()));
+            }
+            Ok((_, None)) => output::message("Already up to date"),
+            Err(err) => {
+                output::warning(&err);
+                failed.push(dir.display().to_string());
+            }
+        }
+    }
+
+    if !updated.is_empty() {
+        output::message(&format!("Updated: {}", updated.join(", ")));
+    }
+    if !failed.is_empty() {
+        output::fail(&format!(
+            "Unable to update {} of {total} environments: {}",
+            failed.len(),
+            failed.join(", ")
+        ));
+    }
+    output::success(&format!(
+        "Updated {} of {total} environments; push the new tags with `araki push <tag>`",
+        updated.len()
+    ));
+}
+
+/// Get the directories of the environments of the recorded projects and the global
+/// environments.
+///
+/// * `matching`: Only include environments whose name or path contains this text
+fn environment_dirs(matching: Option<&str>) -> Vec<PathBuf> {
+    let mut environments: Vec<(String, PathBuf)> = projects::load()
+        .unwrap_or_else(|err| output::fail(&err))
+        .into_iter()
+        .map(|(dir, record)| (record.environment, dir))
+        .collect();
+    // Shared environments which the user can't modify are left to their owner
+    for name in global::list().unwrap_or_default() {
+        if !global::is_writable(&name) {
+            continue;
+        }
+        if let Ok(dir) = global::get_global_env_dir(&name) {
+            environments.push((name, dir));
+        }
+    }
+    environments
+        .into_iter()
+        .filter(|(name, dir)| {
+            matching.is_none_or(|text| name.contains(text) || dir.to_string_lossy().contains(text))
+        })
+        .map(|(_, dir)| dir)
+        .filter(|dir| common::open_araki_git_repo(dir).is_ok())
+        .collect()
+}
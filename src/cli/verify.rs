@@ -0,0 +1,78 @@
+use clap::Parser;
+use clap_complete::ArgValueCompleter;
+
+use crate::cli::completion;
+use crate::output;
+use araki::common::{self, LockSpec};
+use araki::rebuild;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Install the lockfile of this tag into a scratch directory and compare the installed
+    /// packages and their hashes with the lockfile
+    #[arg(long, value_name = "TAG", required = true, add = ArgValueCompleter::new(completion::tags))]
+    rebuild: String,
+
+    /// Also compare the rebuilt packages with the environment installed in the project
+    #[arg(long)]
+    installed: bool,
+}
+
+pub fn execute(args: Args) {
+    let repo = common::get_araki_git_repo()
+        .unwrap_or_else(|err| output::fail(&format!("Couldn't recognize the araki repo: {err}")));
+
+    output::phase(1, 1, &format!("Rebuilding {}...", args.rebuild));
+    let report = rebuild::rebuild(&repo, &args.rebuild).unwrap_or_else(|err| output::fail(&err));
+    let mut problems = report.discrepancies.len();
+    if report.discrepancies.is_empty() {
+        output::message(&format!(
+            "The {} conda packages installed for {} match the lockfile",
+            report.installed.len(),
+            report.platform
+        ));
+    } else {
+        output::warning(&format!(
+            "The rebuilt environment differs from the lockfile of {}:",
+            args.rebuild
+        ));
+        for discrepancy in &report.discrepancies {
+            output::message(&format!("  {discrepancy}"));
+        }
+    }
+
+    if args.installed {
+        let prefix = repo
+            .workdir()
+            .ok_or("The araki repo has no working directory".to_string())
+            .and_then(LockSpec::from_path)
+            .map(|lockspec| lockspec.env_prefix())
+            .unwrap_or_else(|err| output::fail(&err));
+        let installed =
+            rebuild::installed_packages(&prefix).unwrap_or_else(|err| output::fail(&err));
+        if installed.is_empty() {
+            output::fail(&format!(
+                "No environment is installed at {}. Run `pixi install` first.",
+                prefix.display()
+            ));
+        }
+        let discrepancies = rebuild::compare(&report.installed, &installed);
+        if discrepancies.is_empty() {
+            output::message("The installed environment matches the rebuild");
+        } else {
+            output::warning("The installed environment differs from the rebuild:");
+            for discrepancy in &discrepancies {
+                output::message(&format!("  {discrepancy}"));
+            }
+        }
+        problems += discrepancies.len();
+    }
+
+    if problems > 0 {
+        output::fail(&format!(
+            "{} is not reproducible: found {problems} differences",
+            args.rebuild
+        ));
+    }
+    output::success(&format!("{} rebuilds reproducibly", args.rebuild));
+}
@@ -0,0 +1,41 @@
+use clap::Parser;
+use std::env::current_dir;
+use std::path::PathBuf;
+use std::process::exit;
+
+use araki::projects;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Project directory. Defaults to the current directory
+    path: Option<PathBuf>,
+}
+
+pub fn execute(args: Args) {
+    let path = args.path.map(Ok).unwrap_or_else(current_dir);
+    let project = path
+        .and_then(|path| path.canonicalize())
+        .unwrap_or_else(|err| {
+            eprintln!("Could not resolve the project directory: {err}");
+            exit(1);
+        });
+    let projects = projects::load().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    match projects.get(&project) {
+        Some(record) => println!(
+            "{}@{}",
+            record.environment,
+            record.reference.as_deref().unwrap_or("main")
+        ),
+        None => {
+            eprintln!(
+                "{} is not a recorded araki project. Projects are recorded when they are cloned \
+                or checked out.",
+                project.display()
+            );
+            exit(1);
+        }
+    }
+}
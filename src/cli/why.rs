@@ -0,0 +1,42 @@
+use clap::Parser;
+use std::env::current_dir;
+
+use crate::output;
+use araki::common::LockSpec;
+use araki::lockfile::LockFile;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Name of the package to explain
+    package: String,
+}
+
+pub fn execute(args: Args) {
+    let cwd = current_dir()
+        .unwrap_or_else(|err| output::fail(&format!("Could not get the current directory: {err}")));
+    let lockspec = LockSpec::from_path(&cwd).unwrap_or_else(|err| output::fail(&err));
+    let lockfile =
+        LockFile::from_path(&lockspec.lockfile()).unwrap_or_else(|err| output::fail(&err));
+    let dependencies = lockspec
+        .dependencies()
+        .unwrap_or_else(|err| output::fail(&err));
+
+    let chains = lockfile.dependency_chains(&dependencies, &args.package);
+    if chains.is_empty() {
+        match lockfile.find(&args.package) {
+            Some(package) => output::fail(&format!(
+                "{} is locked, but none of the dependencies in pixi.toml require it. Run `pixi \
+                install` to update pixi.lock.",
+                package.name
+            )),
+            None => output::fail(&format!("{} is not in pixi.lock", args.package)),
+        }
+    }
+
+    for chain in &chains {
+        match chain.as_slice() {
+            [package] => println!("{package} is a dependency in pixi.toml"),
+            chain => println!("{}", chain.join(" -> ")),
+        }
+    }
+}
@@ -1,22 +1,54 @@
 use directories::{ProjectDirs, UserDirs};
 use fs::OpenOptions;
-use git2::build::RepoBuilder;
-use git2::{Cred, FetchOptions, PushOptions, RemoteCallbacks, Repository};
-use std::env::{current_dir, temp_dir};
+use git2::build::CheckoutBuilder;
+use git2::{
+    AutotagOption, Cred, Direction, ErrorClass, ErrorCode, FetchOptions, Object, ObjectType, Oid,
+    PushOptions, Remote, RemoteCallbacks, Repository, TreeWalkMode, TreeWalkResult,
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env::current_dir;
 use std::fmt::Display;
 use std::fs;
-use std::io::{Error, ErrorKind, Write};
+use std::io::{self, Error, ErrorKind, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
 use toml::Table;
-use uuid::Uuid;
+
+use crate::network::{self, Throttle};
+use crate::preflight;
+use crate::readme::README_FILE;
 
 pub const ARAKI_DIR: &str = ".araki";
 pub const ARAKI_GIT_DIR_NAME: &str = ".araki-git";
 
+/// Directory of the araki cache holding the clones in progress.
+const CLONES_DIR: &str = "clones";
+
+/// How often a git transfer is attempted before giving up.
+const TRANSFER_ATTEMPTS: u32 = 3;
+
+/// File in the araki git dir naming the subdirectory of the repo holding the lockspec, for
+/// repos containing several lockspecs.
+pub const LOCKSPEC_DIR_FILE: &str = "ARAKI_LOCKSPEC_DIR";
+
+/// File in the araki git dir listing the pixi environments of the lockspec to install, one per
+/// line, for projects which only install some of them.
+pub const PIXI_ENVIRONMENTS_FILE: &str = "ARAKI_PIXI_ENVIRONMENTS";
+
 /// Get the git directory used by git for lockspec version control
 pub fn get_araki_git_repo() -> Result<Repository, Error> {
-    let cwd = current_dir()?;
-    let araki_git_dir = cwd.join(ARAKI_GIT_DIR_NAME);
+    open_araki_git_repo(&current_dir()?)
+}
+
+/// Open the araki git repo of the lockspec in the given directory.
+///
+/// * `path`: Directory containing the lockspec
+pub fn open_araki_git_repo(path: &Path) -> Result<Repository, Error> {
+    let araki_git_dir = path.join(ARAKI_GIT_DIR_NAME);
     if !araki_git_dir.exists() {
         return Err(Error::other(format!(
             "No araki git dir files found in {araki_git_dir:?}",
@@ -32,9 +64,8 @@ pub fn get_araki_dir() -> Result<PathBuf, String> {
         .ok_or("Could not determine the user home directory.".to_string())?;
 
     if !dir.exists() {
-        fs::create_dir_all(&dir).map_err(|err| {
-            format!("Could not create araki directory at {dir:?}: {err}").to_string()
-        })?;
+        preflight::create_dir(&dir)
+            .map_err(|err| format!("Could not create araki directory at {dir:?}: {err}"))?;
     }
     Ok(dir)
 }
@@ -53,7 +84,7 @@ pub fn get_araki_cache() -> Result<PathBuf, Error> {
     let cache = dir.cache_dir();
     if !cache.exists() {
         println!("araki cache does not exist. Creating it at {cache:?}");
-        fs::create_dir_all(cache)?;
+        preflight::create_dir(cache).map_err(Error::other)?;
     }
     Ok(cache.to_path_buf())
 }
@@ -63,10 +94,8 @@ pub fn get_araki_bin_dir() -> Result<PathBuf, String> {
     let dir = get_araki_dir()?.join("bin");
     if !dir.exists() {
         println!("araki bin dir does not exist. Creating it at {dir:?}");
-        fs::create_dir_all(&dir).map_err(|err| {
-            eprintln!("Could not create araki bin directory at {dir:?}. Error:\n{err}");
-            format!("{err}")
-        })?;
+        preflight::create_dir(&dir)
+            .map_err(|err| format!("Could not create araki bin directory at {dir:?}: {err}"))?;
     }
     Ok(dir)
 }
@@ -83,31 +112,125 @@ pub fn get_araki_bin_dir() -> Result<PathBuf, String> {
 /// * `repo`: URL of a git repo to clone
 /// * `path`: Path where the repo should be cloned
 pub fn git_clone(repo: String, path: &Path) -> Result<(), String> {
-    let temp_dir = temp_dir().join(Uuid::new_v4().to_string());
-    fs::create_dir_all(&temp_dir).map_err(|err| {
-        format!("Unable to clone {repo} to a temporary directory at {temp_dir:?}: {err}")
-    })?;
+    git_clone_with_progress(repo, path, &|_, _| {})
+}
+
+/// Clone a git repo to a path, reporting transfer progress. See [`git_clone`].
+///
+/// * `repo`: URL of a git repo to clone
+/// * `path`: Path where the repo should be cloned
+/// * `on_transfer`: Called with the number of objects received so far and the total number of
+///   objects whenever new objects are received
+pub fn git_clone_with_progress(
+    repo: String,
+    path: &Path,
+    on_transfer: &dyn Fn(usize, usize),
+) -> Result<(), String> {
+    network::require_online(&format!("clone {repo}"))?;
 
-    let callbacks = generate_remote_callbacks();
-    let mut fetch_opts = FetchOptions::new();
-    fetch_opts.remote_callbacks(callbacks);
+    // Clones are made in a directory of the araki cache derived from the URL, and only removed
+    // once they succeed, so that a clone which failed resumes from the refs it already received
+    let clones = get_araki_cache()
+        .map_err(|err| format!("Unable to find the araki cache: {err}"))?
+        .join(CLONES_DIR);
+    preflight::create_dir(&clones)?;
+    let key: String = Sha256::digest(repo.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let temp_dir = clones.join(&key);
 
-    let mut builder = RepoBuilder::new();
-    builder.fetch_options(fetch_opts);
+    // Concurrent clones of the same URL wait for each other instead of sharing the directory
+    let lock_path = clones.join(format!("{key}.lock"));
+    let lock = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(&lock_path)
+        .map_err(|err| format!("Unable to open {lock_path:?}: {err}"))?;
+    lock.lock()
+        .map_err(|err| format!("Unable to lock {lock_path:?}: {err}"))?;
+    let uid = lock
+        .metadata()
+        .map_err(|err| format!("Unable to read the owner of {lock_path:?}: {err}"))?
+        .uid();
+    let result = clone_to_temp_dir(&repo, &temp_dir, uid, on_transfer)
+        .and_then(|_| {
+            copy_directory_contents(&temp_dir, &path.to_path_buf()).map_err(|err| {
+                format!("Error copying the clone repo from {temp_dir:?} to {path:?}: {err}")
+            })
+        })
+        .map(|_| {
+            let _ = fs::remove_dir_all(&temp_dir);
+        });
+    let _ = lock.unlock();
+    result?;
 
-    let _ = builder
-        .clone(&repo, &temp_dir)
-        .map_err(|err| format!("Failed to clone {repo} to {temp_dir:?}. Reason: {err}"))?;
+    ignore_lockspec_files(path)
+}
+
+/// Clone a git repo to a directory of the araki cache, resuming a clone which failed before.
+/// A partial clone is only reused if it belongs to the user and was made from the same URL;
+/// anything else found in the directory is removed first.
+///
+/// * `repo`: URL of a git repo to clone
+/// * `temp_dir`: Directory to clone the repo to
+/// * `uid`: User running araki
+/// * `on_transfer`: Called with the number of objects received so far and the total number of
+///   objects whenever new objects are received
+fn clone_to_temp_dir(
+    repo: &str,
+    temp_dir: &Path,
+    uid: u32,
+    on_transfer: &dyn Fn(usize, usize),
+) -> Result<(), String> {
+    let partial_clone = fs::symlink_metadata(temp_dir)
+        .is_ok_and(|metadata| metadata.is_dir() && metadata.uid() == uid)
+        .then(|| Repository::open(temp_dir).ok())
+        .flatten()
+        .filter(|git_repo| {
+            git_repo
+                .find_remote("origin")
+                .is_ok_and(|remote| remote.url() == Some(repo))
+        });
+    let git_repo = match partial_clone {
+        Some(git_repo) => git_repo,
+        None => {
+            let _ = fs::remove_dir_all(temp_dir).or_else(|_| fs::remove_file(temp_dir));
+            fs::create_dir(temp_dir).map_err(|err| {
+                format!("Unable to clone {repo} to a temporary directory at {temp_dir:?}: {err}")
+            })?;
+            let git_repo = Repository::init(temp_dir)
+                .map_err(|err| format!("Unable to initialize a repo at {temp_dir:?}: {err}"))?;
+            git_repo
+                .remote("origin", repo)
+                .map_err(|err| format!("Unable to add {repo} as a remote: {err}"))?;
+            git_repo
+        }
+    };
+    fetch_clone(&git_repo, on_transfer).map_err(|err| {
+        format!(
+            "Failed to clone {repo} to {temp_dir:?}. Reason: {err}. Run the command again to \
+            resume the clone."
+        )
+    })?;
+    drop(git_repo);
 
     // Rename `.git` -> `.araki-git`
     fs::rename(temp_dir.join(".git"), temp_dir.join(ARAKI_GIT_DIR_NAME))
         .map_err(|err| format!("Error modifying the cloned repo: {err}"))?;
+    // The README of the repo describes it on its forge; it must not replace the project's own
+    let _ = fs::remove_file(temp_dir.join(README_FILE));
+    Ok(())
+}
 
-    copy_directory_contents(&temp_dir, &path.to_path_buf()).map_err(|err| {
-        format!("Error copying the clone repo from {temp_dir:?} to {path:?}: {err}")
-    })?;
-
-    // If need be, write `.araki-git` to `.gitignore`, otherwise git treats it as a regular file
+/// Add the araki git dir and the lockspec files to the `.gitignore` of a directory, unless they
+/// are already ignored.
+///
+/// * `path`: Directory containing the lockspec
+pub fn ignore_lockspec_files(path: &Path) -> Result<(), String> {
+    // Write `.araki-git` to `.gitignore`, otherwise git treats it as a regular file
     // even if GIT_DIR is set
     let gitignore = path.join(".gitignore");
     let entry = ".araki-git/";
@@ -127,7 +250,111 @@ pub fn git_clone(repo: String, path: &Path) -> Result<(), String> {
     Ok(())
 }
 
-fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
+/// Fetch the branches and tags of the `origin` remote of a fresh or partial clone, and checkout
+/// its default branch.
+///
+/// The default branch is fetched before everything else, so that it is kept if a later fetch
+/// fails. Each fetch is retried a few times before giving up.
+///
+/// * `git_repo`: Repo being cloned
+/// * `on_transfer`: Called with the number of objects received so far and the total number of
+///   objects whenever new objects are received
+fn fetch_clone(
+    git_repo: &Repository,
+    on_transfer: &dyn Fn(usize, usize),
+) -> Result<(), git2::Error> {
+    let mut remote = git_repo.find_remote("origin")?;
+    // Empty repos have no default branch; there is nothing to fetch then
+    let default_branch = with_transfer_retries(|| {
+        let connection =
+            remote.connect_auth(Direction::Fetch, Some(generate_remote_callbacks()), None)?;
+        Ok(connection
+            .default_branch()
+            .ok()
+            .and_then(|branch| branch.as_str().map(|branch| branch.to_string())))
+    })?;
+    let Some(branch) = default_branch
+        .as_deref()
+        .and_then(|branch| branch.strip_prefix("refs/heads/"))
+    else {
+        return Ok(());
+    };
+
+    for (refspec, tags) in [
+        (
+            format!("+refs/heads/{branch}:refs/remotes/origin/{branch}"),
+            AutotagOption::None,
+        ),
+        (
+            "+refs/heads/*:refs/remotes/origin/*".to_string(),
+            AutotagOption::All,
+        ),
+    ] {
+        with_transfer_retries(|| {
+            let throttle = Throttle::new();
+            let mut last_received = 0;
+            let mut callbacks = generate_remote_callbacks();
+            callbacks.transfer_progress(|stats| {
+                if stats.received_objects() != last_received {
+                    last_received = stats.received_objects();
+                    on_transfer(stats.received_objects(), stats.total_objects());
+                }
+                throttle.wait(stats.received_bytes());
+                true
+            });
+            let mut fetch_opts = FetchOptions::new();
+            fetch_opts.remote_callbacks(callbacks);
+            fetch_opts.download_tags(tags);
+            remote.fetch(&[&refspec], Some(&mut fetch_opts), None)
+        })?;
+    }
+
+    let commit = git_repo
+        .find_reference(&format!("refs/remotes/origin/{branch}"))?
+        .peel_to_commit()?;
+    let mut local = git_repo.branch(branch, &commit, true)?;
+    local.set_upstream(Some(&format!("origin/{branch}")))?;
+    git_repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        &format!("refs/remotes/origin/{branch}"),
+        true,
+        "clone",
+    )?;
+    git_repo.set_head(&format!("refs/heads/{branch}"))?;
+    git_repo.checkout_head(Some(CheckoutBuilder::new().force()))
+}
+
+/// Run a git transfer, retrying it with a growing delay if it fails.
+///
+/// * `transfer`: Transfer to run
+pub fn with_transfer_retries<T>(
+    mut transfer: impl FnMut() -> Result<T, git2::Error>,
+) -> Result<T, git2::Error> {
+    network::apply_git_timeouts();
+    let mut attempt = 1;
+    loop {
+        match transfer() {
+            Err(err) if attempt < TRANSFER_ATTEMPTS && is_transient(&err) => {
+                thread::sleep(Duration::from_secs(2u64.pow(attempt)));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Check whether a git error might go away by retrying, e.g. a dropped connection, as opposed
+/// to e.g. failing authentication.
+///
+/// * `err`: Error of a git transfer
+fn is_transient(err: &git2::Error) -> bool {
+    matches!(
+        err.class(),
+        ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssh | ErrorClass::Os
+    ) && err.code() != ErrorCode::Auth
+}
+
+fn generate_remote_callbacks<'a>() -> RemoteCallbacks<'a> {
     let mut callbacks = RemoteCallbacks::new();
 
     // Keep track of whether we've tried to get credentials from ssh-agent.
@@ -137,6 +364,13 @@ fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
     let mut tried_agent = false;
 
     callbacks.credentials(move |_url, username_from_url, allowed_types| {
+        // Only ssh is authenticated; https is used anonymously, for public repositories
+        if !allowed_types.is_ssh_key() {
+            return Err(git2::Error::from_str(
+                "The repository requires authentication, which araki only supports over ssh. \
+                Is the repository private?",
+            ));
+        }
         let username = username_from_url.ok_or(git2::Error::from_str(
             "Unable to get the ssh username from the URL.",
         ))?;
@@ -147,29 +381,139 @@ fn generate_remote_callbacks() -> RemoteCallbacks<'static> {
             ));
         }
 
-        if allowed_types.is_ssh_key() {
-            tried_agent = true;
-            return Cred::ssh_key_from_agent(username);
-        }
-
-        Err(git2::Error::from_str(
-            "araki only supports ssh for git interactions. Please configure ssh-agent.",
-        ))
+        tried_agent = true;
+        Cred::ssh_key_from_agent(username)
     });
 
+    let throttle = Throttle::new();
+    callbacks.push_transfer_progress(move |_current, _total, bytes| throttle.wait(bytes));
+
     callbacks
 }
 
 pub fn git_push(remote: &str, refs: &[&str]) -> Result<(), git2::Error> {
-    let callbacks = generate_remote_callbacks();
-
-    let mut push_options = PushOptions::new();
-    push_options.remote_callbacks(callbacks);
     let repo =
         get_araki_git_repo().map_err(|err| git2::Error::from_str(format!("{err}").as_str()))?;
+    git_push_repo(&repo, remote, refs)
+}
+
+/// Push refs of a given repository to one of its remotes.
+///
+/// * `repo`: Repository to push from
+/// * `remote`: Name of the remote
+/// * `refs`: Refspecs to push
+pub fn git_push_repo(repo: &Repository, remote: &str, refs: &[&str]) -> Result<(), git2::Error> {
+    network::require_online(&format!("push to {remote}"))
+        .map_err(|err| git2::Error::from_str(&err))?;
     let mut origin = repo.find_remote(remote)?;
-    origin.push(refs, Some(&mut push_options))?;
-    Ok(())
+    with_transfer_retries(|| {
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(generate_remote_callbacks());
+        origin.push(refs, Some(&mut push_options))
+    })
+}
+
+/// Push refs to a URL which isn't configured as a remote of the araki repo, e.g. a mirror.
+///
+/// * `url`: URL to push to
+/// * `refs`: Refspecs to push
+pub fn git_push_url(url: &str, refs: &[&str]) -> Result<(), git2::Error> {
+    network::require_online(&format!("push to {url}"))
+        .map_err(|err| git2::Error::from_str(&err))?;
+    let repo =
+        get_araki_git_repo().map_err(|err| git2::Error::from_str(format!("{err}").as_str()))?;
+    let mut remote = repo.remote_anonymous(url)?;
+    with_transfer_retries(|| {
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(generate_remote_callbacks());
+        remote.push(refs, Some(&mut push_options))
+    })
+}
+
+/// Fetch refs and all tags from a remote of a repository.
+///
+/// * `repo`: Repository to fetch into
+/// * `remote`: Name of the remote
+/// * `refspecs`: Refspecs to fetch
+pub fn git_fetch(repo: &Repository, remote: &str, refspecs: &[&str]) -> Result<(), git2::Error> {
+    network::require_online(&format!("fetch from {remote}"))
+        .map_err(|err| git2::Error::from_str(&err))?;
+    let mut remote = repo.find_remote(remote)?;
+    with_transfer_retries(|| {
+        let throttle = Throttle::new();
+        let mut callbacks = generate_remote_callbacks();
+        callbacks.transfer_progress(|stats| {
+            throttle.wait(stats.received_bytes());
+            true
+        });
+        let mut fetch_opts = FetchOptions::new();
+        fetch_opts.remote_callbacks(callbacks);
+        fetch_opts.download_tags(AutotagOption::All);
+        remote.fetch(refspecs, Some(&mut fetch_opts), None)
+    })
+}
+
+/// List the tags of a remote without fetching them, like `git ls-remote --tags`.
+///
+/// Annotated tags are resolved to the commit they point at.
+///
+/// * `repo`: Repository the remote belongs to
+/// * `remote`: Name of the remote
+pub fn git_ls_remote_tags(
+    repo: &Repository,
+    remote: &str,
+) -> Result<BTreeMap<String, Oid>, git2::Error> {
+    network::require_online(&format!("list the tags of {remote}"))
+        .map_err(|err| git2::Error::from_str(&err))?;
+    network::apply_git_timeouts();
+    let mut remote = repo.find_remote(remote)?;
+    let connection =
+        remote.connect_auth(Direction::Fetch, Some(generate_remote_callbacks()), None)?;
+
+    let mut tags = BTreeMap::new();
+    for head in connection.list()? {
+        let Some(name) = head.name().strip_prefix("refs/tags/") else {
+            continue;
+        };
+        // The peeled entry (`<tag>^{}`) of an annotated tag points at the tagged commit
+        match name.strip_suffix("^{}") {
+            Some(name) => {
+                tags.insert(name.to_string(), head.oid());
+            }
+            None => {
+                tags.entry(name.to_string()).or_insert(head.oid());
+            }
+        }
+    }
+    Ok(tags)
+}
+
+/// Check whether a git repository exists at a URL, by listing its refs. A repository which
+/// doesn't exist can't be told apart from one the user has no access to on some hosts; both are
+/// reported as missing.
+///
+/// * `url`: URL of the repository
+pub fn git_remote_exists(url: &str) -> Result<bool, git2::Error> {
+    network::require_online(&format!("reach {url}")).map_err(|err| git2::Error::from_str(&err))?;
+    network::apply_git_timeouts();
+    let mut remote = Remote::create_detached(url)?;
+    match remote.connect_auth(Direction::Fetch, Some(generate_remote_callbacks()), None) {
+        Ok(_) => Ok(true),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(false),
+        Err(err) => {
+            let message = err.message().to_lowercase();
+            let missing = [
+                "does not appear to be a git repository",
+                "failed to resolve path",
+                "not found",
+                "does not exist",
+            ];
+            match missing.iter().any(|pattern| message.contains(pattern)) {
+                true => Ok(false),
+                false => Err(err),
+            }
+        }
+    }
 }
 
 /// Copy the contents of a directory to another directory.
@@ -292,7 +636,12 @@ fn copy_fs_obj(from: &PathBuf, to: &Path) -> std::io::Result<()> {
 
 #[derive(Debug, Clone)]
 pub struct LockSpec {
+    /// Project directory containing the araki repo
     pub path: PathBuf,
+
+    /// Subdirectory of the project holding the lockspec files, if the araki repo contains
+    /// several lockspecs
+    pub subdir: Option<PathBuf>,
 }
 
 impl Display for LockSpec {
@@ -303,22 +652,41 @@ impl Display for LockSpec {
 
 impl LockSpec {
     pub fn specfile(&self) -> PathBuf {
-        self.path.join("pixi.toml")
+        self.dir().join("pixi.toml")
     }
 
     pub fn lockfile(&self) -> PathBuf {
-        self.path.join("pixi.lock")
+        self.dir().join("pixi.lock")
+    }
+
+    /// Get the directory containing the lockspec files, where pixi is run.
+    pub fn dir(&self) -> PathBuf {
+        match &self.subdir {
+            Some(subdir) => self.path.join(subdir),
+            None => self.path.clone(),
+        }
     }
 
     /// Construct a LockSpec from the given path.
     ///
+    /// If the araki repo in `path` records a lockspec subdirectory, the lockspec files are
+    /// looked up there.
+    ///
     /// * `path`: Path to a directory containing a pixi.lock and a pixi.toml
     pub fn from_path<T>(path: T) -> Result<LockSpec, String>
     where
         T: AsRef<Path> + std::fmt::Debug,
     {
+        let subdir = fs::read_to_string(
+            path.as_ref()
+                .join(ARAKI_GIT_DIR_NAME)
+                .join(LOCKSPEC_DIR_FILE),
+        )
+        .ok()
+        .map(|subdir| PathBuf::from(subdir.trim()));
         let ls = LockSpec {
             path: path.as_ref().to_path_buf(),
+            subdir,
         };
 
         if ls.files_exist() {
@@ -328,6 +696,111 @@ impl LockSpec {
         }
     }
 
+    /// Get the lockspec name recorded in the araki metadata of the specfile, if any.
+    pub fn name(&self) -> Option<String> {
+        let toml_data: Table = fs::read_to_string(self.specfile()).ok()?.parse().ok()?;
+        toml_data
+            .get("araki")?
+            .get("lockspec_name")?
+            .as_str()
+            .map(|name| name.to_string())
+    }
+
+    /// Get the environment variables declared in the `[araki.env]` table of the specfile, to be
+    /// exported whenever the environment is activated.
+    pub fn env_vars(&self) -> Result<BTreeMap<String, String>, String> {
+        let specfile = self.specfile();
+        let toml_data: Table = fs::read_to_string(&specfile)
+            .map_err(|err| format!("Unable to read {specfile:?}: {err}"))?
+            .parse()
+            .map_err(|err| format!("Unable to parse {specfile:?}: {err}"))?;
+        let Some(env) = toml_data.get("araki").and_then(|araki| araki.get("env")) else {
+            return Ok(BTreeMap::new());
+        };
+        env.as_table()
+            .ok_or(format!("araki.env in {specfile:?} is not a table"))?
+            .iter()
+            .map(|(name, value)| match value {
+                toml::Value::String(value) => Ok((name.clone(), value.clone())),
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                    Ok((name.clone(), value.to_string()))
+                }
+                _ => Err(format!(
+                    "araki.env.{name} in {specfile:?} must be a string, number, or boolean"
+                )),
+            })
+            .collect()
+    }
+
+    /// Get the names of the tasks defined in the specfile, including those of its features,
+    /// sorted by name.
+    pub fn tasks(&self) -> Result<Vec<String>, String> {
+        let specfile = self.specfile();
+        let toml_data: Table = fs::read_to_string(&specfile)
+            .map_err(|err| format!("Unable to read {specfile:?}: {err}"))?
+            .parse()
+            .map_err(|err| format!("Unable to parse {specfile:?}: {err}"))?;
+        let mut tables = vec![&toml_data];
+        if let Some(features) = toml_data.get("feature").and_then(|f| f.as_table()) {
+            tables.extend(features.values().filter_map(|feature| feature.as_table()));
+        }
+        let mut tasks: Vec<String> = tables
+            .iter()
+            .filter_map(|table| table.get("tasks")?.as_table())
+            .flat_map(|tasks| tasks.keys().cloned())
+            .collect();
+        tasks.sort();
+        tasks.dedup();
+        Ok(tasks)
+    }
+
+    /// Get the names of the conda and pypi packages the specfile depends on directly, including
+    /// those of its features and targets, sorted by name.
+    pub fn dependencies(&self) -> Result<Vec<String>, String> {
+        let specfile = self.specfile();
+        let toml_data: Table = fs::read_to_string(&specfile)
+            .map_err(|err| format!("Unable to read {specfile:?}: {err}"))?
+            .parse()
+            .map_err(|err| format!("Unable to parse {specfile:?}: {err}"))?;
+        let mut tables = vec![&toml_data];
+        if let Some(features) = toml_data.get("feature").and_then(|f| f.as_table()) {
+            tables.extend(features.values().filter_map(|feature| feature.as_table()));
+        }
+        let targets: Vec<&Table> = tables
+            .iter()
+            .filter_map(|table| table.get("target")?.as_table())
+            .flat_map(|targets| targets.values().filter_map(|target| target.as_table()))
+            .collect();
+        tables.extend(targets);
+        let mut dependencies: Vec<String> = tables
+            .iter()
+            .flat_map(|table| {
+                ["dependencies", "pypi-dependencies"]
+                    .iter()
+                    .filter_map(|key| table.get(*key)?.as_table())
+            })
+            .flat_map(|dependencies| dependencies.keys().cloned())
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+        Ok(dependencies)
+    }
+
+    /// Get the prefix of the default pixi environment of the lockspec.
+    pub fn env_prefix(&self) -> PathBuf {
+        self.dir().join(".pixi").join("envs").join("default")
+    }
+
+    /// Get the lockspec name, falling back to the name of the lockspec directory.
+    pub fn display_name(&self) -> String {
+        self.name().unwrap_or_else(|| {
+            self.dir()
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or("araki".to_string())
+        })
+    }
+
     /// Check whether a lockspec (lockfile+specfile) exist in self.path.
     pub fn files_exist(&self) -> bool {
         self.lockfile().exists() && self.specfile().exists()
@@ -390,3 +863,220 @@ impl LockSpec {
         Ok(())
     }
 }
+
+impl LockSpec {
+    /// Get the path of a lockspec file relative to the root of the araki repo.
+    ///
+    /// * `file`: Name of the lockspec file, e.g. `pixi.toml`
+    pub fn tracked_path(&self, file: &str) -> String {
+        match &self.subdir {
+            Some(subdir) => format!("{}/{file}", subdir.to_string_lossy()),
+            None => file.to_string(),
+        }
+    }
+}
+
+/// Get the pixi environments of the lockspec selected for installation. Empty if no selection
+/// was made, in which case pixi installs its default environment.
+///
+/// * `repo`: araki repo
+pub fn pixi_environments(repo: &Repository) -> Vec<String> {
+    fs::read_to_string(repo.path().join(PIXI_ENVIRONMENTS_FILE))
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Record the pixi environments of the lockspec to install from now on.
+///
+/// * `repo`: araki repo
+/// * `environments`: pixi environments to install; pixi's default environment if empty
+pub fn set_pixi_environments(repo: &Repository, environments: &[String]) -> Result<(), String> {
+    let path = repo.path().join(PIXI_ENVIRONMENTS_FILE);
+    if environments.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(format!("Unable to remove {path:?}: {err}"))
+            }
+            _ => Ok(()),
+        };
+    }
+    fs::write(&path, format!("{}\n", environments.join("\n")))
+        .map_err(|err| format!("Unable to write {path:?}: {err}"))
+}
+
+/// Create a command running pixi, with its stdout sent to stderr so that stdout stays free for
+/// araki's own (possibly machine-readable) output.
+pub fn pixi_command() -> Command {
+    let mut command = Command::new("pixi");
+    command.stdout(io::stderr());
+    command
+}
+
+/// Get the arguments selecting the pixi environments of the lockspec to install, e.g.
+/// `--environment docs`.
+///
+/// * `repo`: araki repo
+pub fn pixi_environment_args(repo: &Repository) -> Vec<String> {
+    pixi_environments(repo)
+        .into_iter()
+        .flat_map(|environment| ["--environment".to_string(), environment])
+        .collect()
+}
+
+/// Get the pixi environment commands of the lockspec run in: the first of the selected pixi
+/// environments, or pixi's default environment if none are selected.
+///
+/// * `dir`: Directory of the lockspec
+pub fn run_environment(dir: &Path) -> Option<String> {
+    let repo = open_araki_git_repo(dir).ok()?;
+    pixi_environments(&repo).into_iter().next()
+}
+
+/// Get the subdirectory of the araki repo holding the lockspec, if the repo contains several.
+///
+/// * `repo`: araki repo
+pub fn lockspec_subdir(repo: &Repository) -> Option<PathBuf> {
+    fs::read_to_string(repo.path().join(LOCKSPEC_DIR_FILE))
+        .ok()
+        .map(|subdir| PathBuf::from(subdir.trim()))
+}
+
+/// Get the path of a lockspec file relative to the root of the araki repo.
+///
+/// * `repo`: araki repo
+/// * `file`: Name of the lockspec file, e.g. `pixi.toml`
+pub fn tracked_path(repo: &Repository, file: &str) -> String {
+    match lockspec_subdir(repo) {
+        Some(subdir) => format!("{}/{file}", subdir.to_string_lossy()),
+        None => file.to_string(),
+    }
+}
+
+/// Get the prefix of the tags of the lockspec, e.g. `gpu/` for the lockspec in `envs/gpu`.
+/// Repos containing a single lockspec have no tag prefix.
+///
+/// * `repo`: araki repo
+pub fn tag_prefix(repo: &Repository) -> String {
+    lockspec_subdir(repo)
+        .and_then(|subdir| {
+            subdir
+                .file_name()
+                .map(|name| format!("{}/", name.to_string_lossy()))
+        })
+        .unwrap_or_default()
+}
+
+/// State of the link between a project's lockspec files and the araki repo tracking them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkState {
+    /// The araki repo's working directory is the project and both lockspec files are tracked
+    Linked,
+
+    /// The lockspec files are no longer tracked by the araki repo
+    Broken(String),
+}
+
+impl LockSpec {
+    /// Check whether the lockspec files are still tracked by the given araki repo.
+    ///
+    /// The link breaks if the repo's working directory no longer points at the project (e.g.
+    /// after copying the project from another machine), or if the lockspec files were dropped
+    /// from the index.
+    ///
+    /// * `repo`: araki repo which should be tracking the lockspec
+    pub fn link_state(&self, repo: &Repository) -> LinkState {
+        let workdir = repo.workdir().and_then(|dir| dir.canonicalize().ok());
+        let project = self.path.canonicalize().ok();
+        if workdir.is_none() || workdir != project {
+            return LinkState::Broken(format!(
+                "The araki repo's working directory ({:?}) is not the project at {:?}.",
+                repo.workdir(),
+                self.path
+            ));
+        }
+
+        let index = match repo.index() {
+            Ok(index) => index,
+            Err(err) => return LinkState::Broken(format!("Unable to read the git index: {err}")),
+        };
+        for file in ["pixi.toml", "pixi.lock"] {
+            if index
+                .get_path(Path::new(&self.tracked_path(file)), 0)
+                .is_none()
+            {
+                return LinkState::Broken(format!("{file} is not tracked by the araki repo."));
+            }
+        }
+        LinkState::Linked
+    }
+}
+
+/// Checkout a commit or tree into the working directory of an araki repo, which is the project
+/// using the lockspec. The README of the repository is left out, so that it never replaces the
+/// project's own; the index is updated to the whole tree, so that it is kept in new commits.
+///
+/// * `repo`: araki repo
+/// * `target`: Commit or tree to checkout
+/// * `builder`: Checkout options, e.g. whether to force the checkout
+pub fn checkout_lockspec(
+    repo: &Repository,
+    target: &Object,
+    mut builder: CheckoutBuilder,
+) -> Result<(), git2::Error> {
+    let tree = target.peel_to_tree()?;
+    // Files of both the target and the index, so that deleted files are removed as well
+    let mut paths = BTreeSet::new();
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            paths.insert(format!(
+                "{root}{}",
+                String::from_utf8_lossy(entry.name_bytes())
+            ));
+        }
+        TreeWalkResult::Ok
+    })?;
+    let mut index = repo.index()?;
+    paths.extend(
+        index
+            .iter()
+            .map(|entry| String::from_utf8_lossy(&entry.path).to_string()),
+    );
+    paths.remove(README_FILE);
+
+    if !paths.is_empty() {
+        builder.disable_pathspec_match(true);
+        for path in &paths {
+            builder.path(path);
+        }
+        repo.checkout_tree(target, Some(&mut builder))?;
+    }
+    index.read(true)?;
+    index.read_tree(&tree)?;
+    index.write()
+}
+
+/// Get the contents of a file as of the araki repo's HEAD commit.
+///
+/// * `repo`: araki repo to read from
+/// * `file`: Path of the file relative to the root of the repo
+pub fn read_head_file(repo: &Repository, file: &str) -> Result<Vec<u8>, git2::Error> {
+    read_rev_file(repo, "HEAD", file)
+}
+
+/// Read the contents of a file as committed at a revision of the repo.
+///
+/// * `repo`: Repository to read from
+/// * `rev`: Revision to read the file at, e.g. a tag name or `HEAD~1`
+/// * `file`: Path of the file relative to the repository root
+pub fn read_rev_file(repo: &Repository, rev: &str, file: &str) -> Result<Vec<u8>, git2::Error> {
+    let tree = repo.revparse_single(rev)?.peel_to_tree()?;
+    let blob = tree
+        .get_path(Path::new(file))?
+        .to_object(repo)?
+        .peel_to_blob()?;
+    Ok(blob.content().to_vec())
+}
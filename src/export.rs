@@ -0,0 +1,135 @@
+use serde_json::json;
+
+use crate::remote::parse_repo_arg;
+
+/// Directory inside the container where the lockspec is installed.
+pub const CONTAINER_WORKDIR: &str = "/opt/araki/env";
+
+/// Render a Dockerfile which installs pixi and the lockspec.
+///
+/// The build context must contain the lockspec's pixi.toml and pixi.lock.
+///
+/// * `base_image`: Image to build on
+/// * `pixi_version`: Tag of the `ghcr.io/prefix-dev/pixi` image to copy pixi from
+pub fn render_dockerfile(base_image: &str, pixi_version: &str) -> String {
+    format!(
+        r#"# Generated by araki
+FROM ghcr.io/prefix-dev/pixi:{pixi_version} AS pixi
+
+FROM {base_image}
+COPY --from=pixi /usr/local/bin/pixi /usr/local/bin/pixi
+
+WORKDIR {CONTAINER_WORKDIR}
+COPY pixi.toml pixi.lock ./
+RUN pixi install --frozen
+
+# Activate the environment for every command run in the container
+RUN pixi shell-hook --frozen > /usr/local/bin/araki-activate.sh \
+    && echo 'exec "$@"' >> /usr/local/bin/araki-activate.sh
+ENTRYPOINT ["/bin/bash", "/usr/local/bin/araki-activate.sh"]
+CMD ["/bin/bash"]
+"#
+    )
+}
+
+/// Render a `devcontainer.json` which builds the Dockerfile next to it, using the parent
+/// directory (which contains the lockspec) as the build context.
+///
+/// * `name`: Name of the devcontainer
+pub fn render_devcontainer(name: &str) -> String {
+    let devcontainer = json!({
+        "name": name,
+        "build": {
+            "dockerfile": "Dockerfile",
+            "context": "..",
+        },
+        "remoteEnv": {
+            "PATH": format!("{CONTAINER_WORKDIR}/.pixi/envs/default/bin:${{containerEnv:PATH}}"),
+        },
+        "customizations": {
+            "vscode": {
+                "settings": {
+                    "python.defaultInterpreterPath":
+                        format!("{CONTAINER_WORKDIR}/.pixi/envs/default/bin/python"),
+                },
+            },
+        },
+    });
+    // Serializing a json! value can't fail
+    serde_json::to_string_pretty(&devcontainer).unwrap_or_default() + "\n"
+}
+
+/// Command used in CI to install araki.
+const INSTALL_ARAKI: &str =
+    "cargo install --locked --git https://github.com/peytondmurray/araki araki";
+
+/// Render a GitHub Actions workflow which reproduces the environment at a tag.
+///
+/// The lockspec repo is cloned over ssh, using the private key in the `ARAKI_SSH_KEY` secret.
+///
+/// * `remote`: URL of the lockspec repository
+/// * `tag`: Tag of the lockspec to install
+pub fn render_github_actions(remote: &str, tag: &str) -> String {
+    format!(
+        r#"# Generated by araki
+name: araki environment
+
+on: [push, pull_request]
+
+jobs:
+  environment:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v5
+      - uses: prefix-dev/setup-pixi@v0.9.0
+        with:
+          run-install: false
+      - uses: webfactory/ssh-agent@v0.9.0
+        with:
+          ssh-private-key: ${{{{ secrets.ARAKI_SSH_KEY }}}}
+      - name: Install araki
+        run: {INSTALL_ARAKI}
+      - name: Cache the environment
+        uses: actions/cache@v4
+        with:
+          path: .pixi
+          key: araki-${{{{ runner.os }}}}-{tag}
+      - name: Clone the environment
+        run: |
+          araki clone {remote}
+          araki checkout {tag}
+"#
+    )
+}
+
+/// Render a GitLab CI configuration which reproduces the environment at a tag.
+///
+/// The lockspec repo is cloned over ssh, using the private key in the `ARAKI_SSH_KEY` variable.
+///
+/// * `remote`: URL of the lockspec repository
+/// * `tag`: Tag of the lockspec to install
+pub fn render_gitlab_ci(remote: &str, tag: &str) -> String {
+    let host = parse_repo_arg(remote)
+        .map(|remote| remote.get_domain())
+        .unwrap_or("github.com".to_string());
+    format!(
+        r#"# Generated by araki
+araki-environment:
+  image: rust:latest
+  cache:
+    key: araki-{tag}
+    paths:
+      - .pixi
+  before_script:
+    - curl -fsSL https://pixi.sh/install.sh | bash
+    - export PATH="$HOME/.pixi/bin:$PATH"
+    - eval "$(ssh-agent -s)"
+    - echo "$ARAKI_SSH_KEY" | tr -d '\r' | ssh-add -
+    - mkdir -p ~/.ssh && ssh-keyscan {host} >> ~/.ssh/known_hosts
+    - {INSTALL_ARAKI}
+  script:
+    - araki clone {remote}
+    - araki checkout {tag}
+"#
+    )
+}
@@ -0,0 +1,105 @@
+//! Removal of installed environments which haven't been used recently.
+//!
+//! Each recorded project keeps the pixi environments it installed in `.pixi/envs`. Those of the
+//! projects whose environment wasn't used (run, activated, or synced) for longer than a given age
+//! can be removed; the lockspec stays, so `pixi install` brings them back.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::common::LockSpec;
+use crate::projects;
+use crate::stats::dir_size;
+
+/// Installed environments of a project which haven't been used recently.
+#[derive(Debug, Clone)]
+pub struct Unused {
+    /// Project directory
+    pub project: PathBuf,
+
+    /// `<org>/<repo>` of the lockspec
+    pub environment: String,
+
+    /// Directory of the installed pixi environments
+    pub envs_dir: PathBuf,
+    pub last_used: DateTime<Utc>,
+
+    /// Bytes used by the installed environments
+    pub size: u64,
+}
+
+/// Parse an age such as `90d`, in hours (`h`), days (`d`), or weeks (`w`). Ages of zero or
+/// less are rejected, since they would make every environment unused.
+///
+/// * `age`: Age to parse
+pub fn parse_age(age: &str) -> Result<TimeDelta, String> {
+    let invalid = || {
+        format!("Invalid age {age}; expected a positive number followed by h, d, or w, e.g. 90d")
+    };
+    let unit = age.chars().last().ok_or_else(invalid)?;
+    let count: i64 = age[..age.len() - unit.len_utf8()]
+        .parse()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(invalid)?;
+    match unit {
+        'h' => TimeDelta::try_hours(count),
+        'd' => TimeDelta::try_days(count),
+        'w' => TimeDelta::try_weeks(count),
+        _ => None,
+    }
+    .ok_or_else(invalid)
+}
+
+/// Find the recorded projects with installed environments which weren't used for longer than
+/// an age, least recently used first.
+///
+/// * `max_age`: Age above which environments are unused
+pub fn unused(max_age: TimeDelta) -> Result<Vec<Unused>, String> {
+    let cutoff = Utc::now() - max_age;
+    let mut unused: Vec<Unused> = projects::load()?
+        .into_iter()
+        .filter_map(|(project, record)| {
+            let last_used = record.last_used()?;
+            let envs_dir = LockSpec::from_path(&project)
+                .ok()?
+                .dir()
+                .join(".pixi")
+                .join("envs");
+            (last_used < cutoff && envs_dir.is_dir()).then(|| Unused {
+                size: dir_size(&envs_dir),
+                environment: record.environment,
+                project,
+                envs_dir,
+                last_used,
+            })
+        })
+        .collect();
+    unused.sort_by_key(|unused| unused.last_used);
+    Ok(unused)
+}
+
+/// Remove the installed environments of a project.
+///
+/// * `unused`: Unused environments of the project
+pub fn remove(unused: &Unused) -> Result<(), String> {
+    fs::remove_dir_all(&unused.envs_dir)
+        .map_err(|err| format!("Unable to remove {:?}: {err}", unused.envs_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90d"), Ok(TimeDelta::days(90)));
+        assert_eq!(parse_age("2w"), Ok(TimeDelta::weeks(2)));
+        assert_eq!(parse_age("12h"), Ok(TimeDelta::hours(12)));
+        assert!(parse_age("90").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("0d").is_err());
+        assert!(parse_age("-1d").is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use toml::Table;
+
+use crate::common::{self, LockSpec};
+use crate::ops::{self, Progress};
+use crate::preflight;
+use crate::remote::parse_repo_arg;
+use crate::settings::get_settings;
+
+/// Marker written into the entry point scripts of global environments, followed by the name of
+/// the environment, so that they can be told apart from other scripts in the bin dir.
+const ENTRY_POINT_MARKER: &str = "# araki global environment:";
+
+/// Where global environments are stored, configured in the `[paths]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PathSettings {
+    /// Machine-level directory of global environments shared by all users, e.g.
+    /// `/opt/araki/envs`. Users who can't write to it still use its environments, and install
+    /// their own with `--user`
+    pub envs_dir: Option<PathBuf>,
+}
+
+/// Directory where a global environment is installed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Store {
+    /// The user's own environments, in the araki dir
+    User,
+
+    /// The machine-level environments of `paths.envs_dir`
+    Shared,
+}
+
+/// Get the directory holding the global environments of the user.
+pub fn get_global_dir() -> Result<PathBuf, String> {
+    Ok(common::get_araki_dir()?.join("global"))
+}
+
+/// Get the directory holding the global environments of a store.
+///
+/// * `store`: Store to get the directory of
+pub fn get_store_dir(store: Store) -> Result<PathBuf, String> {
+    match store {
+        Store::User => get_global_dir(),
+        Store::Shared => get_settings()?
+            .paths
+            .envs_dir
+            .ok_or("No shared environment store is configured; set paths.envs_dir.".to_string()),
+    }
+}
+
+/// Get the store new global environments are installed in: the shared store if one is
+/// configured, the user's otherwise.
+pub fn default_store() -> Store {
+    match get_settings().is_ok_and(|settings| settings.paths.envs_dir.is_some()) {
+        true => Store::Shared,
+        false => Store::User,
+    }
+}
+
+/// Get the store a global environment is installed in. The user's environments take
+/// precedence over shared ones with the same name.
+///
+/// * `name`: Name of the global environment
+pub fn store_of(name: &str) -> Option<Store> {
+    [Store::User, Store::Shared].into_iter().find(|store| {
+        get_store_dir(*store).is_ok_and(|dir| LockSpec::from_path(dir.join(name)).is_ok())
+    })
+}
+
+/// Get the directory of a global environment, in the store it is installed in, or in the
+/// user's store if it isn't installed.
+///
+/// * `name`: Name of the global environment
+pub fn get_global_env_dir(name: &str) -> Result<PathBuf, String> {
+    Ok(get_store_dir(store_of(name).unwrap_or(Store::User))?.join(name))
+}
+
+/// Check whether the current user can modify a global environment, which isn't the case for
+/// environments of a shared store owned by an administrator.
+///
+/// * `name`: Name of the global environment
+pub fn is_writable(name: &str) -> bool {
+    get_global_env_dir(name).is_ok_and(|dir| preflight::check_writable(&dir).is_ok())
+}
+
+/// List the names of the global environments installed in a store.
+///
+/// * `store`: Store to list
+pub fn list_store(store: Store) -> Result<Vec<String>, String> {
+    let Ok(dir) = get_store_dir(store) else {
+        return Ok(vec![]);
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(vec![]);
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| LockSpec::from_path(entry.path()).is_ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// List the names of the installed global environments, of both the user's and the shared
+/// store.
+pub fn list() -> Result<Vec<String>, String> {
+    let mut names = list_store(Store::User)?;
+    names.extend(list_store(Store::Shared)?);
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Install a lockspec as a global environment, outside of any project, and expose its entry
+/// points in the araki bin dir.
+///
+/// Returns the name of the environment and the exposed entry points.
+///
+/// * `env`: URL, `<org>/<repo>`, or alias of the lockspec
+/// * `name`: Name of the global environment; defaults to the name of the repository
+/// * `store`: Store to install the environment in
+/// * `on_progress`: Called as the clone progresses
+pub fn add(
+    env: &str,
+    name: Option<&str>,
+    store: Store,
+    on_progress: &dyn Fn(Progress),
+) -> Result<(String, Vec<String>), String> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let remote = parse_repo_arg(&get_settings()?.resolve_alias(env))?;
+            remote.get_subdir().unwrap_or(remote.get_repo())
+        }
+    };
+    let dir = get_store_dir(store)?.join(&name);
+    if dir.exists() {
+        return Err(format!(
+            "A global environment named {name} already exists at {dir:?}."
+        ));
+    }
+    // Entry points are exposed in the bin dir once the environment is installed; fail before
+    // cloning anything if they can't be
+    common::get_araki_bin_dir()?;
+    preflight::create_dir(&dir).map_err(|err| match store {
+        Store::Shared => {
+            format!("{err} Pass --user to install {name} in your own environment store.")
+        }
+        Store::User => err,
+    })?;
+    if let Err(err) = ops::clone(env, &dir, None, None, &[], on_progress) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(err);
+    }
+    let exposed = expose(&name)?;
+    Ok((name, exposed))
+}
+
+/// Remove a global environment and its entry points.
+///
+/// * `name`: Name of the global environment
+/// * `store`: Store to remove the environment from; the one it is installed in if `None`
+pub fn remove(name: &str, store: Option<Store>) -> Result<(), String> {
+    let dir = get_store_dir(store.unwrap_or(store_of(name).unwrap_or(Store::User)))?.join(name);
+    if !dir.exists() {
+        return Err(format!("No global environment named {name} is installed."));
+    }
+    preflight::check_writable(&dir)?;
+    remove_entry_points(name)?;
+    fs::remove_dir_all(&dir).map_err(|err| format!("Unable to remove {dir:?}: {err}"))
+}
+
+/// Write scripts to the araki bin dir that run the entry points of a global environment,
+/// replacing the scripts written for it before.
+///
+/// Only the executables of the environment's direct dependencies are exposed. Executables
+/// which would shadow a file in the bin dir that doesn't belong to the environment are
+/// skipped.
+///
+/// * `name`: Name of the global environment
+pub fn expose(name: &str) -> Result<Vec<String>, String> {
+    let lockspec = LockSpec::from_path(get_global_env_dir(name)?)?;
+    remove_entry_points(name)?;
+
+    let bin_dir = common::get_araki_bin_dir()?;
+    let prefix = lockspec.env_prefix();
+    let mut exposed = vec![];
+    for executable in entry_points(&lockspec)? {
+        let Some(file_name) = executable.file_name() else {
+            continue;
+        };
+        let script = bin_dir.join(file_name);
+        if script.exists() {
+            continue;
+        }
+        let contents = format!(
+            "#!/bin/sh\n{ENTRY_POINT_MARKER} {name}\nexport CONDA_PREFIX=\"{prefix}\"\n\
+            export PATH=\"{prefix}/bin:$PATH\"\nexec \"{executable}\" \"$@\"\n",
+            prefix = prefix.display(),
+            executable = executable.display(),
+        );
+        fs::write(&script, contents).map_err(|err| format!("Unable to write {script:?}: {err}"))?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+            .map_err(|err| format!("Unable to make {script:?} executable: {err}"))?;
+        exposed.push(file_name.to_string_lossy().to_string());
+    }
+    Ok(exposed)
+}
+
+/// Remove the entry point scripts of a global environment from the araki bin dir.
+///
+/// * `name`: Name of the global environment
+fn remove_entry_points(name: &str) -> Result<(), String> {
+    let bin_dir = common::get_araki_bin_dir()?;
+    let marker = format!("{ENTRY_POINT_MARKER} {name}\n");
+    for entry in fs::read_dir(&bin_dir)
+        .map_err(|err| format!("Unable to read {bin_dir:?}: {err}"))?
+        .flatten()
+    {
+        if fs::read_to_string(entry.path()).is_ok_and(|contents| contents.contains(&marker)) {
+            fs::remove_file(entry.path())
+                .map_err(|err| format!("Unable to remove {:?}: {err}", entry.path()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the executables installed by the direct dependencies of a lockspec.
+///
+/// * `lockspec`: Installed lockspec
+fn entry_points(lockspec: &LockSpec) -> Result<Vec<PathBuf>, String> {
+    let specfile = lockspec.specfile();
+    let manifest: Table = fs::read_to_string(&specfile)
+        .map_err(|err| format!("Unable to read {specfile:?}: {err}"))?
+        .parse()
+        .map_err(|err| format!("Unable to parse {specfile:?}: {err}"))?;
+    let dependencies: BTreeSet<&str> = manifest
+        .get("dependencies")
+        .and_then(|deps| deps.as_table())
+        .map(|deps| deps.keys().map(|name| name.as_str()).collect())
+        .unwrap_or_default();
+
+    let prefix = lockspec.env_prefix();
+    let mut executables = vec![];
+    let Ok(records) = fs::read_dir(prefix.join("conda-meta")) else {
+        return Ok(executables);
+    };
+    for record in records.flatten() {
+        let Some(record) = fs::read_to_string(record.path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        else {
+            continue;
+        };
+        let is_dependency = record["name"]
+            .as_str()
+            .is_some_and(|name| dependencies.contains(name));
+        if !is_dependency {
+            continue;
+        }
+        for file in record["files"].as_array().into_iter().flatten() {
+            if let Some(file) = file.as_str()
+                && is_executable_in_bin(&prefix, file)
+            {
+                executables.push(prefix.join(file));
+            }
+        }
+    }
+    executables.sort();
+    Ok(executables)
+}
+
+/// Check whether a file of a package is an executable directly in the `bin` dir of a prefix.
+///
+/// * `prefix`: Prefix the package is installed in
+/// * `file`: Path of the file relative to the prefix
+fn is_executable_in_bin(prefix: &Path, file: &str) -> bool {
+    let Some(name) = file.strip_prefix("bin/") else {
+        return false;
+    };
+    !name.contains('/')
+        && fs::metadata(prefix.join(file))
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
@@ -0,0 +1,83 @@
+//! Core logic for managing and versioning pixi lockspecs with araki.
+//!
+//! The `araki` binary is a thin command line layer over this library; other tools (editor
+//! plugins, bots, etc.) can use it to work with araki-managed lockspecs directly.
+//!
+//! * [`artifacts`]: Registries holding the lockspec files of tags instead of a git history
+//! * [`audit`]: Vulnerability audits of locked packages against the OSV database
+//! * [`backends`]: Hosting backends where lockspec repositories live, and authentication with them
+//! * [`bundle`]: Self-contained archives of an environment and its package artifacts
+//! * [`catalog`]: Package metadata of remote lockspecs, cached for filtering listings
+//! * [`common`]: The [`common::LockSpec`] type, araki directories, and git helpers
+//! * [`export`]: Container definitions (Dockerfile, devcontainer) for a lockspec
+//! * [`gc`]: Removal of installed environments which haven't been used recently
+//! * [`global`]: Environments installed outside of any project, with entry points on PATH
+//! * [`licenses`]: License metadata of locked packages and license policies
+//! * [`lint`]: Best-practice checks of a lockspec and its araki repo
+//! * [`lockfile`]: Parsing of the packages pinned in a pixi.lock
+//! * [`merge`]: Semantic three-way merges of pixi.toml and pixi.lock, as a git merge driver
+//! * [`migrate`]: Conversion of existing conda environments to locked pixi projects
+//! * [`mirrors`]: Channel mirrors and credentials injected into pixi
+//! * [`network`]: TLS, proxy, and timeout settings shared by all HTTP clients
+//! * [`notifications`]: Webhook notifications when environments are tagged or pushed
+//! * [`oci`]: Lockspecs stored as OCI artifacts in container registries
+//! * [`ops`]: High-level lockspec operations: clone, list, tag, checkout, and status
+//! * [`policy`]: Size and content policies of lockspec repositories, checked on tag and push
+//! * [`preflight`]: Checks run before a lockspec is written to a directory
+//! * [`projects`]: Which projects use which environments, recorded on clone and checkout
+//! * [`readme`]: READMEs generated for browsing lockspec repositories on their forge
+//! * [`rebuild`]: Reproducibility checks installing tags again from their lockfiles
+//! * [`registry`]: A self-hostable lockspec registry server and its backend
+//! * [`remote`]: Parsing and rendering of remote lockspec repository locations
+//! * [`sbom`]: Software bills of materials (CycloneDX, SPDX) for a lockfile
+//! * [`schema`]: JSON Schema of `araki.toml`, and validation of settings files against it
+//! * [`secrets`]: Secrets referenced by the environment variables of a lockspec
+//! * [`security`]: Verification of tag signatures against allowed signers
+//! * [`settings`]: User and project settings read from `araki.toml`
+//! * [`stats`]: Usage statistics of the environments of recorded projects
+//! * [`tags`]: Naming schemes of version tags
+//! * [`tools`]: Versioning of the tools installed with `pixi global`
+//! * [`variant`]: Hardware variants (CPU, GPU) of a lockspec, selected at clone time
+//! * [`workspace`]: Projects using several named environments
+//!
+//! With the `python` feature enabled, the library is also built as a Python extension module
+//! exposing the operations in [`ops`].
+
+pub mod artifacts;
+pub mod audit;
+pub mod backends;
+pub mod bundle;
+pub mod catalog;
+pub mod common;
+pub mod export;
+pub mod gc;
+pub mod global;
+pub mod licenses;
+pub mod lint;
+pub mod lockfile;
+pub mod merge;
+pub mod migrate;
+pub mod mirrors;
+pub mod network;
+pub mod notifications;
+pub mod oci;
+pub mod ops;
+pub mod policy;
+pub mod preflight;
+pub mod projects;
+#[cfg(feature = "python")]
+mod python;
+pub mod readme;
+pub mod rebuild;
+pub mod registry;
+pub mod remote;
+pub mod sbom;
+pub mod schema;
+pub mod secrets;
+pub mod security;
+pub mod settings;
+pub mod stats;
+pub mod tags;
+pub mod tools;
+pub mod variant;
+pub mod workspace;
@@ -0,0 +1,155 @@
+use serde::Deserialize;
+use tokio::task::JoinSet;
+
+use crate::backends::BackendError;
+use crate::lockfile::{LockFile, PackageKind};
+use crate::network;
+
+const PYPI_URL: &str = "https://pypi.org/pypi";
+
+/// License policy, configured in the `[licenses]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LicenseSettings {
+    /// SPDX identifiers of licenses which may not appear in an environment, e.g. `GPL-3.0`
+    pub deny: Vec<String>,
+}
+
+/// License of a locked package.
+#[derive(Debug, Clone)]
+pub struct PackageLicense {
+    pub name: String,
+    pub version: String,
+    pub kind: PackageKind,
+
+    /// License expression; None if the package metadata does not declare one
+    pub license: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PypiRelease {
+    info: PypiInfo,
+}
+
+#[derive(Deserialize)]
+struct PypiInfo {
+    license_expression: Option<String>,
+    license: Option<String>,
+    #[serde(default)]
+    classifiers: Vec<String>,
+}
+
+impl PypiInfo {
+    /// Get the license of a release. PEP 639 license expressions take precedence over the free
+    /// form license field, which in turn takes precedence over trove classifiers.
+    fn license(self) -> Option<String> {
+        let short = |license: &Option<String>| {
+            license
+                .as_ref()
+                .map(|license| license.trim().to_string())
+                // Some packages put the entire license text in the license field
+                .filter(|license| !license.is_empty() && !license.contains('\n'))
+        };
+        short(&self.license_expression)
+            .or(short(&self.license))
+            .or(self
+                .classifiers
+                .iter()
+                .filter_map(|classifier| classifier.strip_prefix("License :: "))
+                .filter_map(|license| license.rsplit(" :: ").next())
+                .map(str::to_string)
+                .reduce(|a, b| format!("{a} OR {b}")))
+    }
+}
+
+/// Get the licenses of every package in a lockfile.
+///
+/// Conda packages carry their license in the lockfile; the licenses of PyPI packages are looked
+/// up on PyPI.
+///
+/// * `lockfile`: Lockfile listing the packages
+pub async fn collect(lockfile: &LockFile) -> Result<Vec<PackageLicense>, BackendError> {
+    let client = network::client_builder()?.build()?;
+    let mut requests = JoinSet::new();
+    let mut licenses = vec![];
+    for package in &lockfile.packages {
+        match package.kind {
+            PackageKind::Conda => licenses.push(PackageLicense {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                kind: package.kind,
+                license: package.license.clone().filter(|license| license != "None"),
+            }),
+            PackageKind::Pypi => {
+                let client = client.clone();
+                let (name, version) = (package.name.clone(), package.version.clone());
+                requests.spawn(async move {
+                    let response = client
+                        .get(format!("{PYPI_URL}/{name}/{version}/json"))
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status());
+                    let license = match response {
+                        Ok(response) => response
+                            .json::<PypiRelease>()
+                            .await
+                            .ok()
+                            .and_then(|release| release.info.license()),
+                        Err(err) if err.status().is_some() => None,
+                        Err(err) => return Err(err),
+                    };
+                    Ok(PackageLicense {
+                        name,
+                        version,
+                        kind: PackageKind::Pypi,
+                        license,
+                    })
+                });
+            }
+        }
+    }
+    while let Some(result) = requests.join_next().await {
+        licenses.push(result??);
+    }
+    licenses.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(licenses)
+}
+
+/// Check whether a license expression contains a denied license.
+///
+/// A denied identifier also matches its variants, so `GPL-3.0` denies `GPL-3.0-only` and
+/// `GPL-3.0-or-later`.
+///
+/// * `license`: License expression, e.g. `MIT OR Apache-2.0`
+/// * `deny`: Denied license identifiers
+pub fn is_denied(license: &str, deny: &[String]) -> bool {
+    license
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ',' || c == '/')
+        .filter(|id| !id.is_empty())
+        .any(|id| {
+            deny.iter().any(|denied| {
+                let (id, denied) = (id.to_lowercase(), denied.to_lowercase());
+                id == denied
+                    || id
+                        .strip_prefix(&denied)
+                        .is_some_and(|rest| rest.starts_with('-') || rest.starts_with('+'))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_denied() {
+        let deny = vec!["GPL-3.0".to_string()];
+        assert!(is_denied("GPL-3.0-only", &deny));
+        assert!(is_denied(
+            "MIT OR (gpl-3.0-or-later AND BSD-3-Clause)",
+            &deny
+        ));
+        assert!(!is_denied("LGPL-3.0-only", &deny));
+        assert!(!is_denied("GPL-3.0.1", &deny));
+    }
+}
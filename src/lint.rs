@@ -0,0 +1,387 @@
+//! Best-practice checks of a lockspec and its araki repo, run by `araki lint`.
+//!
+//! Each rule reports findings at a level configured in the `[lint]` section of `araki.toml`, so
+//! that CI can fail on the rules a team cares about and ignore the others.
+
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use toml::{Table, Value};
+
+use crate::common::{self, LockSpec};
+use crate::lockfile::{LockFile, PackageKind};
+
+/// A channel is referenced by name instead of by URL.
+pub const UNPINNED_CHANNEL: &str = "unpinned-channel";
+
+/// A platform is declared but not locked, or required but not declared.
+pub const MISSING_PLATFORM: &str = "missing-platform";
+
+/// The specfile has no `[araki]` metadata.
+pub const MISSING_METADATA: &str = "missing-metadata";
+
+/// A large file is committed to the araki repo.
+pub const LARGE_FILE: &str = "large-file";
+
+/// The lockfile doesn't lock what the specfile declares.
+pub const LOCKFILE_MISMATCH: &str = "lockfile-mismatch";
+
+/// No README is committed alongside the lockspec.
+pub const MISSING_README: &str = "missing-readme";
+
+/// All rules, with their default levels.
+pub const RULES: [(&str, Level); 6] = [
+    (UNPINNED_CHANNEL, Level::Warning),
+    (MISSING_PLATFORM, Level::Error),
+    (MISSING_METADATA, Level::Warning),
+    (LARGE_FILE, Level::Warning),
+    (LOCKFILE_MISMATCH, Level::Error),
+    (MISSING_README, Level::Warning),
+];
+
+/// Level at which a rule reports its findings.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// The rule is not checked
+    Off,
+    Warning,
+
+    /// Findings fail `araki lint`
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Lint settings, configured in the `[lint]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LintSettings {
+    /// Level of each rule, overriding its default, e.g. `large-file = "error"`
+    pub rules: BTreeMap<String, Level>,
+
+    /// Size in megabytes above which a committed file is reported
+    pub max_file_mb: u64,
+
+    /// Platforms every lockspec must support, e.g. `["linux-64", "osx-arm64"]`
+    pub platforms: Vec<String>,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        LintSettings {
+            rules: BTreeMap::new(),
+            max_file_mb: 1,
+            platforms: vec![],
+        }
+    }
+}
+
+impl LintSettings {
+    /// Get the level of a rule.
+    ///
+    /// * `rule`: Name of the rule, e.g. `large-file`
+    pub fn level(&self, rule: &str) -> Level {
+        self.rules.get(rule).copied().unwrap_or_else(|| {
+            RULES
+                .iter()
+                .find(|(name, _)| *name == rule)
+                .map(|(_, level)| *level)
+                .unwrap_or(Level::Warning)
+        })
+    }
+}
+
+/// An issue found by a rule.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Check a lockspec and its araki repo against the lint rules which aren't turned off.
+///
+/// * `lockspec`: Lockspec to check
+/// * `repo`: araki repo of the lockspec
+/// * `settings`: Lint settings
+pub fn lint(
+    lockspec: &LockSpec,
+    repo: &Repository,
+    settings: &LintSettings,
+) -> Result<Vec<Finding>, String> {
+    for rule in settings.rules.keys() {
+        if !RULES.iter().any(|(name, _)| name == rule) {
+            return Err(format!(
+                "Unknown lint rule {rule} in araki.toml; available: {}",
+                RULES.map(|(name, _)| name).join(", ")
+            ));
+        }
+    }
+
+    let specfile = lockspec.specfile();
+    let spec: Table = fs::read_to_string(&specfile)
+        .map_err(|err| format!("Unable to read {specfile:?}: {err}"))?
+        .parse()
+        .map_err(|err| format!("Unable to parse {specfile:?}: {err}"))?;
+    let lockfile = LockFile::from_path(&lockspec.lockfile())?;
+    let files = committed_files(repo)?;
+
+    let mut findings = vec![];
+    let mut report = |rule: &'static str, message: String| {
+        let level = settings.level(rule);
+        if level != Level::Off {
+            findings.push(Finding {
+                rule,
+                level,
+                message,
+            });
+        }
+    };
+
+    for channel in channels(&spec) {
+        if !channel.contains("://") {
+            report(
+                UNPINNED_CHANNEL,
+                format!(
+                    "Channel {channel} is referenced by name, and resolves through the channel \
+                    alias of each machine; use its URL instead."
+                ),
+            );
+        }
+    }
+
+    let declared = strings(workspace(&spec).and_then(|workspace| workspace.get("platforms")));
+    for platform in &settings.platforms {
+        if !declared.contains(platform) {
+            report(
+                MISSING_PLATFORM,
+                format!("Platform {platform} is required, but not declared in pixi.toml."),
+            );
+        }
+    }
+    for (name, environment) in &lockfile.environments {
+        for platform in &declared {
+            if !environment.packages.contains_key(platform) {
+                report(
+                    MISSING_PLATFORM,
+                    format!(
+                        "Platform {platform} is declared, but not locked for environment {name}."
+                    ),
+                );
+            }
+        }
+    }
+
+    if lockspec.name().is_none() {
+        report(
+            MISSING_METADATA,
+            "pixi.toml has no [araki] lockspec_name; run `araki init` or add it.".to_string(),
+        );
+    }
+
+    let max_bytes = settings.max_file_mb * 1024 * 1024;
+    for (path, size) in &files {
+        // The lockfile is expected to be large
+        if *size > max_bytes && *path != common::tracked_path(repo, "pixi.lock") {
+            report(
+                LARGE_FILE,
+                format!(
+                    "{path} is {:.1} MB; keep large files out of the lockspec repository.",
+                    *size as f64 / (1024.0 * 1024.0)
+                ),
+            );
+        }
+    }
+
+    for message in lockfile_mismatches(&spec, &lockfile) {
+        report(LOCKFILE_MISMATCH, message);
+    }
+
+    let readme_dir = common::lockspec_subdir(repo)
+        .map(|subdir| format!("{}/", subdir.to_string_lossy()))
+        .unwrap_or_default();
+    let has_readme = files.keys().any(|path| {
+        path.strip_prefix(&readme_dir)
+            .is_some_and(|name| !name.contains('/') && name.to_lowercase().starts_with("readme"))
+    });
+    if !has_readme {
+        report(
+            MISSING_README,
+            "No README is committed; describe what the environment is for and how to use it."
+                .to_string(),
+        );
+    }
+
+    findings.sort_by(|a, b| b.level.cmp(&a.level).then(a.rule.cmp(b.rule)));
+    Ok(findings)
+}
+
+/// Get the files committed at the head of an araki repo and their sizes, keyed by path. A repo
+/// without commits has none.
+///
+/// * `repo`: araki repo
+fn committed_files(repo: &Repository) -> Result<BTreeMap<String, u64>, String> {
+    let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) else {
+        return Ok(BTreeMap::new());
+    };
+    let mut files = BTreeMap::new();
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(ObjectType::Blob)
+            && let Some(name) = entry.name()
+            && let Ok(blob) = repo.find_blob(entry.id())
+        {
+            files.insert(format!("{dir}{name}"), blob.size() as u64);
+        }
+        TreeWalkResult::Ok
+    })
+    .map_err(|err| format!("Unable to list the committed files: {err}"))?;
+    Ok(files)
+}
+
+/// Get the `[workspace]` table of a specfile, or the `[project]` table of older ones.
+///
+/// * `spec`: Contents of the specfile
+fn workspace(spec: &Table) -> Option<&Table> {
+    spec.get("workspace")
+        .or_else(|| spec.get("project"))
+        .and_then(|workspace| workspace.as_table())
+}
+
+/// Get the tables of a specfile declaring dependencies: the top level, and each feature.
+///
+/// * `spec`: Contents of the specfile
+fn feature_tables(spec: &Table) -> Vec<&Table> {
+    let mut tables = vec![spec];
+    if let Some(features) = spec.get("feature").and_then(|f| f.as_table()) {
+        tables.extend(features.values().filter_map(|feature| feature.as_table()));
+    }
+    tables
+}
+
+/// Get the channels used by a specfile, including those of its features.
+///
+/// * `spec`: Contents of the specfile
+fn channels(spec: &Table) -> BTreeSet<String> {
+    let mut lists = vec![workspace(spec).and_then(|workspace| workspace.get("channels"))];
+    if let Some(features) = spec.get("feature").and_then(|f| f.as_table()) {
+        lists.extend(features.values().map(|feature| feature.get("channels")));
+    }
+    lists
+        .into_iter()
+        .flatten()
+        .filter_map(|list| list.as_array())
+        .flatten()
+        .filter_map(|channel| match channel {
+            // Channels with a priority are written as `{ channel = "...", priority = 1 }`
+            Value::Table(table) => table.get("channel")?.as_str(),
+            channel => channel.as_str(),
+        })
+        .map(|channel| channel.to_string())
+        .collect()
+}
+
+/// Get the strings of an array of a specfile, if it is one.
+///
+/// * `value`: Value of the array
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|value| value.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(|value| value.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Normalize the name of a PyPI package as in PEP 503.
+///
+/// * `name`: Name of the package
+fn normalize_pypi_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Describe the dependencies and environments declared in a specfile which its lockfile
+/// doesn't lock, which means the lockfile is out of date.
+///
+/// * `spec`: Contents of the specfile
+/// * `lockfile`: Lockfile of the lockspec
+fn lockfile_mismatches(spec: &Table, lockfile: &LockFile) -> Vec<String> {
+    let mut messages = vec![];
+    let locked = |kind: PackageKind, name: &str| {
+        lockfile.packages.iter().any(|package| {
+            package.kind == kind
+                && match kind {
+                    PackageKind::Pypi => {
+                        normalize_pypi_name(&package.name) == normalize_pypi_name(name)
+                    }
+                    _ => package.name == name,
+                }
+        })
+    };
+    for table in feature_tables(spec) {
+        for (key, kind) in [
+            ("dependencies", PackageKind::Conda),
+            ("pypi-dependencies", PackageKind::Pypi),
+        ] {
+            let Some(dependencies) = table.get(key).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for name in dependencies.keys() {
+                if !locked(kind, name) {
+                    messages.push(format!(
+                        "{name} is a dependency in pixi.toml, but is not locked; run `pixi lock`."
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(environments) = spec.get("environments").and_then(|e| e.as_table()) {
+        for name in environments.keys() {
+            if !lockfile.environments.contains_key(name) {
+                messages.push(format!(
+                    "Environment {name} is declared in pixi.toml, but is not locked; run `pixi lock`."
+                ));
+            }
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channels() {
+        let spec: Table = r#"
+            [workspace]
+            channels = ["conda-forge", { channel = "https://prefix.dev/bioconda", priority = 1 }]
+
+            [feature.cuda]
+            channels = ["nvidia"]
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            channels(&spec),
+            BTreeSet::from([
+                "conda-forge".to_string(),
+                "https://prefix.dev/bioconda".to_string(),
+                "nvidia".to_string()
+            ])
+        );
+    }
+}
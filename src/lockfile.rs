@@ -0,0 +1,678 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Package ecosystem of a locked package.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageKind {
+    Conda,
+    Pypi,
+}
+
+impl fmt::Display for PackageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conda => write!(f, "conda"),
+            Self::Pypi => write!(f, "pypi"),
+        }
+    }
+}
+
+/// A package pinned in a pixi.lock.
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub kind: PackageKind,
+    pub name: String,
+    pub version: String,
+
+    /// Location the package is downloaded from
+    pub url: String,
+    pub sha256: Option<String>,
+    pub md5: Option<String>,
+    pub license: Option<String>,
+
+    /// Dependency specs of the package, e.g. `libgcc >=13` or `idna<4,>=2.5`
+    pub depends: Vec<String>,
+}
+
+impl Package {
+    /// Get the package URL (purl) identifying this package.
+    pub fn purl(&self) -> String {
+        match self.kind {
+            PackageKind::Conda => {
+                let channel = self
+                    .url
+                    .rsplitn(3, '/')
+                    .nth(2)
+                    .map(|channel| format!("?channel={channel}"))
+                    .unwrap_or_default();
+                format!("pkg:conda/{}@{}{channel}", self.name, self.version)
+            }
+            PackageKind::Pypi => format!(
+                "pkg:pypi/{}@{}",
+                self.name.to_lowercase().replace('_', "-"),
+                self.version
+            ),
+        }
+    }
+}
+
+/// An environment of a pixi project, as recorded in the pixi.lock.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// URLs of the conda channels used by the environment
+    pub channels: Vec<String>,
+
+    /// URLs of the packages installed on each platform
+    pub packages: BTreeMap<String, Vec<String>>,
+}
+
+/// The contents of a pixi.lock.
+#[derive(Debug, Clone, Default)]
+pub struct LockFile {
+    pub version: u64,
+    pub environments: BTreeMap<String, Environment>,
+    pub packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct RawLockFile {
+    version: u64,
+    #[serde(default)]
+    environments: BTreeMap<String, RawEnvironment>,
+    #[serde(default)]
+    packages: Vec<RawPackage>,
+}
+
+#[derive(Deserialize)]
+struct RawEnvironment {
+    #[serde(default)]
+    channels: Vec<RawChannel>,
+    #[serde(default)]
+    packages: BTreeMap<String, Vec<BTreeMap<String, String>>>,
+}
+
+#[derive(Deserialize)]
+struct RawChannel {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    conda: Option<String>,
+    pypi: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    sha256: Option<String>,
+    md5: Option<String>,
+    license: Option<String>,
+    #[serde(default)]
+    depends: Vec<String>,
+    #[serde(default)]
+    requires_dist: Vec<String>,
+}
+
+impl LockFile {
+    /// Read and parse a pixi.lock.
+    ///
+    /// * `path`: Path to the pixi.lock
+    pub fn from_path(path: &Path) -> Result<LockFile, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| format!("Unable to read {path:?}: {err}"))?;
+        LockFile::parse(&contents).map_err(|err| format!("Unable to parse {path:?}: {err}"))
+    }
+
+    /// Parse the contents of a pixi.lock.
+    ///
+    /// * `contents`: Contents of the pixi.lock
+    pub fn parse(contents: &str) -> Result<LockFile, String> {
+        let raw: RawLockFile = serde_yaml::from_str(contents).map_err(|err| err.to_string())?;
+
+        let environments = raw
+            .environments
+            .into_iter()
+            .map(|(name, env)| {
+                let packages = env
+                    .packages
+                    .into_iter()
+                    .map(|(platform, packages)| {
+                        let urls = packages
+                            .into_iter()
+                            .filter_map(|package| package.into_values().next())
+                            .collect();
+                        (platform, urls)
+                    })
+                    .collect();
+                let channels = env
+                    .channels
+                    .into_iter()
+                    .map(|channel| channel.url)
+                    .collect();
+                (name, Environment { channels, packages })
+            })
+            .collect();
+
+        let packages = raw
+            .packages
+            .into_iter()
+            .map(Package::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(LockFile {
+            version: raw.version,
+            environments,
+            packages,
+        })
+    }
+}
+
+/// A change to the packages of a lockfile.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackageChange {
+    Added {
+        name: String,
+        version: String,
+    },
+    Removed {
+        name: String,
+        version: String,
+    },
+    Updated {
+        name: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl fmt::Display for PackageChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { name, version } => write!(f, "+ {name} {version}"),
+            Self::Removed { name, version } => write!(f, "- {name} {version}"),
+            Self::Updated { name, from, to } => write!(f, "~ {name} {from} -> {to}"),
+        }
+    }
+}
+
+impl LockFile {
+    /// Get the versions of each package, keyed by ecosystem and name. A package can be locked
+    /// at different versions on different platforms.
+    fn versions(&self) -> BTreeMap<(PackageKind, &str), Vec<&str>> {
+        let mut versions: BTreeMap<(PackageKind, &str), Vec<&str>> = BTreeMap::new();
+        for package in &self.packages {
+            let entry = versions
+                .entry((package.kind, package.name.as_str()))
+                .or_default();
+            if !entry.contains(&package.version.as_str()) {
+                entry.push(&package.version);
+            }
+        }
+        versions
+    }
+
+    /// Get the packages which were added, removed, or updated between two lockfiles.
+    ///
+    /// * `old`: Lockfile before the change
+    /// * `new`: Lockfile after the change
+    pub fn diff(old: &LockFile, new: &LockFile) -> Vec<PackageChange> {
+        LockFile::diff_by_kind(old, new)
+            .into_values()
+            .flatten()
+            .collect()
+    }
+
+    /// Get the packages which were added, removed, or updated between two lockfiles, grouped
+    /// by ecosystem. Ecosystems without changes are left out.
+    ///
+    /// * `old`: Lockfile before the change
+    /// * `new`: Lockfile after the change
+    pub fn diff_by_kind(
+        old: &LockFile,
+        new: &LockFile,
+    ) -> BTreeMap<PackageKind, Vec<PackageChange>> {
+        let (old, new) = (old.versions(), new.versions());
+        let mut changes: BTreeMap<PackageKind, Vec<PackageChange>> = BTreeMap::new();
+        for (key, old_versions) in &old {
+            let name = key.1.to_string();
+            let change = match new.get(key) {
+                None => PackageChange::Removed {
+                    name,
+                    version: old_versions.join(", "),
+                },
+                Some(new_versions) if new_versions != old_versions => PackageChange::Updated {
+                    name,
+                    from: old_versions.join(", "),
+                    to: new_versions.join(", "),
+                },
+                Some(_) => continue,
+            };
+            changes.entry(key.0).or_default().push(change);
+        }
+        for (key, new_versions) in &new {
+            if !old.contains_key(key) {
+                changes
+                    .entry(key.0)
+                    .or_default()
+                    .push(PackageChange::Added {
+                        name: key.1.to_string(),
+                        version: new_versions.join(", "),
+                    });
+            }
+        }
+        changes
+    }
+}
+
+impl LockFile {
+    /// Find a locked package by name, compared like in [`LockFile::dependency_chains`].
+    ///
+    /// * `name`: Name of the package
+    pub fn find(&self, name: &str) -> Option<&Package> {
+        let name = normalize_name(name);
+        self.packages
+            .iter()
+            .find(|package| normalize_name(&package.name) == name)
+    }
+
+    /// Get the chains of dependencies leading from root packages to a package, e.g.
+    /// `["pandas", "numpy"]`. Each root which depends on the package, directly or not, gives the
+    /// shortest chain from it; a root which is the package itself gives a chain of one.
+    ///
+    /// Package names are compared case-insensitively, with `-` and `_` considered equal, and
+    /// dependencies only needed for pypi extras are ignored.
+    ///
+    /// * `roots`: Names of the packages to start from, e.g. the dependencies of the specfile
+    /// * `package`: Name of the package to explain
+    pub fn dependency_chains(&self, roots: &[String], package: &str) -> Vec<Vec<String>> {
+        let mut graph: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+        for locked in &self.packages {
+            let (_, depends) = graph
+                .entry(normalize_name(&locked.name))
+                .or_insert((locked.name.clone(), vec![]));
+            for spec in &locked.depends {
+                if spec.contains("extra ==") {
+                    continue;
+                }
+                let name = spec
+                    .split(|c: char| " <>=!~;[(".contains(c))
+                    .next()
+                    .unwrap_or_default();
+                let name = normalize_name(name);
+                if !name.is_empty() && !depends.contains(&name) {
+                    depends.push(name);
+                }
+            }
+        }
+
+        let target = normalize_name(package);
+        let mut chains = vec![];
+        for root in roots {
+            let root = normalize_name(root);
+            let mut parents: BTreeMap<&str, &str> = BTreeMap::new();
+            let mut queue = VecDeque::from([root.as_str()]);
+            let mut found = false;
+            while let Some(name) = queue.pop_front() {
+                if name == target {
+                    found = true;
+                    break;
+                }
+                for dependency in graph
+                    .get(name)
+                    .map(|(_, d)| d.as_slice())
+                    .unwrap_or_default()
+                {
+                    if dependency != &root && !parents.contains_key(dependency.as_str()) {
+                        parents.insert(dependency, name);
+                        queue.push_back(dependency);
+                    }
+                }
+            }
+            if !found {
+                continue;
+            }
+            let mut chain = vec![target.as_str()];
+            while let Some(parent) = parents.get(chain[chain.len() - 1]) {
+                chain.push(parent);
+            }
+            chains.push(
+                chain
+                    .iter()
+                    .rev()
+                    .map(|name| {
+                        graph
+                            .get(*name)
+                            .map_or(name.to_string(), |(n, _)| n.clone())
+                    })
+                    .collect(),
+            );
+        }
+        chains
+    }
+}
+
+/// Normalize a package name for comparisons: lowercase, with `_` replaced by `-`.
+///
+/// * `name`: Name of the package
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase().replace('_', "-")
+}
+
+/// Count the package changes of each type, e.g. `2 added, 1 removed, 3 updated`.
+///
+/// * `changes`: Package changes to count
+pub fn count_changes(changes: &[PackageChange]) -> String {
+    let count = |kind: fn(&PackageChange) -> bool| changes.iter().filter(|c| kind(c)).count();
+    [
+        (count(|c| matches!(c, PackageChange::Added { .. })), "added"),
+        (
+            count(|c| matches!(c, PackageChange::Removed { .. })),
+            "removed",
+        ),
+        (
+            count(|c| matches!(c, PackageChange::Updated { .. })),
+            "updated",
+        ),
+    ]
+    .iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, label)| format!("{count} {label}"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Comparison of a version requirement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+
+    /// The version starts with the given segments, like `=3.11` or `==3.11.*`
+    Prefix,
+}
+
+/// A constraint on the locked versions of a package, e.g. `numpy`, `python>=3.11`, or
+/// `pytorch>=2,<3`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageConstraint {
+    pub name: String,
+    requirements: Vec<(Comparison, String)>,
+}
+
+impl FromStr for PackageConstraint {
+    type Err = String;
+
+    fn from_str(constraint: &str) -> Result<Self, Self::Err> {
+        let constraint = constraint.trim();
+        let split = constraint
+            .find(|c: char| "<>=!~ ".contains(c))
+            .unwrap_or(constraint.len());
+        let (name, spec) = constraint.split_at(split);
+        if name.is_empty() {
+            return Err(format!("No package name in constraint '{constraint}'"));
+        }
+        let mut requirements = vec![];
+        for requirement in spec.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (comparison, version) = [
+                (">=", Comparison::GreaterEqual),
+                ("<=", Comparison::LessEqual),
+                ("==", Comparison::Equal),
+                ("!=", Comparison::NotEqual),
+                (">", Comparison::Greater),
+                ("<", Comparison::Less),
+                ("=", Comparison::Prefix),
+            ]
+            .iter()
+            .find_map(|(op, comparison)| Some((*comparison, requirement.strip_prefix(op)?)))
+            .unwrap_or((Comparison::Prefix, requirement));
+            let version = version.trim();
+            let (comparison, version) = match version.strip_suffix(".*") {
+                Some(prefix) if comparison == Comparison::Equal => (Comparison::Prefix, prefix),
+                _ => (comparison, version),
+            };
+            if version.is_empty() {
+                return Err(format!("No version in requirement '{requirement}'"));
+            }
+            requirements.push((comparison, version.to_string()));
+        }
+        Ok(PackageConstraint {
+            name: name.to_string(),
+            requirements,
+        })
+    }
+}
+
+impl fmt::Display for PackageConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let requirements: Vec<String> = self
+            .requirements
+            .iter()
+            .map(|(comparison, version)| {
+                let op = match comparison {
+                    Comparison::Equal => "==",
+                    Comparison::NotEqual => "!=",
+                    Comparison::Less => "<",
+                    Comparison::LessEqual => "<=",
+                    Comparison::Greater => ">",
+                    Comparison::GreaterEqual => ">=",
+                    Comparison::Prefix => "=",
+                };
+                format!("{op}{version}")
+            })
+            .collect();
+        write!(f, "{}{}", self.name, requirements.join(","))
+    }
+}
+
+impl PackageConstraint {
+    /// Check whether a version satisfies every requirement of the constraint.
+    ///
+    /// * `version`: Version of the package, e.g. `3.12.4`
+    pub fn matches(&self, version: &str) -> bool {
+        self.requirements.iter().all(|(comparison, required)| {
+            let ordering = compare_versions(version, required);
+            match comparison {
+                Comparison::Equal => ordering == Ordering::Equal,
+                Comparison::NotEqual => ordering != Ordering::Equal,
+                Comparison::Less => ordering == Ordering::Less,
+                Comparison::LessEqual => ordering != Ordering::Greater,
+                Comparison::Greater => ordering == Ordering::Greater,
+                Comparison::GreaterEqual => ordering != Ordering::Less,
+                Comparison::Prefix => {
+                    let segments: Vec<&str> = version_segments(version).collect();
+                    let prefix: Vec<&str> = version_segments(required).collect();
+                    segments.starts_with(&prefix)
+                }
+            }
+        })
+    }
+}
+
+/// Split a version into its segments, e.g. `1.2.0rc1` into `1`, `2`, `0rc1`.
+///
+/// * `version`: Version to split
+fn version_segments(version: &str) -> impl Iterator<Item = &str> {
+    version.split(['.', '-', '_', '+'])
+}
+
+/// Compare two versions segment by segment: numerically where both segments are numbers, and
+/// alphabetically otherwise. Missing segments count as `0`, so `3.11` equals `3.11.0`.
+///
+/// * `a`: First version
+/// * `b`: Second version
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b): (Vec<&str>, Vec<&str>) =
+        (version_segments(a).collect(), version_segments(b).collect());
+    for i in 0..a.len().max(b.len()) {
+        let (a, b) = (a.get(i).unwrap_or(&"0"), b.get(i).unwrap_or(&"0"));
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+impl TryFrom<RawPackage> for Package {
+    type Error = String;
+
+    fn try_from(raw: RawPackage) -> Result<Self, Self::Error> {
+        let (kind, url, depends) = match (raw.conda, raw.pypi) {
+            (Some(url), _) => (PackageKind::Conda, url, raw.depends),
+            (None, Some(url)) => (PackageKind::Pypi, url, raw.requires_dist),
+            (None, None) => return Err("Found a package which is neither conda nor pypi".into()),
+        };
+
+        // Newer lockfiles leave out the name and version of conda packages, since they are part
+        // of the file name: <name>-<version>-<build>.conda
+        let (name, version) = match (raw.name, raw.version) {
+            (Some(name), Some(version)) => (name, version),
+            (name, version) => {
+                let file = url.rsplit('/').next().unwrap_or(&url);
+                let stem = file
+                    .strip_suffix(".tar.bz2")
+                    .or(file.strip_suffix(".conda"))
+                    .unwrap_or(file);
+                let mut parts = stem.rsplitn(3, '-').skip(1);
+                match (
+                    version.or(parts.next().map(str::to_string)),
+                    name.or(parts.next().map(str::to_string)),
+                ) {
+                    (Some(version), Some(name)) => (name, version),
+                    _ => return Err(format!("Unable to get the name and version of {url}")),
+                }
+            }
+        };
+
+        Ok(Package {
+            kind,
+            name,
+            version,
+            url,
+            sha256: raw.sha256,
+            md5: raw.md5,
+            license: raw.license,
+            depends,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let lockfile = LockFile::parse(
+            r#"
+version: 6
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+      - conda: https://conda.anaconda.org/conda-forge/linux-64/_openmp_mutex-4.5-2_gnu.tar.bz2
+      - pypi: https://files.pythonhosted.org/packages/requests-2.32.3-py3-none-any.whl
+packages:
+- conda: https://conda.anaconda.org/conda-forge/linux-64/_openmp_mutex-4.5-2_gnu.tar.bz2
+  sha256: fbe2c5e56a653bebb982eda4876a9178aedfc2b545f25d0ce9c4c0b508253d22
+  depends:
+  - libgomp >=7.5.0
+  license: BSD-3-Clause
+- pypi: https://files.pythonhosted.org/packages/requests-2.32.3-py3-none-any.whl
+  name: requests
+  version: 2.32.3
+  requires_dist:
+  - idna<4,>=2.5
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lockfile.environments["default"].packages["linux-64"].len(),
+            2
+        );
+        let conda = &lockfile.packages[0];
+        assert_eq!(
+            (conda.name.as_str(), conda.version.as_str()),
+            ("_openmp_mutex", "4.5")
+        );
+        assert_eq!(
+            conda.purl(),
+            "pkg:conda/_openmp_mutex@4.5?channel=https://conda.anaconda.org/conda-forge"
+        );
+        let pypi = &lockfile.packages[1];
+        assert_eq!(pypi.kind, PackageKind::Pypi);
+        assert_eq!(pypi.depends, vec!["idna<4,>=2.5"]);
+    }
+
+    #[test]
+    fn test_constraint() {
+        let python: PackageConstraint = "python>=3.11,<3.13".parse().unwrap();
+        assert_eq!(python.name, "python");
+        assert!(python.matches("3.11"));
+        assert!(python.matches("3.12.4"));
+        assert!(!python.matches("3.10.14"));
+        assert!(!python.matches("3.13.0"));
+
+        let numpy: PackageConstraint = "numpy=2.1".parse().unwrap();
+        assert!(numpy.matches("2.1.3"));
+        assert!(!numpy.matches("2.10.0"));
+
+        let pytorch: PackageConstraint = "pytorch".parse().unwrap();
+        assert!(pytorch.matches("2.5.1"));
+        assert!("python>=".parse::<PackageConstraint>().is_err());
+    }
+
+    #[test]
+    fn test_dependency_chains() {
+        let package = |name: &str, depends: &[&str]| Package {
+            kind: PackageKind::Conda,
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            url: String::new(),
+            sha256: None,
+            md5: None,
+            license: None,
+            depends: depends.iter().map(|spec| spec.to_string()).collect(),
+        };
+        let lockfile = LockFile {
+            packages: vec![
+                package("pandas", &["numpy >=1.26", "python_abi 3.12.*"]),
+                package("scipy", &["numpy <2.3", "libgcc >=13"]),
+                package("numpy", &["libgcc >=13", "python_abi 3.12.*"]),
+                package("libgcc", &[]),
+                package("python-abi", &[]),
+            ],
+            ..Default::default()
+        };
+        let roots = vec!["pandas".to_string(), "scipy".to_string()];
+        assert_eq!(
+            lockfile.dependency_chains(&roots, "libgcc"),
+            vec![vec!["pandas", "numpy", "libgcc"], vec!["scipy", "libgcc"]]
+        );
+        assert_eq!(
+            lockfile.dependency_chains(&roots, "python_abi"),
+            vec![
+                vec!["pandas", "python-abi"],
+                vec!["scipy", "numpy", "python-abi"]
+            ]
+        );
+        assert_eq!(
+            lockfile.dependency_chains(&roots, "pandas"),
+            vec![vec!["pandas"]]
+        );
+    }
+}
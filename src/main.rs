@@ -1,19 +1,61 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::CompleteEnv;
 
+use crate::cli::alias;
+use crate::cli::audit;
 use crate::cli::auth;
+use crate::cli::backend;
+use crate::cli::bundle;
+use crate::cli::changelog;
 use crate::cli::checkout;
+use crate::cli::ci;
 use crate::cli::clone;
+use crate::cli::config;
+use crate::cli::delete;
+use crate::cli::envrc;
+use crate::cli::exec;
+use crate::cli::export;
+use crate::cli::gc;
+use crate::cli::global;
+use crate::cli::ide;
+use crate::cli::info;
 use crate::cli::init;
+use crate::cli::kernel;
+use crate::cli::licenses;
+use crate::cli::lint;
 use crate::cli::list;
+use crate::cli::merge_lock;
+use crate::cli::migrate;
+use crate::cli::outdated;
+use crate::cli::projects;
 use crate::cli::pull;
 use crate::cli::push;
+use crate::cli::registry;
+use crate::cli::relink;
+use crate::cli::run;
+use crate::cli::sbom;
+use crate::cli::serve;
 use crate::cli::shell;
 use crate::cli::shim;
+use crate::cli::stale;
+use crate::cli::stats;
+use crate::cli::status;
+use crate::cli::sync;
 use crate::cli::tag;
+use crate::cli::task;
+use crate::cli::tools;
+use crate::cli::unbundle;
+use crate::cli::untag;
+use crate::cli::update;
+use crate::cli::verify;
+use crate::cli::which;
+use crate::cli::why;
 
-pub mod backends;
-pub mod cli;
-pub mod common;
+mod cli;
+mod output;
+
+use crate::output::OutputFormat;
+use araki::settings::check_settings_files;
 
 /// Manage and share environments
 #[derive(Parser, Debug)]
@@ -22,32 +64,128 @@ pub struct Cli {
     // Manage environments
     #[command(subcommand)]
     command: Option<Command>,
+
+    /// Format of progress and result output
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Only use local repos and cached remote data; fail operations which need the network
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Profile to use, overriding the `profile` setting, e.g. to log in to a second account
+    #[arg(long, global = true)]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
 #[command(arg_required_else_help = true)]
 pub enum Command {
+    /// Define short names for lockspec repositories
+    Alias(alias::Args),
+
+    /// Check the packages of the environment for known vulnerabilities
+    Audit(audit::Args),
+
     /// Authenticate with the configured backend
     Auth(auth::Args),
 
+    /// Diagnose the connection to the configured backend
+    Backend(backend::Args),
+
+    /// Package an environment and the artifacts of its packages into a single archive, for
+    /// machines without network access
+    Bundle(bundle::Args),
+
+    /// List the package changes between two tags of the environment
+    Changelog(changelog::Args),
+
     /// Checkout a tag of an environment
     Checkout(checkout::Args),
 
+    /// Generate CI configuration for the environment
+    Ci(ci::Args),
+
     /// Clone a lockspec from a remote repository and install it in the current directory
     Clone(clone::Args),
 
+    /// Print the schema of araki.toml and check settings files against it
+    Config(config::Args),
+
+    /// Delete the lockspec of the current directory, and optionally its remote repository
+    Delete(delete::Args),
+
+    /// Write a direnv .envrc which activates the environment
+    Envrc(envrc::Args),
+
+    /// Run a command in the environment registered for the current directory or its parents,
+    /// without an activated shell
+    Exec(exec::Args),
+
+    /// Export the environment as a Dockerfile or devcontainer definition
+    Export(export::Args),
+
+    /// Remove the installed environments of projects which haven't been used recently
+    Gc(gc::Args),
+
+    /// Manage environments installed outside of any project
+    Global(global::Args),
+
+    /// Configure editors to use the environment
+    Ide(ide::Args),
+
+    /// Show details of a tag, including who signed it
+    Info(info::Args),
+
     /// Create a new araki-managed lockspec from an existing lockspec
     Init(init::Args),
 
+    /// Manage the Jupyter kernel of the environment
+    Kernel(kernel::Args),
+
+    /// Report the licenses of the packages in the environment
+    Licenses(licenses::Args),
+
+    /// Check the lockspec and its repository for common issues
+    Lint(lint::Args),
+
     /// List available tags
     List(list::Args),
 
+    /// Merge pixi.toml or pixi.lock as a git merge driver, or register the driver with
+    /// --register
+    MergeLock(merge_lock::Args),
+
+    /// Convert existing conda environments to araki environments
+    Migrate(migrate::Args),
+
+    /// List the packages which have newer versions available
+    Outdated(outdated::Args),
+
+    /// List the projects using an environment
+    Projects(projects::Args),
+
     /// Pull changes from the remote repo
     Pull(pull::Args),
 
     /// Push changes to the remote repo
     Push(push::Args),
 
+    /// Run a self-hosted lockspec registry
+    Registry(registry::Args),
+
+    /// Re-establish tracking of the lockspec files by the araki repo
+    Relink(relink::Args),
+
+    /// Run a command in the environment, or in an environment of the workspace
+    Run(run::Args),
+
+    /// Generate a software bill of materials (SBOM) for the environment
+    Sbom(sbom::Args),
+
+    /// Serve a JSON-RPC interface for editor and IDE integrations
+    Serve(serve::Args),
+
     /// Write config to the shell
     Shell(shell::Args),
 
@@ -56,26 +194,124 @@ pub enum Command {
     #[command(hide = true)]
     Shim(shim::Args),
 
+    /// Warn if the environment is out of date. Run by the .envrc written by `araki envrc` on
+    /// activation
+    #[command(hide = true)]
+    Stale(stale::Args),
+
+    /// Summarize the usage of the environments of the recorded projects
+    Stats(stats::Args),
+
+    /// Show the state of the environment, or of every environment of the workspace
+    Status(status::Args),
+
+    /// Clone and checkout the environments declared in araki.toml
+    Sync(sync::Args),
+
     /// Save the current version of the environment
     Tag(tag::Args),
+
+    /// Run a task defined by the environment, or list its tasks
+    Task(task::Args),
+
+    /// Version the tools installed with pixi global in their own lockspec repo
+    Tools(tools::Args),
+
+    /// Install an environment from a bundle written by `araki bundle`, without network access
+    Unbundle(unbundle::Args),
+
+    /// Delete a tag of the environment
+    Untag(untag::Args),
+
+    /// Update the dependencies of the environment, or of all environments, and tag the results
+    Update(update::Args),
+
+    /// Check that a tag can be installed again exactly as locked
+    Verify(verify::Args),
+
+    /// Show which environment and tag a project uses
+    Which(which::Args),
+
+    /// Explain why a package is in the environment
+    Why(why::Args),
 }
 
 #[tokio::main]
 pub async fn main() {
+    CompleteEnv::with_factory(Cli::command).complete();
     let cli = Cli::parse();
+    output::set_format(cli.output);
+    if cli.offline {
+        araki::network::set_offline(true);
+    }
+    if let Some(profile) = &cli.profile {
+        araki::settings::set_profile(profile);
+    }
+    araki::network::apply_git_timeouts();
+
+    // `araki config check` reports the same problems itself
+    if !matches!(cli.command, Some(Command::Config(_))) {
+        for (path, diagnostic) in check_settings_files().unwrap_or_default() {
+            output::warning(&format!(
+                "{}:{}: {}",
+                path.display(),
+                diagnostic.line,
+                diagnostic.message
+            ));
+        }
+    }
 
     if let Some(cmd) = cli.command {
         match cmd {
+            Command::Alias(cmd) => alias::execute(cmd),
+            Command::Audit(cmd) => audit::execute(cmd).await,
             Command::Auth(cmd) => auth::execute(cmd).await,
+            Command::Backend(cmd) => backend::execute(cmd).await,
+            Command::Bundle(cmd) => bundle::execute(cmd).await,
+            Command::Changelog(cmd) => changelog::execute(cmd),
             Command::Checkout(cmd) => checkout::execute(cmd),
+            Command::Ci(cmd) => ci::execute(cmd),
             Command::Clone(cmd) => clone::execute(cmd),
+            Command::Config(cmd) => config::execute(cmd),
+            Command::Delete(cmd) => delete::execute(cmd).await,
+            Command::Envrc(cmd) => envrc::execute(cmd),
+            Command::Exec(cmd) => exec::execute(cmd),
+            Command::Export(cmd) => export::execute(cmd),
+            Command::Gc(cmd) => gc::execute(cmd),
+            Command::Global(cmd) => global::execute(cmd),
+            Command::Ide(cmd) => ide::execute(cmd),
+            Command::Info(cmd) => info::execute(cmd),
             Command::Init(cmd) => init::execute(cmd).await,
-            Command::List(cmd) => list::execute(cmd),
+            Command::Kernel(cmd) => kernel::execute(cmd),
+            Command::Licenses(cmd) => licenses::execute(cmd).await,
+            Command::Lint(cmd) => lint::execute(cmd),
+            Command::List(cmd) => list::execute(cmd).await,
+            Command::MergeLock(cmd) => merge_lock::execute(cmd),
+            Command::Migrate(cmd) => migrate::execute(cmd).await,
+            Command::Outdated(cmd) => outdated::execute(cmd),
+            Command::Projects(cmd) => projects::execute(cmd),
             Command::Pull(cmd) => pull::execute(cmd),
-            Command::Push(cmd) => push::execute(cmd),
+            Command::Push(cmd) => push::execute(cmd).await,
+            Command::Registry(cmd) => registry::execute(cmd).await,
+            Command::Relink(cmd) => relink::execute(cmd),
+            Command::Run(cmd) => run::execute(cmd),
+            Command::Sbom(cmd) => sbom::execute(cmd),
+            Command::Serve(cmd) => serve::execute(cmd).await,
             Command::Shell(cmd) => shell::execute(cmd),
             Command::Shim(cmd) => shim::execute(cmd),
-            Command::Tag(cmd) => tag::execute(cmd),
+            Command::Stale(cmd) => stale::execute(cmd),
+            Command::Stats(cmd) => stats::execute(cmd),
+            Command::Status(cmd) => status::execute(cmd),
+            Command::Sync(cmd) => sync::execute(cmd),
+            Command::Tag(cmd) => tag::execute(cmd).await,
+            Command::Task(cmd) => task::execute(cmd),
+            Command::Tools(cmd) => tools::execute(cmd).await,
+            Command::Unbundle(cmd) => unbundle::execute(cmd).await,
+            Command::Untag(cmd) => untag::execute(cmd),
+            Command::Update(cmd) => update::execute(cmd).await,
+            Command::Verify(cmd) => verify::execute(cmd),
+            Command::Which(cmd) => which::execute(cmd),
+            Command::Why(cmd) => why::execute(cmd),
         }
     } else {
         std::process::exit(2);
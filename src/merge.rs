@@ -0,0 +1,254 @@
+//! Semantic three-way merges of lockspec files, run by git as the `araki` merge driver.
+//!
+//! Concurrent edits of an environment usually touch different dependencies, yet conflict as
+//! text: both sides append to the same table of pixi.toml and rewrite large parts of pixi.lock.
+//! pixi.toml is merged table by table, so that the dependency changes of both sides are kept,
+//! and pixi.lock is locked again against the merged pixi.toml when both sides changed it. What
+//! can't be merged semantically falls back to a text merge with conflict markers.
+
+use git2::{Oid, Repository};
+use std::env::{self, temp_dir};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use toml::{Table, Value};
+use uuid::Uuid;
+
+use crate::common;
+
+/// Name of the merge driver in the git config and attributes of araki repos.
+pub const DRIVER: &str = "araki";
+
+/// Merge three versions of a specfile, table by table. Returns `None` if both sides changed the
+/// same value differently.
+///
+/// Comments and formatting are not preserved, since the merged specfile is written out again.
+///
+/// * `base`: Contents of the common ancestor
+/// * `ours`: Contents of our side
+/// * `theirs`: Contents of their side
+pub fn merge_specfile(base: &str, ours: &str, theirs: &str) -> Result<Option<String>, String> {
+    if ours == base || ours == theirs {
+        return Ok(Some(theirs.to_string()));
+    }
+    if theirs == base {
+        return Ok(Some(ours.to_string()));
+    }
+    let parse = |contents: &str, side: &str| {
+        contents
+            .parse::<Table>()
+            .map_err(|err| format!("Unable to parse {side} pixi.toml: {err}"))
+    };
+    let merged = merge_tables(
+        &parse(base, "the base")?,
+        &parse(ours, "our")?,
+        &parse(theirs, "their")?,
+    );
+    merged
+        .map(|table| {
+            toml::to_string(&table)
+                .map_err(|err| format!("Unable to serialize the merged pixi.toml: {err}"))
+        })
+        .transpose()
+}
+
+/// Merge three versions of a table, key by key. Returns `None` on a conflict.
+///
+/// * `base`: Table of the common ancestor
+/// * `ours`: Table of our side
+/// * `theirs`: Table of their side
+fn merge_tables(base: &Table, ours: &Table, theirs: &Table) -> Option<Table> {
+    let mut merged = Table::new();
+    let keys = ours.keys().chain(theirs.keys()).chain(base.keys());
+    for key in keys {
+        if merged.contains_key(key) {
+            continue;
+        }
+        let value = match (base.get(key), ours.get(key), theirs.get(key)) {
+            (_, ours, theirs) if ours == theirs => ours.cloned(),
+            (base, ours, theirs) if base == ours => theirs.cloned(),
+            (base, ours, theirs) if base == theirs => ours.cloned(),
+            (base, Some(Value::Table(ours)), Some(Value::Table(theirs))) => {
+                let empty = Table::new();
+                let base = match base {
+                    Some(Value::Table(base)) => base,
+                    _ => &empty,
+                };
+                Some(Value::Table(merge_tables(base, ours, theirs)?))
+            }
+            _ => return None,
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value);
+        }
+    }
+    // Keep the order of our side, then the keys only they added
+    let mut ordered = Table::new();
+    for key in ours.keys().chain(theirs.keys()) {
+        if let Some(value) = merged.remove(key) {
+            ordered.insert(key.clone(), value);
+        }
+    }
+    Some(ordered)
+}
+
+/// Merge three versions of a lockfile. If only one side changed it, that side is taken;
+/// otherwise our lockfile is locked again against the merged specfile, so that the packages
+/// both sides added are locked and our pins are kept where they still apply. Returns `None` if
+/// there is no merged specfile to lock against, or pixi can't lock it.
+///
+/// * `base`: Contents of the common ancestor
+/// * `ours`: Contents of our side
+/// * `theirs`: Contents of their side
+/// * `specfile`: Contents of the merged specfile, if the specfiles could be merged
+pub fn merge_lockfile(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    specfile: Option<&str>,
+) -> Result<Option<String>, String> {
+    if ours == base || ours == theirs {
+        return Ok(Some(theirs.to_string()));
+    }
+    if theirs == base {
+        return Ok(Some(ours.to_string()));
+    }
+    let Some(specfile) = specfile else {
+        return Ok(None);
+    };
+
+    let dir = temp_dir().join(format!("araki-merge-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("Unable to create a temporary directory at {dir:?}: {err}"))?;
+    let result = (|| {
+        fs::write(dir.join("pixi.toml"), specfile)
+            .and_then(|_| fs::write(dir.join("pixi.lock"), ours))
+            .map_err(|err| format!("Unable to write the lockspec to {dir:?}: {err}"))?;
+        let status = common::pixi_command()
+            .arg("lock")
+            .current_dir(&dir)
+            .status()
+            .map_err(|err| format!("Failed to execute pixi lock: {err}"))?;
+        if !status.success() {
+            return Ok(None);
+        }
+        fs::read_to_string(dir.join("pixi.lock"))
+            .map(Some)
+            .map_err(|err| format!("Unable to read the merged pixi.lock: {err}"))
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Merge the specfile of our and their side of the merge in progress in an araki repo, to lock
+/// the merged lockfile against. Returns `None` if no merge is in progress or the specfiles
+/// conflict.
+///
+/// * `repo`: araki repo being merged
+/// * `path`: Path of the specfile in the repo
+pub fn merging_specfile(repo: &Repository, path: &str) -> Result<Option<String>, String> {
+    let (Ok(ours), Some(theirs)) = (repo.refname_to_id("HEAD"), merging_commit(repo)) else {
+        return Ok(None);
+    };
+    let base = repo
+        .merge_base(ours, theirs)
+        .map_err(|err| format!("Unable to find the merge base: {err}"))?;
+    let read = |oid: Oid| {
+        common::read_rev_file(repo, &oid.to_string(), path)
+            .map(|contents| String::from_utf8_lossy(&contents).to_string())
+            .map_err(|err| format!("Unable to read {path} at {oid:.7}: {err}"))
+    };
+    merge_specfile(&read(base)?, &read(ours)?, &read(theirs)?)
+}
+
+/// Get the commit being merged into HEAD. git only writes `MERGE_HEAD` once the merge stops on
+/// a conflict; while merge drivers run, it passes the commit as a `GITHEAD_<sha>` variable
+/// naming it for conflict markers.
+///
+/// * `repo`: araki repo being merged
+fn merging_commit(repo: &Repository) -> Option<Oid> {
+    repo.refname_to_id("MERGE_HEAD").ok().or_else(|| {
+        env::vars()
+            .filter_map(|(name, _)| Oid::from_str(name.strip_prefix("GITHEAD_")?).ok())
+            .find(|oid| repo.find_commit(*oid).is_ok())
+    })
+}
+
+/// Merge three files with git's text merge, writing conflict markers into our file. Returns
+/// whether the merge is clean.
+///
+/// * `base`: File of the common ancestor
+/// * `ours`: File of our side, overwritten with the result
+/// * `theirs`: File of their side
+pub fn merge_text(base: &Path, ours: &Path, theirs: &Path) -> Result<bool, String> {
+    let status = Command::new("git")
+        .args(["merge-file", "-L", "ours", "-L", "base", "-L", "theirs"])
+        .args([ours, base, theirs])
+        .status()
+        .map_err(|err| format!("Failed to execute git merge-file: {err}"))?;
+    Ok(status.success())
+}
+
+/// Register araki as the merge driver of pixi.toml and pixi.lock in an araki repo. The driver
+/// is configured in the repo's own config and attributes, so nothing is added to the project.
+///
+/// * `repo`: araki repo
+pub fn register(repo: &Repository) -> Result<(), String> {
+    let mut config = repo
+        .config()
+        .map_err(|err| format!("Unable to open the git config: {err}"))?;
+    config
+        .set_str(
+            &format!("merge.{DRIVER}.name"),
+            "araki semantic merge of pixi.toml and pixi.lock",
+        )
+        .and_then(|_| {
+            config.set_str(
+                &format!("merge.{DRIVER}.driver"),
+                "araki merge-lock %O %A %B %P",
+            )
+        })
+        .map_err(|err| format!("Unable to configure the merge driver: {err}"))?;
+
+    let info = repo.path().join("info");
+    fs::create_dir_all(&info).map_err(|err| format!("Unable to create {info:?}: {err}"))?;
+    let path = info.join("attributes");
+    let mut attributes = fs::read_to_string(&path).unwrap_or_default();
+    for file in ["pixi.toml", "pixi.lock"] {
+        let line = format!("{file} merge={DRIVER}");
+        if !attributes.lines().any(|existing| existing == line) {
+            if !attributes.is_empty() && !attributes.ends_with('\n') {
+                attributes.push('\n');
+            }
+            attributes.push_str(&line);
+            attributes.push('\n');
+        }
+    }
+    fs::write(&path, attributes).map_err(|err| format!("Unable to write {path:?}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_specfile() {
+        let base = "[dependencies]\npython = \"3.12.*\"\n";
+        let ours = "[dependencies]\npython = \"3.12.*\"\nnumpy = \"*\"\n";
+        let theirs = "[dependencies]\npython = \"3.13.*\"\n";
+        let merged: Table = merge_specfile(base, ours, theirs)
+            .unwrap()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            merged,
+            "[dependencies]\npython = \"3.13.*\"\nnumpy = \"*\"\n"
+                .parse()
+                .unwrap()
+        );
+
+        let conflicting = "[dependencies]\npython = \"3.11.*\"\n";
+        assert_eq!(merge_specfile(base, conflicting, theirs), Ok(None));
+    }
+}
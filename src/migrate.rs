@@ -0,0 +1,140 @@
+//! Migration of existing conda environments to araki.
+//!
+//! Environments created with conda, mamba, or micromamba are found with `<tool> env list`,
+//! exported with the specs they were created from (`--from-history`), and imported into a pixi
+//! project which is locked again, ready to be published with `araki init`.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::common::{self, LockSpec};
+use crate::preflight;
+
+/// Tools whose environments can be migrated, in the order they are tried.
+const TOOLS: [&str; 3] = ["conda", "mamba", "micromamba"];
+
+/// An existing conda environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CondaEnvironment {
+    /// Name of the environment; `base` for the root environment
+    pub name: String,
+    pub prefix: PathBuf,
+
+    /// Tool the environment was found with, e.g. `conda`
+    pub tool: &'static str,
+}
+
+#[derive(Deserialize)]
+struct EnvList {
+    envs: Vec<PathBuf>,
+}
+
+/// Find the conda environments of the machine with the first of conda, mamba, and micromamba
+/// which is installed.
+pub fn discover() -> Result<Vec<CondaEnvironment>, String> {
+    for tool in TOOLS {
+        let Ok(output) = Command::new(tool).args(["env", "list", "--json"]).output() else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+        let list: EnvList = serde_json::from_slice(&output.stdout)
+            .map_err(|err| format!("Unable to parse the output of `{tool} env list`: {err}"))?;
+        return Ok(list
+            .envs
+            .into_iter()
+            .map(|prefix| CondaEnvironment {
+                name: environment_name(&prefix),
+                prefix,
+                tool,
+            })
+            .collect());
+    }
+    Err(format!(
+        "None of {} is installed, so no conda environments can be found.",
+        TOOLS.join(", ")
+    ))
+}
+
+/// Get the name of a conda environment from its prefix: environments live in an `envs`
+/// directory, and any other prefix is the root (`base`) environment.
+///
+/// * `prefix`: Prefix of the environment
+fn environment_name(prefix: &Path) -> String {
+    match prefix.parent().and_then(|parent| parent.file_name()) {
+        Some(parent) if parent == "envs" => prefix
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or("base".to_string()),
+        _ => "base".to_string(),
+    }
+}
+
+/// Convert a conda environment into a locked pixi project in a directory.
+///
+/// Only the specs the environment was created from are kept, so that the project is solved
+/// again rather than pinned to the builds installed years ago; packages installed with pip are
+/// not exported by conda in that mode and must be added again. If anything fails, the files
+/// written to the directory are removed.
+///
+/// * `env`: Environment to convert
+/// * `dir`: Directory of the pixi project; created if it doesn't exist
+pub fn convert(env: &CondaEnvironment, dir: &Path) -> Result<(), String> {
+    preflight::check_no_lockspec(dir)?;
+    preflight::create_dir(dir)?;
+    let spec = dir.join("environment.yml");
+    let result = (|| {
+        let output = Command::new(env.tool)
+            .args(["env", "export", "--from-history", "--prefix"])
+            .arg(&env.prefix)
+            .output()
+            .map_err(|err| format!("Failed to execute {} env export: {err}", env.tool))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Unable to export {}: {}",
+                env.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        fs::write(&spec, &output.stdout)
+            .map_err(|err| format!("Unable to write {spec:?}: {err}"))?;
+
+        for args in [&["init", "--import", "environment.yml"][..], &["lock"]] {
+            let status = common::pixi_command()
+                .args(args)
+                .current_dir(dir)
+                .status()
+                .map_err(|err| format!("Failed to execute pixi {}: {err}", args[0]))?;
+            if !status.success() {
+                return Err(format!(
+                    "pixi {} failed for {}; its specs may not be solvable with the channels \
+                    of the environment.",
+                    args[0], env.name
+                ));
+            }
+        }
+        LockSpec::from_path(dir).map(|_| ())
+    })();
+
+    let _ = fs::remove_file(&spec);
+    if result.is_err() {
+        for file in ["pixi.toml", "pixi.lock"] {
+            let _ = fs::remove_file(dir.join(file));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment_name() {
+        assert_eq!(environment_name(Path::new("/opt/conda/envs/ml")), "ml");
+        assert_eq!(environment_name(Path::new("/opt/conda")), "base");
+    }
+}
@@ -0,0 +1,171 @@
+//! Channel mirrors and credentials which araki injects into pixi when installing environments.
+//!
+//! Mirrors are written to the project-level pixi config (`.pixi/config.toml`), so that
+//! lockspecs resolve against approved mirrors. Credentials are read from environment variables
+//! at install time and handed to pixi in a temporary rattler auth file, which is removed again
+//! once pixi exits.
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use toml::Table;
+use uuid::Uuid;
+
+use crate::settings::{get_settings, merge_tables};
+
+/// Mirror settings, configured in the `[mirrors]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MirrorSettings {
+    /// Conda channel URLs mapped to the mirrors that should be used instead
+    pub conda: BTreeMap<String, Vec<String>>,
+
+    /// Index URL used instead of PyPI
+    pub pypi: Option<String>,
+
+    /// Credentials for mirror hosts, keyed by host name
+    pub credentials: BTreeMap<String, MirrorCredential>,
+}
+
+/// Credentials for a mirror host. Each field names the environment variable holding the value.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MirrorCredential {
+    pub username_env: Option<String>,
+    pub password_env: Option<String>,
+    pub token_env: Option<String>,
+}
+
+impl MirrorCredential {
+    /// Build the rattler auth file entry for these credentials.
+    ///
+    /// * `host`: Host the credentials are for
+    fn auth_entry(&self, host: &str) -> Result<Value, String> {
+        let var = |name: &String| {
+            env::var(name).map_err(|_| {
+                format!("The credentials for {host} need the environment variable {name} to be set")
+            })
+        };
+        match (&self.token_env, &self.username_env, &self.password_env) {
+            (Some(token), _, _) => Ok(json!({ "BearerToken": var(token)? })),
+            (None, Some(username), Some(password)) => Ok(json!({
+                "BasicHTTP": { "username": var(username)?, "password": var(password)? }
+            })),
+            _ => Err(format!(
+                "The credentials for {host} need either token_env, or username_env and password_env"
+            )),
+        }
+    }
+}
+
+impl MirrorSettings {
+    pub fn is_empty(&self) -> bool {
+        self.conda.is_empty() && self.pypi.is_none() && self.credentials.is_empty()
+    }
+
+    /// Get the pixi config equivalent to these settings.
+    fn pixi_config(&self) -> Table {
+        let mut config = Table::new();
+        if !self.conda.is_empty() {
+            let mirrors = self
+                .conda
+                .iter()
+                .map(|(channel, mirrors)| {
+                    (
+                        channel.clone(),
+                        toml::Value::Array(
+                            mirrors.iter().cloned().map(toml::Value::from).collect(),
+                        ),
+                    )
+                })
+                .collect();
+            config.insert("mirrors".to_string(), toml::Value::Table(mirrors));
+        }
+        if let Some(index_url) = &self.pypi {
+            let mut pypi_config = Table::new();
+            pypi_config.insert("index-url".to_string(), index_url.clone().into());
+            config.insert("pypi-config".to_string(), toml::Value::Table(pypi_config));
+        }
+        config
+    }
+}
+
+/// Mirror configuration applied to a pixi project. Removes the temporary auth file when dropped.
+pub struct PixiMirrors {
+    auth_file: Option<PathBuf>,
+}
+
+impl PixiMirrors {
+    /// Point a pixi command at the credentials for the mirrors.
+    ///
+    /// * `command`: pixi command to configure
+    pub fn configure(&self, command: &mut Command) {
+        if let Some(auth_file) = &self.auth_file {
+            command.env("RATTLER_AUTH_FILE", auth_file);
+        }
+    }
+}
+
+impl Drop for PixiMirrors {
+    fn drop(&mut self) {
+        if let Some(auth_file) = &self.auth_file {
+            let _ = fs::remove_file(auth_file);
+        }
+    }
+}
+
+/// Apply the configured mirrors to a pixi project.
+///
+/// * `project`: Directory of the pixi project
+pub fn apply(project: &Path) -> Result<PixiMirrors, String> {
+    let settings = get_settings()?.mirrors;
+    if settings.is_empty() {
+        return Ok(PixiMirrors { auth_file: None });
+    }
+
+    let config = settings.pixi_config();
+    if !config.is_empty() {
+        let dir = project.join(".pixi");
+        let path = dir.join("config.toml");
+        let mut existing: Table = if path.exists() {
+            fs::read_to_string(&path)
+                .map_err(|err| format!("Unable to read {path:?}: {err}"))?
+                .parse()
+                .map_err(|err| format!("Unable to parse {path:?}: {err}"))?
+        } else {
+            Table::new()
+        };
+        merge_tables(&mut existing, config);
+        fs::create_dir_all(&dir).map_err(|err| format!("Unable to create {dir:?}: {err}"))?;
+        fs::write(&path, existing.to_string())
+            .map_err(|err| format!("Unable to write {path:?}: {err}"))?;
+    }
+
+    if settings.credentials.is_empty() {
+        return Ok(PixiMirrors { auth_file: None });
+    }
+    let mut auth = serde_json::Map::new();
+    for (host, credential) in &settings.credentials {
+        auth.insert(host.clone(), credential.auth_entry(host)?);
+    }
+    let auth_file = env::temp_dir().join(format!("araki-auth-{}.json", Uuid::new_v4()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&auth_file)
+        .map_err(|err| format!("Unable to write mirror credentials: {err}"))?;
+
+    // From here on, the auth file is removed again if anything fails
+    let mirrors = PixiMirrors {
+        auth_file: Some(auth_file.clone()),
+    };
+    fs::write(&auth_file, Value::Object(auth).to_string())
+        .map_err(|err| format!("Unable to write mirror credentials: {err}"))?;
+    Ok(mirrors)
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Certificate, ClientBuilder, Method, NoProxy, Proxy, RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::backends::BackendError;
+use crate::settings::get_settings;
+
+/// Offline mode forced for this process by `--offline`, regardless of the settings.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Git timeouts are global to libgit2, so they are applied once per process.
+static GIT_TIMEOUTS: Once = Once::new();
+
+/// Network settings, configured in the `[network]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// PEM file with additional root certificates, e.g. of a TLS-intercepting proxy
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Maximum rate of git transfers, in KiB per second
+    pub max_bandwidth: Option<u64>,
+
+    /// URL of a proxy to send all requests through. Without any proxy settings, the
+    /// `HTTPS_PROXY`, `HTTP_PROXY`, and `NO_PROXY` environment variables are used
+    pub proxy: Option<String>,
+
+    /// URL of a proxy to send HTTPS requests through, instead of `proxy`
+    pub https_proxy: Option<String>,
+
+    /// URL of a proxy to send plain HTTP requests through, instead of `proxy`
+    pub http_proxy: Option<String>,
+
+    /// Hosts, domains, and IP ranges reached without the configured proxies, e.g.
+    /// `.corp.example.com` or `10.0.0.0/8`; defaults to the `NO_PROXY` environment variable
+    pub no_proxy: Vec<String>,
+
+    /// Don't access the network: only use local repos and cached remote data, and fail
+    /// operations that need the network
+    pub offline: bool,
+
+    /// Seconds after which connecting to a server fails; 0 waits indefinitely
+    pub connect_timeout: u64,
+
+    /// Seconds after which a request or git transfer which receives no data fails; 0 waits
+    /// indefinitely. Large downloads which keep receiving data don't time out
+    #[serde(alias = "timeout")]
+    pub request_timeout: u64,
+
+    /// Times a backend request is sent again after a server error, a rate limit, or a dropped
+    /// connection; 0 disables retries. Requests which aren't idempotent, e.g. creating a
+    /// repository, are only sent again after a rate limit or a failed connection
+    pub max_retries: u32,
+
+    /// Seconds to wait before the first retry of a backend request, doubled for each further
+    /// retry, unless the server asks for a delay with `Retry-After`
+    pub retry_backoff: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            ca_bundle: None,
+            max_bandwidth: None,
+            proxy: None,
+            https_proxy: None,
+            http_proxy: None,
+            no_proxy: vec![],
+            offline: false,
+            connect_timeout: 30,
+            request_timeout: 300,
+            max_retries: 3,
+            retry_backoff: 1,
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Apply the network settings to a client builder.
+    ///
+    /// * `builder`: Builder of the client which should use the settings
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder, BackendError> {
+        if let Some(path) = &self.ca_bundle {
+            let pem = fs::read(path)
+                .map_err(|err| format!("Unable to read the CA bundle {path:?}: {err}"))?;
+            for certificate in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        // reqwest reads the proxy environment variables itself, unless proxies are configured
+        let no_proxy = match self.no_proxy.is_empty() {
+            true => NoProxy::from_env(),
+            false => NoProxy::from_string(&self.no_proxy.join(",")),
+        };
+        let proxies = [
+            self.https_proxy.as_deref().map(Proxy::https),
+            self.http_proxy.as_deref().map(Proxy::http),
+            self.proxy.as_deref().map(Proxy::all),
+        ];
+        for proxy in proxies.into_iter().flatten() {
+            let proxy = proxy.map_err(|err| format!("Invalid proxy URL: {err}"))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+        }
+        if self.connect_timeout > 0 {
+            builder = builder.connect_timeout(Duration::from_secs(self.connect_timeout));
+        }
+        if self.request_timeout > 0 {
+            builder = builder.read_timeout(Duration::from_secs(self.request_timeout));
+        }
+        Ok(builder)
+    }
+}
+
+/// Apply the `network.connect_timeout` and `network.request_timeout` settings to git
+/// transfers, unless they were applied already. Called at startup, and before each transfer for
+/// uses of araki as a library.
+pub fn apply_git_timeouts() {
+    GIT_TIMEOUTS.call_once(|| {
+        let settings = get_settings()
+            .map(|settings| settings.network)
+            .unwrap_or_default();
+        let millis = |seconds: u64| i32::try_from(seconds * 1000).unwrap_or(i32::MAX);
+        // SAFETY: libgit2 options are set once, before any transfer of this process starts
+        unsafe {
+            let _ = git2::opts::set_server_connect_timeout_in_milliseconds(millis(
+                settings.connect_timeout,
+            ));
+            let _ =
+                git2::opts::set_server_timeout_in_milliseconds(millis(settings.request_timeout));
+        }
+    });
+}
+
+/// Force offline mode for the rest of the process, as `--offline` does.
+///
+/// * `offline`: Whether araki should stay off the network
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Check whether araki is in offline mode, either through `--offline` or the `network.offline`
+/// setting.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed) || get_settings().is_ok_and(|settings| settings.network.offline)
+}
+
+/// Fail if araki is in offline mode.
+///
+/// * `action`: What needs the network, e.g. `clone my-org/my-env`
+pub fn require_online(action: &str) -> Result<(), String> {
+    if is_offline() {
+        return Err(format!(
+            "Unable to {action} in offline mode (set by --offline or network.offline)"
+        ));
+    }
+    Ok(())
+}
+
+/// Get a builder for HTTP clients which follows the network settings.
+///
+/// Every HTTP client araki uses should be built from this, so that users behind proxies can
+/// reach the backends. Fails in offline mode.
+pub fn client_builder() -> Result<ClientBuilder, BackendError> {
+    require_online("contact the backend")?;
+    get_settings()?
+        .network
+        .apply(ClientBuilder::new().user_agent("araki"))
+}
+
+/// Sending backend requests again when they fail transiently.
+pub trait SendWithRetries {
+    /// Send the request, and send it again after a delay while it fails with a server error, a
+    /// rate limit (e.g. GitHub's secondary rate limits), or a dropped connection, following the
+    /// `network.max_retries` and `network.retry_backoff` settings. Returns the last response once
+    /// the retries are used up, or if the server asks to wait longer than [`MAX_RETRY_DELAY`].
+    ///
+    /// A server error or a timeout doesn't tell whether the server acted on the request, so only
+    /// idempotent requests are retried then; others, e.g. a POST creating a repository, are only
+    /// retried after a rate limit or a failed connection, which the server never acted on.
+    fn send_with_retries(self) -> impl Future<Output = Result<Response, reqwest::Error>> + Send;
+}
+
+/// Longest delay araki waits for before retrying a request; servers asking for longer delays,
+/// e.g. until an hourly rate limit resets, get their response returned instead.
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+impl SendWithRetries for RequestBuilder {
+    async fn send_with_retries(self) -> Result<Response, reqwest::Error> {
+        let settings = get_settings()
+            .map(|settings| settings.network)
+            .unwrap_or_default();
+        let mut attempt = 0;
+        loop {
+            // Requests with streamed bodies can't be sent twice
+            let Some(request) = self.try_clone() else {
+                return self.send().await;
+            };
+            let backoff = Duration::from_secs(settings.retry_backoff)
+                .saturating_mul(2u32.saturating_pow(attempt));
+            let (client, request) = request.build_split();
+            let request = request?;
+            let idempotent = matches!(
+                *request.method(),
+                Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+            );
+            let result = client.execute(request).await;
+            let delay = match &result {
+                Ok(response) => retry_delay(response, backoff, idempotent),
+                Err(err) if err.is_connect() => Some(backoff),
+                Err(err) if err.is_timeout() && idempotent => Some(backoff),
+                Err(_) => None,
+            };
+            match delay {
+                Some(delay) if attempt < settings.max_retries && delay <= MAX_RETRY_DELAY => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+/// Get how long to wait before retrying a request, or `None` if its response is final.
+///
+/// * `response`: Response to the request
+/// * `backoff`: Delay to use when the server doesn't ask for one
+/// * `idempotent`: Whether the request can be sent again after a server error
+fn retry_delay(response: &Response, backoff: Duration, idempotent: bool) -> Option<Duration> {
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim())
+    };
+    // Retry-After is either a number of seconds or an HTTP date
+    let retry_after = header("retry-after").and_then(|value| match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => DateTime::parse_from_rfc2822(value)
+            .ok()
+            .map(|date| until(date.timestamp())),
+    });
+    // GitHub announces when an exhausted rate limit resets, as a Unix timestamp
+    let reset = (header("x-ratelimit-remaining") == Some("0"))
+        .then(|| header("x-ratelimit-reset")?.parse::<i64>().ok())
+        .flatten()
+        .map(until);
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::FORBIDDEN
+            if retry_after.is_some() || reset.is_some() =>
+        {
+            retry_after.or(reset)
+        }
+        StatusCode::TOO_MANY_REQUESTS => Some(backoff),
+        StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT
+            if idempotent =>
+        {
+            Some(retry_after.unwrap_or(backoff))
+        }
+        _ => None,
+    }
+}
+
+/// Get the time left until a Unix timestamp, or zero if it has passed.
+///
+/// * `timestamp`: Seconds since the Unix epoch
+fn until(timestamp: i64) -> Duration {
+    Duration::from_secs(u64::try_from(timestamp - Utc::now().timestamp()).unwrap_or(0))
+}
+
+/// Keeps a git transfer under the `network.max_bandwidth` setting by pausing its progress
+/// callback, which stops the transfer from reading until the callback returns.
+pub struct Throttle {
+    started: Instant,
+
+    /// Maximum rate in bytes per second; transfers are unlimited if unset
+    limit: Option<u64>,
+}
+
+impl Throttle {
+    /// Start throttling a transfer according to the network settings.
+    pub fn new() -> Throttle {
+        Throttle {
+            started: Instant::now(),
+            limit: get_settings()
+                .ok()
+                .and_then(|settings| settings.network.max_bandwidth)
+                .filter(|limit| *limit > 0)
+                .map(|limit| limit * 1024),
+        }
+    }
+
+    /// Pause until the transfer is back under the bandwidth limit.
+    ///
+    /// * `transferred`: Number of bytes transferred since the throttle was created
+    pub fn wait(&self, transferred: usize) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let due = Duration::from_secs_f64(transferred as f64 / limit as f64);
+        if let Some(ahead) = due.checked_sub(self.started.elapsed()) {
+            thread::sleep(ahead);
+        }
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::backends::BackendError;
+use crate::lockfile::PackageChange;
+use crate::network;
+
+/// Template used when `[notifications] template` is not set.
+pub const DEFAULT_TEMPLATE: &str = "araki: {action} {environment} {tag}\n{changes}";
+
+/// At most this many package changes are listed in a notification.
+const MAX_LISTED_CHANGES: usize = 25;
+
+/// Notification settings, configured in the `[notifications]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Incoming webhook URL (e.g. of Slack or Teams) to post messages to
+    pub webhook: Option<String>,
+
+    /// Template of the message. `{action}`, `{environment}`, `{tag}` and `{changes}` are
+    /// replaced with details of the change
+    pub template: Option<String>,
+}
+
+/// A change to a shared environment that teams should hear about.
+#[derive(Debug, Clone)]
+pub struct Notification<'a> {
+    /// What happened, e.g. `tagged` or `pushed`
+    pub action: &'a str,
+    pub environment: &'a str,
+    pub tag: &'a str,
+    pub changes: &'a [PackageChange],
+}
+
+impl Notification<'_> {
+    /// Render the notification into a message.
+    ///
+    /// * `template`: Template of the message
+    pub fn render(&self, template: &str) -> String {
+        let mut changes: Vec<String> = self
+            .changes
+            .iter()
+            .take(MAX_LISTED_CHANGES)
+            .map(|change| change.to_string())
+            .collect();
+        if self.changes.len() > MAX_LISTED_CHANGES {
+            changes.push(format!(
+                "... and {} more",
+                self.changes.len() - MAX_LISTED_CHANGES
+            ));
+        }
+        if changes.is_empty() {
+            changes.push("No package changes".to_string());
+        }
+
+        template
+            .replace("{action}", self.action)
+            .replace("{environment}", self.environment)
+            .replace("{tag}", self.tag)
+            .replace("{changes}", &changes.join("\n"))
+    }
+}
+
+/// Post a notification to the configured webhook. Does nothing if no webhook is configured.
+///
+/// The message is sent as `{"text": ...}`, which Slack and Teams incoming webhooks accept.
+///
+/// * `settings`: Notification settings
+/// * `notification`: Notification to send
+pub async fn notify(
+    settings: &NotificationSettings,
+    notification: &Notification<'_>,
+) -> Result<(), BackendError> {
+    let Some(webhook) = &settings.webhook else {
+        return Ok(());
+    };
+    let message = notification.render(settings.template.as_deref().unwrap_or(DEFAULT_TEMPLATE));
+    network::client_builder()?
+        .build()?
+        .post(webhook)
+        .json(&json!({ "text": message }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
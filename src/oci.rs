@@ -0,0 +1,968 @@
+//! Lockspecs stored as OCI artifacts in a container registry.
+//!
+//! A lockspec repository maps to an OCI repository, e.g. `ghcr.io/my-org/my-env`, and each of
+//! its tags to an artifact tag. Artifacts are laid out the way ORAS pushes files, so that
+//! `oras pull` works on them as well: an empty config, and pixi.toml and pixi.lock as layers
+//! titled with their file names.
+//!
+//! Registries keep no history, so a clone imports the tags of the repository as commits of a
+//! local araki repo, oldest first, with `origin` set to `oci://<registry>/<repository>`. Pulling
+//! imports the tags pushed since, and pushing a tag uploads the lockspec files of its commit.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use git2::build::CheckoutBuilder;
+use git2::{BranchType, Oid, Repository, Signature};
+use regex::Regex;
+use reqwest::header;
+use reqwest::{RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env::{self, temp_dir};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use uuid::Uuid;
+
+use crate::backends::{Backend, BackendError, Visibility};
+use crate::common::{self, ARAKI_GIT_DIR_NAME};
+use crate::network::{self, SendWithRetries};
+use crate::ops::LOCKSPEC_FILES;
+use crate::remote::RemoteRepo;
+use crate::settings::get_settings;
+
+/// Scheme of the `origin` URL of araki repos backed by a registry.
+pub const SCHEME: &str = "oci://";
+
+/// Artifact type of lockspec manifests.
+pub const ARTIFACT_TYPE: &str = "application/vnd.araki.lockspec.v1";
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+const EMPTY_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+const CREATED_ANNOTATION: &str = "org.opencontainers.image.created";
+
+/// Settings of the OCI backend, read from `[backends.oci]`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct OciSettings {
+    /// Registry holding the lockspec artifacts, e.g. `ghcr.io`; repositories are named
+    /// `<org>/<name>` in it
+    pub registry: Option<String>,
+
+    /// Environment variable holding the user name to authenticate with
+    pub username_env: Option<String>,
+
+    /// Environment variable holding the password or token to authenticate with, e.g. a GitHub
+    /// token with the `write:packages` scope for ghcr.io
+    pub password_env: Option<String>,
+
+    /// Registries reached over plain HTTP instead of HTTPS, e.g. `localhost:5000`
+    pub insecure_registries: Vec<String>,
+}
+
+impl OciSettings {
+    /// Load the `[backends.oci]` settings.
+    pub fn load() -> Result<Self, String> {
+        get_settings()?.backend_settings("oci")
+    }
+
+    /// Get the credentials to authenticate with, if both variables are configured and set.
+    fn credentials(&self) -> Option<(String, String)> {
+        let username = env::var(self.username_env.as_ref()?).ok()?;
+        let password = env::var(self.password_env.as_ref()?).ok()?;
+        Some((username, password))
+    }
+}
+
+/// Location of a lockspec artifact in a registry, e.g. `ghcr.io/my-org/my-env:v1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// Host of the registry, with its port if any
+    pub registry: String,
+
+    /// Repository in the registry, e.g. `my-org/my-env`
+    pub repository: String,
+    pub tag: Option<String>,
+}
+
+impl Reference {
+    /// Parse an artifact reference such as `ghcr.io/my-org/my-env:v1` or
+    /// `oci://ghcr.io/my-org/my-env`. Returns `None` for anything else, e.g. `<org>/<repo>` or a
+    /// git URL. Without `oci://`, a reference needs a tag, unless it is in the registry of
+    /// `[backends.oci]`, so that it isn't mistaken for the URL of a git host.
+    ///
+    /// * `env`: Lockspec location to parse
+    pub fn parse(env: &str) -> Option<Reference> {
+        let (explicit, location) = match env.strip_prefix(SCHEME) {
+            Some(location) => (true, location),
+            None if env.contains("://") || env.contains('@') => return None,
+            None => (false, env),
+        };
+        let (registry, path) = location.split_once('/')?;
+        if !(registry.contains('.') || registry.contains(':') || registry == "localhost") {
+            return None;
+        }
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag.to_string())),
+            _ => (path, None),
+        };
+        let valid = Regex::new(r"^[a-z0-9]+([._-][a-z0-9]+)*(/[a-z0-9]+([._-][a-z0-9]+)*)*$")
+            .is_ok_and(|re| re.is_match(repository));
+        if !valid || repository.ends_with(".git") {
+            return None;
+        }
+        if !explicit
+            && tag.is_none()
+            && OciSettings::load()
+                .ok()
+                .and_then(|settings| settings.registry)
+                .is_none_or(|configured| configured != registry)
+        {
+            return None;
+        }
+        Some(Reference {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            tag,
+        })
+    }
+
+    /// Render the repository as `<registry>/<repository>`, without its tag.
+    pub fn short_name(&self) -> String {
+        format!("{}/{}", self.registry, self.repository)
+    }
+
+    /// Get the `oci://` URL of the repository, used as the `origin` of araki repos.
+    pub fn url(&self) -> String {
+        format!("{SCHEME}{}/{}", self.registry, self.repository)
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.short_name())?;
+        match &self.tag {
+            Some(tag) => write!(f, ":{tag}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Get the registry repository an araki repo is backed by, if its `origin` is an `oci://` URL.
+///
+/// * `repo`: araki repo
+pub fn origin(repo: &Repository) -> Option<Reference> {
+    let remote = repo.find_remote("origin").ok()?;
+    let url = remote.url()?;
+    url.starts_with(SCHEME).then(|| Reference::parse(url))?
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    size: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, String>,
+}
+
+impl Descriptor {
+    /// Describe a blob.
+    ///
+    /// * `media_type`: Media type of the blob
+    /// * `data`: Contents of the blob
+    fn new(media_type: &str, data: &[u8]) -> Self {
+        Descriptor {
+            media_type: media_type.to_string(),
+            digest: digest(data),
+            size: data.len() as u64,
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    /// Get the file name a layer was pushed with.
+    fn title(&self) -> Option<&str> {
+        self.annotations
+            .get(TITLE_ANNOTATION)
+            .map(|title| title.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    schema_version: u32,
+    media_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    artifact_type: Option<String>,
+    config: Descriptor,
+    layers: Vec<Descriptor>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Get the layer of a lockspec file.
+    ///
+    /// * `file`: Name of the file, e.g. `pixi.lock`
+    fn layer(&self, file: &str) -> Option<&Descriptor> {
+        self.layers.iter().find(|layer| layer.title() == Some(file))
+    }
+}
+
+/// The lockspec files of a tag, downloaded from a registry.
+pub(crate) struct Artifact {
+    pub(crate) tag: String,
+
+    /// When the artifact was pushed, as an RFC 3339 timestamp, if the manifest records it
+    pub(crate) created: Option<String>,
+    pub(crate) files: Vec<(String, Vec<u8>)>,
+}
+
+/// Get the `sha256:<hex>` digest of a blob.
+///
+/// * `data`: Contents of the blob
+fn digest(data: &[u8]) -> String {
+    let hash = Sha256::digest(data);
+    let hex: String = hash.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("sha256:{hex}")
+}
+
+/// Authorization sent with requests to a registry, once it asked for some.
+enum Authorization {
+    Bearer(String),
+    Basic,
+}
+
+/// Client of the OCI distribution API of a repository in a registry.
+struct Client {
+    http: reqwest::Client,
+
+    /// `<scheme>://<registry>/v2/<repository>/`, which API paths are joined to
+    base: Url,
+    credentials: Option<(String, String)>,
+    authorization: Mutex<Option<Authorization>>,
+}
+
+impl Client {
+    /// Create a client for the repository of a reference.
+    ///
+    /// * `reference`: Artifact reference
+    fn new(reference: &Reference) -> Result<Self, String> {
+        let settings = OciSettings::load()?;
+        let scheme = match settings.insecure_registries.contains(&reference.registry) {
+            true => "http",
+            false => "https",
+        };
+        let base = format!(
+            "{scheme}://{}/v2/{}/",
+            reference.registry, reference.repository
+        );
+        Ok(Client {
+            http: network::client_builder()
+                .and_then(|builder| Ok(builder.build()?))
+                .map_err(|err| err.to_string())?,
+            base: Url::parse(&base).map_err(|err| format!("Invalid registry URL {base}: {err}"))?,
+            credentials: settings.credentials(),
+            authorization: Mutex::new(None),
+        })
+    }
+
+    /// Send a request, authenticating with the registry first if it answers with a challenge.
+    ///
+    /// * `request`: Builds the request; called again to retry it after authenticating
+    async fn send(
+        &self,
+        request: impl Fn(&reqwest::Client) -> RequestBuilder,
+    ) -> Result<Response, String> {
+        let mut retried = false;
+        loop {
+            let mut builder = request(&self.http);
+            builder = match &*self
+                .authorization
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+            {
+                Some(Authorization::Bearer(token)) => builder.bearer_auth(token),
+                Some(Authorization::Basic) => match &self.credentials {
+                    Some((username, password)) => builder.basic_auth(username, Some(password)),
+                    None => builder,
+                },
+                None => builder,
+            };
+            let response = builder
+                .send_with_retries()
+                .await
+                .map_err(|err| format!("Unable to reach the registry: {err}"))?;
+            if response.status() != StatusCode::UNAUTHORIZED || retried {
+                return Ok(response);
+            }
+            let challenge = response
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .and_then(|challenge| challenge.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            self.authenticate(&challenge).await?;
+            retried = true;
+        }
+    }
+
+    /// Answer an authentication challenge of the registry, getting a token from its token
+    /// service for `Bearer` challenges.
+    ///
+    /// * `challenge`: `WWW-Authenticate` header of the rejected request
+    async fn authenticate(&self, challenge: &str) -> Result<(), String> {
+        let authorization = if challenge.starts_with("Basic") {
+            if self.credentials.is_none() {
+                return Err(missing_credentials());
+            }
+            Authorization::Basic
+        } else {
+            let params: BTreeMap<&str, &str> = Regex::new(r#"(\w+)="([^"]*)""#)
+                .map_err(|err| err.to_string())?
+                .captures_iter(challenge)
+                .filter_map(|captures| Some((captures.get(1)?.as_str(), captures.get(2)?.as_str())))
+                .collect();
+            let realm = params
+                .get("realm")
+                .ok_or(format!("Unsupported authentication challenge: {challenge}"))?;
+            let query: Vec<(&str, &str)> = ["service", "scope"]
+                .into_iter()
+                .filter_map(|key| Some((key, *params.get(key)?)))
+                .collect();
+            let mut request = self.http.get(*realm).query(&query);
+            if let Some((username, password)) = &self.credentials {
+                request = request.basic_auth(username, Some(password));
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|err| format!("Unable to reach the token service at {realm}: {err}"))?;
+            if !response.status().is_success() {
+                return Err(match self.credentials {
+                    Some(_) => format!(
+                        "The registry refused the credentials ({})",
+                        response.status()
+                    ),
+                    None => missing_credentials(),
+                });
+            }
+            let body: serde_json::Value = response
+                .json()
+                .await
+                .map_err(|err| format!("Invalid response of the token service: {err}"))?;
+            let token = body
+                .get("token")
+                .or_else(|| body.get("access_token"))
+                .and_then(|token| token.as_str())
+                .ok_or("The token service didn't return a token")?;
+            Authorization::Bearer(token.to_string())
+        };
+        *self
+            .authorization
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = Some(authorization);
+        Ok(())
+    }
+
+    /// Join a path of the repository's API to its base URL.
+    ///
+    /// * `path`: Path relative to `/v2/<repository>/`
+    fn url(&self, path: &str) -> Result<Url, String> {
+        self.base
+            .join(path)
+            .map_err(|err| format!("Invalid registry path {path}: {err}"))
+    }
+
+    /// List the tags of the repository; `None` if the repository doesn't exist.
+    async fn tags(&self) -> Result<Option<Vec<String>>, String> {
+        let url = self.url("tags/list?n=10000")?;
+        let response = self.send(|http| http.get(url.clone())).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body: serde_json::Value = check(response)
+            .await?
+            .json()
+            .await
+            .map_err(|err| format!("Invalid tag list: {err}"))?;
+        let tags = body
+            .get("tags")
+            .and_then(|tags| tags.as_array())
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|tag| tag.as_str().map(|tag| tag.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Some(tags))
+    }
+
+    /// Get the manifest of a tag; `None` if the tag doesn't exist.
+    ///
+    /// * `tag`: Tag of the artifact
+    async fn manifest(&self, tag: &str) -> Result<Option<Manifest>, String> {
+        let url = self.url(&format!("manifests/{tag}"))?;
+        let response = self
+            .send(|http| {
+                http.get(url.clone())
+                    .header(header::ACCEPT, MANIFEST_MEDIA_TYPE)
+            })
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        check(response)
+            .await?
+            .json()
+            .await
+            .map(Some)
+            .map_err(|err| format!("Invalid manifest of {tag}: {err}"))
+    }
+
+    /// Download a blob, checking it against its digest.
+    ///
+    /// * `descriptor`: Descriptor of the blob
+    async fn blob(&self, descriptor: &Descriptor) -> Result<Vec<u8>, String> {
+        let url = self.url(&format!("blobs/{}", descriptor.digest))?;
+        let data = check(self.send(|http| http.get(url.clone())).await?)
+            .await?
+            .bytes()
+            .await
+            .map_err(|err| format!("Unable to download {}: {err}", descriptor.digest))?;
+        if digest(&data) != descriptor.digest {
+            return Err(format!(
+                "The contents of {} don't match their digest",
+                descriptor.digest
+            ));
+        }
+        Ok(data.to_vec())
+    }
+
+    /// Upload a blob, unless the registry already has it.
+    ///
+    /// * `descriptor`: Descriptor of the blob
+    /// * `data`: Contents of the blob
+    async fn upload(&self, descriptor: &Descriptor, data: &[u8]) -> Result<(), String> {
+        let url = self.url(&format!("blobs/{}", descriptor.digest))?;
+        if self
+            .send(|http| http.head(url.clone()))
+            .await?
+            .status()
+            .is_success()
+        {
+            return Ok(());
+        }
+        let response = check(
+            self.send(|http| http.post(self.base.join("blobs/uploads/").unwrap_or(url.clone())))
+                .await?,
+        )
+        .await?;
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or("The registry didn't return an upload location")?;
+        // The location may be relative to the registry, and may have a query already
+        let mut upload = self
+            .base
+            .join(location)
+            .map_err(|err| format!("Invalid upload location {location}: {err}"))?;
+        upload
+            .query_pairs_mut()
+            .append_pair("digest", &descriptor.digest);
+        check(
+            self.send(|http| {
+                http.put(upload.clone())
+                    .header(header::CONTENT_TYPE, "application/octet-stream")
+                    .body(data.to_vec())
+            })
+            .await?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Upload the manifest of a tag.
+    ///
+    /// * `tag`: Tag of the artifact
+    /// * `manifest`: Manifest to upload
+    async fn put_manifest(&self, tag: &str, manifest: &Manifest) -> Result<(), String> {
+        let url = self.url(&format!("manifests/{tag}"))?;
+        let body = serde_json::to_vec(manifest).map_err(|err| err.to_string())?;
+        check(
+            self.send(|http| {
+                http.put(url.clone())
+                    .header(header::CONTENT_TYPE, MANIFEST_MEDIA_TYPE)
+                    .body(body.clone())
+            })
+            .await?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Download the lockspec files of the tags of the repository, except those to skip, in the
+    /// order they were pushed. Tags of artifacts which aren't lockspecs are ignored.
+    ///
+    /// * `skip`: Tags which don't need to be downloaded
+    async fn artifacts(&self, skip: &BTreeSet<String>) -> Result<Vec<Artifact>, String> {
+        let tags = self
+            .tags()
+            .await?
+            .ok_or(format!("No repository found at {}", self.base))?;
+        let mut artifacts = vec![];
+        for tag in tags.into_iter().filter(|tag| !skip.contains(tag)) {
+            let Some(manifest) = self.manifest(&tag).await? else {
+                continue;
+            };
+            let mut files = vec![];
+            for file in LOCKSPEC_FILES {
+                if let Some(layer) = manifest.layer(file) {
+                    files.push((file.to_string(), self.blob(layer).await?));
+                }
+            }
+            if files.len() == LOCKSPEC_FILES.len() {
+                artifacts.push(Artifact {
+                    created: manifest.annotations.get(CREATED_ANNOTATION).cloned(),
+                    tag,
+                    files,
+                });
+            }
+        }
+        artifacts.sort_by(|a, b| a.created.cmp(&b.created));
+        Ok(artifacts)
+    }
+}
+
+/// Fail on responses which aren't successful, with the error the registry sent.
+///
+/// * `response`: Response of the registry
+async fn check(response: Response) -> Result<Response, String> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let url = response.url().clone();
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("{url} answered {status}: {}", body.trim()))
+}
+
+fn missing_credentials() -> String {
+    "The registry requires authentication. Set username_env and password_env in \
+    [backends.oci] to the environment variables holding your credentials."
+        .to_string()
+}
+
+/// Run registry requests from synchronous code, on a thread with a runtime of its own, so that
+/// it works both inside and outside of an async runtime.
+///
+/// * `future`: Requests to run
+pub(crate) fn block_on<T: Send>(
+    future: impl Future<Output = Result<T, String>> + Send,
+) -> Result<T, String> {
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(|err| format!("Unable to start an async runtime: {err}"))?
+                    .block_on(future)
+            })
+            .join()
+            .map_err(|_| "The registry requests panicked".to_string())?
+    })
+}
+
+/// Commit the lockspec files of artifacts on top of a commit, tagging each commit with the tag
+/// of its artifact. Artifacts with the same files as the previous one tag the same commit.
+/// Returns the last commit.
+///
+/// * `repo`: araki repo to commit to
+/// * `parent`: Commit to start from; the first artifact is committed without a parent if unset
+/// * `artifacts`: Artifacts to commit, oldest first
+/// * `source`: URL the artifacts come from, for the commit messages
+pub(crate) fn import(
+    repo: &Repository,
+    mut parent: Option<Oid>,
+    artifacts: &[Artifact],
+    source: &str,
+) -> Result<Option<Oid>, String> {
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Unable to create a signature: {err}"))?;
+    for artifact in artifacts {
+        let parent_commit = parent
+            .map(|oid| repo.find_commit(oid))
+            .transpose()
+            .map_err(|err| format!("Unable to find the parent commit: {err}"))?;
+        let parent_tree = parent_commit
+            .as_ref()
+            .map(|commit| commit.tree())
+            .transpose()
+            .map_err(|err| format!("Unable to read the parent tree: {err}"))?;
+        let tree = (|| {
+            let mut builder = repo.treebuilder(parent_tree.as_ref())?;
+            for (file, contents) in &artifact.files {
+                builder.insert(file, repo.blob(contents)?, 0o100644)?;
+            }
+            builder.write()
+        })()
+        .map_err(|err| format!("Unable to write the files of {}: {err}", artifact.tag))?;
+
+        let commit = match &parent_commit {
+            Some(commit) if commit.tree_id() == tree => commit.id(),
+            _ => {
+                let tree = repo
+                    .find_tree(tree)
+                    .map_err(|err| format!("Unable to find the tree of {}: {err}", artifact.tag))?;
+                let parents: Vec<_> = parent_commit.iter().collect();
+                repo.commit(
+                    None,
+                    &signature,
+                    &signature,
+                    &format!("Import {} from {source}", artifact.tag),
+                    &tree,
+                    &parents,
+                )
+                .map_err(|err| format!("Unable to commit {}: {err}", artifact.tag))?
+            }
+        };
+        repo.find_object(commit, None)
+            .and_then(|object| repo.tag_lightweight(&artifact.tag, &object, true))
+            .map_err(|err| format!("Unable to tag {}: {err}", artifact.tag))?;
+        parent = Some(commit);
+    }
+    Ok(parent)
+}
+
+/// Create an araki repo in a directory, with `origin` set to a registry repository. The repo is
+/// set up in a temporary directory, then copied to the target like a clone.
+///
+/// * `origin`: URL of the registry repository
+/// * `path`: Directory of the lockspec
+/// * `setup`: Called with the new repo to fill it in before it is copied
+pub(crate) fn create_repo(
+    origin: &str,
+    path: &Path,
+    setup: impl FnOnce(&Repository) -> Result<(), String>,
+) -> Result<(), String> {
+    let dir = temp_dir().join(format!("araki-oci-{}", Uuid::new_v4()));
+    let result = (|| {
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("Unable to create a temporary directory at {dir:?}: {err}"))?;
+        let repo = Repository::init(&dir)
+            .map_err(|err| format!("Unable to initialize a repo at {dir:?}: {err}"))?;
+        repo.remote("origin", origin)
+            .map_err(|err| format!("Unable to add {origin} as origin: {err}"))?;
+        setup(&repo)?;
+        drop(repo);
+        fs::rename(dir.join(".git"), dir.join(ARAKI_GIT_DIR_NAME))
+            .map_err(|err| format!("Error modifying the new repo: {err}"))?;
+        common::copy_directory_contents(&dir, &path.to_path_buf())
+            .map_err(|err| format!("Error copying the new repo from {dir:?} to {path:?}: {err}"))
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result?;
+    common::ignore_lockspec_files(path)
+}
+
+/// Clone the lockspec artifacts of a registry repository to a directory: each tag becomes a
+/// commit of `main`, and the most recently pushed one is checked out.
+///
+/// * `reference`: Registry repository; its tag, if any, is not checked out
+/// * `path`: Directory to clone the lockspec to
+pub fn clone(reference: &Reference, path: &Path) -> Result<(), String> {
+    network::require_online(&format!("clone {reference}"))?;
+    let client = Client::new(reference)?;
+    let artifacts = block_on(client.artifacts(&BTreeSet::new()))?;
+    if artifacts.is_empty() {
+        return Err(format!(
+            "No lockspec artifacts found in {}",
+            reference.url()
+        ));
+    }
+    create_repo(&reference.url(), path, |repo| {
+        checkout_imported(repo, &artifacts, &reference.url())
+    })
+}
+
+/// Import artifacts into a new araki repo as the history of `main`, tracking `origin/main`, and
+/// checkout the last one.
+///
+/// * `repo`: New araki repo
+/// * `artifacts`: Artifacts to import, oldest first
+/// * `source`: URL the artifacts come from, for the commit messages
+pub(crate) fn checkout_imported(
+    repo: &Repository,
+    artifacts: &[Artifact],
+    source: &str,
+) -> Result<(), String> {
+    let head = import(repo, None, artifacts, source)?.ok_or("No lockspec artifacts to import")?;
+    for branch in ["refs/heads/main", "refs/remotes/origin/main"] {
+        repo.reference(branch, head, true, "Imported from the registry")
+            .map_err(|err| format!("Unable to create {branch}: {err}"))?;
+    }
+    repo.find_branch("main", BranchType::Local)
+        .and_then(|mut branch| branch.set_upstream(Some("origin/main")))
+        .map_err(|err| format!("Unable to track origin/main: {err}"))?;
+    repo.set_head("refs/heads/main")
+        .and_then(|_| repo.find_object(head, None))
+        .and_then(|commit| {
+            let mut builder = CheckoutBuilder::new();
+            builder.force();
+            common::checkout_lockspec(repo, &commit, builder)
+        })
+        .map_err(|err| format!("Unable to checkout the imported lockspec: {err}"))
+}
+
+/// Create an empty araki repo for a lockspec pushed to a registry repository, as
+/// `araki init` does for git hosts.
+///
+/// * `reference`: Registry repository
+/// * `path`: Directory containing the lockspec
+pub fn init(reference: &Reference, path: &Path) -> Result<(), String> {
+    create_repo(&reference.url(), path, |_| Ok(()))
+}
+
+/// Import the tags pushed to the registry repository of an araki repo since it was cloned or
+/// last pulled, as commits on top of `origin/main`. Returns the new `origin/main`.
+///
+/// * `repo`: araki repo whose `origin` is a registry repository
+pub fn fetch(repo: &Repository) -> Result<Oid, String> {
+    let reference = origin(repo).ok_or("The origin of the araki repo is not a registry")?;
+    network::require_online(&format!("fetch from {reference}"))?;
+    let client = Client::new(&reference)?;
+    let artifacts = block_on(client.artifacts(&local_tags(repo)?))?;
+    import_fetched(repo, &artifacts, &reference.url())
+}
+
+/// Import artifacts fetched from the registry of an araki repo on top of `origin/main`. Returns
+/// the new `origin/main`.
+///
+/// * `repo`: araki repo
+/// * `artifacts`: Artifacts to import, oldest first
+/// * `source`: URL the artifacts come from, for the commit messages
+pub(crate) fn import_fetched(
+    repo: &Repository,
+    artifacts: &[Artifact],
+    source: &str,
+) -> Result<Oid, String> {
+    let parent = repo
+        .refname_to_id("refs/remotes/origin/main")
+        .or_else(|_| repo.refname_to_id("refs/heads/main"))
+        .ok();
+    let head = import(repo, parent, artifacts, source)?
+        .ok_or("No lockspec artifacts found in the registry")?;
+    repo.reference(
+        "refs/remotes/origin/main",
+        head,
+        true,
+        "Fetched from the registry",
+    )
+    .map_err(|err| format!("Unable to update origin/main: {err}"))?;
+    Ok(head)
+}
+
+/// Get the names of the tags of an araki repo.
+///
+/// * `repo`: araki repo
+pub(crate) fn local_tags(repo: &Repository) -> Result<BTreeSet<String>, String> {
+    Ok(repo
+        .tag_names(None)
+        .map_err(|err| format!("Unable to list the local tags: {err}"))?
+        .iter()
+        .flatten()
+        .map(|tag| tag.to_string())
+        .collect())
+}
+
+/// Point `origin/main` at a pushed tag, so that later pulls import the tags pushed by others
+/// on top of it.
+///
+/// * `repo`: araki repo
+/// * `tag`: Tag which was pushed
+pub(crate) fn record_pushed(repo: &Repository, tag: &str) -> Result<(), String> {
+    let commit = repo
+        .revparse_single(&format!("refs/tags/{tag}^{{commit}}"))
+        .map_err(|err| format!("Unable to find tag {tag}: {err}"))?;
+    repo.reference(
+        "refs/remotes/origin/main",
+        commit.id(),
+        true,
+        &format!("Pushed {tag} to the registry"),
+    )
+    .map_err(|err| format!("Unable to update origin/main: {err}"))?;
+    Ok(())
+}
+
+/// Read the lockspec files of a tag, with the descriptors of their layers.
+///
+/// * `repo`: araki repo
+/// * `tag`: Tag to read
+fn tag_files(repo: &Repository, tag: &str) -> Result<Vec<(Descriptor, Vec<u8>)>, String> {
+    LOCKSPEC_FILES
+        .iter()
+        .map(|file| {
+            let contents = common::read_rev_file(repo, &format!("refs/tags/{tag}"), file)
+                .map_err(|err| format!("Unable to read {file} at {tag}: {err}"))?;
+            let mut descriptor = Descriptor::new(LAYER_MEDIA_TYPE, &contents);
+            descriptor
+                .annotations
+                .insert(TITLE_ANNOTATION.to_string(), file.to_string());
+            Ok((descriptor, contents))
+        })
+        .collect()
+}
+
+/// Check whether a tag was pushed to the registry with other lockspec files than it has
+/// locally. Returns `false` if the tag wasn't pushed.
+///
+/// * `repo`: araki repo whose `origin` is a registry repository
+/// * `tag`: Tag to check
+pub fn pushed_tag_differs(repo: &Repository, tag: &str) -> Result<bool, String> {
+    let reference = origin(repo).ok_or("The origin of the araki repo is not a registry")?;
+    let files = tag_files(repo, tag)?;
+    let client = Client::new(&reference)?;
+    let Some(manifest) = block_on(client.manifest(tag))? else {
+        return Ok(false);
+    };
+    Ok(files.iter().any(|(descriptor, _)| {
+        descriptor
+            .title()
+            .and_then(|file| manifest.layer(file))
+            .is_none_or(|layer| layer.digest != descriptor.digest)
+    }))
+}
+
+/// Push the lockspec files of a tag to the registry repository of an araki repo, as an
+/// artifact with the same tag.
+///
+/// * `repo`: araki repo whose `origin` is a registry repository
+/// * `tag`: Tag to push
+pub fn push_tag(repo: &Repository, tag: &str) -> Result<(), String> {
+    let reference = origin(repo).ok_or("The origin of the araki repo is not a registry")?;
+    network::require_online(&format!("push to {reference}"))?;
+    let commit = repo
+        .revparse_single(&format!("refs/tags/{tag}^{{commit}}"))
+        .map_err(|err| format!("Unable to find tag {tag}: {err}"))?;
+    let created = repo
+        .find_commit(commit.id())
+        .ok()
+        .and_then(|commit| DateTime::<Utc>::from_timestamp(commit.time().seconds(), 0))
+        .unwrap_or_else(Utc::now);
+
+    let files = tag_files(repo, tag)?;
+    let config = b"{}";
+    let manifest = Manifest {
+        schema_version: 2,
+        media_type: MANIFEST_MEDIA_TYPE.to_string(),
+        artifact_type: Some(ARTIFACT_TYPE.to_string()),
+        config: Descriptor::new(EMPTY_MEDIA_TYPE, config),
+        layers: files
+            .iter()
+            .map(|(descriptor, _)| descriptor.clone())
+            .collect(),
+        annotations: BTreeMap::from([(CREATED_ANNOTATION.to_string(), created.to_rfc3339())]),
+    };
+    let client = Client::new(&reference)?;
+    block_on(async {
+        client.upload(&manifest.config, config).await?;
+        for (descriptor, contents) in &files {
+            client.upload(descriptor, contents).await?;
+        }
+        client.put_manifest(tag, &manifest).await
+    })?;
+    record_pushed(repo, tag)
+}
+
+/// Backend for lockspecs stored as OCI artifacts in a container registry, configured in
+/// `[backends.oci]`.
+pub struct OciBackend {
+    registry: String,
+}
+
+impl OciBackend {
+    /// Create a new OciBackend from the `[backends.oci]` settings.
+    pub fn new() -> Result<Self, BackendError> {
+        let registry = OciSettings::load()?.registry.ok_or(
+            "No registry configured. Set `registry` in [backends.oci] in araki.toml, e.g. to \
+            \"ghcr.io\".",
+        )?;
+        Ok(Self { registry })
+    }
+
+    /// Get the registry repository of a lockspec.
+    ///
+    /// * `org`: Organization of the lockspec
+    /// * `name`: Name of the lockspec
+    fn reference(&self, org: &str, name: &str) -> Reference {
+        Reference {
+            registry: self.registry.clone(),
+            repository: format!("{org}/{name}").to_lowercase(),
+            tag: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for OciBackend {
+    fn get(&self, _path: &str) -> Result<RequestBuilder, BackendError> {
+        Err("The OCI backend has no API to send requests to.".into())
+    }
+    fn post(&self, _path: &str) -> Result<RequestBuilder, BackendError> {
+        Err("The OCI backend has no API to send requests to.".into())
+    }
+    async fn is_existing_lockspec(&self, org: &str, name: &str) -> Result<bool, BackendError> {
+        let client = Client::new(&self.reference(org, name))?;
+        Ok(client.tags().await?.is_some_and(|tags| !tags.is_empty()))
+    }
+    async fn create_repository(
+        &self,
+        _org: &str,
+        _name: &str,
+        _visibility: Visibility,
+    ) -> Result<(), BackendError> {
+        // Registries create repositories when the first artifact is pushed to them
+        Ok(())
+    }
+    fn get_repo_info(&self, org: &str, repo: &str) -> RemoteRepo {
+        RemoteRepo::new(Some(org.to_string()), repo.to_string(), None, None)
+            .with_url(Some(self.reference(org, repo).url()))
+    }
+    async fn login(&self, _scopes: &[String]) -> Result<(), BackendError> {
+        Err(missing_credentials().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference() {
+        assert_eq!(
+            Reference::parse("ghcr.io/my-org/my-env:v1"),
+            Some(Reference {
+                registry: "ghcr.io".to_string(),
+                repository: "my-org/my-env".to_string(),
+                tag: Some("v1".to_string()),
+            })
+        );
+        assert_eq!(
+            Reference::parse("oci://localhost:5000/my-org/my-env").map(|reference| reference.url()),
+            Some("oci://localhost:5000/my-org/my-env".to_string())
+        );
+        assert_eq!(Reference::parse("my-org/my-env"), None);
+        assert_eq!(Reference::parse("github.com/my-org/my-env"), None);
+        assert_eq!(Reference::parse("https://github.com/my-org/my-env"), None);
+        assert_eq!(Reference::parse("git@github.com:my-org/my-env.git"), None);
+    }
+}
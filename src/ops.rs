@@ -0,0 +1,1692 @@
+use chrono::Local;
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{Commit, Index, IndexEntry, IndexTime, ObjectType, Oid, Repository, Signature};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::artifacts::{self, ArtifactOrigin};
+use crate::common::{self, LinkState, LockSpec};
+use crate::lockfile::{LockFile, PackageChange, PackageKind, count_changes};
+use crate::mirrors;
+use crate::network;
+use crate::preflight;
+use crate::projects;
+use crate::remote::{Protocol, RemoteRepo, parse_repo_arg};
+use crate::secrets;
+use crate::security;
+use crate::settings::get_settings;
+use crate::tags::{self, Bump, TagSettings};
+
+/// The lockspec files tracked by araki.
+pub const LOCKSPEC_FILES: [&str; 2] = ["pixi.toml", "pixi.lock"];
+
+/// Prefix of the tags created by [`snapshot`], followed by a timestamp.
+pub const SNAPSHOT_TAG_PREFIX: &str = "snapshot/";
+
+/// File in the araki git dir recording the last checkout.
+const CHECKOUT_RECORD_FILE: &str = "ARAKI_CHECKOUT";
+
+/// File in the araki git dir listing previous checkouts, oldest first.
+const CHECKOUT_HISTORY_FILE: &str = "ARAKI_CHECKOUT_HISTORY";
+
+/// At most this many checkouts are kept in the checkout history.
+const MAX_CHECKOUT_HISTORY: usize = 50;
+
+/// File in the araki git dir caching the tags of the remote.
+const REMOTE_TAGS_CACHE_FILE: &str = "ARAKI_REMOTE_TAGS";
+
+/// How a tag compares between the araki repo and its remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagSync {
+    /// The tag exists locally and on the remote, pointing at the same commit
+    Synced,
+    LocalOnly,
+    RemoteOnly,
+
+    /// The tag exists locally and on the remote, but points at different commits
+    Diverged,
+}
+
+/// A tag of the araki repo or its remote, with how it compares between the two.
+#[derive(Debug, Clone)]
+pub struct TagComparison {
+    pub name: String,
+
+    /// Message of the local tag, if it exists locally and is annotated
+    pub message: Option<String>,
+    pub sync: TagSync,
+}
+
+/// Progress of a long-running operation.
+#[derive(Debug, Clone, Copy)]
+pub enum Progress<'a> {
+    /// A new phase of the operation started
+    Phase {
+        step: usize,
+        total: usize,
+        message: &'a str,
+    },
+
+    /// Objects were received from a remote
+    Transfer { received: usize, total: usize },
+}
+
+/// A tag of an araki-managed lockspec.
+#[derive(Debug, Clone)]
+pub struct TagInfo {
+    pub name: String,
+
+    /// Message of an annotated tag; lightweight tags have none
+    pub message: Option<String>,
+}
+
+/// State of a lockspec relative to its araki repo.
+#[derive(Debug, Clone)]
+pub struct Status {
+    /// Branch name, or the tag/commit checked out if HEAD is detached
+    pub head: String,
+
+    /// Whether the lockspec files are tracked by the araki repo
+    pub link: LinkState,
+
+    /// Tag of the HEAD commit, if it is tagged
+    pub tag: Option<String>,
+
+    /// Lockspec files whose contents differ from HEAD, including untracked and deleted ones
+    pub modified: Vec<String>,
+
+    /// Lockspec files of `modified` which HEAD doesn't have
+    pub untracked: Vec<String>,
+
+    /// Lockspec files of `modified` which HEAD has, but the lockspec directory doesn't
+    pub deleted: Vec<String>,
+}
+
+/// What fetching the remote of an araki repo brought in.
+#[derive(Debug, Clone, Default)]
+pub struct Updates {
+    /// Tags of the lockspec which didn't exist locally before
+    pub new_tags: Vec<String>,
+
+    /// Number of commits of `main` on origin which the local `main` doesn't have
+    pub new_commits: usize,
+}
+
+impl Updates {
+    pub fn is_empty(&self) -> bool {
+        self.new_tags.is_empty() && self.new_commits == 0
+    }
+}
+
+/// Pull settings, configured in the `[pull]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PullSettings {
+    /// Install the lockfile with `pixi install --frozen` when a pull fast-forwards to a new one
+    pub autoinstall: bool,
+}
+
+/// Clone a lockspec and install it with pixi.
+///
+/// If the installation fails, the cloned lockspec is removed again.
+///
+/// * `env`: URL, `<org>/<repo>`, or alias of the lockspec to clone
+/// * `path`: Directory where the lockspec should be cloned
+/// * `tag`: Tag, branch, or commit to checkout before installing; defaults to the default
+///   branch of the repo
+/// * `protocol`: Protocol to clone with; by default ssh is tried first, then anonymous https
+/// * `environments`: pixi environments of the lockspec to install, recorded for later installs;
+///   pixi's default environment if empty
+/// * `on_progress`: Called as the clone progresses
+pub fn clone(
+    env: &str,
+    path: &Path,
+    tag: Option<&str>,
+    protocol: Option<Protocol>,
+    environments: &[String],
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), String> {
+    // Check that the target directory can receive the lockspec before cloning anything
+    on_progress(Progress::Phase {
+        step: 1,
+        total: 3,
+        message: "Checking the target directory...",
+    });
+    preflight::check_clone_target(path)?;
+
+    let env = &get_settings()?.resolve_alias(env);
+    let (name, lockspec) = match ArtifactOrigin::parse(env) {
+        Some(origin) => {
+            on_progress(Progress::Phase {
+                step: 2,
+                total: 3,
+                message: &format!("Pulling {origin}..."),
+            });
+            let tag = tag.or(origin.tag());
+            (
+                origin.short_name(),
+                fetch_registry_lockspec(&origin, path, tag)?,
+            )
+        }
+        None => {
+            let remote = parse_repo_arg(env)
+                .map_err(|err| format!("{env} is not a valid lockspec repository: {err}"))?;
+            on_progress(Progress::Phase {
+                step: 2,
+                total: 3,
+                message: &format!("Cloning {remote}..."),
+            });
+            let lockspec = fetch_lockspec(&remote, path, tag, protocol, &|received, total| {
+                on_progress(Progress::Transfer { received, total })
+            })?;
+            (remote.short_name(), lockspec)
+        }
+    };
+    let repo = common::open_araki_git_repo(path)
+        .map_err(|err| format!("Couldn't recognize the cloned araki repo: {err}"))?;
+    if let Err(err) = select_pixi_environments(&lockspec, &repo, environments) {
+        let _ = lockspec.remove_files();
+        return Err(err);
+    }
+
+    // Install the pixi project.
+    // If this fails, remove the lockspec repository if it was cloned before,
+    // in addition to the hardlinked files.
+    on_progress(Progress::Phase {
+        step: 3,
+        total: 3,
+        message: "Installing the environment with pixi...",
+    });
+    let status = mirrors::apply(&lockspec.dir()).and_then(|mirrors| {
+        let mut command = common::pixi_command();
+        command
+            .args(["install", "--frozen", "--locked", "--color", "always"])
+            .args(common::pixi_environment_args(&repo))
+            .current_dir(lockspec.dir());
+        mirrors.configure(&mut command);
+        command.status().map_err(|err| err.to_string())
+    });
+
+    if status.is_err() || status.is_ok_and(|code| !code.success()) {
+        let cleanup = LockSpec::from_path(path).and_then(|lockspec| lockspec.remove_files());
+        return Err(match cleanup {
+            Ok(_) => "Failed to install the environment with pixi.".to_string(),
+            Err(err) => format!(
+                "Failed to install the environment with pixi. Unable to clean up the lockspec \
+                in {path:?}: {err}"
+            ),
+        });
+    }
+    verify_install(&lockspec, &repo)?;
+    // The project registry is bookkeeping only; failing to update it doesn't fail the clone
+    let _ = projects::record(path, &name, tag);
+    Ok(())
+}
+
+/// Check the installed packages of a lockspec against the hashes in its lockfile, if
+/// `security.verify_hashes` is set.
+///
+/// * `lockspec`: Installed lockspec
+/// * `repo`: araki repo of the lockspec
+pub fn verify_install(lockspec: &LockSpec, repo: &Repository) -> Result<(), String> {
+    if !get_settings()?.security.verify_hashes {
+        return Ok(());
+    }
+    security::verify_installed_hashes(lockspec, &common::pixi_environments(repo))
+}
+
+/// Record the pixi environments of a lockspec to install, after checking that its lockfile
+/// locks them.
+///
+/// * `lockspec`: Lockspec to install
+/// * `repo`: araki repo of the lockspec
+/// * `environments`: pixi environments to install; pixi's default environment if empty
+pub fn select_pixi_environments(
+    lockspec: &LockSpec,
+    repo: &Repository,
+    environments: &[String],
+) -> Result<(), String> {
+    if !environments.is_empty() {
+        let locked = LockFile::from_path(&lockspec.lockfile())?.environments;
+        if let Some(missing) = environments
+            .iter()
+            .find(|environment| !locked.contains_key(*environment))
+        {
+            return Err(format!(
+                "pixi.lock has no environment named {missing}; available: {}",
+                locked.keys().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+    common::set_pixi_environments(repo, environments)
+}
+
+/// Clone a lockspec without installing it.
+///
+/// * `remote`: Repository of the lockspec
+/// * `path`: Directory where the lockspec should be cloned
+/// * `tag`: Tag, branch, or commit to checkout; defaults to the default branch of the repo
+/// * `protocol`: Protocol to clone with; by default ssh is tried first, then anonymous https
+/// * `on_transfer`: Called with the number of objects received so far and the total number of
+///   objects whenever new objects are received
+pub fn fetch_lockspec(
+    remote: &RemoteRepo,
+    path: &Path,
+    tag: Option<&str>,
+    protocol: Option<Protocol>,
+    on_transfer: &dyn Fn(usize, usize),
+) -> Result<LockSpec, String> {
+    let mut errors = vec![];
+    for url in remote.clone_urls(protocol) {
+        match common::git_clone_with_progress(url, path, on_transfer) {
+            Ok(()) => {
+                errors.clear();
+                break;
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!(
+            "Unable to clone the lockspec:\n{}",
+            errors.join("\n")
+        ));
+    }
+    if let Some(subdir) = remote.get_subdir() {
+        select_lockspec_subdir(path, &subdir)?;
+    }
+
+    if let Some(tag) = tag {
+        let repo = common::open_araki_git_repo(path)
+            .map_err(|err| format!("Couldn't recognize the cloned araki repo: {err}"))?;
+        if let Err(err) = checkout_ref(&repo, tag) {
+            let _ = LockSpec::from_path(path).and_then(|lockspec| lockspec.remove_files());
+            return Err(err);
+        }
+    }
+
+    LockSpec::from_path(path).map_err(|_| {
+        format!(
+            "The cloned lockspec repo is not valid. Is pixi.toml or pixi.lock missing from \
+                {}/{} ?",
+            remote.get_org(),
+            remote.get_repo()
+        )
+    })
+}
+
+/// Bring the lockspec artifacts of a registry into a directory, and checkout a tag.
+///
+/// * `origin`: OCI registry repository or araki registry lockspec
+/// * `path`: Directory to clone the lockspec to
+/// * `tag`: Tag to checkout; defaults to the most recently pushed one
+pub fn fetch_registry_lockspec(
+    origin: &ArtifactOrigin,
+    path: &Path,
+    tag: Option<&str>,
+) -> Result<LockSpec, String> {
+    origin.clone_to(path)?;
+    if let Some(tag) = tag {
+        let repo = common::open_araki_git_repo(path)
+            .map_err(|err| format!("Couldn't recognize the cloned araki repo: {err}"))?;
+        if let Err(err) = checkout_ref(&repo, tag) {
+            let _ = LockSpec::from_path(path).and_then(|lockspec| lockspec.remove_files());
+            return Err(err);
+        }
+    }
+    LockSpec::from_path(path)
+}
+
+/// Record which subdirectory of a freshly cloned repo holds the lockspec.
+///
+/// * `path`: Directory the repo was cloned to
+/// * `subdir`: Subdirectory of the lockspec, or its name under `envs/`
+fn select_lockspec_subdir(path: &Path, subdir: &str) -> Result<(), String> {
+    let candidates = [subdir.to_string(), format!("envs/{subdir}")];
+    let subdir = candidates
+        .iter()
+        .find(|candidate| {
+            let dir = path.join(candidate);
+            dir.join("pixi.toml").exists() && dir.join("pixi.lock").exists()
+        })
+        .ok_or(format!(
+            "No lockspec found in {subdir}/ or envs/{subdir}/ of the cloned repo."
+        ))?;
+    let file = path
+        .join(common::ARAKI_GIT_DIR_NAME)
+        .join(common::LOCKSPEC_DIR_FILE);
+    fs::write(&file, format!("{subdir}\n"))
+        .map_err(|err| format!("Unable to write {file:?}: {err}"))
+}
+
+/// Bring the lockspec of an existing git project into a directory, so that it can be put under
+/// araki management: the project is cloned to a temporary directory, its lockspec is checked
+/// and copied to `path`, and installed there with pixi.
+///
+/// If the installation fails, the copied lockspec files are removed again.
+///
+/// * `url`: URL of the git project, with pixi.toml and pixi.lock at its root
+/// * `path`: Directory to copy the lockspec to; created if it doesn't exist
+pub fn import(url: &str, path: &Path) -> Result<(), String> {
+    preflight::check_no_lockspec(path)?;
+    let source = temp_dir().join(format!("araki-import-{}", Uuid::new_v4()));
+    fs::create_dir_all(&source)
+        .map_err(|err| format!("Unable to create a temporary directory at {source:?}: {err}"))?;
+
+    let result = (|| -> Result<(), String> {
+        common::git_clone(url.to_string(), &source)?;
+        let lockspec = LockSpec::from_path(&source)
+            .map_err(|_| format!("{url} has no pixi.toml and pixi.lock at its root."))?;
+        preflight::create_dir(path)?;
+        for file in LOCKSPEC_FILES {
+            fs::copy(lockspec.dir().join(file), path.join(file))
+                .map_err(|err| format!("Unable to copy {file} to {path:?}: {err}"))?;
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&source);
+    result?;
+
+    let lockspec = LockSpec::from_path(path)?;
+    let status = mirrors::apply(&lockspec.dir()).and_then(|mirrors| {
+        let mut command = common::pixi_command();
+        command
+            .args(["install", "--locked", "--color", "always"])
+            .current_dir(lockspec.dir());
+        mirrors.configure(&mut command);
+        command.status().map_err(|err| err.to_string())
+    });
+    if status.is_err() || status.is_ok_and(|code| !code.success()) {
+        let _ = lockspec.remove_files();
+        return Err(format!(
+            "The lockspec of {url} could not be installed with `pixi install --locked`."
+        ));
+    }
+    Ok(())
+}
+
+/// Get the full name of a tag of the lockspec, adding the tag prefix of lockspecs in a
+/// subdirectory (e.g. `v1` becomes `gpu/v1`).
+///
+/// * `repo`: araki repo
+/// * `name`: Name of the tag, with or without the prefix
+pub fn full_tag_name(repo: &Repository, name: &str) -> String {
+    let prefix = common::tag_prefix(repo);
+    if name.starts_with(&prefix) {
+        name.to_string()
+    } else {
+        format!("{prefix}{name}")
+    }
+}
+
+/// List the tags of an araki repo. In repos with several lockspecs, only the tags of the
+/// lockspec are listed.
+///
+/// * `repo`: araki repo to list the tags of
+pub fn list_tags(repo: &Repository) -> Result<Vec<TagInfo>, String> {
+    let names = repo
+        .tag_names(Some(&format!("{}*", common::tag_prefix(repo))))
+        .map_err(|err| format!("Unable to list tags: {err}"))?;
+
+    let mut tags = vec![];
+    for name in names.iter().flatten() {
+        let obj = repo
+            .revparse_single(name)
+            .map_err(|err| format!("Unable to get tag {name}: {err}"))?;
+        tags.push(TagInfo {
+            name: name.to_string(),
+            message: obj
+                .as_tag()
+                .and_then(|tag| tag.message())
+                .map(|message| message.to_string()),
+        });
+    }
+    Ok(tags)
+}
+
+/// Fetch `main` and the tags of the `origin` remote of an araki repo, without changing the
+/// local branches or the working directory.
+///
+/// * `repo`: araki repo
+pub fn fetch_updates(repo: &Repository) -> Result<Updates, String> {
+    let before: Vec<String> = list_tags(repo)?.into_iter().map(|tag| tag.name).collect();
+    match ArtifactOrigin::of(repo) {
+        Some(_) => artifacts::fetch(repo).map(|_| ())?,
+        None => common::git_fetch(
+            repo,
+            "origin",
+            &["+refs/heads/main:refs/remotes/origin/main"],
+        )
+        .map_err(|err| format!("Unable to fetch from origin: {err}"))?,
+    }
+    let new_tags = list_tags(repo)?
+        .into_iter()
+        .map(|tag| tag.name)
+        .filter(|name| !before.contains(name))
+        .collect();
+
+    let new_commits = match (
+        repo.refname_to_id("refs/heads/main"),
+        repo.refname_to_id("refs/remotes/origin/main"),
+    ) {
+        (Ok(local), Ok(remote)) => repo
+            .graph_ahead_behind(local, remote)
+            .map(|(_, behind)| behind)
+            .unwrap_or(0),
+        _ => 0,
+    };
+    Ok(Updates {
+        new_tags,
+        new_commits,
+    })
+}
+
+/// Fast-forward the local `main` to the fetched `main` of origin, updating the lockspec files if
+/// `main` is checked out. Returns whether `main` moved; branches which diverged from origin are
+/// left alone.
+///
+/// * `repo`: araki repo
+pub fn fast_forward_main(repo: &Repository) -> Result<bool, String> {
+    let (Ok(local), Ok(remote)) = (
+        repo.refname_to_id("refs/heads/main"),
+        repo.refname_to_id("refs/remotes/origin/main"),
+    ) else {
+        return Ok(false);
+    };
+    let can_fast_forward = local != remote
+        && repo
+            .graph_descendant_of(remote, local)
+            .map_err(|err| format!("Unable to compare main with origin/main: {err}"))?;
+    if !can_fast_forward {
+        return Ok(false);
+    }
+    let on_main = repo
+        .head()
+        .is_ok_and(|head| head.name() == Some("refs/heads/main"));
+    if on_main {
+        let commit = repo
+            .find_object(remote, None)
+            .map_err(|err| format!("Unable to find origin/main: {err}"))?;
+        common::checkout_lockspec(repo, &commit, CheckoutBuilder::new())
+            .map_err(|err| format!("Unable to checkout origin/main: {err}"))?;
+    }
+    repo.reference(
+        "refs/heads/main",
+        remote,
+        true,
+        "araki: fast-forward to origin/main",
+    )
+    .map_err(|err| format!("Unable to fast-forward main: {err}"))?;
+    Ok(true)
+}
+
+/// Get when a tag was created: the tagger's time for annotated tags, and the time of the tagged
+/// commit for lightweight ones. Returns seconds since the epoch.
+///
+/// * `repo`: araki repo
+/// * `name`: Full name of the tag
+pub fn tag_time(repo: &Repository, name: &str) -> Option<i64> {
+    let object = repo.revparse_single(&format!("refs/tags/{name}")).ok()?;
+    match object.as_tag().and_then(|tag| tag.tagger()) {
+        Some(tagger) => Some(tagger.when().seconds()),
+        None => Some(object.peel_to_commit().ok()?.time().seconds()),
+    }
+}
+
+/// Check whether an environment is falling behind, and describe how.
+///
+/// An environment is stale when its newest tag (snapshots aside) is older than
+/// `tag.stale_after_days`, or when origin has tags which the araki repo doesn't. The tags of
+/// origin are read from the cache unless it is older than a day. Returns a warning for each
+/// problem found.
+///
+/// * `repo`: araki repo
+pub fn staleness(repo: &Repository) -> Result<Vec<String>, String> {
+    let settings = get_settings()?.tag;
+    let prefix = common::tag_prefix(repo);
+    let snapshots = full_tag_name(repo, SNAPSHOT_TAG_PREFIX);
+    let local: Vec<String> = list_tags(repo)?
+        .into_iter()
+        .map(|tag| tag.name)
+        .filter(|name| !name.starts_with(&snapshots))
+        .collect();
+
+    let mut warnings = vec![];
+    let newest = local
+        .iter()
+        .filter_map(|name| Some((tag_time(repo, name)?, name)))
+        .max();
+    if let Some((time, name)) = newest
+        && settings.stale_after_days > 0
+    {
+        let days = (Local::now().timestamp() - time) / (24 * 60 * 60);
+        if days > settings.stale_after_days as i64 {
+            warnings.push(format!(
+                "The newest tag of the environment, {name}, is {days} days old. Consider \
+                updating it with `araki update`."
+            ));
+        }
+    }
+
+    // Registries are only read on pull, which imports the tags they hold
+    if repo.find_remote("origin").is_ok() && ArtifactOrigin::of(repo).is_none() {
+        let remote: Vec<String> = remote_tags(repo, Duration::from_secs(24 * 60 * 60))?
+            .into_keys()
+            .filter(|name| name.starts_with(&prefix) && !name.starts_with(&snapshots))
+            .filter(|name| !local.contains(name))
+            .collect();
+        if !remote.is_empty() {
+            warnings.push(format!(
+                "origin has tags which aren't available locally: {}. Run `araki pull` to get them.",
+                remote.join(", ")
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Get the tags of the `origin` remote, resolved to commits.
+///
+/// The tags are cached in the araki git dir; the remote is only contacted when the cache is
+/// older than `max_age`. In offline mode the cache is used however old it is.
+///
+/// * `repo`: araki repo
+/// * `max_age`: How old the cached tags may be before they are refreshed
+pub fn remote_tags(repo: &Repository, max_age: Duration) -> Result<BTreeMap<String, Oid>, String> {
+    let cache = repo.path().join(REMOTE_TAGS_CACHE_FILE);
+    let fresh = fs::metadata(&cache)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < max_age)
+        || network::is_offline();
+    if fresh && let Ok(contents) = fs::read_to_string(&cache) {
+        let cached: Option<BTreeMap<String, Oid>> = contents
+            .lines()
+            .map(|line| {
+                let (name, oid) = line.rsplit_once(' ')?;
+                Some((name.to_string(), Oid::from_str(oid).ok()?))
+            })
+            .collect();
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+    }
+
+    if let Some(origin) = ArtifactOrigin::of(repo) {
+        return Err(format!("origin is the registry {origin}, not a git remote"));
+    }
+    network::require_online("list the tags of origin")?;
+    let tags = common::git_ls_remote_tags(repo, "origin")
+        .map_err(|err| format!("Unable to list the tags of origin: {err}"))?;
+    let contents: String = tags
+        .iter()
+        .map(|(name, oid)| format!("{name} {oid}\n"))
+        .collect();
+    // The cache is only an optimization; failing to write it is not an error
+    let _ = fs::write(&cache, contents);
+    Ok(tags)
+}
+
+/// Compare the local tags of an araki repo with the tags of its remote.
+///
+/// * `repo`: araki repo
+/// * `remote`: Tags of the remote, as returned by [`remote_tags`]
+pub fn compare_tags(
+    repo: &Repository,
+    remote: &BTreeMap<String, Oid>,
+) -> Result<Vec<TagComparison>, String> {
+    let mut comparisons = vec![];
+    for tag in list_tags(repo)? {
+        let local = repo
+            .revparse_single(&tag.name)
+            .and_then(|obj| obj.peel_to_commit())
+            .map(|commit| commit.id())
+            .ok();
+        let sync = match remote.get(&tag.name) {
+            None => TagSync::LocalOnly,
+            Some(oid) if Some(*oid) == local => TagSync::Synced,
+            Some(_) => TagSync::Diverged,
+        };
+        comparisons.push(TagComparison {
+            name: tag.name,
+            message: tag.message,
+            sync,
+        });
+    }
+    let prefix = common::tag_prefix(repo);
+    for name in remote.keys().filter(|name| name.starts_with(&prefix)) {
+        if !comparisons.iter().any(|tag| &tag.name == name) {
+            comparisons.push(TagComparison {
+                name: name.clone(),
+                message: None,
+                sync: TagSync::RemoteOnly,
+            });
+        }
+    }
+    Ok(comparisons)
+}
+
+/// Fail if a tag is protected by the `[tag.protection]` settings.
+///
+/// * `name`: Name of the tag
+/// * `action`: What would be done to the tag, e.g. `delete`
+fn check_tag_protection(name: &str, action: &str) -> Result<(), String> {
+    if get_settings()?.tag.is_protected(name) {
+        return Err(format!(
+            "Tag {name} is protected by the [tag.protection] settings; refusing to {action} it."
+        ));
+    }
+    Ok(())
+}
+
+/// Commit the current lockspec files and create an annotated tag pointing at the new commit.
+///
+/// * `repo`: araki repo to tag
+/// * `name`: Name of the tag
+/// * `description`: Message of the tag
+/// * `force`: Move the tag if it already exists, unless it is protected
+pub fn tag(
+    repo: &Repository,
+    name: &str,
+    description: Option<&str>,
+    force: bool,
+) -> Result<Oid, String> {
+    let name = &full_tag_name(repo, name);
+    if force && repo.find_reference(&format!("refs/tags/{name}")).is_ok() {
+        check_tag_protection(name, "move")?;
+    }
+    let mut index = repo
+        .index()
+        .map_err(|err| format!("Failed to get index: {err}"))?;
+
+    // Add files
+    for file in LOCKSPEC_FILES {
+        index
+            .add_path(Path::new(&common::tracked_path(repo, file)))
+            .map_err(|err| format!("Unable to add {file}: {err}"))?;
+    }
+    index
+        .write()
+        .map_err(|err| format!("Failed to write index: {err}"))?;
+
+    let tree_oid = index
+        .write_tree()
+        .map_err(|err| format!("Failed to write tree: {err}"))?;
+    let tree = repo
+        .find_tree(tree_oid)
+        .map_err(|err| format!("Failed to find tree: {err}"))?;
+    get_settings()?.policy.check_tree(repo, &tree)?;
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    let parent_commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|err| format!("Failed to find parent commit: {err}"))?;
+
+    // Commit change
+    repo.commit(
+        Some("HEAD"),      // Update the HEAD reference
+        &signature,        // Author
+        &signature,        // Committer
+        name,              // Commit message
+        &tree,             // Tree containing the staged changes
+        &[&parent_commit], // Parent commit(s)
+    )
+    .map_err(|err| format!("Failed to create commit: {err}"))?;
+
+    // Create tag
+    let head = repo
+        .revparse_single("HEAD")
+        .map_err(|err| format!("Unable to find HEAD: {err}"))?;
+    create_tag(repo, name, &head, description, force)
+}
+
+/// Tag an existing commit of the lockspec, e.g. a state from its history known to work. Unlike
+/// [`tag`], nothing is committed and the working tree is left alone.
+///
+/// * `repo`: araki repo
+/// * `name`: Name of the tag
+/// * `reference`: Tag, branch, or (abbreviated) commit SHA to tag
+/// * `description`: Message of the tag; defaults to a generic one
+/// * `force`: Move the tag if it already exists, unless it is protected
+pub fn tag_ref(
+    repo: &Repository,
+    name: &str,
+    reference: &str,
+    description: Option<&str>,
+    force: bool,
+) -> Result<Oid, String> {
+    let name = &full_tag_name(repo, name);
+    if force && repo.find_reference(&format!("refs/tags/{name}")).is_ok() {
+        check_tag_protection(name, "move")?;
+    }
+    let commit = resolve_ref(repo, reference)?.commit;
+    get_settings()?
+        .policy
+        .check_tree(repo, &commit.tree().map_err(|err| err.to_string())?)?;
+    create_tag(repo, name, commit.as_object(), description, force)
+}
+
+/// Create an annotated tag.
+///
+/// * `repo`: araki repo
+/// * `name`: Full name of the tag
+/// * `target`: Object to tag
+/// * `description`: Message of the tag; defaults to a generic one
+/// * `force`: Replace the tag if it already exists
+fn create_tag(
+    repo: &Repository,
+    name: &str,
+    target: &git2::Object,
+    description: Option<&str>,
+    force: bool,
+) -> Result<Oid, String> {
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    let tag_message = match description {
+        Some(message) => message.to_string(),
+        None => {
+            let lockfile = common::read_rev_file(
+                repo,
+                &target.id().to_string(),
+                &common::tracked_path(repo, "pixi.lock"),
+            )
+            .ok()
+            .and_then(|contents| LockFile::parse(&String::from_utf8_lossy(&contents)).ok());
+            match (lockfile, target.peel_to_commit()) {
+                (Some(lockfile), Ok(commit)) => tag_message(repo, name, &lockfile, commit.id()),
+                _ => format!("araki environment tag: {name}"),
+            }
+        }
+    };
+
+    repo.tag(name, target, &signature, &tag_message, force)
+        .map_err(|err| format!("Unable to tag: {err}"))
+}
+
+/// Generate the message of a tag from the package changes since the previous tag: how many
+/// packages were added, removed, and updated, in total and in each ecosystem, followed by the
+/// changes. The first line is the total, so that it can be shown in tag lists.
+///
+/// The previous tag is the newest tag, snapshots aside, of an ancestor of the tagged commit.
+/// Without one, every package counts as added.
+///
+/// * `repo`: araki repo
+/// * `name`: Full name of the tag
+/// * `lockfile`: Lockfile of the tagged state
+/// * `commit`: Commit to tag
+pub fn tag_message(repo: &Repository, name: &str, lockfile: &LockFile, commit: Oid) -> String {
+    let snapshots = full_tag_name(repo, SNAPSHOT_TAG_PREFIX);
+    let previous = list_tags(repo)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tag| tag.name)
+        .filter(|tag| tag != name && !tag.starts_with(&snapshots))
+        .filter(|tag| {
+            repo.revparse_single(&format!("refs/tags/{tag}^{{commit}}"))
+                .is_ok_and(|tagged| {
+                    tagged.id() == commit
+                        || repo
+                            .graph_descendant_of(commit, tagged.id())
+                            .unwrap_or(false)
+                })
+        })
+        .filter_map(|tag| Some((tag_time(repo, &tag)?, tag)))
+        .max()
+        .map(|(_, tag)| tag);
+    let old = previous
+        .as_ref()
+        .and_then(|tag| lockfile_at(repo, tag).ok())
+        .unwrap_or_default();
+
+    let changes = LockFile::diff_by_kind(&old, lockfile);
+    let since = previous
+        .map(|tag| format!(" since {tag}"))
+        .unwrap_or_default();
+    if changes.is_empty() {
+        return format!("No package changes{since}\n");
+    }
+    let all: Vec<PackageChange> = changes.values().flatten().cloned().collect();
+    let mut message = format!("Package changes{since}: {}\n\n", count_changes(&all));
+    for (kind, changes) in &changes {
+        message.push_str(&format!("  {kind}: {}\n", count_changes(changes)));
+    }
+    message.push('\n');
+    for change in &all {
+        message.push_str(&format!("{change}\n"));
+    }
+    message
+}
+
+/// Record the current state of the lockspec as a lightweight snapshot tag, named after the
+/// current time, if its lockfile differs from both HEAD and the latest snapshot.
+///
+/// The snapshot is a commit on top of HEAD that no branch points to, so the working tree, index,
+/// and branches are left alone. Returns the name of the created tag, if any.
+///
+/// * `repo`: araki repo
+pub fn snapshot(repo: &Repository) -> Result<Option<String>, String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory".to_string())?;
+    let lockspec = LockSpec::from_path(workdir)?;
+    let lockfile = Oid::hash_file(ObjectType::Blob, lockspec.lockfile())
+        .map_err(|err| format!("Unable to read {:?}: {err}", lockspec.lockfile()))?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|err| format!("Unable to find HEAD: {err}"))?;
+
+    let prefix = full_tag_name(repo, SNAPSHOT_TAG_PREFIX);
+    let latest = repo
+        .tag_names(Some(&format!("{prefix}*")))
+        .ok()
+        .and_then(|names| names.iter().flatten().max().map(|name| name.to_string()))
+        .and_then(|name| {
+            repo.revparse_single(&format!("refs/tags/{name}^{{commit}}"))
+                .ok()
+        })
+        .and_then(|commit| commit.peel_to_commit().ok());
+    let lockfile_path = common::tracked_path(repo, "pixi.lock");
+    let unchanged = [Some(head.clone()), latest].iter().flatten().any(|commit| {
+        commit
+            .tree()
+            .and_then(|tree| tree.get_path(Path::new(&lockfile_path)))
+            .is_ok_and(|entry| entry.id() == lockfile)
+    });
+    if unchanged {
+        return Ok(None);
+    }
+
+    let mut index = Index::new().map_err(|err| format!("Failed to create an index: {err}"))?;
+    head.tree()
+        .and_then(|tree| index.read_tree(&tree))
+        .map_err(|err| format!("Failed to read the tree of HEAD: {err}"))?;
+    for (file, path) in LOCKSPEC_FILES
+        .iter()
+        .zip([lockspec.specfile(), lockspec.lockfile()])
+    {
+        let contents = fs::read(&path).map_err(|err| format!("Unable to read {path:?}: {err}"))?;
+        let blob = repo
+            .blob(&contents)
+            .map_err(|err| format!("Unable to store {file}: {err}"))?;
+        let entry = IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: contents.len() as u32,
+            id: blob,
+            flags: 0,
+            flags_extended: 0,
+            path: common::tracked_path(repo, file).into_bytes(),
+        };
+        index
+            .add(&entry)
+            .map_err(|err| format!("Unable to add {file}: {err}"))?;
+    }
+    let tree = index
+        .write_tree_to(repo)
+        .and_then(|oid| repo.find_tree(oid))
+        .map_err(|err| format!("Failed to write tree: {err}"))?;
+
+    let name = format!("{prefix}{}", Local::now().format("%Y%m%d-%H%M%S"));
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    let commit = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            &format!("araki snapshot: {name}"),
+            &tree,
+            &[&head],
+        )
+        .and_then(|oid| repo.find_object(oid, None))
+        .map_err(|err| format!("Failed to create commit: {err}"))?;
+    repo.tag_lightweight(&name, &commit, false)
+        .map_err(|err| format!("Unable to tag: {err}"))?;
+    Ok(Some(name))
+}
+
+/// Select the tags of an araki repo which its tag retention policy doesn't keep.
+///
+/// Besides protected tags, tags are pinned and never selected if HEAD points at them or a
+/// recorded project last checked them out.
+///
+/// * `repo`: araki repo
+/// * `settings`: Tag settings holding the retention policy
+pub fn prunable_tags(repo: &Repository, settings: &TagSettings) -> Result<Vec<String>, String> {
+    if !settings.retention.is_configured() {
+        return Err(
+            "No tag retention policy is set. Configure [tag.retention] or pass --keep-last or \
+            --keep-matching."
+                .to_string(),
+        );
+    }
+
+    let mut tags: Vec<(i64, String)> = list_tags(repo)?
+        .into_iter()
+        .map(|tag| {
+            let time = repo
+                .revparse_single(&format!("refs/tags/{}", tag.name))
+                .ok()
+                .and_then(|object| match object.as_tag() {
+                    Some(annotated) => annotated.tagger().map(|tagger| tagger.when().seconds()),
+                    None => object
+                        .peel_to_commit()
+                        .ok()
+                        .map(|commit| commit.time().seconds()),
+                })
+                .unwrap_or(0);
+            (time, tag.name)
+        })
+        .collect();
+    tags.sort_by(|a, b| b.cmp(a));
+
+    let head = repo.head().ok().and_then(|head| head.target());
+    let mut pinned: Vec<String> = projects::environment_name(repo)
+        .and_then(|environment| projects::users_of(&environment).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(_, record)| record.reference)
+        .map(|reference| full_tag_name(repo, &reference))
+        .collect();
+    pinned.extend(tags.iter().map(|(_, name)| name.clone()).filter(|name| {
+        let commit = repo
+            .revparse_single(&format!("refs/tags/{name}^{{commit}}"))
+            .map(|commit| commit.id())
+            .ok();
+        commit.is_some() && commit == head
+    }));
+
+    let names: Vec<&str> = tags.iter().map(|(_, name)| name.as_str()).collect();
+    Ok(settings
+        .prunable(&names)
+        .into_iter()
+        .filter(|name| !pinned.iter().any(|pin| pin == name))
+        .map(|name| name.to_string())
+        .collect())
+}
+
+/// Delete a tag from the araki repo, and optionally from its remote.
+///
+/// * `repo`: araki repo containing the tag
+/// * `name`: Name of the tag
+/// * `remote`: Also delete the tag from `origin`
+pub fn untag(repo: &Repository, name: &str, remote: bool) -> Result<(), String> {
+    let name = &full_tag_name(repo, name);
+    check_tag_protection(name, "delete")?;
+    if remote && let Some(origin) = ArtifactOrigin::of(repo) {
+        return Err(format!("Tags can't be deleted from the registry {origin}"));
+    }
+    if remote {
+        common::git_push("origin", &[&format!(":refs/tags/{name}")])
+            .map_err(|err| format!("Unable to delete {name} from origin: {err}"))?;
+    }
+    repo.tag_delete(name)
+        .map_err(|err| format!("Unable to delete tag {name}: {err}"))
+}
+
+/// A git reference resolved for checkout.
+struct ResolvedRef<'repo> {
+    commit: Commit<'repo>,
+
+    /// Full name of the local branch to attach HEAD to; HEAD is detached for anything else
+    branch: Option<String>,
+
+    /// Name of the tag, if the reference is a tag
+    tag: Option<String>,
+}
+
+/// Resolve a tag, branch, or (abbreviated) commit SHA. Tags take precedence over branches,
+/// which take precedence over SHAs.
+///
+/// * `repo`: araki repo to resolve the reference in
+/// * `name`: Reference to resolve; `latest` is the tip of `main`
+fn resolve_ref<'repo>(repo: &'repo Repository, name: &str) -> Result<ResolvedRef<'repo>, String> {
+    let name = if name == "latest" { "main" } else { name };
+    let peel = |reference: git2::Reference<'repo>| {
+        reference
+            .peel_to_commit()
+            .map_err(|err| format!("{name} does not point to a commit: {err}"))
+    };
+
+    for tag in [full_tag_name(repo, name), name.to_string()] {
+        if let Ok(reference) = repo.find_reference(&format!("refs/tags/{tag}")) {
+            return Ok(ResolvedRef {
+                commit: peel(reference)?,
+                branch: None,
+                tag: Some(tag),
+            });
+        }
+    }
+    let branch = format!("refs/heads/{name}");
+    if let Ok(reference) = repo.find_reference(&branch) {
+        return Ok(ResolvedRef {
+            commit: peel(reference)?,
+            branch: Some(branch),
+            tag: None,
+        });
+    }
+    // Branches which only exist on the remote are checked out detached
+    if let Ok(reference) = repo.find_reference(&format!("refs/remotes/origin/{name}")) {
+        return Ok(ResolvedRef {
+            commit: peel(reference)?,
+            branch: None,
+            tag: None,
+        });
+    }
+    let commit = repo
+        .revparse_single(name)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|_| format!("No tag, branch, or commit found: {name}"))?;
+    Ok(ResolvedRef {
+        commit,
+        branch: None,
+        tag: None,
+    })
+}
+
+/// Checkout a tag, branch, or commit of the lockspec and install it with pixi.
+///
+/// Local branches are checked out attached, so that new tags are committed on top of them;
+/// anything else leaves HEAD detached. What was checked out is recorded in the araki repo.
+///
+/// If `security.require_signed_tags` is set, only tags signed by an allowed signer can be
+/// checked out.
+///
+/// * `repo`: araki repo to checkout from
+/// * `tag`: Tag, branch, or commit SHA; `latest` checks out the tip of `main`, and `-` or
+///   `@{-N}` what was checked out before (see [`expand_previous_checkout`])
+pub fn checkout(repo: &Repository, tag: &str) -> Result<(), String> {
+    checkout_ref(repo, tag)?;
+    install(repo)
+}
+
+/// Install the checked out lockspec of an araki repo with pixi, in the pixi environments
+/// selected for the project.
+///
+/// * `repo`: araki repo
+pub fn install(repo: &Repository) -> Result<(), String> {
+    let output = pixi_install(repo, &[])?;
+    if !output.status.success() {
+        return Err(format!(
+            "pixi install failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory")?;
+    verify_install(&LockSpec::from_path(workdir)?, repo)
+}
+
+/// Install the checked out lockfile of an araki repo exactly as locked, without updating it,
+/// e.g. after a pull brought in a new one.
+///
+/// * `repo`: araki repo
+pub fn install_frozen(repo: &Repository) -> Result<(), String> {
+    let output = pixi_install(repo, &["--frozen"])?;
+    if !output.status.success() {
+        return Err(format!(
+            "pixi install --frozen failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory")?;
+    verify_install(&LockSpec::from_path(workdir)?, repo)
+}
+
+/// Get the directory of the lockspec of an araki repo.
+///
+/// * `repo`: araki repo
+fn lockspec_dir(repo: &Repository) -> Result<PathBuf, String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory")?;
+    Ok(match common::lockspec_subdir(repo) {
+        Some(subdir) => workdir.join(subdir),
+        None => workdir.to_path_buf(),
+    })
+}
+
+/// Run `pixi install` in the lockspec directory of an araki repo, with the configured mirrors,
+/// in the pixi environments selected for the project.
+///
+/// * `repo`: araki repo
+/// * `args`: Additional arguments of `pixi install`
+fn pixi_install(repo: &Repository, args: &[&str]) -> Result<Output, String> {
+    let dir = lockspec_dir(repo)?;
+    let mirrors = mirrors::apply(&dir)?;
+    let mut command = common::pixi_command();
+    command
+        .arg("install")
+        .args(args)
+        .args(common::pixi_environment_args(repo))
+        .current_dir(&dir);
+    mirrors.configure(&mut command);
+    command
+        .output()
+        .map_err(|err| format!("Failed to execute pixi install: {err}"))
+}
+
+/// Get the blob of the lockfile committed at HEAD of an araki repo, to tell whether an update
+/// changed it.
+///
+/// * `repo`: araki repo
+pub fn head_lockfile(repo: &Repository) -> Option<Oid> {
+    repo.head()
+        .and_then(|head| head.peel_to_tree())
+        .and_then(|tree| tree.get_path(Path::new(&common::tracked_path(repo, "pixi.lock"))))
+        .map(|entry| entry.id())
+        .ok()
+}
+
+/// Checkout a tag, branch, or commit of the lockspec without installing it, recording the
+/// checkout in the checkout history and the project registry.
+///
+/// * `repo`: araki repo
+/// * `tag`: Tag, branch, or commit to checkout
+pub fn checkout_ref(repo: &Repository, tag: &str) -> Result<(), String> {
+    let tag = &expand_previous_checkout(repo, tag)?;
+    let resolved = resolve_ref(repo, tag)?;
+    let security = get_settings()?.security;
+    if security.require_signed_tags {
+        let name = resolved.tag.as_deref().ok_or(format!(
+            "security.require_signed_tags is set, so only signed tags can be checked out, but \
+            {tag} is not a tag."
+        ))?;
+        security::verify_tag(repo, name, &security)?;
+    }
+    let commit = &resolved.commit;
+    common::checkout_lockspec(repo, commit.as_object(), CheckoutBuilder::new())
+        .map_err(|err| format!("Unable to checkout {tag}: {err}"))?;
+    match &resolved.branch {
+        Some(branch) => repo.set_head(branch),
+        None => repo.set_head_detached(commit.id()),
+    }
+    .map_err(|err| format!("Unable to set head: {err}"))?;
+    record_checkout(repo, tag, commit.id())?;
+    let _ = projects::record_checkout(repo, tag);
+    Ok(())
+}
+
+/// Checkout a tag, branch, or commit of a lockspec into a separate directory and install it
+/// there, leaving the project of the repo alone, e.g. to compare two tags side by side.
+///
+/// The directory gets its own araki repo, cloned from the local one, so that it can be used like
+/// any other project.
+///
+/// * `repo`: araki repo
+/// * `tag`: Tag, branch, or commit to checkout
+/// * `path`: Directory to checkout into; created if it doesn't exist
+pub fn checkout_to_path(repo: &Repository, tag: &str, path: &Path) -> Result<(), String> {
+    let tag = &expand_previous_checkout(repo, tag)?;
+    resolve_ref(repo, tag)?;
+    preflight::check_clone_target(path)?;
+    preflight::create_dir(path)?;
+
+    let git_dir = path.join(common::ARAKI_GIT_DIR_NAME);
+    let source = repo.path().to_string_lossy().to_string();
+    let copy = RepoBuilder::new()
+        .bare(true)
+        .clone(&source, &git_dir)
+        .map_err(|err| format!("Unable to copy the araki repo to {git_dir:?}: {err}"))?;
+    let origin = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|origin| origin.url().map(|url| url.to_string()));
+    match origin {
+        Some(url) => copy.remote_set_url("origin", &url),
+        None => copy.remote_delete("origin"),
+    }
+    .and_then(|_| copy.config()?.set_bool("core.bare", false))
+    .map_err(|err| format!("Unable to configure the araki repo in {git_dir:?}: {err}"))?;
+    if let Some(subdir) = common::lockspec_subdir(repo) {
+        let file = git_dir.join(common::LOCKSPEC_DIR_FILE);
+        fs::write(&file, format!("{}\n", subdir.display()))
+            .map_err(|err| format!("Unable to write {file:?}: {err}"))?;
+    }
+    drop(copy);
+    common::ignore_lockspec_files(path)?;
+
+    let copy = common::open_araki_git_repo(path)
+        .map_err(|err| format!("Couldn't recognize the araki repo in {path:?}: {err}"))?;
+    checkout(&copy, tag)
+}
+
+/// Get the environment variables to export when activating a lockspec: those declared in its
+/// `[araki.env]` table, overridden by the `[env]` table of the araki settings. Values
+/// referencing a secret are replaced by the secret.
+///
+/// * `lockspec`: Lockspec being activated
+pub fn activation_env(lockspec: &LockSpec) -> Result<BTreeMap<String, String>, String> {
+    let mut env = lockspec.env_vars()?;
+    env.extend(get_settings()?.env);
+    secrets::resolve_env(env)
+}
+
+/// Get the environment variables which pixi's activation of a lockspec sets, e.g. `PATH` and
+/// `CONDA_PREFIX`, from `pixi shell-hook --json`, in the pixi environment commands run in.
+///
+/// * `lockspec`: Lockspec being activated
+pub fn pixi_activation(lockspec: &LockSpec) -> Result<BTreeMap<String, String>, String> {
+    #[derive(Deserialize)]
+    struct ShellHook {
+        environment_variables: BTreeMap<String, String>,
+    }
+
+    let output = Command::new("pixi")
+        .args(["shell-hook", "--json", "--manifest-path"])
+        .arg(lockspec.specfile())
+        .args(
+            common::run_environment(&lockspec.dir())
+                .map(|environment| ["--environment".to_string(), environment])
+                .into_iter()
+                .flatten(),
+        )
+        .output()
+        .map_err(|err| format!("Failed to execute pixi shell-hook: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Unable to activate the environment: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice::<ShellHook>(&output.stdout)
+        .map(|hook| hook.environment_variables)
+        .map_err(|err| format!("Unable to parse the output of pixi shell-hook: {err}"))
+}
+
+/// Check that the lockfile of a lockspec installs on this platform.
+///
+/// The lockspec files are copied to a temporary directory and installed there with
+/// `pixi install --locked`, leaving the environment of the lockspec untouched.
+///
+/// * `lockspec`: Lockspec to verify
+pub fn verify_installable(lockspec: &LockSpec) -> Result<(), String> {
+    let dir = temp_dir().join(format!("araki-verify-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("Unable to create a temporary directory at {dir:?}: {err}"))?;
+
+    let result = (|| {
+        for file in LOCKSPEC_FILES {
+            fs::copy(lockspec.dir().join(file), dir.join(file))
+                .map_err(|err| format!("Unable to copy {file} to {dir:?}: {err}"))?;
+        }
+        let mirrors = mirrors::apply(&dir)?;
+        let mut command = common::pixi_command();
+        command
+            .args(["install", "--locked", "--color", "always"])
+            .current_dir(&dir);
+        mirrors.configure(&mut command);
+        let status = command
+            .status()
+            .map_err(|err| format!("Failed to execute pixi install: {err}"))?;
+        if !status.success() {
+            return Err(
+                "The lockfile could not be installed with `pixi install --locked`.".to_string(),
+            );
+        }
+        Ok(())
+    })();
+
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Find the locked packages which have newer versions available within the constraints of the
+/// specfile, grouped by ecosystem.
+///
+/// The lockspec files are copied to a temporary directory, where `pixi update --no-install`
+/// resolves them again against the configured channels and PyPI; the packages it updates are
+/// the outdated ones. The lockspec itself is left untouched.
+///
+/// * `lockspec`: Lockspec to check
+pub fn outdated(lockspec: &LockSpec) -> Result<BTreeMap<PackageKind, Vec<PackageChange>>, String> {
+    let dir = temp_dir().join(format!("araki-outdated-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("Unable to create a temporary directory at {dir:?}: {err}"))?;
+
+    let result = (|| {
+        for file in LOCKSPEC_FILES {
+            fs::copy(lockspec.dir().join(file), dir.join(file))
+                .map_err(|err| format!("Unable to copy {file} to {dir:?}: {err}"))?;
+        }
+        let mirrors = mirrors::apply(&dir)?;
+        let mut command = common::pixi_command();
+        command
+            .args(["update", "--no-install", "--color", "always"])
+            .current_dir(&dir);
+        mirrors.configure(&mut command);
+        let status = command
+            .status()
+            .map_err(|err| format!("Failed to execute pixi update: {err}"))?;
+        if !status.success() {
+            return Err("pixi update failed.".to_string());
+        }
+        let old = LockFile::from_path(&lockspec.lockfile())?;
+        let new = LockFile::from_path(&dir.join("pixi.lock"))?;
+        let mut changes = LockFile::diff_by_kind(&old, &new);
+        for changes in changes.values_mut() {
+            changes.retain(|change| matches!(change, PackageChange::Updated { .. }));
+        }
+        changes.retain(|_, changes| !changes.is_empty());
+        Ok(changes)
+    })();
+
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Stash uncommitted changes to the lockspec files, so that they can be recovered later with
+/// `git stash pop`.
+///
+/// * `repo`: araki repo whose changes should be stashed
+/// * `message`: Message describing the stash
+pub fn stash(repo: &mut Repository, message: &str) -> Result<Oid, String> {
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    repo.stash_save(&signature, message, None)
+        .map_err(|err| format!("Unable to stash changes: {err}"))
+}
+
+/// What was last checked out with [`checkout`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckoutRecord {
+    /// Tag, branch, or SHA as given to [`checkout`]
+    pub reference: String,
+    pub commit: Oid,
+}
+
+/// Get the path of the file recording the last checkout.
+///
+/// * `repo`: araki repo
+fn checkout_record_file(repo: &Repository) -> std::path::PathBuf {
+    repo.path().join(CHECKOUT_RECORD_FILE)
+}
+
+/// Record what was checked out, as `<reference> <commit>`, and add it to the checkout
+/// history unless the same reference was checked out last.
+///
+/// * `repo`: araki repo
+/// * `reference`: Reference that was checked out
+/// * `commit`: Commit the reference resolved to
+fn record_checkout(repo: &Repository, reference: &str, commit: Oid) -> Result<(), String> {
+    let line = format!("{reference} {commit}\n");
+    let path = checkout_record_file(repo);
+    fs::write(&path, &line).map_err(|err| format!("Unable to write {path:?}: {err}"))?;
+
+    let path = repo.path().join(CHECKOUT_HISTORY_FILE);
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<&str> = contents.lines().collect();
+    if lines
+        .last()
+        .and_then(|last| last.rsplit_once(' '))
+        .is_some_and(|(last, _)| last == reference)
+    {
+        lines.pop();
+    }
+    lines.push(line.trim_end());
+    let start = lines.len().saturating_sub(MAX_CHECKOUT_HISTORY);
+    fs::write(&path, lines[start..].join("\n") + "\n")
+        .map_err(|err| format!("Unable to write {path:?}: {err}"))
+}
+
+/// Get the checkouts made with [`checkout`], most recent first.
+///
+/// * `repo`: araki repo
+pub fn checkout_history(repo: &Repository) -> Vec<CheckoutRecord> {
+    let contents = fs::read_to_string(repo.path().join(CHECKOUT_HISTORY_FILE)).unwrap_or_default();
+    contents
+        .lines()
+        .rev()
+        .filter_map(|line| {
+            let (reference, commit) = line.rsplit_once(' ')?;
+            Some(CheckoutRecord {
+                reference: reference.to_string(),
+                commit: Oid::from_str(commit).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Expand `-` and `@{-N}` into the reference checked out before the current one, or N
+/// checkouts ago, like `git checkout -`. Any other reference is returned unchanged.
+///
+/// * `repo`: araki repo
+/// * `reference`: Reference to expand
+pub fn expand_previous_checkout(repo: &Repository, reference: &str) -> Result<String, String> {
+    let steps = match reference {
+        "-" => 1,
+        _ => match reference
+            .strip_prefix("@{-")
+            .and_then(|rest| rest.strip_suffix('}'))
+        {
+            Some(steps) => steps
+                .parse()
+                .map_err(|_| format!("Invalid checkout history reference: {reference}"))?,
+            None => return Ok(reference.to_string()),
+        },
+    };
+    checkout_history(repo)
+        .into_iter()
+        .nth(steps)
+        .map(|record| record.reference)
+        .ok_or(format!(
+            "There is no checkout {steps} before the current one. See `araki checkout --history`."
+        ))
+}
+
+/// Get what was last checked out with [`checkout`], if anything.
+///
+/// * `repo`: araki repo
+pub fn last_checkout(repo: &Repository) -> Option<CheckoutRecord> {
+    let contents = fs::read_to_string(checkout_record_file(repo)).ok()?;
+    let (reference, commit) = contents.trim().rsplit_once(' ')?;
+    Some(CheckoutRecord {
+        reference: reference.to_string(),
+        commit: Oid::from_str(commit).ok()?,
+    })
+}
+
+/// Get the name of the next version tag of the lockspec, following the `[tag]` settings.
+///
+/// * `repo`: araki repo
+/// * `bump`: Part of the version to increment
+pub fn next_tag(repo: &Repository, bump: Bump) -> Result<String, String> {
+    let settings = get_settings()?;
+    let existing = list_tags(repo)?;
+    let prefix = common::tag_prefix(repo);
+    Ok(tags::next_version(
+        existing
+            .iter()
+            .map(|tag| tag.name.strip_prefix(&prefix).unwrap_or(&tag.name)),
+        &settings.tag,
+        bump,
+    ))
+}
+
+/// Update the dependencies of a lockspec with `pixi update`, then commit and tag the new
+/// lockfile with the next version if it changed.
+///
+/// Returns the new tag and the package changes it holds, or `None` if nothing was updated.
+///
+/// * `repo`: araki repo of the lockspec
+/// * `packages`: Packages to update; all of them if empty
+/// * `bump`: Part of the version to increment for the new tag
+pub fn update(
+    repo: &Repository,
+    packages: &[String],
+    bump: Bump,
+) -> Result<Option<(String, Vec<PackageChange>)>, String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory".to_string())?;
+    let lockspec = LockSpec::from_path(workdir)?;
+    let mirrors = mirrors::apply(&lockspec.dir())?;
+    let mut command = common::pixi_command();
+    command
+        .args(["update", "--color", "always"])
+        .args(packages)
+        .current_dir(lockspec.dir());
+    mirrors.configure(&mut command);
+    let status = command
+        .status()
+        .map_err(|err| format!("Failed to execute pixi update: {err}"))?;
+    if !status.success() {
+        return Err("pixi update failed.".to_string());
+    }
+
+    let lockfile = Oid::hash_file(ObjectType::Blob, lockspec.lockfile())
+        .map_err(|err| format!("Unable to read {:?}: {err}", lockspec.lockfile()))?;
+    let unchanged = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .and_then(|tree| tree.get_path(Path::new(&common::tracked_path(repo, "pixi.lock"))))
+        .is_ok_and(|entry| entry.id() == lockfile);
+    if unchanged {
+        return Ok(None);
+    }
+
+    if get_settings()?.tag.verify {
+        verify_installable(&lockspec)?;
+    }
+    let name = full_tag_name(repo, &next_tag(repo, bump)?);
+    let description = match packages {
+        [] => "araki update of all packages".to_string(),
+        packages => format!("araki update of {}", packages.join(", ")),
+    };
+    tag(repo, &name, Some(&description), false)?;
+    Ok(Some((name.clone(), lock_changes(repo, &name)?)))
+}
+
+/// Get the package changes a commit made to the lockfile, compared to its parent.
+///
+/// A commit without a parent, or whose parent has no lockfile, adds every package.
+///
+/// * `repo`: araki repo containing the commit
+/// * `rev`: Revision of the commit, e.g. a tag name
+pub fn lock_changes(repo: &Repository, rev: &str) -> Result<Vec<PackageChange>, String> {
+    let new = lockfile_at(repo, rev)?;
+    let old = lockfile_at(repo, &format!("{rev}~1"))?;
+    Ok(LockFile::diff(&old, &new))
+}
+
+/// Read the lockfile of the lockspec as committed at a revision. A revision without a lockfile
+/// has no packages.
+///
+/// * `repo`: araki repo
+/// * `rev`: Revision to read the lockfile at, e.g. a tag name
+pub fn lockfile_at(repo: &Repository, rev: &str) -> Result<LockFile, String> {
+    match common::read_rev_file(repo, rev, &common::tracked_path(repo, "pixi.lock")) {
+        Ok(contents) => LockFile::parse(&String::from_utf8_lossy(&contents))
+            .map_err(|err| format!("Unable to parse pixi.lock at {rev}: {err}")),
+        Err(_) => Ok(LockFile::default()),
+    }
+}
+
+/// Get the package changes between two revisions of the lockspec, grouped by ecosystem.
+///
+/// * `repo`: araki repo
+/// * `from`: Older revision, e.g. a tag name
+/// * `to`: Newer revision
+pub fn changelog(
+    repo: &Repository,
+    from: &str,
+    to: &str,
+) -> Result<BTreeMap<PackageKind, Vec<PackageChange>>, String> {
+    for rev in [from, to] {
+        repo.revparse_single(&format!("{rev}^{{commit}}"))
+            .map_err(|_| format!("No tag, branch, or commit found: {rev}"))?;
+    }
+    Ok(LockFile::diff_by_kind(
+        &lockfile_at(repo, from)?,
+        &lockfile_at(repo, to)?,
+    ))
+}
+
+/// Get the names of the tags of the lockspec, snapshots aside, newest first.
+///
+/// * `repo`: araki repo
+pub fn newest_tags(repo: &Repository) -> Result<Vec<String>, String> {
+    let snapshots = full_tag_name(repo, SNAPSHOT_TAG_PREFIX);
+    let mut tags: Vec<(i64, String)> = list_tags(repo)?
+        .into_iter()
+        .filter(|tag| !tag.name.starts_with(&snapshots))
+        .filter_map(|tag| Some((tag_time(repo, &tag.name)?, tag.name)))
+        .collect();
+    tags.sort_by(|a, b| b.cmp(a));
+    Ok(tags.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Get the state of a lockspec relative to its araki repo.
+///
+/// * `lockspec`: Lockspec to check
+/// * `repo`: araki repo tracking the lockspec
+pub fn status(lockspec: &LockSpec, repo: &Repository) -> Result<Status, String> {
+    let head = repo
+        .head()
+        .map_err(|err| format!("Unable to get HEAD: {err}"))?;
+    let commit = head
+        .peel_to_commit()
+        .map_err(|err| format!("Unable to get the HEAD commit: {err}"))?;
+    let tag = list_tags(repo)?
+        .into_iter()
+        .find(|tag| {
+            repo.revparse_single(&tag.name)
+                .and_then(|obj| obj.peel_to_commit())
+                .is_ok_and(|tagged| tagged.id() == commit.id())
+        })
+        .map(|tag| tag.name);
+    let head_name = if repo.head_detached().unwrap_or(false) {
+        match (&tag, last_checkout(repo)) {
+            (Some(tag), _) => format!("detached at {tag}"),
+            (None, Some(record)) if record.commit == commit.id() => {
+                format!("detached at {}", record.reference)
+            }
+            _ => format!("detached at {:.7}", commit.id()),
+        }
+    } else {
+        head.shorthand().unwrap_or("HEAD").to_string()
+    };
+
+    let mut modified = vec![];
+    let mut untracked = vec![];
+    let mut deleted = vec![];
+    for file in LOCKSPEC_FILES {
+        let committed = common::read_head_file(repo, &lockspec.tracked_path(file)).ok();
+        let current = fs::read(lockspec.dir().join(file)).ok();
+        if committed == current {
+            continue;
+        }
+        match (&committed, &current) {
+            (None, Some(_)) => untracked.push(file.to_string()),
+            (Some(_), None) => deleted.push(file.to_string()),
+            _ => {}
+        }
+        modified.push(file.to_string());
+    }
+
+    Ok(Status {
+        head: head_name,
+        link: lockspec.link_state(repo),
+        tag,
+        modified,
+        untracked,
+        deleted,
+    })
+}
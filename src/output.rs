@@ -0,0 +1,175 @@
+use clap::ValueEnum;
+use console::{Term, style};
+use serde_json::{Value, json};
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio, exit};
+use std::sync::OnceLock;
+
+/// Format used to report progress and results of commands.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+
+    /// One JSON event per line on stdout
+    Jsonl,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Set the output format for the rest of the process. Only the first call has any effect.
+///
+/// * `format`: Format to use
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Get the output format in use.
+pub fn format() -> OutputFormat {
+    *FORMAT.get().unwrap_or(&OutputFormat::Text)
+}
+
+/// Print a JSON event on its own line.
+///
+/// * `event`: Type of the event
+/// * `fields`: Additional fields of the event
+fn emit(event: &str, fields: Value) {
+    let mut object = json!({ "event": event });
+    if let (Some(object), Value::Object(fields)) = (object.as_object_mut(), fields) {
+        object.extend(fields);
+    }
+    println!("{object}");
+}
+
+/// Report that a phase of a command has started.
+///
+/// * `step`: Number of the phase, starting at 1
+/// * `total`: Total number of phases
+/// * `message`: Description of the phase
+pub fn phase(step: usize, total: usize, message: &str) {
+    match format() {
+        OutputFormat::Text => println!(
+            "{} {message}",
+            style(format!("[{step}/{total}]")).bold().dim()
+        ),
+        OutputFormat::Jsonl => emit(
+            "phase",
+            json!({ "step": step, "total": total, "message": message }),
+        ),
+    }
+}
+
+/// Report progress within the current phase. Only reported in jsonl mode.
+///
+/// * `current`: Amount of work done
+/// * `total`: Total amount of work
+pub fn progress(current: usize, total: usize) {
+    if format() == OutputFormat::Jsonl {
+        emit("progress", json!({ "current": current, "total": total }));
+    }
+}
+
+/// Report an informational message.
+///
+/// * `message`: Message to report
+pub fn message(message: &str) {
+    match format() {
+        OutputFormat::Text => println!("{message}"),
+        OutputFormat::Jsonl => emit("message", json!({ "message": message })),
+    }
+}
+
+/// Report a warning.
+///
+/// * `message`: Warning to report
+pub fn warning(message: &str) {
+    match format() {
+        OutputFormat::Text => eprintln!("{} {message}", style("warning:").bold().yellow()),
+        OutputFormat::Jsonl => emit("warning", json!({ "message": message })),
+    }
+}
+
+/// Report an action that would have been taken if `--dry-run` had not been passed.
+///
+/// * `message`: Description of the skipped action
+pub fn dry_run(message: &str) {
+    match format() {
+        OutputFormat::Text => println!("{} {message}", style("[dry-run]").bold().yellow()),
+        OutputFormat::Jsonl => emit("dry_run", json!({ "message": message })),
+    }
+}
+
+/// Print text, through a pager if it is too long to fit on the terminal.
+///
+/// Uses `$PAGER`, falling back to `less -FRX`; the text is printed directly if stdout is not a
+/// terminal or the pager can't be started.
+///
+/// * `text`: Text to print
+pub fn page(text: &str) {
+    let term = Term::stdout();
+    let fits = term
+        .size_checked()
+        .is_none_or(|(rows, _)| text.lines().count() < rows as usize);
+    if format() == OutputFormat::Jsonl || !term.is_term() || fits {
+        print!("{text}");
+        return;
+    }
+
+    let pager = env::var("PAGER").unwrap_or("less -FRX".to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        print!("{text}");
+        return;
+    };
+    let child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The user may quit the pager before reading everything
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            let _ = child.wait();
+        }
+        Err(_) => print!("{text}"),
+    }
+}
+
+/// Report that the command succeeded.
+///
+/// * `message`: Summary of the result
+pub fn success(message: &str) {
+    match format() {
+        OutputFormat::Text => println!("{message}"),
+        OutputFormat::Jsonl => emit("result", json!({ "status": "ok", "message": message })),
+    }
+}
+
+/// Ask the user to confirm an action on the terminal. Returns `false` without asking when stdin
+/// is not a terminal or the output is machine-readable; commands offer `--yes` for those cases.
+///
+/// * `prompt`: Question to ask, answered with y or n
+pub fn confirm(prompt: &str) -> bool {
+    if format() == OutputFormat::Jsonl || !io::stdin().is_terminal() {
+        return false;
+    }
+    eprint!("{prompt} [y/N] ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Report that the command failed and exit.
+///
+/// * `message`: Reason for the failure
+pub fn fail(message: &str) -> ! {
+    match format() {
+        OutputFormat::Text => eprintln!("{message}"),
+        OutputFormat::Jsonl => emit("result", json!({ "status": "error", "message": message })),
+    }
+    exit(1);
+}
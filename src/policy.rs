@@ -0,0 +1,125 @@
+//! Content policies of lockspec repositories, checked before tagging and pushing.
+//!
+//! Lockspec repositories should hold a handful of small text files. The `[policy]` section of
+//! `araki.toml` limits their size and forbids file patterns (data files, notebooks, ...), so that
+//! they don't silently turn into data dumps.
+
+use git2::{ObjectType, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::common;
+use crate::tags::glob_match;
+
+const MB: u64 = 1024 * 1024;
+
+/// Content policy, configured in the `[policy]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PolicySettings {
+    /// Maximum size of the araki repo, history included, in megabytes
+    pub max_repo_mb: Option<u64>,
+
+    /// Maximum size of pixi.lock, in megabytes
+    pub max_lockfile_mb: Option<u64>,
+
+    /// Glob patterns (`*` matches any characters) of files which may not be committed, matched
+    /// against their path in the repo and their name, e.g. `*.csv` or `data/*`
+    pub forbidden: Vec<String>,
+}
+
+impl PolicySettings {
+    /// Check whether any policy is set.
+    pub fn is_configured(&self) -> bool {
+        self.max_repo_mb.is_some() || self.max_lockfile_mb.is_some() || !self.forbidden.is_empty()
+    }
+
+    /// Check a tree of an araki repo, and the size of the repo, against the policy.
+    ///
+    /// * `repo`: araki repo
+    /// * `tree`: Tree to check, e.g. of a commit about to be tagged
+    pub fn check_tree(&self, repo: &Repository, tree: &Tree) -> Result<(), String> {
+        if !self.is_configured() {
+            return Ok(());
+        }
+        let lockfile = common::tracked_path(repo, "pixi.lock");
+        let mut violations = vec![];
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() != Some(ObjectType::Blob) {
+                return TreeWalkResult::Ok;
+            }
+            let name = entry.name().unwrap_or_default();
+            let path = format!("{dir}{name}");
+            if let Some(pattern) = self
+                .forbidden
+                .iter()
+                .find(|pattern| glob_match(pattern, &path) || glob_match(pattern, name))
+            {
+                violations.push(format!("{path} matches the forbidden pattern {pattern}"));
+            }
+            if path == lockfile
+                && let Some(max) = self.max_lockfile_mb
+                && let Ok(blob) = repo.find_blob(entry.id())
+                && blob.size() as u64 > max * MB
+            {
+                violations.push(format!(
+                    "{path} is {:.1} MB, above policy.max_lockfile_mb ({max} MB)",
+                    blob.size() as f64 / MB as f64
+                ));
+            }
+            TreeWalkResult::Ok
+        })
+        .map_err(|err| format!("Unable to list the files to check: {err}"))?;
+
+        if let Some(max) = self.max_repo_mb {
+            let size = dir_size(repo.path());
+            if size > max * MB {
+                violations.push(format!(
+                    "the repository is {:.1} MB, above policy.max_repo_mb ({max} MB)",
+                    size as f64 / MB as f64
+                ));
+            }
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(format!(
+                "The lockspec repository violates the [policy] settings:\n  {}",
+                violations.join("\n  ")
+            )),
+        }
+    }
+
+    /// Check a revision of an araki repo, and the size of the repo, against the policy.
+    ///
+    /// * `repo`: araki repo
+    /// * `rev`: Tag, branch, or commit to check
+    pub fn check_rev(&self, repo: &Repository, rev: &str) -> Result<(), String> {
+        if !self.is_configured() {
+            return Ok(());
+        }
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|err| format!("Unable to find {rev}: {err}"))?;
+        self.check_tree(repo, &tree)
+    }
+}
+
+/// Get the total size of the files in a directory and its subdirectories.
+///
+/// * `dir`: Directory to measure
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
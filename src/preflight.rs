@@ -0,0 +1,197 @@
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+use uuid::Uuid;
+
+use crate::common::{ARAKI_GIT_DIR_NAME, LockSpec};
+
+/// Minimum free space required at the target before a lockspec is cloned and installed.
+pub const MIN_FREE_SPACE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Check that a lockspec can be cloned into the given directory.
+///
+/// This is meant to be run before any cloning starts, so that failures don't leave behind a
+/// partially-cloned lockspec.
+///
+/// * `path`: Directory where the lockspec is going to be cloned
+pub fn check_clone_target(path: &Path) -> Result<(), String> {
+    check_no_lockspec(path)?;
+    check_writable(path)?;
+    check_free_space(path, MIN_FREE_SPACE_BYTES)
+}
+
+/// Check that the directory doesn't already contain a lockspec, or part of one.
+///
+/// * `path`: Directory to check
+pub fn check_no_lockspec(path: &Path) -> Result<(), String> {
+    if LockSpec::from_path(path).is_ok() {
+        return Err(format!("A lockspec already exists at {path:?}."));
+    }
+
+    let lockspec = LockSpec {
+        path: path.to_path_buf(),
+        subdir: None,
+    };
+    for file in [lockspec.specfile(), lockspec.lockfile()] {
+        if file.exists() {
+            return Err(format!(
+                "{file:?} already exists and would be overwritten by the cloned lockspec."
+            ));
+        }
+    }
+
+    let git_dir = path.join(ARAKI_GIT_DIR_NAME);
+    if git_dir.exists() {
+        return Err(format!(
+            "{path:?} is already managed by araki ({git_dir:?} exists)."
+        ));
+    }
+    Ok(())
+}
+
+/// Check that files can be created in the directory.
+///
+/// If the directory doesn't exist yet, the closest existing ancestor is checked instead, since
+/// that's where the directory will be created.
+///
+/// * `path`: Directory to check
+pub fn check_writable(path: &Path) -> Result<(), String> {
+    let dir = nearest_existing_ancestor(path)
+        .ok_or(format!("No existing parent directory found for {path:?}."))?;
+    if !dir.is_dir() {
+        return Err(format!("{dir:?} is not a directory."));
+    }
+
+    let probe = dir.join(format!(".araki-write-check-{}", Uuid::new_v4()));
+    fs::write(&probe, b"").map_err(|err| format!("{dir:?} is not writable. Reason: {err}"))?;
+    fs::remove_file(&probe)
+        .map_err(|err| format!("Unable to clean up write check file {probe:?}: {err}"))?;
+    Ok(())
+}
+
+/// Create a directory and its missing parents, after checking that they can be created.
+///
+/// Unlike [`fs::create_dir_all`], a failure doesn't leave part of the tree behind, and nothing is
+/// created by root inside a tree owned by another user (e.g. in the home directory of a user
+/// running araki with sudo), which would leave directories that user can't modify.
+///
+/// * `path`: Directory to create
+pub fn create_dir(path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        return Ok(());
+    }
+    let ancestor = nearest_existing_ancestor(path)
+        .ok_or(format!("No existing parent directory found for {path:?}."))?;
+    let owner = fs::metadata(&ancestor)
+        .map_err(|err| format!("Unable to read the permissions of {ancestor:?}: {err}"))?;
+    check_writable(path).map_err(|err| {
+        format!(
+            "{err} It is owned by uid {} with mode {:o}; ask its owner for write access, or use \
+            a directory you own.",
+            owner.uid(),
+            owner.mode() & 0o7777
+        )
+    })?;
+
+    // Whoever runs araki owns what it creates; as root, that is only fine in root's own trees
+    let probe = ancestor.join(format!(".araki-owner-check-{}", Uuid::new_v4()));
+    let creator = fs::write(&probe, b"")
+        .and_then(|_| fs::metadata(&probe))
+        .map(|metadata| metadata.uid());
+    let _ = fs::remove_file(&probe);
+    if creator.is_ok_and(|uid| uid == 0) && owner.uid() != 0 {
+        return Err(format!(
+            "araki is running as root, but {ancestor:?} belongs to uid {}. Creating {path:?} \
+            would leave directories that user can't modify; run araki without sudo.",
+            owner.uid()
+        ));
+    }
+
+    let missing: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+    for (created, dir) in missing.iter().rev().enumerate() {
+        if let Err(err) = fs::create_dir(dir) {
+            for dir in missing.iter().rev().take(created).rev() {
+                let _ = fs::remove_dir(dir);
+            }
+            return Err(format!("Unable to create {dir:?}: {err}"));
+        }
+    }
+    Ok(())
+}
+
+/// Check that the disk containing the directory has at least `required` bytes available.
+///
+/// If the disk can't be determined, the check is skipped rather than failing.
+///
+/// * `path`: Directory to check
+/// * `required`: Number of bytes which must be available
+pub fn check_free_space(path: &Path, required: u64) -> Result<(), String> {
+    let Some(dir) = nearest_existing_ancestor(path).and_then(|dir| dir.canonicalize().ok()) else {
+        return Ok(());
+    };
+
+    // The disk holding the directory is the one with the longest matching mount point
+    let disks = Disks::new_with_refreshed_list();
+    let disk = disks
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len());
+
+    match disk {
+        Some(disk) if disk.available_space() < required => Err(format!(
+            "Not enough free space at {dir:?}: {} MiB available, at least {} MiB required.",
+            disk.available_space() / (1024 * 1024),
+            required / (1024 * 1024),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Return the path itself if it exists, otherwise its closest existing ancestor.
+///
+/// * `path`: Path to start searching from
+fn nearest_existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .map(|ancestor| ancestor.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_check_no_lockspec() {
+        let dir = temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).expect("should be able to create a temp dir");
+
+        // An empty directory is a valid target
+        assert!(check_no_lockspec(&dir).is_ok());
+
+        // A lone pixi.toml would be overwritten, so the target is rejected
+        fs::write(dir.join("pixi.toml"), "").expect("should be able to write pixi.toml");
+        assert!(check_no_lockspec(&dir).is_err());
+
+        fs::remove_dir_all(&dir).expect("should be able to remove the temp dir");
+    }
+
+    #[test]
+    fn test_create_dir() {
+        let dir = temp_dir().join(Uuid::new_v4().to_string());
+        let nested = dir.join("a").join("b");
+        assert!(create_dir(&nested).is_ok());
+        assert!(nested.is_dir());
+
+        // Creating a directory which already exists is fine
+        assert!(create_dir(&nested).is_ok());
+
+        // Nothing can be created below a file, and nothing is left behind trying
+        fs::write(dir.join("file"), "").expect("should be able to write a file");
+        assert!(create_dir(&dir.join("file").join("c")).is_err());
+        assert!(!dir.join("file").join("c").exists());
+
+        fs::remove_dir_all(&dir).expect("should be able to remove the temp dir");
+    }
+}
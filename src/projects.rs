@@ -0,0 +1,292 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::common::{self, LockSpec};
+use crate::remote::parse_repo_arg;
+
+/// File in the araki directory recording which projects use which environments.
+const PROJECTS_FILE: &str = "projects.json";
+
+/// The environment a project uses, as last recorded by clone or checkout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectRecord {
+    /// `<org>/<repo>` of the lockspec, followed by `#<subdir>` for lockspecs in a subdirectory
+    pub environment: String,
+
+    /// Tag, branch, or commit last checked out; unset right after cloning
+    pub reference: Option<String>,
+
+    /// When the record was last updated, in RFC 3339 format
+    pub updated: String,
+
+    /// When a command last ran in the environment of the project, in RFC 3339 format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<String>,
+
+    /// When the environment of the project was last used (run, activated, or synced), in
+    /// RFC 3339 format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<String>,
+}
+
+impl ProjectRecord {
+    /// Get when the environment of the project was last used, counting its clone or checkout
+    /// as a use, so that projects recorded before usage was tracked have a date too.
+    pub fn last_used(&self) -> Option<DateTime<Utc>> {
+        [&self.last_used, &self.last_run, &Some(self.updated.clone())]
+            .into_iter()
+            .flatten()
+            .filter_map(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc))
+            .max()
+    }
+}
+
+/// Get the path of the file recording the projects.
+fn get_projects_file() -> Result<PathBuf, String> {
+    Ok(common::get_araki_dir()?.join(PROJECTS_FILE))
+}
+
+/// Load the recorded projects, keyed by project directory.
+pub fn load() -> Result<BTreeMap<PathBuf, ProjectRecord>, String> {
+    read(&get_projects_file()?)
+}
+
+/// Read the recorded projects from a file. The file is only ever replaced whole, so it can be
+/// read without holding its lock.
+///
+/// * `path`: File recording the projects
+fn read(path: &Path) -> Result<BTreeMap<PathBuf, ProjectRecord>, String> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let contents =
+        fs::read_to_string(path).map_err(|err| format!("Unable to read {path:?}: {err}"))?;
+    serde_json::from_str(&contents).map_err(|err| format!("Unable to parse {path:?}: {err}"))
+}
+
+/// Write the recorded projects to a temporary file, then rename it over the projects file.
+///
+/// * `path`: File recording the projects
+/// * `projects`: Projects keyed by project directory
+fn save(path: &Path, projects: &BTreeMap<PathBuf, ProjectRecord>) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(projects)
+        .map_err(|err| format!("Unable to serialize the projects: {err}"))?;
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}.tmp", Uuid::new_v4()));
+    let temp = PathBuf::from(name);
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        writeln!(file, "{contents}")?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.map_err(|err| format!("Unable to write {path:?}: {err}"))
+}
+
+/// Open the lock file of the projects file, creating it if needed.
+///
+/// * `path`: File recording the projects
+fn lock_file(path: &Path) -> Result<File, String> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    let lock = PathBuf::from(name);
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock)
+        .map_err(|err| format!("Unable to open {lock:?}: {err}"))
+}
+
+/// Change the recorded projects. The projects are loaded, changed, and saved while holding a
+/// lock on the projects file, so that commands updating them at the same time don't lose each
+/// other's changes. The file is only written if the projects changed.
+///
+/// * `change`: Changes the projects, keyed by project directory
+fn update<T>(change: impl FnOnce(&mut BTreeMap<PathBuf, ProjectRecord>) -> T) -> Result<T, String> {
+    let path = get_projects_file()?;
+    let lock = lock_file(&path)?;
+    lock.lock()
+        .map_err(|err| format!("Unable to lock {path:?}: {err}"))?;
+    let result = (|| {
+        let before = read(&path)?;
+        let mut projects = before.clone();
+        let value = change(&mut projects);
+        if projects != before {
+            save(&path, &projects)?;
+        }
+        Ok(value)
+    })();
+    let _ = lock.unlock();
+    result
+}
+
+/// Record the environment a project uses.
+///
+/// * `project`: Project directory
+/// * `environment`: `<org>/<repo>` of the lockspec
+/// * `reference`: Tag, branch, or commit checked out, if any
+pub fn record(project: &Path, environment: &str, reference: Option<&str>) -> Result<(), String> {
+    let project = project
+        .canonicalize()
+        .map_err(|err| format!("Unable to resolve {project:?}: {err}"))?;
+    update(|projects| {
+        let (last_run, last_used) = projects
+            .get(&project)
+            .map(|record| (record.last_run.clone(), record.last_used.clone()))
+            .unwrap_or_default();
+        projects.insert(
+            project,
+            ProjectRecord {
+                environment: environment.to_string(),
+                reference: reference.map(|reference| reference.to_string()),
+                updated: now(),
+                last_run,
+                last_used,
+            },
+        );
+    })
+}
+
+/// Record that a command ran in the environment of a project. Projects which aren't recorded
+/// are left alone.
+///
+/// * `project`: Project directory
+pub fn record_run(project: &Path) -> Result<(), String> {
+    let project = project
+        .canonicalize()
+        .map_err(|err| format!("Unable to resolve {project:?}: {err}"))?;
+    update(|projects| {
+        if let Some(record) = projects.get_mut(&project) {
+            record.last_run = Some(now());
+            record.last_used = record.last_run.clone();
+        }
+    })
+}
+
+/// Record that the environment of a project was used, e.g. activated by the shell hook or
+/// synced. Projects which aren't recorded are left alone.
+///
+/// * `project`: Project directory
+pub fn record_use(project: &Path) -> Result<(), String> {
+    let project = project
+        .canonicalize()
+        .map_err(|err| format!("Unable to resolve {project:?}: {err}"))?;
+    update(|projects| {
+        if let Some(record) = projects.get_mut(&project) {
+            record.last_used = Some(now());
+        }
+    })
+}
+
+/// Get the current time in the format of the records.
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Get the name under which the projects using an araki repo are recorded, from the `origin`
+/// remote of the repo.
+///
+/// * `repo`: araki repo
+pub fn environment_name(repo: &Repository) -> Option<String> {
+    let remote = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|origin| origin.url().map(|url| url.to_string()))
+        .and_then(|url| parse_repo_arg(&url).ok())?;
+    let environment = remote.short_name();
+    match common::lockspec_subdir(repo).and_then(|subdir| {
+        subdir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }) {
+        Some(subdir) => Some(format!("{environment}#{subdir}")),
+        None => Some(environment),
+    }
+}
+
+/// Record a checkout in the project of an araki repo. The environment is identified by the
+/// `origin` remote of the repo; repos without one are not recorded.
+///
+/// * `repo`: araki repo
+/// * `reference`: Tag, branch, or commit checked out
+pub fn record_checkout(repo: &Repository, reference: &str) -> Result<(), String> {
+    let (Some(workdir), Some(environment)) = (repo.workdir(), environment_name(repo)) else {
+        return Ok(());
+    };
+    record(workdir, &environment, Some(reference))
+}
+
+/// Forget a project, e.g. once its lockspec is deleted. Projects which aren't recorded are
+/// left alone.
+///
+/// * `project`: Project directory
+pub fn forget(project: &Path) -> Result<(), String> {
+    let project = project
+        .canonicalize()
+        .map_err(|err| format!("Unable to resolve {project:?}: {err}"))?;
+    update(|projects| {
+        projects.remove(&project);
+    })
+}
+
+/// Forget the projects which no longer contain a lockspec.
+///
+/// Returns the directories of the forgotten projects.
+pub fn prune() -> Result<Vec<PathBuf>, String> {
+    update(|projects| {
+        let stale: Vec<PathBuf> = projects
+            .keys()
+            .filter(|project| LockSpec::from_path(project).is_err())
+            .cloned()
+            .collect();
+        for project in &stale {
+            projects.remove(project);
+        }
+        stale
+    })
+}
+
+/// Find the recorded project containing a directory: the directory itself, or the closest of
+/// its parents which is recorded.
+///
+/// * `dir`: Directory in the project, e.g. the current directory
+pub fn find(dir: &Path) -> Result<Option<(PathBuf, ProjectRecord)>, String> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|err| format!("Unable to resolve {dir:?}: {err}"))?;
+    let mut projects = load()?;
+    Ok(dir
+        .ancestors()
+        .find_map(|ancestor| projects.remove_entry(ancestor)))
+}
+
+/// Get the projects using an environment.
+///
+/// * `environment`: `<org>/<repo>` of the lockspec; lockspecs in subdirectories of the repo
+///   match as well
+pub fn users_of(environment: &str) -> Result<Vec<(PathBuf, ProjectRecord)>, String> {
+    Ok(load()?
+        .into_iter()
+        .filter(|(_, record)| {
+            record.environment == environment
+                || record
+                    .environment
+                    .strip_prefix(environment)
+                    .is_some_and(|rest| rest.starts_with('#'))
+        })
+        .collect())
+}
@@ -0,0 +1,135 @@
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use std::env::current_dir;
+use std::path::PathBuf;
+
+use crate::common::{self, LinkState, LockSpec};
+use crate::ops;
+
+/// Convert an araki error message into a Python exception.
+fn to_py_err(err: impl ToString) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
+}
+
+/// Resolve an optional path argument, defaulting to the current directory.
+///
+/// * `path`: Path passed from Python
+fn resolve_path(path: Option<PathBuf>) -> PyResult<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => current_dir().map_err(to_py_err),
+    }
+}
+
+/// A tag of an araki-managed lockspec.
+#[pyclass(name = "Tag", frozen, get_all)]
+#[derive(Clone)]
+struct PyTag {
+    name: String,
+    message: Option<String>,
+}
+
+#[pymethods]
+impl PyTag {
+    fn __repr__(&self) -> String {
+        format!("Tag(name={:?}, message={:?})", self.name, self.message)
+    }
+}
+
+/// State of a lockspec relative to its araki repo.
+#[pyclass(name = "Status", frozen, get_all)]
+#[derive(Clone)]
+struct PyStatus {
+    head: String,
+    linked: bool,
+    modified: Vec<String>,
+}
+
+#[pymethods]
+impl PyStatus {
+    fn __repr__(&self) -> String {
+        format!(
+            "Status(head={:?}, linked={}, modified={:?})",
+            self.head, self.linked, self.modified
+        )
+    }
+}
+
+/// Clone a lockspec and install it with pixi.
+#[pyfunction]
+#[pyo3(signature = (env, path=None, tag=None))]
+fn clone(py: Python<'_>, env: String, path: Option<PathBuf>, tag: Option<String>) -> PyResult<()> {
+    let path = resolve_path(path)?;
+    py.detach(|| ops::clone(&env, &path, tag.as_deref(), None, &[], &|_| {}))
+        .map_err(to_py_err)
+}
+
+/// List the tags of the lockspec at `path`.
+#[pyfunction]
+#[pyo3(signature = (path=None))]
+fn list_tags(path: Option<PathBuf>) -> PyResult<Vec<PyTag>> {
+    let repo = common::open_araki_git_repo(&resolve_path(path)?).map_err(to_py_err)?;
+    Ok(ops::list_tags(&repo)
+        .map_err(to_py_err)?
+        .into_iter()
+        .map(|tag| PyTag {
+            name: tag.name,
+            message: tag.message,
+        })
+        .collect())
+}
+
+/// Commit the lockspec at `path` and tag the new commit.
+#[pyfunction]
+#[pyo3(signature = (name, description=None, path=None, force=false))]
+fn tag(
+    name: String,
+    description: Option<String>,
+    path: Option<PathBuf>,
+    force: bool,
+) -> PyResult<()> {
+    let repo = common::open_araki_git_repo(&resolve_path(path)?).map_err(to_py_err)?;
+    ops::tag(&repo, &name, description.as_deref(), force)
+        .map(|_| ())
+        .map_err(to_py_err)
+}
+
+/// Checkout a tag of the lockspec at `path` and install it with pixi.
+#[pyfunction]
+#[pyo3(signature = (tag, path=None))]
+fn checkout(py: Python<'_>, tag: String, path: Option<PathBuf>) -> PyResult<()> {
+    let path = resolve_path(path)?;
+    py.detach(|| {
+        let repo = common::open_araki_git_repo(&path).map_err(|err| err.to_string())?;
+        ops::checkout(&repo, &tag)
+    })
+    .map_err(to_py_err)
+}
+
+/// Get the state of the lockspec at `path` relative to its araki repo.
+#[pyfunction]
+#[pyo3(signature = (path=None))]
+fn status(path: Option<PathBuf>) -> PyResult<PyStatus> {
+    let path = resolve_path(path)?;
+    let lockspec = LockSpec::from_path(&path).map_err(to_py_err)?;
+    let repo = common::open_araki_git_repo(&path).map_err(to_py_err)?;
+    let status = ops::status(&lockspec, &repo).map_err(to_py_err)?;
+    Ok(PyStatus {
+        head: status.head,
+        linked: status.link == LinkState::Linked,
+        modified: status.modified,
+    })
+}
+
+/// Python bindings for araki.
+#[pymodule]
+fn araki(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTag>()?;
+    m.add_class::<PyStatus>()?;
+    m.add_function(wrap_pyfunction!(clone, m)?)?;
+    m.add_function(wrap_pyfunction!(list_tags, m)?)?;
+    m.add_function(wrap_pyfunction!(tag, m)?)?;
+    m.add_function(wrap_pyfunction!(checkout, m)?)?;
+    m.add_function(wrap_pyfunction!(status, m)?)?;
+    Ok(())
+}
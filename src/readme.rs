@@ -0,0 +1,193 @@
+use git2::{Index, IndexEntry, IndexTime, Repository, Signature};
+use std::fs;
+use toml::{Table, Value};
+
+use crate::common::{self, LockSpec};
+use crate::ops::SNAPSHOT_TAG_PREFIX;
+use crate::remote::parse_repo_arg;
+
+/// Name of the README generated at the root of lockspec repositories. It is only meant for
+/// browsing the repository on its forge, so it isn't copied into projects cloning the lockspec.
+pub const README_FILE: &str = "README.md";
+
+/// Number of dependencies listed in a generated README.
+const MAX_DEPENDENCIES: usize = 15;
+
+/// Number of tags listed in a generated README.
+const MAX_TAGS: usize = 5;
+
+/// Render a README describing the lockspec of an araki repo: its name and description, how to
+/// clone it, its top dependencies, and its latest tags.
+///
+/// * `repo`: araki repo
+/// * `lockspec`: Lockspec of the repo
+pub fn render(repo: &Repository, lockspec: &LockSpec) -> String {
+    let manifest: Table = fs::read_to_string(lockspec.specfile())
+        .ok()
+        .and_then(|contents| contents.parse().ok())
+        .unwrap_or_default();
+    let name = lockspec.display_name();
+    let mut readme = format!("# {name}\n\n");
+
+    let description = ["workspace", "project"]
+        .iter()
+        .find_map(|table| manifest.get(*table)?.get("description")?.as_str());
+    if let Some(description) = description {
+        readme.push_str(&format!("{description}\n\n"));
+    }
+
+    let source = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|origin| parse_repo_arg(origin.url()?).ok())
+        .map(|remote| remote.short_name())
+        .unwrap_or(name);
+    readme.push_str(&format!(
+        "This environment is managed with [araki](https://github.com/peytondmurray/araki). \
+        Install it in a project with\n\n```\n$ araki clone {source}\n```\n"
+    ));
+
+    if let Some(dependencies) = manifest
+        .get("dependencies")
+        .and_then(|deps| deps.as_table())
+    {
+        readme.push_str("\n## Dependencies\n\n");
+        for (package, spec) in dependencies.iter().take(MAX_DEPENDENCIES) {
+            let spec = match spec {
+                Value::String(version) => version.clone(),
+                Value::Table(table) => table
+                    .get("version")
+                    .and_then(|version| version.as_str())
+                    .unwrap_or("*")
+                    .to_string(),
+                _ => "*".to_string(),
+            };
+            readme.push_str(&format!("- `{package}` {spec}\n"));
+        }
+        if dependencies.len() > MAX_DEPENDENCIES {
+            readme.push_str(&format!(
+                "- ... and {} more, see `pixi.toml`\n",
+                dependencies.len() - MAX_DEPENDENCIES
+            ));
+        }
+    }
+
+    let tags = latest_tags(repo);
+    if !tags.is_empty() {
+        readme.push_str("\n## Latest tags\n\n");
+        for tag in tags {
+            readme.push_str(&format!("- `{tag}`\n"));
+        }
+        readme.push_str(&format!(
+            "\nCheckout a tag with `araki checkout <tag>`, or clone it directly with \
+            `araki clone {source} --tag <tag>`.\n"
+        ));
+    }
+    readme
+}
+
+/// Add a generated README to an index, without writing it to the working directory, which is
+/// the project using the lockspec.
+///
+/// * `repo`: araki repo
+/// * `index`: Index to add the README to
+pub fn stage(repo: &Repository, index: &mut Index) -> Result<(), String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("The araki repo has no working directory".to_string())?;
+    let lockspec = LockSpec::from_path(workdir)?;
+    let contents = render(repo, &lockspec);
+    let blob = repo
+        .blob(contents.as_bytes())
+        .map_err(|err| format!("Unable to store {README_FILE}: {err}"))?;
+    let entry = IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: contents.len() as u32,
+        id: blob,
+        flags: 0,
+        flags_extended: 0,
+        path: README_FILE.as_bytes().to_vec(),
+    };
+    index
+        .add(&entry)
+        .map_err(|err| format!("Unable to add {README_FILE}: {err}"))
+}
+
+/// Commit a generated README on top of the current branch if the repo doesn't have one yet.
+///
+/// Repos holding several lockspecs and detached HEADs are left alone. Returns whether a README
+/// was committed.
+///
+/// * `repo`: araki repo
+pub fn ensure(repo: &Repository) -> Result<bool, String> {
+    if common::lockspec_subdir(repo).is_some() || repo.head_detached().unwrap_or(true) {
+        return Ok(false);
+    }
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|err| format!("Unable to find HEAD: {err}"))?;
+    let has_readme = head
+        .tree()
+        .is_ok_and(|tree| tree.get_name(README_FILE).is_some());
+    if has_readme {
+        return Ok(false);
+    }
+
+    let mut index = repo
+        .index()
+        .map_err(|err| format!("Failed to get index: {err}"))?;
+    stage(repo, &mut index)?;
+    index
+        .write()
+        .map_err(|err| format!("Failed to write index: {err}"))?;
+    let tree = index
+        .write_tree()
+        .and_then(|oid| repo.find_tree(oid))
+        .map_err(|err| format!("Failed to write tree: {err}"))?;
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &format!("Add {README_FILE}"),
+        &tree,
+        &[&head],
+    )
+    .map_err(|err| format!("Failed to create commit: {err}"))?;
+    Ok(true)
+}
+
+/// Get the names of the most recently created tags of the lockspec, newest first.
+///
+/// * `repo`: araki repo
+fn latest_tags(repo: &Repository) -> Vec<String> {
+    let Ok(names) = repo.tag_names(Some(&format!("{}*", common::tag_prefix(repo)))) else {
+        return vec![];
+    };
+    let mut tags: Vec<(i64, String)> = names
+        .iter()
+        .flatten()
+        .filter(|name| !name.contains(SNAPSHOT_TAG_PREFIX))
+        .filter_map(|name| {
+            let commit = repo
+                .revparse_single(&format!("refs/tags/{name}^{{commit}}"))
+                .ok()?
+                .peel_to_commit()
+                .ok()?;
+            Some((commit.time().seconds(), name.to_string()))
+        })
+        .collect();
+    tags.sort_by(|a, b| b.cmp(a));
+    tags.into_iter()
+        .take(MAX_TAGS)
+        .map(|(_, name)| name)
+        .collect()
+}
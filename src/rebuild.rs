@@ -0,0 +1,294 @@
+use git2::Repository;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env::{consts, temp_dir};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::common;
+use crate::lockfile::{LockFile, PackageKind};
+use crate::mirrors;
+use crate::ops::{LOCKSPEC_FILES, full_tag_name};
+
+/// A conda package installed in a prefix, as recorded in its `conda-meta` directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+
+    /// Location the package was downloaded from
+    pub url: Option<String>,
+    pub sha256: Option<String>,
+    pub md5: Option<String>,
+}
+
+/// A difference between the packages of a lockfile, or of an environment, and the packages
+/// installed in a prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// Expected, but not installed
+    Missing { name: String, version: String },
+
+    /// Installed, but not expected
+    Unexpected { name: String, version: String },
+
+    /// Installed at a different version than expected
+    VersionMismatch {
+        name: String,
+        expected: String,
+        installed: String,
+    },
+
+    /// Installed with different contents than expected
+    HashMismatch {
+        name: String,
+        version: String,
+        expected: String,
+        installed: String,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { name, version } => write!(f, "missing: {name} {version}"),
+            Self::Unexpected { name, version } => write!(f, "unexpected: {name} {version}"),
+            Self::VersionMismatch {
+                name,
+                expected,
+                installed,
+            } => write!(
+                f,
+                "version mismatch: {name} (expected {expected}, installed {installed})"
+            ),
+            Self::HashMismatch {
+                name,
+                version,
+                expected,
+                installed,
+            } => write!(
+                f,
+                "hash mismatch: {name} {version} (expected {expected}, installed {installed})"
+            ),
+        }
+    }
+}
+
+/// Result of rebuilding a tag of a lockspec from its lockfile.
+#[derive(Debug, Clone)]
+pub struct RebuildReport {
+    /// Platform the tag was rebuilt for, e.g. `linux-64`
+    pub platform: String,
+
+    /// Conda packages installed by the rebuild
+    pub installed: Vec<InstalledPackage>,
+
+    /// Differences between the lockfile of the tag and the rebuilt prefix
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Get the name pixi uses for the platform araki runs on, e.g. `linux-64` or `osx-arm64`.
+pub fn current_platform() -> Option<&'static str> {
+    match (consts::OS, consts::ARCH) {
+        ("linux", "x86_64") => Some("linux-64"),
+        ("linux", "aarch64") => Some("linux-aarch64"),
+        ("linux", "powerpc64") => Some("linux-ppc64le"),
+        ("macos", "x86_64") => Some("osx-64"),
+        ("macos", "aarch64") => Some("osx-arm64"),
+        ("windows", "x86_64") => Some("win-64"),
+        ("windows", "aarch64") => Some("win-arm64"),
+        _ => None,
+    }
+}
+
+/// Get the conda packages installed in a prefix. A prefix without a `conda-meta` directory has
+/// none.
+///
+/// * `prefix`: Prefix of the environment, e.g. `.pixi/envs/default`
+pub fn installed_packages(prefix: &Path) -> Result<Vec<InstalledPackage>, String> {
+    let Ok(records) = fs::read_dir(prefix.join("conda-meta")) else {
+        return Ok(vec![]);
+    };
+    let mut packages = vec![];
+    for record in records.flatten() {
+        if record.path().extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let contents = fs::read_to_string(record.path())
+            .map_err(|err| format!("Unable to read {:?}: {err}", record.path()))?;
+        let record: Value = serde_json::from_str(&contents)
+            .map_err(|err| format!("Unable to parse {:?}: {err}", record.path()))?;
+        let field = |name: &str| record[name].as_str().map(|value| value.to_string());
+        let (Some(name), Some(version)) = (field("name"), field("version")) else {
+            continue;
+        };
+        packages.push(InstalledPackage {
+            name,
+            version,
+            url: field("url"),
+            sha256: field("sha256"),
+            md5: field("md5"),
+        });
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+/// Compare the conda packages a lockfile pins for an environment and platform with the packages
+/// installed in a prefix.
+///
+/// * `lockfile`: Lockfile to compare against
+/// * `environment`: Name of the environment in the lockfile, e.g. `default`
+/// * `platform`: Platform of the prefix, e.g. `linux-64`
+/// * `installed`: Packages installed in the prefix
+pub fn compare_lockfile(
+    lockfile: &LockFile,
+    environment: &str,
+    platform: &str,
+    installed: &[InstalledPackage],
+) -> Vec<Discrepancy> {
+    let urls = lockfile
+        .environments
+        .get(environment)
+        .and_then(|environment| environment.packages.get(platform))
+        .cloned()
+        .unwrap_or_default();
+    let expected: Vec<InstalledPackage> = lockfile
+        .packages
+        .iter()
+        .filter(|package| package.kind == PackageKind::Conda && urls.contains(&package.url))
+        .map(|package| InstalledPackage {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            url: Some(package.url.clone()),
+            sha256: package.sha256.clone(),
+            md5: package.md5.clone(),
+        })
+        .collect();
+    compare(&expected, installed)
+}
+
+/// Compare two sets of installed conda packages, matched by name.
+///
+/// * `expected`: Packages which should be installed
+/// * `installed`: Packages which are installed
+pub fn compare(expected: &[InstalledPackage], installed: &[InstalledPackage]) -> Vec<Discrepancy> {
+    let installed: BTreeMap<&str, &InstalledPackage> = installed
+        .iter()
+        .map(|package| (package.name.as_str(), package))
+        .collect();
+    let mut discrepancies = vec![];
+    for package in expected {
+        let Some(found) = installed.get(package.name.as_str()) else {
+            discrepancies.push(Discrepancy::Missing {
+                name: package.name.clone(),
+                version: package.version.clone(),
+            });
+            continue;
+        };
+        if package.version != found.version {
+            discrepancies.push(Discrepancy::VersionMismatch {
+                name: package.name.clone(),
+                expected: package.version.clone(),
+                installed: found.version.clone(),
+            });
+            continue;
+        }
+        // Prefer sha256, which rattler records; older records may only have md5
+        let hashes = match (&package.sha256, &found.sha256) {
+            (Some(expected), Some(installed)) => Some((expected, installed)),
+            _ => package.md5.as_ref().zip(found.md5.as_ref()),
+        };
+        if let Some((expected, installed)) = hashes
+            && expected != installed
+        {
+            discrepancies.push(Discrepancy::HashMismatch {
+                name: package.name.clone(),
+                version: found.version.clone(),
+                expected: expected.clone(),
+                installed: installed.clone(),
+            });
+        }
+    }
+    for (name, package) in &installed {
+        if !expected.iter().any(|expected| expected.name == *name) {
+            discrepancies.push(Discrepancy::Unexpected {
+                name: name.to_string(),
+                version: package.version.clone(),
+            });
+        }
+    }
+    discrepancies
+}
+
+/// Rebuild a tag of a lockspec: install its lockfile with `pixi install --locked` into a
+/// scratch directory, and compare the installed packages with the lockfile.
+///
+/// Installing fails if a locked package can no longer be downloaded, e.g. because it was
+/// yanked from its channel. The scratch directory is removed afterwards.
+///
+/// * `repo`: araki repo of the lockspec
+/// * `tag`: Tag to rebuild
+pub fn rebuild(repo: &Repository, tag: &str) -> Result<RebuildReport, String> {
+    let platform = current_platform()
+        .ok_or(format!(
+            "Unsupported platform {}-{}",
+            consts::OS,
+            consts::ARCH
+        ))?
+        .to_string();
+    let tag = full_tag_name(repo, tag);
+    repo.revparse_single(&format!("refs/tags/{tag}"))
+        .map_err(|_| format!("No tag found: {tag}"))?;
+
+    let dir = temp_dir().join(format!("araki-rebuild-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("Unable to create a temporary directory at {dir:?}: {err}"))?;
+    let result = (|| {
+        for file in LOCKSPEC_FILES {
+            let contents = common::read_rev_file(repo, &tag, &common::tracked_path(repo, file))
+                .map_err(|err| format!("Unable to read {file} at {tag}: {err}"))?;
+            fs::write(dir.join(file), contents)
+                .map_err(|err| format!("Unable to write {file} to {dir:?}: {err}"))?;
+        }
+        let lockfile = LockFile::from_path(&dir.join("pixi.lock"))?;
+        if !lockfile
+            .environments
+            .get("default")
+            .is_some_and(|environment| environment.packages.contains_key(&platform))
+        {
+            return Err(format!(
+                "{tag} doesn't lock the default environment for {platform}."
+            ));
+        }
+
+        let mirrors = mirrors::apply(&dir)?;
+        let mut command = common::pixi_command();
+        command
+            .args(["install", "--locked", "--color", "always"])
+            .current_dir(&dir);
+        mirrors.configure(&mut command);
+        let status = command
+            .status()
+            .map_err(|err| format!("Failed to execute pixi install: {err}"))?;
+        if !status.success() {
+            return Err(format!(
+                "The lockfile of {tag} could not be installed. Its packages may have been removed \
+                from their channels, or the channels may have moved."
+            ));
+        }
+
+        let installed = installed_packages(&dir.join(".pixi").join("envs").join("default"))?;
+        let discrepancies = compare_lockfile(&lockfile, "default", &platform, &installed);
+        Ok(RebuildReport {
+            platform: platform.clone(),
+            installed,
+            discrepancies,
+        })
+    })();
+
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
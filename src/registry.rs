@@ -0,0 +1,810 @@
+//! A self-hostable lockspec registry which doesn't depend on a git forge.
+//!
+//! The registry stores lockspecs in a directory laid out as
+//! `<root>/<org>/<name>/<tag>/{pixi.toml,pixi.lock}` and serves them over a small HTTP API:
+//!
+//! * `GET /v1/{org}`: The lockspecs of an org (`[{"name", "updated"}]`)
+//! * `GET /v1/{org}/{name}`: Lockspec metadata (`{"org", "name", "tags"}`, with the tags oldest
+//!   first), or 404
+//! * `POST /v1/{org}`: Create a lockspec; the body is `{"name": "..."}`
+//! * `GET /v1/{org}/{name}/tags/{tag}/{file}`: Download a lockspec file at a tag
+//! * `PUT /v1/{org}/{name}/tags/{tag}/{file}`: Upload a lockspec file at a tag
+//!
+//! If the server is started with a token, requests which modify the registry must send it as
+//! `Authorization: Bearer <token>`.
+//!
+//! araki repos backed by a registry have an `araki+http(s)://<host>/<org>/<name>` URL as their
+//! `origin`. Like OCI registries, the registry keeps no history: cloning imports each tag as a
+//! commit, and pushing uploads the lockspec files of a tag.
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::extract::{Path as UrlPath, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository};
+use reqwest::{Client, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+use crate::backends::{
+    Backend, BackendError, HealthCheck, RepositoryInfo, RepositoryQuery, Visibility,
+};
+use crate::common;
+use crate::network::{self, SendWithRetries};
+use crate::oci::{self, Artifact};
+use crate::ops::LOCKSPEC_FILES;
+use crate::remote::RemoteRepo;
+use crate::settings::get_settings;
+
+/// Prefix of the URLs of registry lockspecs, e.g. `araki+https://registry.mycorp.com/org/env`,
+/// which tells them apart from git URLs.
+pub const SCHEME_PREFIX: &str = "araki+";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LockspecMetadata {
+    pub org: String,
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CreateLockspecRequestBody {
+    name: String,
+}
+
+/// Status and message sent back for requests which can't be served.
+type Rejection = (StatusCode, &'static str);
+
+struct RegistryState {
+    root: PathBuf,
+    token: Option<String>,
+}
+
+/// Check that a path component sent by a client is a valid org, lockspec, or tag name.
+///
+/// * `component`: Name to check
+fn is_valid_name(component: &str) -> bool {
+    !component.is_empty()
+        && component != "."
+        && component != ".."
+        && component
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+impl RegistryState {
+    /// Check that the request carries the registry token, if one is required.
+    ///
+    /// * `headers`: Headers of the request
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), Rejection> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+        let sent = headers
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if sent.is_some_and(|sent| sent.trim() == token) {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid registry token",
+            ))
+        }
+    }
+
+    /// Get the directory of a lockspec, validating the names sent by the client.
+    ///
+    /// * `org`: Organization the lockspec belongs to
+    /// * `name`: Name of the lockspec
+    fn lockspec_dir(&self, org: &str, name: &str) -> Result<PathBuf, Rejection> {
+        if !is_valid_name(org) || !is_valid_name(name) {
+            return Err((StatusCode::BAD_REQUEST, "Invalid org or lockspec name"));
+        }
+        Ok(self.root.join(org).join(name))
+    }
+
+    /// Get the path of a lockspec file at a tag, validating the names sent by the client.
+    ///
+    /// * `org`: Organization the lockspec belongs to
+    /// * `name`: Name of the lockspec
+    /// * `tag`: Tag of the lockspec
+    /// * `file`: Name of the lockspec file
+    fn lockspec_file(
+        &self,
+        org: &str,
+        name: &str,
+        tag: &str,
+        file: &str,
+    ) -> Result<PathBuf, Rejection> {
+        if !is_valid_name(tag) || !LOCKSPEC_FILES.contains(&file) {
+            return Err((StatusCode::BAD_REQUEST, "Invalid tag or file name"));
+        }
+        Ok(self.lockspec_dir(org, name)?.join(tag).join(file))
+    }
+}
+
+async fn get_lockspec(
+    State(state): State<Arc<RegistryState>>,
+    UrlPath((org, name)): UrlPath<(String, String)>,
+) -> Response {
+    let dir = match state.lockspec_dir(&org, &name) {
+        Ok(dir) => dir,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // Clients import the tags in this order, so list them from the first pushed to the last
+    let mut tags: Vec<(SystemTime, String)> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified());
+            Some((modified.ok()?, entry.file_name().into_string().ok()?))
+        })
+        .collect();
+    tags.sort();
+    let tags = tags.into_iter().map(|(_, tag)| tag).collect();
+    Json(LockspecMetadata { org, name, tags }).into_response()
+}
+
+async fn list_lockspecs(
+    State(state): State<Arc<RegistryState>>,
+    UrlPath(org): UrlPath<String>,
+) -> Response {
+    if !is_valid_name(&org) {
+        return (StatusCode::BAD_REQUEST, "Invalid org name").into_response();
+    }
+    let Ok(entries) = fs::read_dir(state.root.join(&org)) else {
+        return Json(Vec::<RepositoryInfo>::new()).into_response();
+    };
+    let lockspecs: Vec<RepositoryInfo> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let updated = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339());
+            Some(RepositoryInfo {
+                name: entry.file_name().into_string().ok()?,
+                description: None,
+                topics: vec![],
+                updated,
+            })
+        })
+        .collect();
+    Json(lockspecs).into_response()
+}
+
+async fn create_lockspec(
+    State(state): State<Arc<RegistryState>>,
+    UrlPath(org): UrlPath<String>,
+    headers: HeaderMap,
+    Json(body): Json<CreateLockspecRequestBody>,
+) -> Response {
+    if let Err(rejection) = state.authorize(&headers) {
+        return rejection.into_response();
+    }
+    let dir = match state.lockspec_dir(&org, &body.name) {
+        Ok(dir) => dir,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if dir.exists() {
+        return (StatusCode::CONFLICT, "Lockspec already exists").into_response();
+    }
+    match fs::create_dir_all(&dir) {
+        Ok(_) => StatusCode::CREATED.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+async fn download_file(
+    State(state): State<Arc<RegistryState>>,
+    UrlPath((org, name, tag, file)): UrlPath<(String, String, String, String)>,
+) -> Response {
+    let path = match state.lockspec_file(&org, &name, &tag, &file) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match fs::read(path) {
+        Ok(contents) => contents.into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn upload_file(
+    State(state): State<Arc<RegistryState>>,
+    UrlPath((org, name, tag, file)): UrlPath<(String, String, String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(rejection) = state.authorize(&headers) {
+        return rejection.into_response();
+    }
+    let path = match state.lockspec_file(&org, &name, &tag, &file) {
+        Ok(path) => path,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if !path
+        .parent()
+        .and_then(Path::parent)
+        .is_some_and(Path::is_dir)
+    {
+        return (StatusCode::NOT_FOUND, "Lockspec does not exist").into_response();
+    }
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, &body));
+    match written {
+        Ok(_) => StatusCode::CREATED.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// Serve a registry from the given directory until the process is stopped.
+///
+/// * `root`: Directory where lockspecs are stored
+/// * `address`: Address to listen on
+/// * `token`: Token required for requests which modify the registry
+pub async fn serve(
+    root: PathBuf,
+    address: SocketAddr,
+    token: Option<String>,
+) -> Result<(), String> {
+    fs::create_dir_all(&root)
+        .map_err(|err| format!("Unable to create the registry directory {root:?}: {err}"))?;
+
+    let state = Arc::new(RegistryState { root, token });
+    let app = Router::new()
+        .route("/v1/{org}", get(list_lockspecs).post(create_lockspec))
+        .route("/v1/{org}/{name}", get(get_lockspec))
+        .route(
+            "/v1/{org}/{name}/tags/{tag}/{file}",
+            get(download_file).put(upload_file),
+        )
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .map_err(|err| format!("Unable to listen on {address}: {err}"))?;
+    axum::serve(listener, app)
+        .await
+        .map_err(|err| format!("Registry server error: {err}"))
+}
+
+/// Settings of the registry backend, read from `[backends.registry]`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct RegistrySettings {
+    /// Base URL of the registry, e.g. `http://registry.mycorp.com:8080/`
+    pub url: Option<String>,
+
+    /// Token sent with requests which modify the registry
+    pub token: Option<String>,
+}
+
+impl RegistrySettings {
+    /// Load the settings of the registry backend.
+    pub fn load() -> Result<Self, String> {
+        get_settings()?.backend_settings("registry")
+    }
+
+    /// Get the configured base URL of the registry, ending with a slash so that paths can be
+    /// joined to it.
+    pub fn base_url(&self) -> Result<Url, String> {
+        let url = self
+            .url
+            .as_deref()
+            .ok_or("No registry URL configured. Set `url` in [backends.registry] in araki.toml.")?;
+        let url = if url.ends_with('/') {
+            url.to_string()
+        } else {
+            format!("{url}/")
+        };
+        Url::parse(&url).map_err(|err| format!("Invalid registry URL {url}: {err}"))
+    }
+}
+
+/// A lockspec in an araki registry.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Location {
+    /// Base URL of the registry, ending with a slash
+    pub base: Url,
+    pub org: String,
+    pub name: String,
+    pub tag: Option<String>,
+}
+
+impl Location {
+    /// Parse a registry lockspec location such as
+    /// `araki+https://registry.mycorp.com/my-org/my-env:v1`, or `my-org/my-env:v1` in the
+    /// registry of `[backends.registry]` when it is the current backend. Returns `None` for
+    /// anything else.
+    ///
+    /// * `env`: Lockspec location to parse
+    pub fn parse(env: &str) -> Option<Location> {
+        let (base, path) = match env.strip_prefix(SCHEME_PREFIX) {
+            Some(url) => {
+                let mut base = Url::parse(url).ok()?;
+                if !matches!(base.scheme(), "http" | "https") {
+                    return None;
+                }
+                let segments: Vec<String> = base
+                    .path_segments()?
+                    .filter(|segment| !segment.is_empty())
+                    .map(|segment| segment.to_string())
+                    .collect();
+                let (prefix, path) = segments.split_at(segments.len().checked_sub(2)?);
+                base.set_path(&format!("{}/", prefix.join("/")));
+                base.set_query(None);
+                base.set_fragment(None);
+                (base, path.join("/"))
+            }
+            None if env.contains("://") || env.contains('@') => return None,
+            None => {
+                if get_settings().ok()?.backend != "registry" {
+                    return None;
+                }
+                (
+                    RegistrySettings::load().ok()?.base_url().ok()?,
+                    env.to_string(),
+                )
+            }
+        };
+        let (org, name) = path.split_once('/')?;
+        let (name, tag) = match name.split_once(':') {
+            Some((name, tag)) => (name, Some(tag.to_string())),
+            None => (name, None),
+        };
+        if ![org, name]
+            .into_iter()
+            .chain(tag.as_deref())
+            .all(is_valid_name)
+        {
+            return None;
+        }
+        Some(Location {
+            base,
+            org: org.to_string(),
+            name: name.to_string(),
+            tag,
+        })
+    }
+
+    /// Render the lockspec as `<org>/<name>`, without its tag.
+    pub fn short_name(&self) -> String {
+        format!("{}/{}", self.org, self.name)
+    }
+
+    /// Get the `araki+` URL of the lockspec, used as the `origin` of araki repos.
+    pub fn url(&self) -> String {
+        format!("{SCHEME_PREFIX}{}{}/{}", self.base, self.org, self.name)
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.short_name())?;
+        match &self.tag {
+            Some(tag) => write!(f, ":{tag}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Get the registry lockspec an araki repo is backed by, if its `origin` is an `araki+` URL.
+///
+/// * `repo`: araki repo
+pub fn origin(repo: &Repository) -> Option<Location> {
+    let remote = repo.find_remote("origin").ok()?;
+    let url = remote.url()?;
+    url.starts_with(SCHEME_PREFIX)
+        .then(|| Location::parse(url))?
+}
+
+/// Read the lockspec files of a tag.
+///
+/// * `repo`: araki repo
+/// * `tag`: Tag to read
+fn tag_files(repo: &Repository, tag: &str) -> Result<Vec<(String, Vec<u8>)>, String> {
+    LOCKSPEC_FILES
+        .iter()
+        .map(|file| {
+            common::read_rev_file(repo, &format!("refs/tags/{tag}"), file)
+                .map(|contents| (file.to_string(), contents))
+                .map_err(|err| format!("Unable to read {file} at {tag}: {err}"))
+        })
+        .collect()
+}
+
+/// Clone a registry lockspec to a directory: each tag becomes a commit of `main`, and the most
+/// recently pushed one is checked out.
+///
+/// * `location`: Registry lockspec; its tag, if any, is not checked out
+/// * `path`: Directory to clone the lockspec to
+pub fn clone(location: &Location, path: &Path) -> Result<(), String> {
+    network::require_online(&format!("clone {location}"))?;
+    let client = RegistryBackend::for_location(location)?;
+    let artifacts = oci::block_on(client.artifacts(location, &BTreeSet::new()))?;
+    if artifacts.is_empty() {
+        return Err(format!("No tags found in {}", location.url()));
+    }
+    oci::create_repo(&location.url(), path, |repo| {
+        oci::checkout_imported(repo, &artifacts, &location.url())
+    })
+}
+
+/// Create an empty araki repo for a lockspec pushed to a registry, as `araki init` does for git
+/// hosts.
+///
+/// * `location`: Registry lockspec
+/// * `path`: Directory containing the lockspec
+pub fn init(location: &Location, path: &Path) -> Result<(), String> {
+    oci::create_repo(&location.url(), path, |_| Ok(()))
+}
+
+/// Import the tags pushed to the registry lockspec of an araki repo since it was cloned or last
+/// pulled, as commits on top of `origin/main`. Returns the new `origin/main`.
+///
+/// * `repo`: araki repo whose `origin` is a registry lockspec
+pub fn fetch(repo: &Repository) -> Result<Oid, String> {
+    let location = origin(repo).ok_or("The origin of the araki repo is not an araki registry")?;
+    network::require_online(&format!("fetch from {location}"))?;
+    let client = RegistryBackend::for_location(&location)?;
+    let artifacts = oci::block_on(client.artifacts(&location, &oci::local_tags(repo)?))?;
+    oci::import_fetched(repo, &artifacts, &location.url())
+}
+
+/// Check whether a tag was pushed to the registry with other lockspec files than it has locally.
+/// Returns `false` if the tag wasn't pushed.
+///
+/// * `repo`: araki repo whose `origin` is a registry lockspec
+/// * `tag`: Tag to check
+pub fn pushed_tag_differs(repo: &Repository, tag: &str) -> Result<bool, String> {
+    let location = origin(repo).ok_or("The origin of the araki repo is not an araki registry")?;
+    let files = tag_files(repo, tag)?;
+    let client = RegistryBackend::for_location(&location)?;
+    let pushed = oci::block_on(async {
+        client
+            .download(&location.org, &location.name, tag)
+            .await
+            .map_err(|err| err.to_string())
+    })?;
+    Ok(pushed.is_some_and(|pushed| pushed != files))
+}
+
+/// Upload the lockspec files of a tag to the registry lockspec of an araki repo.
+///
+/// * `repo`: araki repo whose `origin` is a registry lockspec
+/// * `tag`: Tag to push
+pub fn push_tag(repo: &Repository, tag: &str) -> Result<(), String> {
+    let location = origin(repo).ok_or("The origin of the araki repo is not an araki registry")?;
+    network::require_online(&format!("push to {location}"))?;
+    let files = tag_files(repo, tag)?;
+    let client = RegistryBackend::for_location(&location)?;
+    oci::block_on(async {
+        client
+            .upload(&location.org, &location.name, tag, &files)
+            .await
+            .map_err(|err| err.to_string())
+    })?;
+    oci::record_pushed(repo, tag)
+}
+
+/// Backend for lockspecs hosted in an araki registry.
+pub struct RegistryBackend {
+    url: Url,
+    token: Option<String>,
+    client: Client,
+}
+
+impl RegistryBackend {
+    /// Create a new RegistryBackend from the `[backends.registry]` settings.
+    pub fn new() -> Result<Self, BackendError> {
+        let settings = RegistrySettings::load()?;
+        Ok(Self {
+            url: settings.base_url()?,
+            token: settings.token,
+            client: network::client_builder()?.build()?,
+        })
+    }
+
+    /// Create a client for the registry of a lockspec. The token of `[backends.registry]` is
+    /// only sent to the registry it is configured for.
+    ///
+    /// * `location`: Registry lockspec
+    fn for_location(location: &Location) -> Result<Self, String> {
+        let settings = RegistrySettings::load()?;
+        let configured = settings.base_url().is_ok_and(|url| url == location.base);
+        let token = settings.token.filter(|_| configured);
+        Ok(Self {
+            url: location.base.clone(),
+            token,
+            client: network::client_builder()
+                .and_then(|builder| Ok(builder.build()?))
+                .map_err(|err| err.to_string())?,
+        })
+    }
+
+    /// Add the registry token to a request, if one is configured.
+    ///
+    /// * `request`: Request to authenticate
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Upload lockspec files to the registry under the given tag.
+    ///
+    /// * `org`: Organization the lockspec belongs to
+    /// * `name`: Name of the lockspec
+    /// * `tag`: Tag to upload the files as
+    /// * `files`: Names and contents of pixi.toml and pixi.lock
+    pub async fn upload(
+        &self,
+        org: &str,
+        name: &str,
+        tag: &str,
+        files: &[(String, Vec<u8>)],
+    ) -> Result<(), BackendError> {
+        for (file, contents) in files {
+            let url = self
+                .url
+                .join(&format!("v1/{org}/{name}/tags/{tag}/{file}"))?;
+            let response = self
+                .authenticate(self.client.put(url))
+                .body(contents.clone())
+                .send_with_retries()
+                .await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                return Err(format!("{status}: {}", response.text().await?.trim()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Download the lockspec files of a tag from the registry. Returns `None` if the tag wasn't
+    /// pushed.
+    ///
+    /// * `org`: Organization the lockspec belongs to
+    /// * `name`: Name of the lockspec
+    /// * `tag`: Tag to download
+    pub async fn download(
+        &self,
+        org: &str,
+        name: &str,
+        tag: &str,
+    ) -> Result<Option<Vec<(String, Vec<u8>)>>, BackendError> {
+        let mut files = vec![];
+        for file in LOCKSPEC_FILES {
+            let response = self
+                .get(&format!("v1/{org}/{name}/tags/{tag}/{file}"))?
+                .send_with_retries()
+                .await?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let contents = response.error_for_status()?.bytes().await?;
+            files.push((file.to_string(), contents.to_vec()));
+        }
+        Ok(Some(files))
+    }
+
+    /// Get the tags of a lockspec, oldest first. Returns `None` if the lockspec doesn't exist.
+    ///
+    /// * `org`: Organization the lockspec belongs to
+    /// * `name`: Name of the lockspec
+    pub async fn tags(&self, org: &str, name: &str) -> Result<Option<Vec<String>>, BackendError> {
+        let response = self
+            .get(&format!("v1/{org}/{name}"))?
+            .send_with_retries()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let metadata = response
+            .error_for_status()?
+            .json::<LockspecMetadata>()
+            .await?;
+        Ok(Some(metadata.tags))
+    }
+
+    /// Download the lockspec files of each tag of a lockspec, oldest first.
+    ///
+    /// * `location`: Registry lockspec
+    /// * `skip`: Tags not to download, e.g. those which were already imported
+    async fn artifacts(
+        &self,
+        location: &Location,
+        skip: &BTreeSet<String>,
+    ) -> Result<Vec<Artifact>, String> {
+        let (org, name) = (&location.org, &location.name);
+        let tags = self
+            .tags(org, name)
+            .await
+            .map_err(|err| err.to_string())?
+            .ok_or(format!("No lockspec found at {}", location.url()))?;
+        let mut artifacts = vec![];
+        for tag in tags.into_iter().filter(|tag| !skip.contains(tag)) {
+            let files = self
+                .download(org, name, &tag)
+                .await
+                .map_err(|err| format!("Unable to download {tag}: {err}"))?;
+            if let Some(files) = files {
+                artifacts.push(Artifact {
+                    tag,
+                    created: None,
+                    files,
+                });
+            }
+        }
+        Ok(artifacts)
+    }
+}
+
+#[async_trait]
+impl Backend for RegistryBackend {
+    fn get(&self, path: &str) -> Result<RequestBuilder, BackendError> {
+        Ok(self.authenticate(self.client.get(self.url.join(path)?)))
+    }
+    fn post(&self, path: &str) -> Result<RequestBuilder, BackendError> {
+        Ok(self.authenticate(self.client.post(self.url.join(path)?)))
+    }
+    async fn is_existing_lockspec(&self, org: &str, name: &str) -> Result<bool, BackendError> {
+        let response = self
+            .get(&format!("v1/{org}/{name}"))?
+            .send_with_retries()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        Ok(response.error_for_status()?.status().is_success())
+    }
+    async fn list_repositories(
+        &self,
+        org: &str,
+        query: &RepositoryQuery,
+    ) -> Result<Vec<RepositoryInfo>, BackendError> {
+        if query.topic.is_some() {
+            return Err("The registry backend doesn't support topics.".into());
+        }
+        let repositories = self
+            .get(&format!("v1/{org}"))?
+            .send_with_retries()
+            .await?
+            .error_for_status()?
+            .json::<Vec<RepositoryInfo>>()
+            .await?;
+        Ok(query.apply(repositories))
+    }
+    async fn create_repository(
+        &self,
+        org: &str,
+        name: &str,
+        _visibility: Visibility,
+    ) -> Result<(), BackendError> {
+        let result = self
+            .post(&format!("v1/{org}"))?
+            .json(&CreateLockspecRequestBody {
+                name: name.to_string(),
+            })
+            .send_with_retries()
+            .await?;
+
+        if result.status().is_success() {
+            Ok(())
+        } else {
+            Err(result.text().await?.into())
+        }
+    }
+    async fn ping(&self, org: &str) -> Vec<HealthCheck> {
+        let started = Instant::now();
+        let api = match self.client.get(self.url.clone()).send().await {
+            // The registry serves no page at its root, but answering at all means it is up
+            Ok(response) => HealthCheck::ok(
+                "api",
+                format!("Reached {} ({})", self.url, response.status()),
+                started,
+            ),
+            Err(err) => HealthCheck::failed("api", format!("Unable to reach {}: {err}", self.url)),
+        };
+        // Reads are public; the registry only checks the token when files are uploaded
+        let auth = match &self.token {
+            Some(_) => HealthCheck::skipped("auth", "The token is only checked on push"),
+            None => HealthCheck::skipped("auth", "No token is set; the registry can only be read"),
+        };
+
+        let started = Instant::now();
+        let response = match self.get(&format!("v1/{org}")) {
+            Ok(request) => request.send().await.map_err(BackendError::from),
+            Err(err) => Err(err),
+        };
+        let org = match response {
+            Ok(response) if response.status().is_success() => {
+                HealthCheck::ok("org", format!("{org} is accessible"), started)
+            }
+            Ok(response) => HealthCheck::failed(
+                "org",
+                format!("Unable to access {org}: {}", response.status()),
+            ),
+            Err(err) => HealthCheck::failed("org", format!("Unable to access {org}: {err}")),
+        };
+        vec![
+            api,
+            auth,
+            org,
+            HealthCheck::skipped("ssh", "The registry is only reached over HTTP"),
+        ]
+    }
+    fn get_repo_info(&self, org: &str, repo: &str) -> RemoteRepo {
+        let domain = match (self.url.host_str(), self.url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => self.url.to_string(),
+        };
+        let location = Location {
+            base: self.url.clone(),
+            org: org.to_string(),
+            name: repo.to_string(),
+            tag: None,
+        };
+        RemoteRepo::new(
+            Some(org.to_string()),
+            repo.to_string(),
+            Some(domain),
+            Some(format!("{}://", self.url.scheme())),
+        )
+        .with_url(Some(location.url()))
+    }
+    async fn login(&self, _scopes: &[String]) -> Result<(), BackendError> {
+        Err(
+            "The registry backend doesn't support interactive login. Set `token` in \
+            [backends.registry] in araki.toml instead."
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            Location::parse("araki+https://registry.mycorp.com/araki/my-org/my-env:v1"),
+            Some(Location {
+                base: Url::parse("https://registry.mycorp.com/araki/").unwrap(),
+                org: "my-org".to_string(),
+                name: "my-env".to_string(),
+                tag: Some("v1".to_string()),
+            })
+        );
+        assert_eq!(
+            Location::parse("araki+http://localhost:8080/my-org/my-env").map(|loc| loc.url()),
+            Some("araki+http://localhost:8080/my-org/my-env".to_string())
+        );
+        assert_eq!(Location::parse("araki+http://localhost:8080/my-env"), None);
+        assert_eq!(Location::parse("araki+ssh://localhost/my-org/my-env"), None);
+        assert_eq!(Location::parse("https://github.com/my-org/my-env"), None);
+        assert_eq!(Location::parse("git@github.com:my-org/my-env.git"), None);
+    }
+}
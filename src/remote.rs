@@ -0,0 +1,348 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt::Display;
+
+use crate::backends::{GitHubSettings, GitSettings};
+
+/// Additional remotes of lockspec repositories, configured in the `[remotes]` section of
+/// `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RemoteSettings {
+    /// URLs of mirrors that `araki push` pushes to after `origin`. `{org}` and `{repo}` are
+    /// replaced by the organization and name of the `origin` repository.
+    pub mirrors: Vec<String>,
+}
+
+impl RemoteSettings {
+    /// Get the URLs of the mirrors of a repository.
+    ///
+    /// * `origin`: URL of the `origin` remote of the repository
+    pub fn mirror_urls(&self, origin: &str) -> Vec<String> {
+        let remote = parse_repo_arg(origin).ok();
+        self.mirrors
+            .iter()
+            .map(|mirror| match &remote {
+                Some(remote) => mirror
+                    .replace("{org}", &remote.get_org())
+                    .replace("{repo}", &remote.get_repo()),
+                None => mirror.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Protocol used to clone a lockspec repository.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    /// Authenticated with the keys in ssh-agent
+    Ssh,
+
+    /// Unauthenticated, which only works for public repositories
+    Https,
+}
+
+/// Location of a lockspec repository on a remote git host.
+#[derive(Debug, Default)]
+pub struct RemoteRepo {
+    org: Option<String>,
+    repo: String,
+    domain: Option<String>,
+    protocol: Option<String>,
+
+    /// Lockspec within a repo containing several, given as `<org>/<repo>#<subdir>`
+    subdir: Option<String>,
+
+    /// git URL of the repository, for hosts whose URLs aren't made of a domain, org, and repo,
+    /// e.g. a bare repository on an SSH server
+    url: Option<String>,
+}
+
+impl RemoteRepo {
+    pub fn new(
+        org: Option<String>,
+        repo: String,
+        domain: Option<String>,
+        protocol: Option<String>,
+    ) -> RemoteRepo {
+        RemoteRepo {
+            org,
+            repo,
+            domain,
+            protocol,
+            subdir: None,
+            url: None,
+        }
+    }
+
+    /// Use a git URL as is, instead of one rendered from the domain, org, and repo.
+    ///
+    /// * `url`: git URL of the repository
+    pub fn with_url(mut self, url: Option<String>) -> RemoteRepo {
+        self.url = url;
+        self
+    }
+
+    /// Get the git URL used as is for the repository: the one it was given with, or, for a
+    /// repository given as `<org>/<repo>` with the git backend, the one rendered from the
+    /// backend's URL template.
+    fn explicit_url(&self) -> Option<String> {
+        match (&self.url, &self.domain) {
+            (Some(url), _) => Some(url.clone()),
+            (None, None) => GitSettings::load()
+                .ok()
+                .flatten()?
+                .repo_url(&self.get_org(), &self.get_repo()),
+            (None, Some(_)) => None,
+        }
+    }
+
+    /// Address a lockspec in a subdirectory of the repository.
+    ///
+    /// * `subdir`: Subdirectory, or name of the lockspec under `envs/`
+    pub fn with_subdir(mut self, subdir: Option<String>) -> RemoteRepo {
+        self.subdir = subdir;
+        self
+    }
+
+    /// Render the repository as a git url
+    pub fn as_url(&self) -> String {
+        if let Some(url) = self.explicit_url() {
+            return url;
+        }
+        format!(
+            "{}{}/{}/{}",
+            self.get_protocol(),
+            self.get_domain(),
+            self.get_org(),
+            self.get_repo(),
+        )
+    }
+
+    /// Render the repository as `<org>/<repo>`, followed by `#<subdir>` for a lockspec in a
+    /// subdirectory.
+    pub fn short_name(&self) -> String {
+        let name = format!("{}/{}", self.get_org(), self.get_repo());
+        match &self.subdir {
+            Some(subdir) => format!("{name}#{subdir}"),
+            None => name,
+        }
+    }
+
+    /// Get the URLs to try, in order, when cloning the repository.
+    ///
+    /// Without an explicit protocol, a repository given as an `https://` URL is cloned over https;
+    /// otherwise ssh is tried first and anonymous https second, so that public repositories can
+    /// be cloned without ssh-agent.
+    ///
+    /// * `protocol`: Protocol to use; inferred if unset
+    pub fn clone_urls(&self, protocol: Option<Protocol>) -> Vec<String> {
+        if let Some(url) = self.explicit_url() {
+            return vec![url];
+        }
+        match protocol {
+            Some(Protocol::Ssh) => vec![self.as_ssh_url()],
+            Some(Protocol::Https) => vec![self.as_https_url()],
+            None if self
+                .protocol
+                .as_deref()
+                .is_some_and(|p| p.ends_with("https://")) =>
+            {
+                vec![self.as_https_url()]
+            }
+            None => vec![self.as_ssh_url(), self.as_https_url()],
+        }
+    }
+
+    /// Render the repository as an https URL, regardless of the protocol it was given with
+    pub fn as_https_url(&self) -> String {
+        if let Some(url) = self.explicit_url() {
+            return url;
+        }
+        format!(
+            "https://{}/{}/{}",
+            self.get_domain(),
+            self.get_org(),
+            self.get_repo()
+        )
+    }
+
+    /// Render the repository as an ssh URL
+    pub fn as_ssh_url(&self) -> String {
+        if let Some(url) = self.explicit_url() {
+            return url;
+        }
+        format!(
+            "git@{}:{}/{}.git",
+            self.get_domain(),
+            self.get_org(),
+            self.get_repo(),
+        )
+    }
+
+    pub fn get_org(&self) -> String {
+        self.org.clone().unwrap_or("nos-environments".into())
+    }
+    pub fn get_repo(&self) -> String {
+        self.repo.clone()
+    }
+    pub fn get_protocol(&self) -> String {
+        self.protocol.clone().unwrap_or("https://".into())
+    }
+    pub fn get_domain(&self) -> String {
+        self.domain.clone().unwrap_or_else(default_domain)
+    }
+    pub fn get_subdir(&self) -> Option<String> {
+        self.subdir.clone()
+    }
+}
+
+impl Display for RemoteRepo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.subdir {
+            Some(subdir) => write!(f, "{}#{subdir}", self.as_url()),
+            None => write!(f, "{}", self.as_url()),
+        }
+    }
+}
+
+/// Get the host of repositories given without one, e.g. as `<org>/<repo>`: github.com, or the
+/// `git_domain` of the GitHub backend on GitHub Enterprise Server.
+fn default_domain() -> String {
+    GitHubSettings::load()
+        .map(|settings| settings.git_domain())
+        .unwrap_or("github.com".into())
+}
+
+/// Parse a lockspec repository name or URL into a RemoteRepo.
+///
+/// * `env`: Remote URL for an lockspec. If only `<org>/<repo>` is passed, the repository is
+///   assumed to live on github.com, or the configured GitHub Enterprise Server host. A
+///   `#<subdir>` suffix selects one of several lockspecs in the repository. A `.git` suffix of
+///   the repo name is dropped.
+pub fn parse_repo_arg(env: &str) -> Result<RemoteRepo, String> {
+    let url = is_explicit_url(env).then(|| env.split('#').next().unwrap_or(env).to_string());
+    let re = Regex::new(
+        r"((?<protocol>(git\+)?https?://)?(?<domain>[-a-zA-Z0-9_]+(\.[-a-zA-Z0-9_]+)+(:[0-9]+)?)/)?((?<org>[-a-zA-Z0-9_.]{1,100})/)?(?<repo>[-a-zA-Z0-9_.]{1,100})(#(?<subdir>[-a-zA-Z0-9_./]{1,200}))?$",
+    )
+    .map_err(|_| "Invalid regex for processing git url.")?;
+
+    let captures = re
+        .captures(env)
+        .ok_or(format!("Unrecognized format for repo name or URL: {env}."))?;
+
+    Ok(RemoteRepo::new(
+        captures.name("org").map(|name| name.as_str().to_string()),
+        captures
+            .name("repo")
+            .map(|repo| repo.as_str())
+            .map(|repo| repo.strip_suffix(".git").unwrap_or(repo))
+            .ok_or(format!("No repo name found in {env}"))?
+            .to_string(),
+        captures
+            .name("domain")
+            .map(|name| name.as_str().to_string()),
+        captures
+            .name("protocol")
+            .map(|name| name.as_str().to_string()),
+    )
+    .with_subdir(
+        captures
+            .name("subdir")
+            .map(|name| name.as_str().trim_matches('/').to_string()),
+    )
+    .with_url(url))
+}
+
+/// Whether a lockspec location is a git URL which can't be rendered again from its domain, org,
+/// and repo: an `ssh://`, `git://`, or `file://` URL, or an scp-like `<user>@<host>:<path>` on
+/// another host than github.com (or the configured GitHub Enterprise Server host).
+///
+/// * `env`: Lockspec location
+fn is_explicit_url(env: &str) -> bool {
+    if ["ssh://", "git://", "file://"]
+        .iter()
+        .any(|scheme| env.starts_with(scheme))
+    {
+        return true;
+    }
+    if env.contains("://") {
+        return false;
+    }
+    let Some((user_host, _)) = env.split_once(':') else {
+        return false;
+    };
+    match user_host.split_once('@') {
+        Some((_, host)) => !host.contains('/') && host != default_domain(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clone_urls() {
+        let remote = parse_repo_arg("my-org/my-env").unwrap();
+        assert_eq!(
+            remote.clone_urls(None),
+            vec![
+                "git@github.com:my-org/my-env.git",
+                "https://github.com/my-org/my-env"
+            ]
+        );
+        assert_eq!(
+            remote.clone_urls(Some(Protocol::Https)),
+            vec!["https://github.com/my-org/my-env"]
+        );
+        let remote = parse_repo_arg("https://github.com/my-org/my-env").unwrap();
+        assert_eq!(
+            remote.clone_urls(None),
+            vec!["https://github.com/my-org/my-env"]
+        );
+        let remote = parse_repo_arg("https://github.mycorp.com/my-org/my-env").unwrap();
+        assert_eq!(
+            remote.clone_urls(None),
+            vec!["https://github.mycorp.com/my-org/my-env"]
+        );
+        let remote = parse_repo_arg("git@github.com:my-org/my-env.git").unwrap();
+        assert_eq!(remote.short_name(), "my-org/my-env");
+        assert_eq!(
+            remote.clone_urls(None),
+            vec![
+                "git@github.com:my-org/my-env.git",
+                "https://github.com/my-org/my-env"
+            ]
+        );
+        let remote = parse_repo_arg("https://github.com/my-org/my-env.git").unwrap();
+        assert_eq!(remote.get_repo(), "my-env");
+        assert_eq!(
+            remote.clone_urls(Some(Protocol::Ssh)),
+            vec!["git@github.com:my-org/my-env.git"]
+        );
+        let remote = parse_repo_arg("alice@git.mycorp.com:/srv/git/my-org/my-env.git").unwrap();
+        assert_eq!(remote.short_name(), "my-org/my-env");
+        assert_eq!(
+            remote.clone_urls(Some(Protocol::Https)),
+            vec!["alice@git.mycorp.com:/srv/git/my-org/my-env.git"]
+        );
+    }
+
+    #[test]
+    fn test_mirror_urls() {
+        let settings = RemoteSettings {
+            mirrors: vec![
+                "git@gitea.mycorp.com:{org}/{repo}.git".to_string(),
+                "https://backup.mycorp.com/envs.git".to_string(),
+            ],
+        };
+        assert_eq!(
+            settings.mirror_urls("https://github.com/my-org/my-env.git"),
+            vec![
+                "git@gitea.mycorp.com:my-org/my-env.git",
+                "https://backup.mycorp.com/envs.git"
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::lockfile::{LockFile, Package};
+
+/// Render the packages of a lockfile as a CycloneDX 1.5 JSON document.
+///
+/// * `name`: Name of the environment
+/// * `lockfile`: Lockfile listing the packages of the environment
+pub fn render_cyclonedx(name: &str, lockfile: &LockFile) -> Value {
+    let components: Vec<Value> = lockfile
+        .packages
+        .iter()
+        .map(|package| {
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": package.purl(),
+                "name": package.name,
+                "version": package.version,
+                "purl": package.purl(),
+                "hashes": hashes(package)
+                    .into_iter()
+                    .map(|(alg, content)| json!({"alg": alg, "content": content}))
+                    .collect::<Vec<_>>(),
+                "externalReferences": [{"type": "distribution", "url": package.url}],
+            });
+            if let Some(license) = declared_license(package) {
+                component["licenses"] = json!([{"expression": license}]);
+            }
+            component
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp(),
+            "tools": {"components": [{"type": "application", "name": "araki"}]},
+            "component": {"type": "application", "name": name},
+        },
+        "components": components,
+    })
+}
+
+/// Render the packages of a lockfile as an SPDX 2.3 JSON document.
+///
+/// * `name`: Name of the environment
+/// * `lockfile`: Lockfile listing the packages of the environment
+pub fn render_spdx(name: &str, lockfile: &LockFile) -> Value {
+    let packages: Vec<Value> = lockfile
+        .packages
+        .iter()
+        .enumerate()
+        .map(|(i, package)| {
+            json!({
+                "SPDXID": format!("SPDXRef-Package-{i}"),
+                "name": package.name,
+                "versionInfo": package.version,
+                "downloadLocation": package.url,
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": declared_license(package).unwrap_or("NOASSERTION"),
+                "copyrightText": "NOASSERTION",
+                "checksums": hashes(package)
+                    .into_iter()
+                    .map(|(alg, value)| json!({"algorithm": alg.replace('-', ""), "checksumValue": value}))
+                    .collect::<Vec<_>>(),
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": package.purl(),
+                }],
+            })
+        })
+        .collect();
+    let relationships: Vec<Value> = (0..packages.len())
+        .map(|i| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": format!("SPDXRef-Package-{i}"),
+            })
+        })
+        .collect();
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!("https://spdx.org/spdxdocs/araki-{name}-{}", Uuid::new_v4()),
+        "creationInfo": {
+            "created": timestamp(),
+            "creators": ["Tool: araki"],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// Get the current time as an ISO 8601 timestamp.
+fn timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Get the hashes of a package as (algorithm, hex digest) pairs.
+///
+/// * `package`: Package to get the hashes of
+fn hashes(package: &Package) -> Vec<(&'static str, &str)> {
+    let mut hashes = vec![];
+    if let Some(sha256) = &package.sha256 {
+        hashes.push(("SHA-256", sha256.as_str()));
+    }
+    if let Some(md5) = &package.md5 {
+        hashes.push(("MD5", md5.as_str()));
+    }
+    hashes
+}
+
+/// Get the license declared by a package, if it declares a meaningful one.
+///
+/// * `package`: Package to get the license of
+fn declared_license(package: &Package) -> Option<&str> {
+    package
+        .license
+        .as_deref()
+        .filter(|license| !license.is_empty() && *license != "None")
+}
@@ -0,0 +1,847 @@
+//! JSON Schema of `araki.toml`, and validation of settings files against it.
+//!
+//! The schema mirrors the typed [`Settings`]: each settings type implements [`Schema`] next to
+//! the others here, so that editors can complete and check `araki.toml`, and araki can report
+//! unknown keys and values of the wrong type at the line they appear on, rather than silently
+//! ignoring them.
+
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use toml::Spanned;
+use toml::de::{DeTable, DeValue};
+
+use crate::backends::{AuthSettings, CredentialStore, InitSettings, Visibility};
+use crate::global::PathSettings;
+use crate::licenses::LicenseSettings;
+use crate::lint::{Level, LintSettings};
+use crate::mirrors::{MirrorCredential, MirrorSettings};
+use crate::network::NetworkSettings;
+use crate::notifications::NotificationSettings;
+use crate::ops::PullSettings;
+use crate::policy::PolicySettings;
+use crate::remote::RemoteSettings;
+use crate::secrets::SecretSettings;
+use crate::security::SecuritySettings;
+use crate::settings::Settings;
+use crate::tags::{TagProtection, TagRetention, TagScheme, TagSettings};
+use crate::workspace::WorkspaceEnvironment;
+
+/// A type with a JSON Schema describing how it is written in `araki.toml`.
+pub trait Schema {
+    fn schema() -> Value;
+}
+
+impl Schema for String {
+    fn schema() -> Value {
+        json!({"type": "string"})
+    }
+}
+
+impl Schema for PathBuf {
+    fn schema() -> Value {
+        json!({"type": "string"})
+    }
+}
+
+impl Schema for bool {
+    fn schema() -> Value {
+        json!({"type": "boolean"})
+    }
+}
+
+impl Schema for u64 {
+    fn schema() -> Value {
+        json!({"type": "integer", "minimum": 0})
+    }
+}
+
+impl Schema for usize {
+    fn schema() -> Value {
+        json!({"type": "integer", "minimum": 0})
+    }
+}
+
+// toml has no null, so an unset option is a missing key
+impl<T: Schema> Schema for Option<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: Schema> Schema for BTreeMap<String, T> {
+    fn schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::schema()})
+    }
+}
+
+impl Schema for toml::Table {
+    fn schema() -> Value {
+        json!({"type": "object"})
+    }
+}
+
+/// Schema of a table with a fixed set of keys.
+///
+/// * `properties`: Key, description, and schema of each value
+fn object(properties: &[(&str, &str, Value)]) -> Value {
+    let properties: Map<String, Value> = properties
+        .iter()
+        .map(|(key, description, schema)| {
+            let mut schema = schema.clone();
+            if let Some(schema) = schema.as_object_mut() {
+                schema.insert("description".to_string(), json!(description));
+            }
+            (key.to_string(), schema)
+        })
+        .collect();
+    json!({"type": "object", "properties": properties, "additionalProperties": false})
+}
+
+impl Schema for Settings {
+    fn schema() -> Value {
+        let mut schema = object(&[
+            (
+                "aliases",
+                "Short names for lockspec repositories, usable wherever a `<org>/<repo>` or URL is",
+                BTreeMap::<String, String>::schema(),
+            ),
+            (
+                "auth",
+                "Where the tokens of `araki auth login` are stored",
+                AuthSettings::schema(),
+            ),
+            (
+                "backend",
+                "Name of the backend used to host lockspec repositories",
+                json!({"type": "string", "default": "github"}),
+            ),
+            (
+                "backends",
+                "Backend-specific settings, keyed by backend name (e.g. `[backends.registry]`)",
+                BTreeMap::<String, toml::Table>::schema(),
+            ),
+            (
+                "env",
+                "Environment variables exported on activation, overriding those declared by the lockspec",
+                BTreeMap::<String, String>::schema(),
+            ),
+            (
+                "environments",
+                "Named environments of the project, for projects using several of them",
+                BTreeMap::<String, WorkspaceEnvironment>::schema(),
+            ),
+            (
+                "init",
+                "Settings of the lockspec repositories created by `araki init`",
+                InitSettings::schema(),
+            ),
+            (
+                "licenses",
+                "License policy checked by `araki licenses`",
+                LicenseSettings::schema(),
+            ),
+            (
+                "lint",
+                "Rule levels and limits of `araki lint`",
+                LintSettings::schema(),
+            ),
+            (
+                "mirrors",
+                "Mirrors injected into pixi when installing environments",
+                MirrorSettings::schema(),
+            ),
+            (
+                "network",
+                "TLS, proxy, and timeout settings for HTTP requests",
+                NetworkSettings::schema(),
+            ),
+            (
+                "notifications",
+                "Webhook notified when environments are tagged or pushed",
+                NotificationSettings::schema(),
+            ),
+            (
+                "paths",
+                "Where global environments are stored",
+                PathSettings::schema(),
+            ),
+            (
+                "profile",
+                "Profile in use, e.g. `work`: its `[profiles.<name>]` settings apply, and its \
+                tokens are stored apart from those of other profiles",
+                json!({"type": "string"}),
+            ),
+            (
+                "profiles",
+                "Settings of each profile, overriding the other settings when the profile is in use",
+                BTreeMap::<String, toml::Table>::schema(),
+            ),
+            (
+                "policy",
+                "Size and content limits of lockspec repositories, checked on tag and push",
+                PolicySettings::schema(),
+            ),
+            (
+                "pull",
+                "Whether `araki pull` installs the lockfiles it brings in",
+                PullSettings::schema(),
+            ),
+            (
+                "remotes",
+                "Mirrors that lockspec repositories are pushed to",
+                RemoteSettings::schema(),
+            ),
+            (
+                "secrets",
+                "How secrets referenced by `[araki.env]` are read",
+                SecretSettings::schema(),
+            ),
+            (
+                "security",
+                "Signature requirements for tags",
+                SecuritySettings::schema(),
+            ),
+            (
+                "tag",
+                "Naming, verification, and protection of tags",
+                TagSettings::schema(),
+            ),
+            ("tags", "Alias of `tag`", TagSettings::schema()),
+        ]);
+        if let Value::Object(root) = &mut schema {
+            root.insert(
+                "$schema".to_string(),
+                json!("https://json-schema.org/draft/2020-12/schema"),
+            );
+            root.insert("title".to_string(), json!("araki.toml"));
+        }
+        schema
+    }
+}
+
+impl Schema for WorkspaceEnvironment {
+    fn schema() -> Value {
+        let mut schema = object(&[
+            (
+                "lockspec",
+                "URL or `<org>/<repo>` of the lockspec",
+                String::schema(),
+            ),
+            (
+                "tag",
+                "Tag, branch, or commit to checkout; the cloned default branch is kept if unset",
+                String::schema(),
+            ),
+            (
+                "path",
+                "Directory of the environment relative to the project. Defaults to `envs/<name>`",
+                PathBuf::schema(),
+            ),
+            (
+                "environments",
+                "pixi environments of the lockspec to install",
+                Vec::<String>::schema(),
+            ),
+        ]);
+        schema["required"] = json!(["lockspec"]);
+        schema
+    }
+}
+
+impl Schema for AuthSettings {
+    fn schema() -> Value {
+        object(&[(
+            "credential_store",
+            "Where tokens are stored: the OS keyring, falling back to a file when it can't be \
+            reached (`auto`), the keyring only (`keyring`), or a file only readable by the user \
+            (`file`)",
+            CredentialStore::schema(),
+        )])
+    }
+}
+
+impl Schema for CredentialStore {
+    fn schema() -> Value {
+        json!({"type": "string", "enum": ["auto", "keyring", "file"], "default": "auto"})
+    }
+}
+
+impl Schema for InitSettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "protect_default_branch",
+                "Require reviewed pull requests for changes to `main` of new repositories",
+                bool::schema(),
+            ),
+            (
+                "default_visibility",
+                "Visibility of new repositories, unless `araki init` is passed `--public` or \
+                `--private`",
+                Visibility::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for Visibility {
+    fn schema() -> Value {
+        json!({"type": "string", "enum": ["private", "public"], "default": "private"})
+    }
+}
+
+impl Schema for LicenseSettings {
+    fn schema() -> Value {
+        object(&[(
+            "deny",
+            "SPDX identifiers of licenses which may not appear in an environment, e.g. `GPL-3.0`",
+            Vec::<String>::schema(),
+        )])
+    }
+}
+
+impl Schema for Level {
+    fn schema() -> Value {
+        json!({"type": "string", "enum": ["off", "warning", "error"]})
+    }
+}
+
+impl Schema for LintSettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "rules",
+                "Level of each rule, overriding its default, e.g. `large-file = \"error\"`",
+                BTreeMap::<String, Level>::schema(),
+            ),
+            (
+                "max_file_mb",
+                "Size in megabytes above which a committed file is reported",
+                u64::schema(),
+            ),
+            (
+                "platforms",
+                "Platforms every lockspec must support, e.g. `[\"linux-64\", \"osx-arm64\"]`",
+                Vec::<String>::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for MirrorCredential {
+    fn schema() -> Value {
+        object(&[
+            (
+                "username_env",
+                "Environment variable holding the user name",
+                String::schema(),
+            ),
+            (
+                "password_env",
+                "Environment variable holding the password",
+                String::schema(),
+            ),
+            (
+                "token_env",
+                "Environment variable holding a bearer token",
+                String::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for MirrorSettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "conda",
+                "Conda channel URLs mapped to the mirrors that should be used instead",
+                BTreeMap::<String, Vec<String>>::schema(),
+            ),
+            ("pypi", "Index URL used instead of PyPI", String::schema()),
+            (
+                "credentials",
+                "Credentials for mirror hosts, keyed by host name",
+                BTreeMap::<String, MirrorCredential>::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for NetworkSettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "ca_bundle",
+                "PEM file with additional root certificates, e.g. of a TLS-intercepting proxy",
+                PathBuf::schema(),
+            ),
+            (
+                "max_bandwidth",
+                "Maximum rate of git transfers, in KiB per second",
+                u64::schema(),
+            ),
+            (
+                "proxy",
+                "URL of a proxy to send all requests through; defaults to the `HTTPS_PROXY` and `HTTP_PROXY` environment variables",
+                String::schema(),
+            ),
+            (
+                "https_proxy",
+                "URL of a proxy to send HTTPS requests through, instead of `proxy`",
+                String::schema(),
+            ),
+            (
+                "http_proxy",
+                "URL of a proxy to send plain HTTP requests through, instead of `proxy`",
+                String::schema(),
+            ),
+            (
+                "no_proxy",
+                "Hosts, domains, and IP ranges reached without the configured proxies; defaults to the `NO_PROXY` environment variable",
+                Vec::<String>::schema(),
+            ),
+            (
+                "offline",
+                "Don't access the network: only use local repos and cached remote data",
+                bool::schema(),
+            ),
+            (
+                "connect_timeout",
+                "Seconds after which connecting to a server fails; 0 waits indefinitely",
+                json!({"type": "integer", "minimum": 0, "default": 30}),
+            ),
+            (
+                "request_timeout",
+                "Seconds after which a request or git transfer which receives no data fails; 0 waits indefinitely",
+                json!({"type": "integer", "minimum": 0, "default": 300}),
+            ),
+            ("timeout", "Alias of `request_timeout`", u64::schema()),
+            (
+                "max_retries",
+                "Times a backend request is sent again after a server error, a rate limit, or a dropped connection; 0 disables retries",
+                json!({"type": "integer", "minimum": 0, "default": 3}),
+            ),
+            (
+                "retry_backoff",
+                "Seconds to wait before the first retry of a backend request, doubled for each further retry",
+                json!({"type": "integer", "minimum": 0, "default": 1}),
+            ),
+        ])
+    }
+}
+
+impl Schema for NotificationSettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "webhook",
+                "Incoming webhook URL (e.g. of Slack or Teams) to post messages to",
+                String::schema(),
+            ),
+            (
+                "template",
+                "Template of the message; `{action}`, `{environment}`, `{tag}` and `{changes}` are replaced",
+                String::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for PathSettings {
+    fn schema() -> Value {
+        object(&[(
+            "envs_dir",
+            "Machine-level directory of global environments shared by all users, e.g. `/opt/araki/envs`",
+            PathBuf::schema(),
+        )])
+    }
+}
+
+impl Schema for PolicySettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "max_repo_mb",
+                "Maximum size of the araki repo, history included, in megabytes",
+                u64::schema(),
+            ),
+            (
+                "max_lockfile_mb",
+                "Maximum size of pixi.lock, in megabytes",
+                u64::schema(),
+            ),
+            (
+                "forbidden",
+                "Glob patterns of files which may not be committed, e.g. `*.csv` or `data/*`",
+                Vec::<String>::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for PullSettings {
+    fn schema() -> Value {
+        object(&[(
+            "autoinstall",
+            "Install the lockfile with `pixi install --frozen` when a pull fast-forwards to a new one",
+            bool::schema(),
+        )])
+    }
+}
+
+impl Schema for RemoteSettings {
+    fn schema() -> Value {
+        object(&[(
+            "mirrors",
+            "URLs of mirrors that `araki push` pushes to after `origin`; `{org}` and `{repo}` are replaced",
+            Vec::<String>::schema(),
+        )])
+    }
+}
+
+impl Schema for SecretSettings {
+    fn schema() -> Value {
+        object(&[(
+            "command",
+            "Command printing the value of a secret, run with `sh -c`; `{name}` is replaced by its name",
+            String::schema(),
+        )])
+    }
+}
+
+impl Schema for SecuritySettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "require_signed_tags",
+                "Only checkout tags whose signature is made by an allowed signer",
+                bool::schema(),
+            ),
+            (
+                "allowed_signers",
+                "Allowed signers file for SSH signatures, in the format of `ssh-keygen -Y verify`",
+                PathBuf::schema(),
+            ),
+            (
+                "allowed_gpg_keys",
+                "Fingerprints of the GPG keys allowed to sign tags",
+                Vec::<String>::schema(),
+            ),
+            (
+                "verify_hashes",
+                "After installing an environment, check the installed conda packages against pixi.lock",
+                bool::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for TagScheme {
+    fn schema() -> Value {
+        json!({"type": "string", "enum": ["semver", "calver"], "default": "semver"})
+    }
+}
+
+impl Schema for TagProtection {
+    fn schema() -> Value {
+        object(&[(
+            "patterns",
+            "Glob patterns (`*` matches any characters) of protected tags, e.g. `v*`",
+            Vec::<String>::schema(),
+        )])
+    }
+}
+
+impl Schema for TagRetention {
+    fn schema() -> Value {
+        object(&[
+            (
+                "keep_last",
+                "Number of most recent tags to keep",
+                usize::schema(),
+            ),
+            (
+                "keep_matching",
+                "Glob patterns of tags to keep regardless of their age, e.g. `v*`",
+                Vec::<String>::schema(),
+            ),
+        ])
+    }
+}
+
+impl Schema for TagSettings {
+    fn schema() -> Value {
+        object(&[
+            (
+                "prefix",
+                "Prefix of version tags, e.g. `v` for `v1.2.3`",
+                json!({"type": "string", "default": "v"}),
+            ),
+            (
+                "scheme",
+                "Naming scheme used by `araki tag --bump`",
+                TagScheme::schema(),
+            ),
+            (
+                "verify",
+                "Check that the lockfile installs before creating a tag",
+                bool::schema(),
+            ),
+            (
+                "auto_snapshot",
+                "Create a timestamped snapshot tag whenever pixi changes the lockfile",
+                bool::schema(),
+            ),
+            (
+                "auto_push",
+                "Push `main` and the new tag to origin after `araki tag`",
+                bool::schema(),
+            ),
+            (
+                "protection",
+                "Tags which may not be deleted or moved",
+                TagProtection::schema(),
+            ),
+            (
+                "retention",
+                "Which tags `araki tag prune` keeps",
+                TagRetention::schema(),
+            ),
+            (
+                "stale_after_days",
+                "Warn when the newest tag of an environment is older than this many days; 0 disables the warning",
+                u64::schema(),
+            ),
+        ])
+    }
+}
+
+/// A problem found in a settings file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Line of the offending key or value, starting at 1
+    pub line: usize,
+
+    /// Dotted path of the offending key, e.g. `tag.scheme`
+    pub key: String,
+
+    /// Whether the key isn't a recognized setting, rather than having a value of the wrong type
+    pub unknown: bool,
+    pub message: String,
+}
+
+/// Validate the contents of a settings file against a schema. Returns the problems found, in
+/// the order they appear in the file.
+///
+/// * `schema`: Schema to validate against, e.g. of [`Settings`]
+/// * `contents`: Contents of the settings file
+pub fn validate(schema: &Value, contents: &str) -> Result<Vec<Diagnostic>, String> {
+    let table = DeTable::parse(contents).map_err(|err| err.to_string())?;
+    let mut validator = Validator {
+        contents,
+        diagnostics: Vec::new(),
+    };
+    validator.table(schema, "", table.get_ref(), table.span());
+    validator
+        .diagnostics
+        .sort_by_key(|diagnostic| diagnostic.line);
+    Ok(validator.diagnostics)
+}
+
+struct Validator<'a> {
+    contents: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, offset: usize, key: &str, unknown: bool, message: String) {
+        let offset = offset.min(self.contents.len());
+        self.diagnostics.push(Diagnostic {
+            line: self.contents[..offset].matches('\n').count() + 1,
+            key: key.to_string(),
+            unknown,
+            message,
+        });
+    }
+
+    fn table(&mut self, schema: &Value, path: &str, table: &DeTable, span: std::ops::Range<usize>) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties");
+        for required in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !table.keys().any(|key| key.get_ref() == required) {
+                self.report(
+                    span.start,
+                    path,
+                    false,
+                    format!("`{path}` is missing the required key `{required}`"),
+                );
+            }
+        }
+        for (key, value) in table {
+            let name = key.get_ref().as_ref();
+            let path = match path {
+                "" => name.to_string(),
+                parent => format!("{parent}.{name}"),
+            };
+            let schema = match properties.and_then(|properties| properties.get(name)) {
+                Some(schema) => Some(schema),
+                None => match additional {
+                    Some(Value::Bool(false)) => {
+                        self.report(
+                            key.span().start,
+                            &path,
+                            true,
+                            format!("unknown key `{path}`"),
+                        );
+                        continue;
+                    }
+                    Some(schema @ Value::Object(_)) => Some(schema),
+                    _ => None,
+                },
+            };
+            if let Some(schema) = schema {
+                self.value(schema, &path, value);
+            }
+        }
+    }
+
+    fn value(&mut self, schema: &Value, path: &str, value: &Spanned<DeValue>) {
+        let found = match value.get_ref() {
+            DeValue::String(_) => "string",
+            DeValue::Integer(_) => "integer",
+            DeValue::Float(_) => "float",
+            DeValue::Boolean(_) => "boolean",
+            DeValue::Datetime(_) => "datetime",
+            DeValue::Array(_) => "array",
+            DeValue::Table(_) => "table",
+        };
+        let expected = match schema.get("type").and_then(Value::as_str) {
+            Some("object") => Some("table"),
+            expected => expected,
+        };
+        if let Some(expected) = expected
+            && expected != found
+        {
+            self.report(
+                value.span().start,
+                path,
+                false,
+                format!(
+                    "`{path}` must be {}, found {}",
+                    with_article(expected),
+                    with_article(found)
+                ),
+            );
+            return;
+        }
+
+        match value.get_ref() {
+            DeValue::String(string) => {
+                if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+                    && !allowed.iter().any(|allowed| allowed == string.as_ref())
+                {
+                    let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                    self.report(
+                        value.span().start,
+                        path,
+                        false,
+                        format!("`{path}` must be one of {}", allowed.join(", ")),
+                    );
+                }
+            }
+            DeValue::Integer(integer)
+                if schema.get("minimum").is_some() && integer.as_str().starts_with('-') =>
+            {
+                self.report(
+                    value.span().start,
+                    path,
+                    false,
+                    format!("`{path}` must not be negative"),
+                );
+            }
+            DeValue::Array(array) => {
+                if let Some(items) = schema.get("items") {
+                    for item in array.iter() {
+                        self.value(items, path, item);
+                    }
+                }
+            }
+            DeValue::Table(table) => self.table(schema, path, table, value.span()),
+            _ => {}
+        }
+    }
+}
+
+/// Prefix the name of a type with its indefinite article, e.g. `an integer`.
+///
+/// * `kind`: Name of the type
+fn with_article(kind: &str) -> String {
+    match kind.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        true => format!("an {kind}"),
+        false => format!("a {kind}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let contents = "\
+backend = \"github\"
+
+[tag]
+scheme = \"weekly\"
+prefx = \"v\"
+
+[lint]
+max_file_mb = \"10\"
+
+[environments.data]
+tag = \"v1\"
+";
+        let diagnostics = validate(&Settings::schema(), contents).unwrap();
+        let found: Vec<(usize, &str, bool)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.key.as_str(), diagnostic.unknown))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (4, "tag.scheme", false),
+                (5, "tag.prefx", true),
+                (8, "lint.max_file_mb", false),
+                (10, "environments.data", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_readme_settings_are_valid() {
+        let readme = include_str!("../README.md");
+        let start = readme
+            .find("## Configuration")
+            .expect("the README documents the configuration");
+        let block = readme[start..]
+            .split("```toml\n")
+            .nth(1)
+            .and_then(|block| block.split("```").next())
+            .expect("the configuration section has a toml example");
+        assert_eq!(validate(&Settings::schema(), block), Ok(vec![]));
+    }
+}
@@ -0,0 +1,289 @@
+//! Secrets referenced by the environment variables of a lockspec.
+//!
+//! A value of the form `secret:<NAME>` in `[araki.env]` is replaced at activation time by the
+//! secret `<NAME>`, so that lockspecs needing credentials can be shared without committing them.
+//! Secrets are read with the command configured in `[secrets]`, or from the OS keyring
+//! (`secret-tool` on Linux, `security` on macOS) under the service `araki`. The tokens of
+//! `araki auth login` are kept in the keyring under another service, `araki-auth`, so that a
+//! lockspec can't reference them as secrets.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env::consts;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::settings::get_settings;
+
+/// Prefix of environment variable values referencing a secret.
+pub const SECRET_PREFIX: &str = "secret:";
+
+/// Service under which secrets are stored in the OS keyring.
+const KEYRING_SERVICE: &str = "araki";
+
+/// Service under which araki stores its own credentials in the OS keyring, out of reach of the
+/// secrets of lockspecs.
+pub const CREDENTIALS_SERVICE: &str = "araki-auth";
+
+/// Secret settings, configured in the `[secrets]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SecretSettings {
+    /// Command printing the value of a secret, run with `sh -c`; `{name}` is replaced by the
+    /// name of the secret. The OS keyring is used if not set
+    pub command: Option<String>,
+}
+
+/// Get the value of a secret.
+///
+/// * `name`: Name of the secret
+pub fn resolve(name: &str) -> Result<String, String> {
+    // Names end up in a shell command; keep them to characters which need no quoting
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./".contains(c))
+    {
+        return Err(format!(
+            "Invalid secret name {name:?}; use letters, digits, and _-./ only."
+        ));
+    }
+
+    let template = get_settings()?.secrets.command;
+    let mut command = secret_command(name, template.as_deref())?;
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .map_err(|err| format!("Unable to run {program} to read the secret {name}: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Unable to read the secret {name}: {program} failed with {}{}{}",
+            output.status,
+            match stderr.trim() {
+                "" => String::new(),
+                stderr => format!(": {stderr}"),
+            },
+            match template {
+                Some(_) => String::new(),
+                None => store_hint(name),
+            }
+        ));
+    }
+    let value = String::from_utf8(output.stdout)
+        .map_err(|_| format!("The secret {name} is not valid UTF-8."))?;
+    let value = value.strip_suffix('\n').unwrap_or(&value);
+    Ok(value.strip_suffix('\r').unwrap_or(value).to_string())
+}
+
+/// Build the command printing a secret: the configured command, or a lookup in the OS keyring
+/// under the service of secrets.
+///
+/// * `name`: Name of the secret
+/// * `template`: Configured command, with `{name}` standing for the name of the secret
+fn secret_command(name: &str, template: Option<&str>) -> Result<Command, String> {
+    match template {
+        Some(template) => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(template.replace("{name}", name));
+            Ok(command)
+        }
+        None => keyring_command(KEYRING_SERVICE, name),
+    }
+}
+
+/// Replace the values of environment variables referencing a secret by the secret.
+///
+/// * `env`: Environment variables, keyed by name
+pub fn resolve_env(env: BTreeMap<String, String>) -> Result<BTreeMap<String, String>, String> {
+    env.into_iter()
+        .map(|(var, value)| match value.strip_prefix(SECRET_PREFIX) {
+            Some(name) => Ok((var, resolve(name.trim())?)),
+            None => Ok((var, value)),
+        })
+        .collect()
+}
+
+/// Read an entry of the OS keyring. Returns `None` if the keyring has no such entry, or can't be
+/// reached, e.g. without a running Secret Service on Linux.
+///
+/// * `service`: Service of the entry, e.g. [`CREDENTIALS_SERVICE`]
+/// * `account`: Name of the entry
+pub fn keyring_read(service: &str, account: &str) -> Result<Option<String>, String> {
+    let output = keyring_command(service, account)?
+        .output()
+        .map_err(|err| format!("Unable to read the OS keyring: {err}"))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(Some(value).filter(|value| !value.is_empty()))
+}
+
+/// Store an entry in the OS keyring, replacing any existing one. The value is passed on stdin,
+/// which keeps it out of the process list.
+///
+/// * `service`: Service of the entry, e.g. [`CREDENTIALS_SERVICE`]
+/// * `account`: Name of the entry
+/// * `value`: Value to store
+pub fn keyring_write(service: &str, account: &str, value: &str) -> Result<(), String> {
+    let (mut command, input) = match consts::OS {
+        "linux" => {
+            let mut command = Command::new("secret-tool");
+            command.args([
+                "store",
+                &format!("--label={service} {account}"),
+                "service",
+                service,
+                "account",
+                account,
+            ]);
+            (command, value.to_string())
+        }
+        "macos" => {
+            // security only takes the value as an argument, so run it as a command read from
+            // stdin by `security -i`, hex-encoded to need no quoting
+            let mut command = Command::new("security");
+            command.arg("-i");
+            let hex: String = value.bytes().map(|byte| format!("{byte:02x}")).collect();
+            let input = format!("add-generic-password -U -s {service} -a {account} -X {hex}\n");
+            (command, input)
+        }
+        os => return Err(format!("araki can't write to the {os} keyring.")),
+    };
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Unable to run {program} to write to the OS keyring: {err}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| format!("Unable to pass the value to {program}: {err}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Unable to run {program}: {err}"))?;
+    // `security -i` reports failed commands on stderr, but still exits successfully
+    if !output.status.success() || !output.stderr.is_empty() {
+        return Err(format!(
+            "Unable to write to the OS keyring: {program} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Remove an entry of the OS keyring, if it exists.
+///
+/// * `service`: Service of the entry, e.g. [`CREDENTIALS_SERVICE`]
+/// * `account`: Name of the entry
+pub fn keyring_delete(service: &str, account: &str) -> Result<(), String> {
+    let mut command = match consts::OS {
+        "linux" => {
+            let mut command = Command::new("secret-tool");
+            command.args(["clear", "service", service, "account", account]);
+            command
+        }
+        "macos" => {
+            let mut command = Command::new("security");
+            command.args(["delete-generic-password", "-s", service, "-a", account]);
+            command
+        }
+        os => return Err(format!("araki can't write to the {os} keyring.")),
+    };
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("Unable to run {program} to write to the OS keyring: {err}"))?;
+    // security exits with 44 when there is no such entry
+    if !output.status.success() && output.status.code() != Some(44) {
+        return Err(format!(
+            "Unable to remove {account} from the OS keyring: {program} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Build the command reading an entry of the OS keyring.
+///
+/// * `service`: Service of the entry
+/// * `name`: Name of the entry
+fn keyring_command(service: &str, name: &str) -> Result<Command, String> {
+    let mut command = match consts::OS {
+        "linux" => {
+            let mut command = Command::new("secret-tool");
+            command.args(["lookup", "service", service, "account", name]);
+            command
+        }
+        "macos" => {
+            let mut command = Command::new("security");
+            command.args(["find-generic-password", "-s", service, "-a", name, "-w"]);
+            command
+        }
+        os => {
+            return Err(format!(
+                "araki can't read the {os} keyring; set secrets.command to read the secret {name}."
+            ));
+        }
+    };
+    command.stdin(Stdio::null());
+    Ok(command)
+}
+
+/// Explain how to store a secret where araki looks for it.
+///
+/// * `name`: Name of the secret
+fn store_hint(name: &str) -> String {
+    match consts::OS {
+        "linux" => format!(
+            "\nStore it with `secret-tool store --label={name} service {KEYRING_SERVICE} account \
+            {name}`, or set secrets.command."
+        ),
+        "macos" => format!(
+            "\nStore it with `security add-generic-password -s {KEYRING_SERVICE} -a {name} -w`, \
+            or set secrets.command."
+        ),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_env() {
+        let env = BTreeMap::from([("JAVA_HOME".to_string(), "/opt/java".to_string())]);
+        assert_eq!(resolve_env(env.clone()), Ok(env));
+
+        // Names which would need quoting in a secrets command are rejected before running it
+        let env = BTreeMap::from([("TOKEN".to_string(), "secret:$(whoami)".to_string())]);
+        assert!(resolve_env(env).is_err());
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn test_secrets_cant_reach_credentials() {
+        // Lockspecs can name any entry, e.g. `secret:github-token/github.com`, but secrets are
+        // only looked up under their own service
+        let args = |command: Command| -> Vec<String> {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect()
+        };
+        let secret = args(secret_command("github-token/github.com", None).unwrap());
+        assert!(secret.iter().any(|arg| arg == KEYRING_SERVICE));
+        assert!(!secret.iter().any(|arg| arg == CREDENTIALS_SERVICE));
+        let credential =
+            args(keyring_command(CREDENTIALS_SERVICE, "github-token/github.com").unwrap());
+        assert!(credential.iter().any(|arg| arg == CREDENTIALS_SERVICE));
+    }
+}
@@ -0,0 +1,244 @@
+use git2::Repository;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::common::LockSpec;
+use crate::lockfile::LockFile;
+use crate::rebuild::{self, Discrepancy};
+
+/// Security settings, configured in the `[security]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SecuritySettings {
+    /// Only checkout tags whose signature is made by an allowed signer
+    pub require_signed_tags: bool,
+
+    /// Allowed signers file for SSH signatures, in the format of `ssh-keygen -Y verify`
+    /// (`<principal> <key type> <public key>` per line)
+    pub allowed_signers: Option<PathBuf>,
+
+    /// Fingerprints of the GPG keys allowed to sign tags
+    pub allowed_gpg_keys: Vec<String>,
+
+    /// After installing an environment, check the installed conda packages against the hashes
+    /// recorded in pixi.lock
+    pub verify_hashes: bool,
+}
+
+/// Kind of signature on a tag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureKind {
+    Gpg,
+    Ssh,
+}
+
+impl Display for SignatureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureKind::Gpg => write!(f, "GPG"),
+            SignatureKind::Ssh => write!(f, "SSH"),
+        }
+    }
+}
+
+/// The verified signer of a tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSigner {
+    pub kind: SignatureKind,
+
+    /// Principal (SSH) or user ID (GPG) of the signer
+    pub signer: String,
+
+    /// SSH key fingerprint or GPG key fingerprint
+    pub key: String,
+}
+
+impl Display for TagSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} key {})", self.signer, self.kind, self.key)
+    }
+}
+
+/// Get the kind of signature of an annotated tag, if it is signed.
+///
+/// * `repo`: Repository containing the tag
+/// * `tag`: Name of the tag
+pub fn signature_kind(repo: &Repository, tag: &str) -> Result<Option<SignatureKind>, String> {
+    let tag_object = repo
+        .find_reference(&format!("refs/tags/{tag}"))
+        .and_then(|reference| reference.peel_to_tag())
+        .map_err(|_| format!("{tag} is not an annotated tag, so it cannot be signed."))?;
+    let message = String::from_utf8_lossy(tag_object.message_bytes().unwrap_or_default());
+    Ok(if message.contains("-----BEGIN SSH SIGNATURE-----") {
+        Some(SignatureKind::Ssh)
+    } else if message.contains("-----BEGIN PGP SIGNATURE-----") {
+        Some(SignatureKind::Gpg)
+    } else {
+        None
+    })
+}
+
+/// Verify the signature of a tag against the allowed signers, using `git verify-tag`.
+///
+/// * `repo`: Repository containing the tag
+/// * `tag`: Name of the tag
+/// * `settings`: Security settings listing the allowed signers
+pub fn verify_tag(
+    repo: &Repository,
+    tag: &str,
+    settings: &SecuritySettings,
+) -> Result<TagSigner, String> {
+    let kind = signature_kind(repo, tag)?.ok_or(format!("Tag {tag} is not signed."))?;
+
+    let mut command = Command::new("git");
+    command.arg("--git-dir").arg(repo.path());
+    if kind == SignatureKind::Ssh {
+        let allowed_signers = settings.allowed_signers.as_ref().ok_or(format!(
+            "Tag {tag} has an SSH signature, but no security.allowed_signers file is configured."
+        ))?;
+        command.arg("-c").arg(format!(
+            "gpg.ssh.allowedSignersFile={}",
+            allowed_signers.display()
+        ));
+    }
+    let output = command
+        .args(["verify-tag", "--raw", tag])
+        .output()
+        .map_err(|err| format!("Failed to execute git verify-tag: {err}"))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let signer = match kind {
+        SignatureKind::Ssh => parse_ssh_signer(&stderr),
+        SignatureKind::Gpg => parse_gpg_signer(&stderr),
+    };
+
+    match signer {
+        Some(signer) if output.status.success() => {
+            if kind == SignatureKind::Gpg && !is_allowed_gpg_key(&signer.key, settings) {
+                return Err(format!(
+                    "Tag {tag} is signed by {signer}, which is not in security.allowed_gpg_keys."
+                ));
+            }
+            Ok(signer)
+        }
+        _ => Err(format!(
+            "The signature of tag {tag} could not be verified: {}",
+            stderr.trim()
+        )),
+    }
+}
+
+/// Check that the conda packages installed for a lockspec have the sha256 (or md5) hashes
+/// recorded in its lockfile, e.g. to detect packages altered on a mirror after locking.
+///
+/// * `lockspec`: Installed lockspec
+/// * `environments`: Installed pixi environments; pixi's default environment if empty
+pub fn verify_installed_hashes(lockspec: &LockSpec, environments: &[String]) -> Result<(), String> {
+    let platform = rebuild::current_platform()
+        .ok_or("Unsupported platform; installed hashes can't be verified.")?;
+    let lockfile = LockFile::from_path(&lockspec.lockfile())?;
+    let default = ["default".to_string()];
+    let environments = match environments.is_empty() {
+        true => &default[..],
+        false => environments,
+    };
+
+    let mut mismatches = vec![];
+    for environment in environments {
+        let prefix = lockspec.dir().join(".pixi").join("envs").join(environment);
+        let installed = rebuild::installed_packages(&prefix)?;
+        mismatches.extend(
+            rebuild::compare_lockfile(&lockfile, environment, platform, &installed)
+                .into_iter()
+                .filter(|discrepancy| matches!(discrepancy, Discrepancy::HashMismatch { .. }))
+                .map(|discrepancy| format!("  {environment}: {discrepancy}")),
+        );
+    }
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Installed packages don't match the hashes in pixi.lock, so they may have been altered \
+        since the environment was locked:\n{}",
+        mismatches.join("\n")
+    ))
+}
+
+/// Check whether a GPG fingerprint is one of the allowed keys.
+///
+/// * `fingerprint`: Fingerprint of the signing key
+/// * `settings`: Security settings listing the allowed keys
+fn is_allowed_gpg_key(fingerprint: &str, settings: &SecuritySettings) -> bool {
+    settings.allowed_gpg_keys.iter().any(|key| {
+        let key: String = key.chars().filter(|c| !c.is_whitespace()).collect();
+        key.eq_ignore_ascii_case(fingerprint)
+    })
+}
+
+/// Parse the signer from the output of `ssh-keygen -Y verify`, e.g.
+/// `Good "git" signature for alice@example.com with ED25519 key SHA256:...`.
+///
+/// * `output`: Output of the verification
+fn parse_ssh_signer(output: &str) -> Option<TagSigner> {
+    let line = output
+        .lines()
+        .find(|line| line.starts_with("Good \"git\" signature for "))?;
+    let (signer, key) = line
+        .strip_prefix("Good \"git\" signature for ")?
+        .split_once(" with ")?;
+    Some(TagSigner {
+        kind: SignatureKind::Ssh,
+        signer: signer.to_string(),
+        key: key.rsplit(' ').next()?.to_string(),
+    })
+}
+
+/// Parse the signer from the `--status-fd` output of gpg.
+///
+/// * `output`: Output of the verification
+fn parse_gpg_signer(output: &str) -> Option<TagSigner> {
+    let status = |keyword: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("[GNUPG:] {keyword} ")))
+    };
+    let (_, signer) = status("GOODSIG")?.split_once(' ')?;
+    let fingerprint = status("VALIDSIG")?.split(' ').next()?;
+    Some(TagSigner {
+        kind: SignatureKind::Gpg,
+        signer: signer.to_string(),
+        key: fingerprint.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signers() {
+        let ssh = "Good \"git\" signature for alice@example.com with ED25519 key SHA256:abc\n";
+        assert_eq!(
+            parse_ssh_signer(ssh),
+            Some(TagSigner {
+                kind: SignatureKind::Ssh,
+                signer: "alice@example.com".to_string(),
+                key: "SHA256:abc".to_string(),
+            })
+        );
+
+        let gpg = "[GNUPG:] NEWSIG\n\
+            [GNUPG:] GOODSIG 0123456789ABCDEF Alice <alice@example.com>\n\
+            [GNUPG:] VALIDSIG AAAA0123456789ABCDEF 2024-01-01 1704067200 0\n";
+        assert_eq!(
+            parse_gpg_signer(gpg),
+            Some(TagSigner {
+                kind: SignatureKind::Gpg,
+                signer: "Alice <alice@example.com>".to_string(),
+                key: "AAAA0123456789ABCDEF".to_string(),
+            })
+        );
+        assert_eq!(parse_gpg_signer("[GNUPG:] BADSIG 0123 Alice\n"), None);
+    }
+}
@@ -0,0 +1,354 @@
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::env::current_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml::Table;
+
+use crate::backends::{AuthSettings, InitSettings};
+use crate::common::get_project_dirs;
+use crate::global::PathSettings;
+use crate::licenses::LicenseSettings;
+use crate::lint::LintSettings;
+use crate::mirrors::MirrorSettings;
+use crate::network::NetworkSettings;
+use crate::notifications::NotificationSettings;
+use crate::ops::PullSettings;
+use crate::policy::PolicySettings;
+use crate::preflight;
+use crate::remote::RemoteSettings;
+use crate::schema::{self, Diagnostic, Schema};
+use crate::secrets::SecretSettings;
+use crate::security::SecuritySettings;
+use crate::tags::TagSettings;
+use crate::workspace::WorkspaceEnvironment;
+
+pub const SETTINGS_FILE_NAME: &str = "araki.toml";
+
+/// Profile selected with `--profile`, taking precedence over the `profile` setting.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// araki settings.
+///
+/// Settings are read from the user config file (`<config-dir>/araki/araki.toml`), then
+/// overridden by an `araki.toml` in the current directory, if one exists.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Short names for lockspec repositories, usable wherever a `<org>/<repo>` or URL is
+    pub aliases: BTreeMap<String, String>,
+
+    /// Where the tokens of `araki auth login` are stored
+    pub auth: AuthSettings,
+
+    /// Name of the backend used to host lockspec repositories
+    pub backend: String,
+
+    /// Backend-specific settings, keyed by backend name (e.g. `[backends.registry]`)
+    pub backends: Table,
+
+    /// Environment variables exported on activation, overriding those declared by the lockspec
+    pub env: BTreeMap<String, String>,
+
+    /// Named environments of the project, for projects using several of them
+    pub environments: BTreeMap<String, WorkspaceEnvironment>,
+
+    /// Settings of the lockspec repositories created by `araki init`
+    pub init: InitSettings,
+
+    /// License policy checked by `araki licenses`
+    pub licenses: LicenseSettings,
+
+    /// Rule levels and limits of `araki lint`
+    pub lint: LintSettings,
+
+    /// Mirrors injected into pixi when installing environments
+    pub mirrors: MirrorSettings,
+
+    /// TLS, proxy, and timeout settings for HTTP requests
+    pub network: NetworkSettings,
+
+    /// Webhook notified when environments are tagged or pushed
+    pub notifications: NotificationSettings,
+
+    /// Where global environments are stored
+    pub paths: PathSettings,
+
+    /// Profile in use, e.g. `work`: its `[profiles.<name>]` settings apply, and its tokens are
+    /// stored apart from those of other profiles
+    pub profile: Option<String>,
+
+    /// Settings of each profile, overriding the other settings when the profile is in use
+    pub profiles: BTreeMap<String, Table>,
+
+    /// Size and content limits of lockspec repositories, checked on tag and push
+    pub policy: PolicySettings,
+
+    /// Whether `araki pull` installs the lockfiles it brings in
+    pub pull: PullSettings,
+
+    /// Mirrors that lockspec repositories are pushed to
+    pub remotes: RemoteSettings,
+
+    /// How secrets referenced by `[araki.env]` are read
+    pub secrets: SecretSettings,
+
+    /// Signature requirements for tags
+    pub security: SecuritySettings,
+
+    /// Naming, verification, and protection of tags; `[tags]` is accepted as well
+    #[serde(alias = "tags")]
+    pub tag: TagSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            aliases: BTreeMap::new(),
+            auth: AuthSettings::default(),
+            backend: "github".to_string(),
+            backends: Table::new(),
+            env: BTreeMap::new(),
+            environments: BTreeMap::new(),
+            init: InitSettings::default(),
+            licenses: LicenseSettings::default(),
+            lint: LintSettings::default(),
+            mirrors: MirrorSettings::default(),
+            network: NetworkSettings::default(),
+            notifications: NotificationSettings::default(),
+            paths: PathSettings::default(),
+            profile: None,
+            profiles: BTreeMap::new(),
+            policy: PolicySettings::default(),
+            pull: PullSettings::default(),
+            remotes: RemoteSettings::default(),
+            secrets: SecretSettings::default(),
+            security: SecuritySettings::default(),
+            tag: TagSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Replace an alias by the lockspec repository it stands for. `<alias>#<subdir>` keeps the
+    /// subdirectory; anything that isn't an alias is returned unchanged.
+    ///
+    /// * `env`: Alias, `<org>/<repo>`, or URL of a lockspec
+    pub fn resolve_alias(&self, env: &str) -> String {
+        let (name, subdir) = match env.split_once('#') {
+            Some((name, subdir)) => (name, Some(subdir)),
+            None => (env, None),
+        };
+        match (self.aliases.get(name), subdir) {
+            (Some(target), Some(subdir)) => format!("{target}#{subdir}"),
+            (Some(target), None) => target.clone(),
+            (None, _) => env.to_string(),
+        }
+    }
+
+    /// Get the settings section of a backend. A missing section yields the default settings.
+    ///
+    /// * `name`: Name of the backend
+    pub fn backend_settings<T: DeserializeOwned + Default>(&self, name: &str) -> Result<T, String> {
+        match self.backends.get(name) {
+            Some(section) => section
+                .clone()
+                .try_into()
+                .map_err(|err| format!("Invalid settings in [backends.{name}]: {err}")),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+/// Get the path to the user settings file.
+pub fn get_user_settings_file() -> Result<PathBuf, String> {
+    Ok(get_project_dirs()
+        .map_err(|err| format!("Unable to find the araki config directory: {err}"))?
+        .config_dir()
+        .join(SETTINGS_FILE_NAME))
+}
+
+/// Get the path to the project settings file in the current directory.
+pub fn get_project_settings_file() -> Result<PathBuf, String> {
+    Ok(current_dir()
+        .map_err(|err| format!("Could not get the current directory: {err}"))?
+        .join(SETTINGS_FILE_NAME))
+}
+
+/// Select the profile to use, overriding the `profile` setting. Only the first call has an
+/// effect.
+///
+/// * `profile`: Name of the profile
+pub fn set_profile(profile: &str) {
+    let _ = PROFILE.set(profile.to_string());
+}
+
+/// Load the araki settings, merging the project settings over the user settings, and the
+/// settings of the profile in use over both. `[backends.*]` is only read from the user settings.
+pub fn get_settings() -> Result<Settings, String> {
+    let mut table = read_settings_file(&get_user_settings_file()?)?;
+    let mut project = read_settings_file(&get_project_settings_file()?)?;
+    // Backends are only configured by the user: they decide which hosts get the tokens
+    project.remove("backends");
+    if let Some(profiles) = project.get_mut("profiles").and_then(|p| p.as_table_mut()) {
+        for (_, overrides) in profiles.iter_mut() {
+            if let Some(overrides) = overrides.as_table_mut() {
+                overrides.remove("backends");
+            }
+        }
+    }
+    merge_tables(&mut table, project);
+    let profile = match PROFILE.get() {
+        Some(profile) => Some(profile.clone()),
+        None => table
+            .get("profile")
+            .and_then(|profile| profile.as_str())
+            .map(|profile| profile.to_string()),
+    };
+    if let Some(profile) = profile {
+        // Profile names are used as directory names for their tokens
+        if profile.is_empty()
+            || !profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid profile name '{profile}': use letters, digits, '-' and '_' only."
+            ));
+        }
+        let overrides = table
+            .get("profiles")
+            .and_then(|profiles| profiles.get(&profile))
+            .and_then(|overrides| overrides.as_table())
+            .cloned();
+        if let Some(overrides) = overrides {
+            merge_tables(&mut table, overrides);
+        }
+        table.insert("profile".to_string(), toml::Value::String(profile));
+    }
+
+    toml::Value::Table(table).try_into().map_err(|err| {
+        // Point at the offending lines rather than the merged table, if the schema finds them
+        let invalid: Vec<String> = check_settings_files()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, diagnostic)| !diagnostic.unknown)
+            .map(|(path, diagnostic)| {
+                format!(
+                    "{}:{}: {}",
+                    path.display(),
+                    diagnostic.line,
+                    diagnostic.message
+                )
+            })
+            .collect();
+        match invalid.is_empty() {
+            true => format!("Invalid araki settings: {err}"),
+            false => format!("Invalid araki settings:\n{}", invalid.join("\n")),
+        }
+    })
+}
+
+/// Validate the user and project settings files against the schema of [`Settings`]. Returns
+/// the unknown keys and invalid values found, with the file they were found in.
+pub fn check_settings_files() -> Result<Vec<(PathBuf, Diagnostic)>, String> {
+    let schema = Settings::schema();
+    let mut problems = Vec::new();
+    for path in [get_user_settings_file()?, get_project_settings_file()?] {
+        if !path.exists() {
+            continue;
+        }
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("Unable to read {path:?}: {err}"))?;
+        let diagnostics = schema::validate(&schema, &contents)
+            .map_err(|err| format!("Unable to parse {path:?} as valid toml.\nReason: {err}"))?;
+        problems.extend(
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| (path.clone(), diagnostic)),
+        );
+    }
+    Ok(problems)
+}
+
+/// Modify the user settings file, creating it if it doesn't exist.
+///
+/// The file is rewritten from its parsed contents, so comments in it are not preserved.
+///
+/// * `update`: Called with the contents of the file to modify them
+pub fn update_user_settings(update: impl FnOnce(&mut Table)) -> Result<(), String> {
+    let path = get_user_settings_file()?;
+    let mut table = read_settings_file(&path)?;
+    update(&mut table);
+
+    if let Some(dir) = path.parent() {
+        preflight::create_dir(dir)?;
+    }
+    let contents = toml::to_string(&table)
+        .map_err(|err| format!("Unable to serialize the settings: {err}"))?;
+    fs::write(&path, contents).map_err(|err| format!("Unable to write {path:?}: {err}"))
+}
+
+/// Read a settings file into a toml table. Missing files are treated as empty.
+///
+/// * `path`: Path to the settings file
+fn read_settings_file(path: &Path) -> Result<Table, String> {
+    if !path.exists() {
+        return Ok(Table::new());
+    }
+    fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {path:?}: {err}"))?
+        .parse()
+        .map_err(|err| format!("Unable to parse {path:?} as valid toml.\nReason: {err}"))
+}
+
+/// Recursively merge `overrides` into `base`. Nested tables are merged key by key; any other
+/// value in `overrides` replaces the one in `base`.
+///
+/// * `base`: Table to merge into
+/// * `overrides`: Table whose values take precedence
+pub fn merge_tables(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(override_table)) => {
+                merge_tables(base_table, override_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_tables() {
+        let mut base: Table = "backend = \"github\"\n[a]\nx = 1\ny = 2\n".parse().unwrap();
+        let overrides: Table = "backend = \"git\"\n[a]\ny = 3\n".parse().unwrap();
+        merge_tables(&mut base, overrides);
+
+        assert_eq!(base["backend"].as_str(), Some("git"));
+        assert_eq!(base["a"]["x"].as_integer(), Some(1));
+        assert_eq!(base["a"]["y"].as_integer(), Some(3));
+    }
+
+    #[test]
+    fn test_resolve_alias() {
+        let mut settings = Settings::default();
+        settings
+            .aliases
+            .insert("ds".to_string(), "my-org/data-science-env".to_string());
+
+        assert_eq!(settings.resolve_alias("ds"), "my-org/data-science-env");
+        assert_eq!(
+            settings.resolve_alias("ds#gpu"),
+            "my-org/data-science-env#gpu"
+        );
+        assert_eq!(settings.resolve_alias("my-org/ds"), "my-org/ds");
+    }
+}
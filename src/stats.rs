@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::common::{self, LockSpec};
+use crate::ops;
+use crate::projects;
+
+/// Usage statistics of an environment, gathered from the projects using it.
+#[derive(Debug, Clone, Default)]
+pub struct EnvironmentStats {
+    /// `<org>/<repo>` of the lockspec
+    pub environment: String,
+
+    /// Number of recorded projects using the environment
+    pub projects: usize,
+
+    /// When a command last ran in any of the projects
+    pub last_run: Option<DateTime<Utc>>,
+
+    /// Bytes used by the installed environments of the projects
+    pub disk_usage: u64,
+
+    /// Number of tags of the lockspec
+    pub tags: usize,
+
+    /// When the newest commit of the lockspec's `main` branch was made
+    pub updated: Option<DateTime<Utc>>,
+}
+
+/// Gather usage statistics for every environment used by a recorded project.
+///
+/// Projects which no longer contain a lockspec are skipped.
+pub fn stats() -> Result<Vec<EnvironmentStats>, String> {
+    let mut stats: BTreeMap<String, EnvironmentStats> = BTreeMap::new();
+    for (project, record) in projects::load()? {
+        let Ok(lockspec) = LockSpec::from_path(&project) else {
+            continue;
+        };
+        let entry = stats
+            .entry(record.environment.clone())
+            .or_insert_with(|| EnvironmentStats {
+                environment: record.environment.clone(),
+                ..Default::default()
+            });
+        entry.projects += 1;
+        entry.disk_usage += dir_size(&lockspec.dir().join(".pixi"));
+        let last_run = record
+            .last_run
+            .as_deref()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc));
+        entry.last_run = entry.last_run.max(last_run);
+
+        if let Ok(repo) = common::open_araki_git_repo(&project) {
+            entry.tags = entry
+                .tags
+                .max(ops::list_tags(&repo).map(|tags| tags.len()).unwrap_or(0));
+            let updated = repo
+                .revparse_single("main^{commit}")
+                .ok()
+                .and_then(|commit| commit.peel_to_commit().ok())
+                .and_then(|commit| DateTime::from_timestamp(commit.time().seconds(), 0));
+            entry.updated = entry.updated.max(updated);
+        }
+    }
+    Ok(stats.into_values().collect())
+}
+
+/// Get the total size of the files in a directory and its subdirectories, without following
+/// symlinks.
+///
+/// * `path`: Directory to measure
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => {
+                entry.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+            }
+            _ => 0,
+        })
+        .sum()
+}
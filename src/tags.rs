@@ -0,0 +1,242 @@
+use chrono::{Datelike, Local};
+use serde::Deserialize;
+
+/// Naming scheme of environment tags.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagScheme {
+    /// `<prefix><major>.<minor>.<patch>`
+    #[default]
+    Semver,
+
+    /// `<prefix><year>.<month>.<n>`, where n counts the tags of the month from 0
+    Calver,
+}
+
+/// Tag settings, configured in the `[tag]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TagSettings {
+    /// Prefix of version tags, e.g. `v` for `v1.2.3`
+    pub prefix: String,
+
+    /// Naming scheme used by `araki tag --bump`
+    pub scheme: TagScheme,
+
+    /// Check that the lockfile installs before creating a tag
+    pub verify: bool,
+
+    /// Create a timestamped snapshot tag whenever pixi changes the lockfile, through the pixi
+    /// shim or `araki run`
+    pub auto_snapshot: bool,
+
+    /// Push `main` and the new tag to origin after `araki tag`
+    pub auto_push: bool,
+
+    /// Tags which may not be deleted or moved
+    pub protection: TagProtection,
+
+    /// Which tags `araki tag prune` keeps
+    pub retention: TagRetention,
+
+    /// Warn on clone and activation when the newest tag of an environment is older than this
+    /// many days; 0 disables the warning
+    pub stale_after_days: u64,
+}
+
+impl Default for TagSettings {
+    fn default() -> Self {
+        Self {
+            prefix: "v".to_string(),
+            scheme: TagScheme::default(),
+            verify: false,
+            auto_snapshot: false,
+            auto_push: false,
+            protection: TagProtection::default(),
+            retention: TagRetention::default(),
+            stale_after_days: 90,
+        }
+    }
+}
+
+/// Tag protection rules, configured in the `[tag.protection]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TagProtection {
+    /// Glob patterns (`*` matches any characters) of protected tags, e.g. `v*`
+    pub patterns: Vec<String>,
+}
+
+/// Tag retention policy, configured in the `[tag.retention]` section of `araki.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TagRetention {
+    /// Number of most recent tags to keep
+    pub keep_last: Option<usize>,
+
+    /// Glob patterns of tags to keep regardless of their age, e.g. `v*`
+    pub keep_matching: Vec<String>,
+}
+
+impl TagRetention {
+    /// Check whether any retention rule is set. Without one, pruning would delete every tag.
+    pub fn is_configured(&self) -> bool {
+        self.keep_last.is_some() || !self.keep_matching.is_empty()
+    }
+}
+
+impl TagSettings {
+    /// Check whether a tag is protected from being deleted or moved.
+    ///
+    /// * `tag`: Name of the tag
+    pub fn is_protected(&self, tag: &str) -> bool {
+        self.protection
+            .patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, tag))
+    }
+
+    /// Select the tags which the retention policy doesn't keep. Protected tags are never
+    /// selected.
+    ///
+    /// * `tags`: Names of the tags, from newest to oldest
+    pub fn prunable<'a>(&self, tags: &[&'a str]) -> Vec<&'a str> {
+        let retention = &self.retention;
+        tags.iter()
+            .skip(retention.keep_last.unwrap_or(0))
+            .filter(|tag| {
+                !self.is_protected(tag)
+                    && !retention
+                        .keep_matching
+                        .iter()
+                        .any(|pattern| glob_match(pattern, tag))
+            })
+            .copied()
+            .collect()
+    }
+}
+
+/// Match a name against a glob pattern in which `*` matches any (possibly empty) sequence of
+/// characters.
+///
+/// * `pattern`: Glob pattern
+/// * `name`: Name to match
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Part of a semantic version to increment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// Parse a tag as `<prefix><a>.<b>.<c>`.
+///
+/// * `tag`: Tag to parse
+/// * `prefix`: Prefix of version tags
+fn parse_version(tag: &str, prefix: &str) -> Option<[u64; 3]> {
+    let mut parts = tag.strip_prefix(prefix)?.split('.');
+    let version = [
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    ];
+    parts.next().is_none().then_some(version)
+}
+
+/// Get the name of the next version tag.
+///
+/// Tags which don't follow the naming scheme are ignored. With the calver scheme, `bump` is
+/// ignored and the next tag of the current month is returned.
+///
+/// * `tags`: Names of the existing tags
+/// * `settings`: Tag naming settings
+/// * `bump`: Part of the version to increment
+pub fn next_version<'a>(
+    tags: impl IntoIterator<Item = &'a str>,
+    settings: &TagSettings,
+    bump: Bump,
+) -> String {
+    let prefix = &settings.prefix;
+    let versions = tags
+        .into_iter()
+        .filter_map(|tag| parse_version(tag, prefix));
+
+    match settings.scheme {
+        TagScheme::Semver => {
+            let [major, minor, patch] = match (versions.max(), bump) {
+                (None, Bump::Major) => [1, 0, 0],
+                (None, Bump::Minor) => [0, 1, 0],
+                (None, Bump::Patch) => [0, 0, 1],
+                (Some([major, _, _]), Bump::Major) => [major + 1, 0, 0],
+                (Some([major, minor, _]), Bump::Minor) => [major, minor + 1, 0],
+                (Some([major, minor, patch]), Bump::Patch) => [major, minor, patch + 1],
+            };
+            format!("{prefix}{major}.{minor}.{patch}")
+        }
+        TagScheme::Calver => {
+            let today = Local::now();
+            let (year, month) = (today.year() as u64, today.month() as u64);
+            let n = versions
+                .filter(|[y, m, _]| *y == year && *m == month)
+                .map(|[_, _, n]| n + 1)
+                .max()
+                .unwrap_or(0);
+            format!("{prefix}{year}.{month:02}.{n}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_version() {
+        let settings = TagSettings::default();
+        let tags = ["v1.2.3", "v1.10.0", "latest", "v2.0", "x3.0.0"];
+        assert_eq!(next_version(tags, &settings, Bump::Patch), "v1.10.1");
+        assert_eq!(next_version(tags, &settings, Bump::Minor), "v1.11.0");
+        assert_eq!(next_version(tags, &settings, Bump::Major), "v2.0.0");
+        assert_eq!(next_version([], &settings, Bump::Minor), "v0.1.0");
+    }
+
+    #[test]
+    fn test_prunable() {
+        let mut settings = TagSettings::default();
+        settings.retention.keep_last = Some(1);
+        settings.retention.keep_matching = vec!["v*".to_string()];
+        settings.protection.patterns = vec!["stable".to_string()];
+        let tags = ["snapshot/3", "v2", "snapshot/2", "stable", "ci-1"];
+        assert_eq!(settings.prunable(&tags), vec!["snapshot/2", "ci-1"]);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("v*", "v1.2.3"));
+        assert!(glob_match("release-*-final", "release-1-final"));
+        assert!(glob_match("stable", "stable"));
+        assert!(!glob_match("v*", "dev"));
+        assert!(!glob_match("stable", "stable-2"));
+        assert!(!glob_match("a*a", "a"));
+    }
+}
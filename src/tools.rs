@@ -0,0 +1,313 @@
+use directories::UserDirs;
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Repository, Signature};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Table;
+
+use crate::common;
+use crate::ops::Progress;
+use crate::preflight;
+use crate::remote::parse_repo_arg;
+use crate::settings::get_settings;
+
+/// Name of the pixi global manifest, both in the pixi home and in the tools repo.
+pub const MANIFEST_FILE: &str = "pixi-global.toml";
+
+/// Get the directory of the tools repo, which versions the pixi global manifest.
+pub fn get_tools_dir() -> Result<PathBuf, String> {
+    Ok(common::get_araki_dir()?.join("tools"))
+}
+
+/// Get the path of the manifest listing the tools installed with `pixi global`.
+///
+/// Like pixi, this respects `PIXI_HOME` and defaults to `~/.pixi`.
+pub fn get_pixi_global_manifest() -> Result<PathBuf, String> {
+    let pixi_home = match env::var_os("PIXI_HOME") {
+        Some(home) => PathBuf::from(home),
+        None => UserDirs::new()
+            .map(|dirs| dirs.home_dir().join(".pixi"))
+            .ok_or("Could not determine the user home directory.".to_string())?,
+    };
+    Ok(pixi_home.join("manifests").join(MANIFEST_FILE))
+}
+
+/// List the environments of the current pixi global manifest, which each expose some tools.
+pub fn manifest_envs() -> Result<Vec<String>, String> {
+    let manifest = get_pixi_global_manifest()?;
+    if !manifest.exists() {
+        return Err(format!(
+            "No pixi global manifest found at {manifest:?}. Install tools with \
+            `pixi global install` first."
+        ));
+    }
+    let contents: Table = fs::read_to_string(&manifest)
+        .map_err(|err| format!("Unable to read {manifest:?}: {err}"))?
+        .parse()
+        .map_err(|err| format!("Unable to parse {manifest:?}: {err}"))?;
+    Ok(contents
+        .get("envs")
+        .and_then(|envs| envs.as_table())
+        .map(|envs| envs.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Open the tools repo.
+pub fn open() -> Result<Repository, String> {
+    common::open_araki_git_repo(&get_tools_dir()?).map_err(|_| {
+        "No tools repo found. Run `araki tools init` or `araki tools clone` first.".to_string()
+    })
+}
+
+/// Start versioning the pixi global manifest in a new lockspec repo: clone the (empty) repo to
+/// the tools dir, commit the current manifest, and push it.
+///
+/// * `url`: URL of the empty remote repo
+pub fn init(url: &str) -> Result<(), String> {
+    create(url, None)
+}
+
+/// Import the current pixi global manifest into a new lockspec repo: like [`init`], but the
+/// manifest is also tagged, so that others can clone and check out the tools right away.
+///
+/// * `url`: URL of the empty remote repo
+/// * `name`: Name of the tag
+pub fn import(url: &str, name: &str) -> Result<(), String> {
+    create(url, Some(name))
+}
+
+/// Clone the (empty) repo to the tools dir, commit the current manifest, optionally tag it,
+/// and push it.
+///
+/// * `url`: URL of the empty remote repo
+/// * `tag_name`: Name of the tag, if any
+fn create(url: &str, tag_name: Option<&str>) -> Result<(), String> {
+    let dir = get_tools_dir()?;
+    if dir.exists() {
+        return Err(format!("A tools repo already exists at {dir:?}."));
+    }
+    preflight::create_dir(&dir)?;
+    let result = common::git_clone(url.to_string(), &dir).and_then(|_| {
+        let repo = open()?;
+        commit(&repo, "Initial commit")?;
+        if let Some(name) = tag_name {
+            tag(
+                &repo,
+                name,
+                Some("Import the tools installed with pixi global"),
+            )?;
+        }
+        push(&repo)
+    });
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&dir);
+    }
+    result
+}
+
+/// Clone an existing tools repo and install the tools of its latest commit.
+///
+/// * `env`: URL, `<org>/<repo>`, or alias of the tools repo
+/// * `on_progress`: Called as the clone progresses
+pub fn clone(env: &str, on_progress: &dyn Fn(Progress)) -> Result<(), String> {
+    let dir = get_tools_dir()?;
+    if dir.exists() {
+        return Err(format!(
+            "A tools repo already exists at {dir:?}. Remove it to clone another one."
+        ));
+    }
+    let env = &get_settings()?.resolve_alias(env);
+    let remote = parse_repo_arg(env)
+        .map_err(|err| format!("{env} is not a valid lockspec repository: {err}"))?;
+
+    on_progress(Progress::Phase {
+        step: 1,
+        total: 2,
+        message: &format!("Cloning {remote}..."),
+    });
+    preflight::create_dir(&dir)?;
+    let mut errors = vec![];
+    for url in remote.clone_urls(None) {
+        match common::git_clone_with_progress(url, &dir, &|received, total| {
+            on_progress(Progress::Transfer { received, total })
+        }) {
+            Ok(()) => {
+                errors.clear();
+                break;
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+    if !errors.is_empty() {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(format!(
+            "Unable to clone the tools repo:\n{}",
+            errors.join("\n")
+        ));
+    }
+
+    on_progress(Progress::Phase {
+        step: 2,
+        total: 2,
+        message: "Installing the tools with pixi global...",
+    });
+    install(&dir)
+}
+
+/// Commit the current pixi global manifest to the `main` branch of the tools repo, unless it is
+/// unchanged. Returns the new commit, or the tip of `main` if nothing changed.
+///
+/// * `repo`: Tools repo
+/// * `message`: Commit message
+pub fn commit(repo: &Repository, message: &str) -> Result<Oid, String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("The tools repo has no working directory".to_string())?;
+    let manifest = get_pixi_global_manifest()?;
+    fs::copy(&manifest, workdir.join(MANIFEST_FILE)).map_err(|err| {
+        format!("Unable to copy {manifest:?}. Are any tools installed with pixi global? {err}")
+    })?;
+
+    let mut index = repo
+        .index()
+        .map_err(|err| format!("Failed to get index: {err}"))?;
+    index
+        .add_path(Path::new(MANIFEST_FILE))
+        .map_err(|err| format!("Unable to add {MANIFEST_FILE}: {err}"))?;
+    index
+        .write()
+        .map_err(|err| format!("Failed to write index: {err}"))?;
+    let tree = index
+        .write_tree()
+        .and_then(|oid| repo.find_tree(oid))
+        .map_err(|err| format!("Failed to write tree: {err}"))?;
+
+    let parent = repo
+        .find_reference("refs/heads/main")
+        .and_then(|main| main.peel_to_commit())
+        .ok();
+    if let Some(parent) = &parent
+        && parent.tree_id() == tree.id()
+    {
+        return Ok(parent.id());
+    }
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    let parents: Vec<_> = parent.iter().collect();
+    let oid = repo
+        .commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .map_err(|err| format!("Failed to create commit: {err}"))?;
+    repo.set_head("refs/heads/main")
+        .map_err(|err| format!("Unable to set head: {err}"))?;
+    Ok(oid)
+}
+
+/// Commit the current pixi global manifest and tag the commit.
+///
+/// * `repo`: Tools repo
+/// * `name`: Name of the tag
+/// * `description`: Message of the tag
+pub fn tag(repo: &Repository, name: &str, description: Option<&str>) -> Result<Oid, String> {
+    let oid = commit(repo, name)?;
+    let target = repo
+        .find_object(oid, None)
+        .map_err(|err| format!("Unable to find the new commit: {err}"))?;
+    let signature = Signature::now("araki", "place@holder.com")
+        .map_err(|err| format!("Failed to get signature: {err}"))?;
+    let message = match description {
+        Some(message) => message.to_string(),
+        None => format!("araki tools tag: {name}"),
+    };
+    repo.tag(name, &target, &signature, &message, false)
+        .map_err(|err| format!("Unable to tag: {err}"))
+}
+
+/// List the tags of the tools repo, sorted by name.
+///
+/// * `repo`: Tools repo
+pub fn list(repo: &Repository) -> Result<Vec<String>, String> {
+    let names = repo
+        .tag_names(None)
+        .map_err(|err| format!("Unable to list the tags: {err}"))?;
+    let mut names: Vec<String> = names
+        .iter()
+        .flatten()
+        .map(|name| name.to_string())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Restore the pixi global manifest of a tag, branch, or commit and install its tools.
+///
+/// * `repo`: Tools repo
+/// * `reference`: Tag, branch, or commit to restore
+pub fn checkout(repo: &Repository, reference: &str) -> Result<(), String> {
+    let commit = repo
+        .revparse_single(reference)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|err| format!("Unable to find {reference} in the tools repo: {err}"))?;
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))
+        .map_err(|err| format!("Unable to checkout {reference}: {err}"))?;
+    match repo.find_branch(reference, git2::BranchType::Local) {
+        Ok(branch) => repo.set_head(
+            branch
+                .get()
+                .name()
+                .ok_or(format!("{reference} is not a valid branch name"))?,
+        ),
+        Err(_) => repo.set_head_detached(commit.id()),
+    }
+    .map_err(|err| format!("Unable to set head: {err}"))?;
+    let workdir = repo
+        .workdir()
+        .ok_or("The tools repo has no working directory".to_string())?;
+    install(workdir)
+}
+
+/// Push `main` and all tags of the tools repo to its origin.
+///
+/// * `repo`: Tools repo
+pub fn push(repo: &Repository) -> Result<(), String> {
+    let mut refs = vec!["refs/heads/main".to_string()];
+    refs.extend(
+        list(repo)?
+            .into_iter()
+            .map(|name| format!("refs/tags/{name}")),
+    );
+    let refs: Vec<&str> = refs.iter().map(|name| name.as_str()).collect();
+    common::git_push_repo(repo, "origin", &refs)
+        .map_err(|err| format!("Unable to push the tools repo: {err}"))
+}
+
+/// Copy the manifest in the working directory of the tools repo to the pixi home, and install
+/// the tools it lists with `pixi global sync`.
+///
+/// * `workdir`: Working directory of the tools repo
+fn install(workdir: &Path) -> Result<(), String> {
+    let source = workdir.join(MANIFEST_FILE);
+    if !source.exists() {
+        return Err(format!("The tools repo has no {MANIFEST_FILE}."));
+    }
+    let manifest = get_pixi_global_manifest()?;
+    if let Some(parent) = manifest.parent() {
+        preflight::create_dir(parent)?;
+    }
+    fs::copy(&source, &manifest).map_err(|err| format!("Unable to write {manifest:?}: {err}"))?;
+    let status = common::pixi_command()
+        .args(["global", "sync"])
+        .status()
+        .map_err(|err| format!("Failed to execute pixi global sync: {err}"))?;
+    if !status.success() {
+        return Err("Failed to install the tools with pixi global sync.".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Hardware variants of a lockspec.
+//!
+//! A lockspec supporting both CPU-only and GPU machines defines pixi environments named `cpu`
+//! and `gpu`; cloning it with a variant installs the matching environment, and records it so
+//! that later checkouts, syncs, and runs use the same one.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Hardware variant of a lockspec, installed from the pixi environment of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Cpu,
+    Gpu,
+}
+
+impl Variant {
+    /// Get the name of the pixi environment of the variant.
+    pub fn environment(&self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Gpu => "gpu",
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.environment())
+    }
+}
+
+/// Get the variant suiting the hardware araki runs on: `gpu` if an NVIDIA GPU is present,
+/// `cpu` otherwise.
+pub fn detect() -> Variant {
+    match has_nvidia_gpu() {
+        true => Variant::Gpu,
+        false => Variant::Cpu,
+    }
+}
+
+/// Check whether an NVIDIA GPU is present, either through its driver or through `nvidia-smi`.
+fn has_nvidia_gpu() -> bool {
+    let driver = Path::new("/proc/driver/nvidia/gpus");
+    if fs::read_dir(driver).is_ok_and(|mut gpus| gpus.next().is_some()) {
+        return true;
+    }
+    Command::new("nvidia-smi")
+        .arg("-L")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).contains("GPU")
+        })
+}
@@ -0,0 +1,137 @@
+use git2::Repository;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::common::{self, LockSpec};
+use crate::ops::{self, Progress};
+use crate::preflight;
+
+/// An environment of a workspace, declared in the `[environments]` section of the project's
+/// `araki.toml`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WorkspaceEnvironment {
+    /// URL or `<org>/<repo>` of the lockspec
+    pub lockspec: String,
+
+    /// Tag, branch, or commit to checkout; the cloned default branch is kept if unset
+    pub tag: Option<String>,
+
+    /// Directory of the environment relative to the project. Defaults to `envs/<name>`
+    pub path: Option<PathBuf>,
+
+    /// pixi environments of the lockspec to install. Defaults to the selection recorded when
+    /// the environment was cloned, or pixi's default environment
+    #[serde(default)]
+    pub environments: Vec<String>,
+}
+
+impl WorkspaceEnvironment {
+    /// Get the directory of the environment.
+    ///
+    /// * `root`: Project directory containing the `araki.toml` declaring the environment
+    /// * `name`: Name of the environment
+    pub fn dir(&self, root: &Path, name: &str) -> PathBuf {
+        match &self.path {
+            Some(path) => root.join(path),
+            None => root.join("envs").join(name),
+        }
+    }
+}
+
+/// What [`sync`] did to an environment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncAction {
+    Cloned,
+    CheckedOut(String),
+    Reinstalled(Vec<String>),
+    UpToDate,
+}
+
+/// Bring an environment of the workspace in line with its declaration, cloning it if it doesn't
+/// exist yet, checking out the declared tag, and installing the declared pixi environments.
+///
+/// Environments with uncommitted changes to their lockspec are left alone.
+///
+/// * `root`: Project directory containing the `araki.toml` declaring the environment
+/// * `name`: Name of the environment
+/// * `env`: Declaration of the environment
+/// * `on_progress`: Called as the clone progresses
+pub fn sync(
+    root: &Path,
+    name: &str,
+    env: &WorkspaceEnvironment,
+    on_progress: &dyn Fn(Progress),
+) -> Result<SyncAction, String> {
+    let dir = env.dir(root, name);
+    let mut action = SyncAction::UpToDate;
+    if LockSpec::from_path(&dir).is_err() {
+        preflight::create_dir(&dir)?;
+        ops::clone(
+            &env.lockspec,
+            &dir,
+            env.tag.as_deref(),
+            None,
+            &env.environments,
+            on_progress,
+        )?;
+        action = SyncAction::Cloned;
+    }
+
+    let lockspec = LockSpec::from_path(&dir)?;
+    let repo = common::open_araki_git_repo(&dir)
+        .map_err(|err| format!("Couldn't recognize the araki repo in {dir:?}: {err}"))?;
+    let reselect =
+        !env.environments.is_empty() && common::pixi_environments(&repo) != env.environments;
+    if reselect {
+        ops::select_pixi_environments(&lockspec, &repo, &env.environments)?;
+    }
+    let Some(tag) = &env.tag else {
+        return reinstall(&repo, &env.environments, reselect, action);
+    };
+    let wanted = repo
+        .revparse_single(&format!("{}^{{commit}}", ops::full_tag_name(&repo, tag)))
+        .or_else(|_| repo.revparse_single(&format!("{tag}^{{commit}}")))
+        .map(|commit| commit.id())
+        .ok();
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map(|commit| commit.id())
+        .ok();
+    if wanted.is_some() && wanted == head {
+        return reinstall(&repo, &env.environments, reselect, action);
+    }
+
+    let modified = ops::status(&lockspec, &repo)?.modified;
+    if !modified.is_empty() {
+        return Err(format!(
+            "{} in {dir:?} {} uncommitted changes; not checking out {tag}.",
+            modified.join(" and "),
+            if modified.len() == 1 { "has" } else { "have" },
+        ));
+    }
+    ops::checkout(&repo, tag)?;
+    Ok(match action {
+        SyncAction::Cloned => SyncAction::Cloned,
+        _ => SyncAction::CheckedOut(tag.clone()),
+    })
+}
+
+/// Reinstall an environment whose selection of pixi environments changed.
+///
+/// * `repo`: araki repo of the environment
+/// * `environments`: Newly selected pixi environments
+/// * `reselect`: Whether the selection changed
+/// * `action`: What [`sync`] did to the environment so far
+fn reinstall(
+    repo: &Repository,
+    environments: &[String],
+    reselect: bool,
+    action: SyncAction,
+) -> Result<SyncAction, String> {
+    if !reselect || action == SyncAction::Cloned {
+        return Ok(action);
+    }
+    ops::install(repo)?;
+    Ok(SyncAction::Reinstalled(environments.to_vec()))
+}